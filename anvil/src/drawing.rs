@@ -18,7 +18,7 @@ use smithay::{
     backend::renderer::{
         element::{Element, Id, RenderElement},
         utils::CommitCounter,
-        Frame,
+        BlendMode, Frame,
     },
     utils::{Buffer, Logical, Rectangle, Size, Transform},
 };
@@ -225,6 +225,7 @@ where
                 &damage,
                 Transform::Normal,
                 1.0,
+                BlendMode::Premultiplied,
             )?;
             offset += Point::from((24.0, 0.0)).to_physical(scale);
         }
@@ -3,7 +3,10 @@ pub use smithay::{
     desktop::{LayerSurface, PopupKind},
     input::{
         keyboard::{KeyboardTarget, KeysymHandle, ModifiersState},
-        pointer::{AxisFrame, ButtonEvent, MotionEvent, PointerTarget, RelativeMotionEvent},
+        pointer::{
+            AxisFrame, ButtonEvent, GesturePinchEvent, GestureSwipeEvent, MotionEvent, PointerTarget,
+            RelativeMotionEvent,
+        },
         Seat,
     },
     reexports::wayland_server::{backend::ObjectId, protocol::wl_surface::WlSurface, Resource},
@@ -100,6 +103,30 @@ impl<BackendData: Backend> PointerTarget<AnvilState<BackendData>> for FocusTarge
             FocusTarget::Popup(p) => PointerTarget::axis(p.wl_surface(), seat, data, frame),
         }
     }
+    fn gesture_swipe(
+        &self,
+        seat: &Seat<AnvilState<BackendData>>,
+        data: &mut AnvilState<BackendData>,
+        event: &GestureSwipeEvent,
+    ) {
+        match self {
+            FocusTarget::Window(w) => PointerTarget::gesture_swipe(w, seat, data, event),
+            FocusTarget::LayerSurface(l) => PointerTarget::gesture_swipe(l, seat, data, event),
+            FocusTarget::Popup(p) => PointerTarget::gesture_swipe(p.wl_surface(), seat, data, event),
+        }
+    }
+    fn gesture_pinch(
+        &self,
+        seat: &Seat<AnvilState<BackendData>>,
+        data: &mut AnvilState<BackendData>,
+        event: &GesturePinchEvent,
+    ) {
+        match self {
+            FocusTarget::Window(w) => PointerTarget::gesture_pinch(w, seat, data, event),
+            FocusTarget::LayerSurface(l) => PointerTarget::gesture_pinch(l, seat, data, event),
+            FocusTarget::Popup(p) => PointerTarget::gesture_pinch(p.wl_surface(), seat, data, event),
+        }
+    }
     fn leave(
         &self,
         seat: &Seat<AnvilState<BackendData>>,
@@ -17,7 +17,7 @@ use smithay::{
     },
     output::Scale,
     reexports::wayland_server::{protocol::wl_pointer, DisplayHandle},
-    utils::{Logical, Point, Serial, Transform, SERIAL_COUNTER as SCOUNTER},
+    utils::{time::Time, Logical, Point, Serial, Transform, SERIAL_COUNTER as SCOUNTER},
     wayland::{
         compositor::with_states,
         input_method::InputMethodSeat,
@@ -201,7 +201,7 @@ impl<BackendData: Backend> AnvilState<BackendData> {
                 button,
                 state: state.try_into().unwrap(),
                 serial,
-                time: evt.time_msec(),
+                time: Time::from_micros(evt.time()),
             },
         );
     }
@@ -343,14 +343,19 @@ impl<BackendData: Backend> AnvilState<BackendData> {
             .unwrap_or_else(|| evt.amount_discrete(input::Axis::Vertical).unwrap_or(0.0) * 3.0);
         let horizontal_amount_discrete = evt.amount_discrete(input::Axis::Horizontal);
         let vertical_amount_discrete = evt.amount_discrete(input::Axis::Vertical);
+        let horizontal_amount_v120 = evt.amount_v120(input::Axis::Horizontal);
+        let vertical_amount_v120 = evt.amount_v120(input::Axis::Vertical);
 
         {
-            let mut frame = AxisFrame::new(evt.time_msec()).source(evt.source());
+            let mut frame = AxisFrame::new(Time::from_micros(evt.time())).source(evt.source());
             if horizontal_amount != 0.0 {
                 frame = frame.value(Axis::Horizontal, horizontal_amount);
                 if let Some(discrete) = horizontal_amount_discrete {
                     frame = frame.discrete(Axis::Horizontal, discrete as i32);
                 }
+                if let Some(v120) = horizontal_amount_v120 {
+                    frame = frame.value120(Axis::Horizontal, v120 as i32);
+                }
             } else if evt.source() == AxisSource::Finger {
                 frame = frame.stop(Axis::Horizontal);
             }
@@ -359,6 +364,9 @@ impl<BackendData: Backend> AnvilState<BackendData> {
                 if let Some(discrete) = vertical_amount_discrete {
                     frame = frame.discrete(Axis::Vertical, discrete as i32);
                 }
+                if let Some(v120) = vertical_amount_v120 {
+                    frame = frame.value120(Axis::Vertical, v120 as i32);
+                }
             } else if evt.source() == AxisSource::Finger {
                 frame = frame.stop(Axis::Vertical);
             }
@@ -476,7 +484,7 @@ impl<Backend: crate::state::Backend> AnvilState<Backend> {
             &MotionEvent {
                 location: pos,
                 serial,
-                time: evt.time_msec(),
+                time: Time::from_micros(evt.time()),
             },
         );
     }
@@ -539,7 +547,7 @@ impl AnvilState<UdevData> {
                                 &MotionEvent {
                                     location: self.pointer_location,
                                     serial: SCOUNTER.next_serial(),
-                                    time: 0,
+                                    time: Time::from_millis(0),
                                 },
                             );
                         }
@@ -578,7 +586,7 @@ impl AnvilState<UdevData> {
                                 &MotionEvent {
                                     location: self.pointer_location,
                                     serial: SCOUNTER.next_serial(),
-                                    time: 0,
+                                    time: Time::from_millis(0),
                                 },
                             );
                         }
@@ -665,7 +673,7 @@ impl AnvilState<UdevData> {
                 &MotionEvent {
                     location: self.pointer_location,
                     serial,
-                    time: evt.time_msec(),
+                    time: Time::from_micros(evt.time()),
                 },
             );
 
@@ -715,7 +723,7 @@ impl AnvilState<UdevData> {
                 &MotionEvent {
                     location: self.pointer_location,
                     serial,
-                    time: evt.time_msec(),
+                    time: Time::from_micros(evt.time()),
                 },
             );
         }
@@ -14,7 +14,10 @@ use smithay::{
     desktop::{space::SpaceElement, utils::OutputPresentationFeedback, Window, WindowSurfaceType},
     input::{
         keyboard::{KeyboardTarget, KeysymHandle, ModifiersState},
-        pointer::{AxisFrame, ButtonEvent, MotionEvent, PointerTarget, RelativeMotionEvent},
+        pointer::{
+            AxisFrame, ButtonEvent, GesturePinchEvent, GestureSwipeEvent, MotionEvent, PointerTarget,
+            RelativeMotionEvent,
+        },
         Seat,
     },
     output::Output,
@@ -193,10 +196,12 @@ impl<Backend: crate::state::Backend> PointerTarget<AnvilState<Backend>> for Wind
             if event.location.y < HEADER_BAR_HEIGHT as f64 {
                 match self {
                     WindowElement::Wayland(w) => {
-                        PointerTarget::leave(w, seat, data, event.serial, event.time)
+                        PointerTarget::leave(w, seat, data, event.serial, event.time.as_millis_u32())
                     }
                     #[cfg(feature = "xwayland")]
-                    WindowElement::X11(w) => PointerTarget::leave(w, seat, data, event.serial, event.time),
+                    WindowElement::X11(w) => {
+                        PointerTarget::leave(w, seat, data, event.serial, event.time.as_millis_u32())
+                    }
                 };
                 state.ptr_entered_window = false;
                 state.header_bar.pointer_enter(event.location);
@@ -272,6 +277,36 @@ impl<Backend: crate::state::Backend> PointerTarget<AnvilState<Backend>> for Wind
             }
         }
     }
+    fn gesture_swipe(
+        &self,
+        seat: &Seat<AnvilState<Backend>>,
+        data: &mut AnvilState<Backend>,
+        event: &GestureSwipeEvent,
+    ) {
+        let state = self.decoration_state();
+        if !state.is_ssd || state.ptr_entered_window {
+            match self {
+                WindowElement::Wayland(w) => PointerTarget::gesture_swipe(w, seat, data, event),
+                #[cfg(feature = "xwayland")]
+                WindowElement::X11(w) => PointerTarget::gesture_swipe(w, seat, data, event),
+            }
+        }
+    }
+    fn gesture_pinch(
+        &self,
+        seat: &Seat<AnvilState<Backend>>,
+        data: &mut AnvilState<Backend>,
+        event: &GesturePinchEvent,
+    ) {
+        let state = self.decoration_state();
+        if !state.is_ssd || state.ptr_entered_window {
+            match self {
+                WindowElement::Wayland(w) => PointerTarget::gesture_pinch(w, seat, data, event),
+                #[cfg(feature = "xwayland")]
+                WindowElement::X11(w) => PointerTarget::gesture_pinch(w, seat, data, event),
+            }
+        }
+    }
     fn leave(
         &self,
         seat: &Seat<AnvilState<Backend>>,
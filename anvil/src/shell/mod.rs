@@ -256,9 +256,10 @@ fn place_new_window(space: &mut Space<WindowElement>, window: &WindowElement, ac
 
     let output = space.outputs().next().cloned();
     let output_geometry = output
+        .as_ref()
         .and_then(|o| {
-            let geo = space.output_geometry(&o)?;
-            let map = layer_map_for_output(&o);
+            let geo = space.output_geometry(o)?;
+            let map = layer_map_for_output(o);
             let zone = map.non_exclusive_zone();
             Some(Rectangle::from_loc_and_size(geo.loc + zone.loc, zone.size))
         })
@@ -272,6 +273,12 @@ fn place_new_window(space: &mut Space<WindowElement>, window: &WindowElement, ac
     let x = x_range.sample(&mut rng);
     let y = y_range.sample(&mut rng);
 
+    // tell the client how large it may get without overlapping any exclusive zones
+    if let (Some(output), WindowElement::Wayland(window)) = (output.as_ref(), window) {
+        let zone = layer_map_for_output(output).non_exclusive_zone();
+        window.toplevel().send_configure_bounds(zone.size);
+    }
+
     space.map_element(window.clone(), (x, y), activate);
 }
 
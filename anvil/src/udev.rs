@@ -34,7 +34,7 @@ use smithay::{
 use smithay::{
     backend::{
         drm::{
-            DrmDevice, DrmDeviceFd, DrmError, DrmEvent, DrmEventMetadata, DrmNode, GbmBufferedSurface,
+            DrmDevice, DrmDeviceFd, DrmError, DrmEvent, DrmEventMetadata, DrmNode, Edid, GbmBufferedSurface,
             NodeType,
         },
         egl::{EGLContext, EGLDevice, EGLDisplay},
@@ -499,16 +499,19 @@ fn scan_connectors(
             let output_name = format!("{}-{}", interface_short_name, connector_info.interface_id());
 
             let (phys_w, phys_h) = connector_info.size().unwrap_or((0, 0));
-            let output = Output::new(
-                output_name,
-                PhysicalProperties {
+            let physical = device
+                .edid(connector_info.handle())
+                .ok()
+                .flatten()
+                .and_then(|data| Edid::parse(&data))
+                .map(|edid| edid.physical_properties((phys_w, phys_h), Subpixel::Unknown))
+                .unwrap_or_else(|| PhysicalProperties {
                     size: (phys_w as i32, phys_h as i32).into(),
                     subpixel: Subpixel::Unknown,
                     make: "Smithay".into(),
                     model: "Generic DRM".into(),
-                },
-                None,
-            );
+                });
+            let output = Output::new(output_name, physical, None);
             let global = output.create_global::<AnvilState<UdevData>>(&display.handle());
             let position = (
                 space
@@ -612,6 +615,8 @@ impl AnvilState<UdevData> {
                     DrmEvent::Error(error) => {
                         error!(data.state.log, "{:?}", error);
                     }
+                    // connector changes are handled through the udev backend
+                    DrmEvent::ConnectorAdded(_) | DrmEvent::ConnectorRemoved(_) => {}
                 },
             );
         let registration_token = self.handle.register_dispatcher(event_dispatcher.clone()).unwrap();
@@ -1122,7 +1127,9 @@ fn render_surface<'a>(
     )
     .map(|(damage, states)| (damage.is_some(), states))
     .map_err(|err| match err {
-        DamageTrackedRendererError::Rendering(err) => SwapBuffersError::from(err),
+        DamageTrackedRendererError::Rendering(err) | DamageTrackedRendererError::ContextLost(err) => {
+            SwapBuffersError::from(err)
+        }
         _ => unreachable!(),
     })?;
 
@@ -23,6 +23,7 @@ use smithay::{
             damage::{DamageTrackedRenderer, DamageTrackedRendererError},
             element::AsRenderElements,
             gles2::{Gles2Renderer, Gles2Texture},
+            ImportMemWl,
         },
         winit::{self, WinitEvent, WinitGraphicsBackend},
         SwapBuffersError,
@@ -112,6 +113,7 @@ pub fn run_winit(log: Logger) {
     let _global = output.create_global::<AnvilState<WinitData>>(&display.handle());
     output.change_current_state(Some(mode), Some(Transform::Flipped180), None, Some((0, 0).into()));
     output.set_preferred(mode);
+    winit.set_output(Some(output.clone()));
 
     #[cfg(feature = "debug")]
     let fps_image =
@@ -160,6 +162,9 @@ pub fn run_winit(log: Logger) {
     };
     let mut state = AnvilState::init(&mut display, event_loop.handle(), data, log.clone(), true);
     state.space.map_output(&output, (0, 0));
+    for format in state.backend_data.backend.renderer().shm_formats() {
+        state.shm_state.add_format(*format);
+    }
 
     #[cfg(feature = "xwayland")]
     if let Err(e) = state.xwayland.start(
@@ -179,15 +184,13 @@ pub fn run_winit(log: Logger) {
         if winit
             .dispatch_new_events(|event| match event {
                 WinitEvent::Resized { size, .. } => {
-                    // We only have one output
+                    // We only have one output, its current mode is updated by the backend
                     let output = state.space.outputs().next().unwrap().clone();
                     state.space.map_output(&output, (0, 0));
-                    let mode = Mode {
+                    output.set_preferred(Mode {
                         size,
                         refresh: 60_000,
-                    };
-                    output.change_current_state(Some(mode), None, None, None);
-                    output.set_preferred(mode);
+                    });
                     crate::shell::fixup_positions(&mut state.space);
                 }
                 WinitEvent::Input(event) => {
@@ -305,7 +308,8 @@ pub fn run_winit(log: Logger) {
                     &log,
                 )
                 .map_err(|err| match err {
-                    DamageTrackedRendererError::Rendering(err) => err.into(),
+                    DamageTrackedRendererError::Rendering(err)
+                    | DamageTrackedRendererError::ContextLost(err) => err.into(),
                     _ => unreachable!(),
                 })
             });
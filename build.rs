@@ -42,6 +42,9 @@ fn gl_generate() {
                 "EGL_EXT_buffer_age",
                 "EGL_EXT_swap_buffers_with_damage",
                 "EGL_KHR_swap_buffers_with_damage",
+                "EGL_KHR_fence_sync",
+                "EGL_KHR_wait_sync",
+                "EGL_ANDROID_native_fence_sync",
             ],
         )
         .write_bindings(gl_generator::GlobalGenerator, &mut file)
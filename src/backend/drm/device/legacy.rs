@@ -7,7 +7,7 @@ use std::sync::{
 use drm::control::{connector, crtc, Device as ControlDevice};
 
 use super::DrmDeviceFd;
-use crate::backend::drm::error::Error;
+use crate::backend::drm::{error::Error, DpmsState};
 use crate::utils::DevPath;
 
 use slog::{error, info, o};
@@ -139,6 +139,21 @@ pub fn set_connector_state<D>(
     connectors: impl Iterator<Item = connector::Handle>,
     enabled: bool,
 ) -> Result<(), Error>
+where
+    D: DevPath + ControlDevice,
+{
+    set_connector_dpms(
+        dev,
+        connectors,
+        if enabled { DpmsState::On } else { DpmsState::Off },
+    )
+}
+
+pub fn set_connector_dpms<D>(
+    dev: &D,
+    connectors: impl Iterator<Item = connector::Handle>,
+    state: DpmsState,
+) -> Result<(), Error>
 where
     D: DevPath + ControlDevice,
 {
@@ -169,20 +184,12 @@ where
                 // to find out, if we got the handle of the "DPMS" property ...
                 if info.name().to_str().map(|x| x == "DPMS").unwrap_or(false) {
                     // so we can use that to turn on / off the connector
-                    dev.set_property(
-                        conn,
-                        *handle,
-                        if enabled {
-                            0 /*DRM_MODE_DPMS_ON*/
-                        } else {
-                            3 /*DRM_MODE_DPMS_OFF*/
-                        },
-                    )
-                    .map_err(|source| Error::Access {
-                        errmsg: "Failed to set property of connector",
-                        dev: dev.dev_path(),
-                        source,
-                    })?;
+                    dev.set_property(conn, *handle, state as u64)
+                        .map_err(|source| Error::Access {
+                            errmsg: "Failed to set property of connector",
+                            dev: dev.dev_path(),
+                            source,
+                        })?;
                 }
             }
         }
@@ -1,3 +1,7 @@
+#[cfg(feature = "backend_udev")]
+use std::collections::HashSet;
+#[cfg(feature = "backend_udev")]
+use std::fmt;
 use std::io;
 use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd};
 use std::sync::atomic::Ordering;
@@ -5,7 +9,10 @@ use std::sync::{atomic::AtomicBool, Arc};
 use std::time::{Duration, SystemTime};
 
 use calloop::{EventSource, Interest, Poll, PostAction, Readiness, Token, TokenFactory};
-use drm::control::{connector, crtc, Device as ControlDevice, Event, Mode, ResourceHandles};
+use drm::control::{
+    atomic::AtomicModeReq, connector, crtc, property, AtomicCommitFlags, Device as ControlDevice, Event,
+    Mode, ResourceHandles,
+};
 use drm::{ClientCapability, Device as BasicDevice, DriverCapability};
 use nix::libc::dev_t;
 
@@ -16,10 +23,12 @@ pub(super) mod legacy;
 use crate::utils::{DevPath, Physical, Size};
 
 use super::surface::{atomic::AtomicDrmSurface, legacy::LegacyDrmSurface, DrmSurface, DrmSurfaceInternal};
-use super::{error::Error, planes, Planes};
+use super::{error::Error, output::ColorSpace, planes, Planes};
 use atomic::AtomicDrmDevice;
 use legacy::LegacyDrmDevice;
 
+#[cfg(feature = "backend_udev")]
+use slog::{debug, warn};
 use slog::{info, o, trace};
 
 /// An open drm device
@@ -33,6 +42,28 @@ pub struct DrmDevice {
     resources: ResourceHandles,
     pub(super) logger: ::slog::Logger,
     token: Option<Token>,
+    #[cfg(feature = "backend_udev")]
+    hotplug: Option<HotplugMonitor>,
+}
+
+#[cfg(feature = "backend_udev")]
+struct HotplugMonitor {
+    monitor: udev::MonitorSocket,
+    token: Option<Token>,
+    connected: HashSet<connector::Handle>,
+}
+
+// MonitorSocket does not implement debug, so we have to impl Debug manually
+#[cfg(feature = "backend_udev")]
+impl fmt::Debug for HotplugMonitor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use udev::AsRaw;
+        f.debug_struct("HotplugMonitor")
+            .field("monitor", &format!("MonitorSocket ({:?})", self.monitor.as_raw()))
+            .field("token", &self.token)
+            .field("connected", &self.connected)
+            .finish()
+    }
 }
 
 impl AsFd for DrmDevice {
@@ -130,9 +161,49 @@ impl DrmDevice {
             resources,
             logger: log,
             token: None,
+            #[cfg(feature = "backend_udev")]
+            hotplug: None,
         })
     }
 
+    /// Monitor udev for connectors of this device being connected or disconnected
+    ///
+    /// Once enabled, the device emits [`DrmEvent::ConnectorAdded`] and [`DrmEvent::ConnectorRemoved`]
+    /// events, whenever the kernel reports a hotplug event for this device and the connection state
+    /// of a connector changed.
+    ///
+    /// This needs to be called before the device is inserted into an event loop, otherwise the
+    /// event source has to be updated through [`LoopHandle::update`](calloop::LoopHandle::update)
+    /// afterwards. Calling this multiple times has no effect.
+    #[cfg(feature = "backend_udev")]
+    pub fn enable_udev_hotplug(&mut self) -> io::Result<()> {
+        if self.hotplug.is_some() {
+            return Ok(());
+        }
+        let monitor = udev::MonitorBuilder::new()?.match_subsystem("drm")?.listen()?;
+        let connected = self.connected_connectors();
+        self.hotplug = Some(HotplugMonitor {
+            monitor,
+            token: None,
+            connected,
+        });
+        Ok(())
+    }
+
+    #[cfg(feature = "backend_udev")]
+    fn connected_connectors(&self) -> HashSet<connector::Handle> {
+        self.resources
+            .connectors()
+            .iter()
+            .copied()
+            .filter(|conn| {
+                self.get_connector(*conn, true)
+                    .map(|info| info.state() == connector::State::Connected)
+                    .unwrap_or(false)
+            })
+            .collect()
+    }
+
     fn create_internal(
         fd: DrmDeviceFd,
         active: Arc<AtomicBool>,
@@ -177,6 +248,188 @@ impl DrmDevice {
         planes(self, crtc, self.has_universal_planes)
     }
 
+    /// Returns all properties of a given crtc with their current values
+    ///
+    /// Besides the standard properties used by smithay, drivers may expose vendor-specific
+    /// ones, e.g. for content protection or panel self-refresh.
+    pub fn crtc_properties(&self, crtc: crtc::Handle) -> Result<Vec<DrmProperty>, Error> {
+        let props = self.get_properties(crtc).map_err(|source| Error::Access {
+            errmsg: "Failed to get properties of crtc",
+            dev: self.dev_path(),
+            source,
+        })?;
+        let (handles, values) = props.as_props_and_values();
+        handles
+            .iter()
+            .zip(values.iter())
+            .map(|(&handle, &value)| {
+                let info = self.get_property(handle).map_err(|source| Error::Access {
+                    errmsg: "Failed to get property info",
+                    dev: self.dev_path(),
+                    source,
+                })?;
+                Ok(DrmProperty {
+                    handle,
+                    name: info.name().to_string_lossy().into_owned(),
+                    value,
+                })
+            })
+            .collect()
+    }
+
+    /// Reads the raw EDID of the monitor connected to a given connector
+    ///
+    /// Returns `None` if the connector does not provide an EDID, e.g. because nothing is connected.
+    /// The data can be parsed with [`Edid`](super::output::Edid).
+    pub fn edid(&self, connector: connector::Handle) -> Result<Option<Vec<u8>>, Error> {
+        let props = self.get_properties(connector).map_err(|source| Error::Access {
+            errmsg: "Failed to get properties of connector",
+            dev: self.dev_path(),
+            source,
+        })?;
+        let (handles, values) = props.as_props_and_values();
+        for (&handle, &value) in handles.iter().zip(values.iter()) {
+            let info = self.get_property(handle).map_err(|source| Error::Access {
+                errmsg: "Failed to get property info",
+                dev: self.dev_path(),
+                source,
+            })?;
+            if info.name().to_bytes() != b"EDID" {
+                continue;
+            }
+
+            return match info.value_type().convert_value(value) {
+                property::Value::Blob(0) => Ok(None),
+                property::Value::Blob(blob) => {
+                    let data = self.get_property_blob(blob).map_err(|source| Error::Access {
+                        errmsg: "Failed to query EDID blob",
+                        dev: self.dev_path(),
+                        source,
+                    })?;
+                    Ok(Some(data))
+                }
+                _ => Ok(None),
+            };
+        }
+
+        Ok(None)
+    }
+
+    /// Sets the color space of the signal sent through a given connector
+    ///
+    /// This changes the `Colorspace` property of the connector, which tells the monitor how
+    /// to interpret the transmitted colors. The content is not converted, rendering in the
+    /// selected color space is up to the compositor. Changing the color space might require a modeset.
+    ///
+    /// Returns [`Error::PropertyNotFound`] if the connector does not support the given color space.
+    pub fn set_color_space(
+        &self,
+        connector: connector::Handle,
+        color_space: ColorSpace,
+    ) -> Result<(), Error> {
+        if !self.is_active() {
+            return Err(Error::DeviceInactive);
+        }
+
+        let props = self.get_properties(connector).map_err(|source| Error::Access {
+            errmsg: "Failed to get properties of connector",
+            dev: self.dev_path(),
+            source,
+        })?;
+        let (handles, _) = props.as_props_and_values();
+        let mut property = None;
+        for &handle in handles {
+            let info = self.get_property(handle).map_err(|source| Error::Access {
+                errmsg: "Failed to get property info",
+                dev: self.dev_path(),
+                source,
+            })?;
+            if info.name().to_bytes() != b"Colorspace" {
+                continue;
+            }
+            if let property::ValueType::Enum(values) = info.value_type() {
+                let (raw_values, enum_values) = values.values();
+                property = raw_values
+                    .iter()
+                    .zip(enum_values.iter())
+                    .find(|(_, value)| value.name().to_bytes() == color_space.drm_name().as_bytes())
+                    .map(|(raw, _)| (handle, *raw));
+            }
+            break;
+        }
+        let (handle, value) = property.ok_or_else(|| Error::PropertyNotFound {
+            handle: connector.into(),
+            name: format!("Colorspace ({})", color_space.drm_name()),
+        })?;
+
+        match &*self.internal {
+            DrmDeviceInternal::Atomic(dev) => {
+                let mut req = AtomicModeReq::new();
+                req.add_property(connector, handle, property::Value::Unknown(value));
+                dev.fd
+                    .atomic_commit(AtomicCommitFlags::ALLOW_MODESET, req)
+                    .map_err(|source| Error::Access {
+                        errmsg: "Failed to commit connector color space",
+                        dev: self.dev_path(),
+                        source,
+                    })
+            }
+            DrmDeviceInternal::Legacy(dev) => {
+                dev.fd
+                    .set_property(connector, handle, value)
+                    .map_err(|source| Error::Access {
+                        errmsg: "Failed to set connector color space",
+                        dev: self.dev_path(),
+                        source,
+                    })
+            }
+        }
+    }
+
+    /// Sets a property of a given crtc by name
+    ///
+    /// On atomic devices the property is changed with a separate commit, which might fail,
+    /// if a page flip is currently pending on the crtc. Properties requiring a modeset
+    /// should rather be changed through the [`DrmSurface`].
+    pub fn set_crtc_property(&self, crtc: crtc::Handle, prop_name: &str, value: u64) -> Result<(), Error> {
+        if !self.is_active() {
+            return Err(Error::DeviceInactive);
+        }
+
+        let handle = self
+            .crtc_properties(crtc)?
+            .into_iter()
+            .find(|prop| prop.name == prop_name)
+            .map(|prop| prop.handle)
+            .ok_or_else(|| Error::PropertyNotFound {
+                handle: crtc.into(),
+                name: prop_name.to_owned(),
+            })?;
+
+        match &*self.internal {
+            DrmDeviceInternal::Atomic(dev) => {
+                let mut req = AtomicModeReq::new();
+                req.add_property(crtc, handle, property::Value::Unknown(value));
+                dev.fd
+                    .atomic_commit(AtomicCommitFlags::empty(), req)
+                    .map_err(|source| Error::Access {
+                        errmsg: "Failed to commit crtc property",
+                        dev: self.dev_path(),
+                        source,
+                    })
+            }
+            DrmDeviceInternal::Legacy(dev) => {
+                dev.fd
+                    .set_property(crtc, handle, value)
+                    .map_err(|source| Error::Access {
+                        errmsg: "Failed to set crtc property",
+                        dev: self.dev_path(),
+                        source,
+                    })
+            }
+        }
+    }
+
     /// Returns the size of the hardware cursor
     ///
     /// Note: In case of universal planes this is the
@@ -319,6 +572,17 @@ impl DrmDevice {
     }
 }
 
+/// A property of a drm object together with its current value
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DrmProperty {
+    /// Handle of the property
+    pub handle: property::Handle,
+    /// Name of the property
+    pub name: String,
+    /// Raw value of the property
+    pub value: u64,
+}
+
 /// Events that can be generated by a DrmDevice
 #[derive(Debug)]
 pub enum DrmEvent {
@@ -326,6 +590,10 @@ pub enum DrmEvent {
     VBlank(crtc::Handle),
     /// An error happened while processing events
     Error(Error),
+    /// A connector was connected, see [`DrmDevice::enable_udev_hotplug`]
+    ConnectorAdded(connector::Handle),
+    /// A connector was disconnected, see [`DrmDevice::enable_udev_hotplug`]
+    ConnectorRemoved(connector::Handle),
 }
 
 /// Timing metadata for page-flip events
@@ -356,6 +624,41 @@ impl EventSource for DrmDevice {
     where
         F: FnMut(Self::Event, &mut Self::Metadata) -> Self::Ret,
     {
+        #[cfg(feature = "backend_udev")]
+        if let Some(hotplug) = self
+            .hotplug
+            .as_ref()
+            .filter(|hotplug| hotplug.token == Some(token))
+        {
+            // drain all pending events, even if we already know about a hotplug event
+            let hotplug_event = hotplug
+                .monitor
+                .iter()
+                .filter(|event| {
+                    event.event_type() == udev::EventType::Change
+                        && event.devnum() == Some(self.dev_id)
+                        && event
+                            .property_value("HOTPLUG")
+                            .map(|value| value == "1")
+                            .unwrap_or(false)
+                })
+                .count()
+                > 0;
+            if hotplug_event {
+                let connected = self.connected_connectors();
+                let previous = std::mem::replace(&mut self.hotplug.as_mut().unwrap().connected, connected);
+                let current = &self.hotplug.as_ref().unwrap().connected;
+                for conn in previous.difference(current) {
+                    debug!(self.logger, "Connector {:?} disconnected", conn);
+                    callback(DrmEvent::ConnectorRemoved(*conn), &mut None);
+                }
+                for conn in current.difference(&previous) {
+                    debug!(self.logger, "Connector {:?} connected", conn);
+                    callback(DrmEvent::ConnectorAdded(*conn), &mut None);
+                }
+            }
+            return Ok(PostAction::Continue);
+        }
         if Some(token) != self.token {
             return Ok(PostAction::Continue);
         }
@@ -403,7 +706,18 @@ impl EventSource for DrmDevice {
             Interest::READ,
             calloop::Mode::Level,
             self.token.unwrap(),
-        )
+        )?;
+        #[cfg(feature = "backend_udev")]
+        if let Some(hotplug) = self.hotplug.as_mut() {
+            hotplug.token = Some(factory.token());
+            poll.register(
+                hotplug.monitor.as_raw_fd(),
+                Interest::READ,
+                calloop::Mode::Level,
+                hotplug.token.unwrap(),
+            )?;
+        }
+        Ok(())
     }
 
     fn reregister(&mut self, poll: &mut Poll, factory: &mut TokenFactory) -> calloop::Result<()> {
@@ -413,11 +727,41 @@ impl EventSource for DrmDevice {
             Interest::READ,
             calloop::Mode::Level,
             self.token.unwrap(),
-        )
+        )?;
+        #[cfg(feature = "backend_udev")]
+        if let Some(hotplug) = self.hotplug.as_mut() {
+            // the monitor might have been enabled after the device was registered initially
+            let registered = hotplug.token.is_some();
+            hotplug.token = Some(factory.token());
+            if registered {
+                poll.reregister(
+                    hotplug.monitor.as_raw_fd(),
+                    Interest::READ,
+                    calloop::Mode::Level,
+                    hotplug.token.unwrap(),
+                )?;
+            } else {
+                poll.register(
+                    hotplug.monitor.as_raw_fd(),
+                    Interest::READ,
+                    calloop::Mode::Level,
+                    hotplug.token.unwrap(),
+                )?;
+            }
+        }
+        Ok(())
     }
 
     fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
         self.token = None;
+        #[cfg(feature = "backend_udev")]
+        if let Some(hotplug) = self.hotplug.as_mut() {
+            if hotplug.token.take().is_some() {
+                if let Err(err) = poll.unregister(hotplug.monitor.as_raw_fd()) {
+                    warn!(self.logger, "Failed to unregister udev monitor: {}", err);
+                }
+            }
+        }
         poll.unregister(self.as_fd().as_raw_fd())
     }
 }
@@ -56,6 +56,14 @@ pub enum Error {
         /// Property name
         name: &'static str,
     },
+    /// The requested property does not exist for the given handle
+    #[error("The property '{name}' does not exist for handle ({handle:?})")]
+    PropertyNotFound {
+        /// Handle of the drm object
+        handle: RawResourceHandle,
+        /// Property name
+        name: String,
+    },
     /// Atomic Test failed for new properties
     #[error("Atomic Test failed for new properties on crtc ({0:?})")]
     TestFailed(crtc::Handle),
@@ -72,16 +72,20 @@
 pub(crate) mod device;
 pub(self) mod error;
 pub mod node;
+pub mod output;
 
 pub(self) mod surface;
 
 use crate::utils::DevPath;
-pub use device::{DrmDevice, DrmDeviceFd, DrmEvent, EventMetadata as DrmEventMetadata, Time as DrmEventTime};
+pub use device::{
+    DrmDevice, DrmDeviceFd, DrmEvent, DrmProperty, EventMetadata as DrmEventMetadata, Time as DrmEventTime,
+};
 pub use error::Error as DrmError;
 pub use node::{CreateDrmNodeError, DrmNode, NodeType};
+pub use output::{ColorPrimaries, ColorSpace, Edid};
 #[cfg(feature = "backend_gbm")]
 pub use surface::gbm::{Error as GbmBufferedSurfaceError, GbmBufferedSurface};
-pub use surface::DrmSurface;
+pub use surface::{DpmsState, DrmSurface, PlaneConfig, PlaneState};
 
 use drm::control::{crtc, plane, Device as ControlDevice, PlaneType};
 
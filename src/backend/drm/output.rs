@@ -0,0 +1,338 @@
+//! Output related helpers for drm connectors
+//!
+//! Monitors describe themselves through their EDID (Extended Display Identification Data),
+//! which can be read from a connector using [`DrmDevice::edid`](super::DrmDevice::edid).
+//! [`Edid`] extracts the commonly needed information from the raw EDID bytes,
+//! like the manufacturer, monitor name and serial number, for example to fill
+//! the make and model of a `wl_output`.
+//!
+//! Only the base block of the EDID is parsed, extension blocks (like CEA-861) are ignored.
+
+use drm::control::Mode;
+
+use crate::output::{PhysicalProperties, Subpixel};
+
+const HEADER: [u8; 8] = [0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00];
+const BLOCK_SIZE: usize = 128;
+const DESCRIPTORS_OFFSET: usize = 54;
+const DESCRIPTOR_SIZE: usize = 18;
+
+const DESCRIPTOR_SERIAL_NUMBER: u8 = 0xFF;
+const DESCRIPTOR_MONITOR_NAME: u8 = 0xFC;
+
+const DRM_MODE_TYPE_PREFERRED: u32 = 1 << 3;
+const DRM_MODE_TYPE_DRIVER: u32 = 1 << 6;
+const DRM_MODE_FLAG_PHSYNC: u32 = 1 << 0;
+const DRM_MODE_FLAG_NHSYNC: u32 = 1 << 1;
+const DRM_MODE_FLAG_PVSYNC: u32 = 1 << 2;
+const DRM_MODE_FLAG_NVSYNC: u32 = 1 << 3;
+const DRM_MODE_FLAG_INTERLACE: u32 = 1 << 4;
+
+/// Color space of the signal sent to a monitor
+///
+/// See [`DrmDevice::set_color_space`](super::DrmDevice::set_color_space).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorSpace {
+    /// The default color space of the monitor, usually sRGB
+    Srgb,
+    /// ITU-R BT.2020 with RGB encoding
+    Rec2020,
+    /// DCI-P3 with a D65 white point and RGB encoding
+    DciP3,
+}
+
+impl ColorSpace {
+    /// Name of the matching value of the `Colorspace` connector property
+    pub(crate) fn drm_name(&self) -> &'static str {
+        match self {
+            ColorSpace::Srgb => "Default",
+            ColorSpace::Rec2020 => "BT2020_RGB",
+            ColorSpace::DciP3 => "DCI-P3_RGB_D65",
+        }
+    }
+}
+
+/// Chromaticity coordinates of a monitor
+///
+/// All coordinates are given in the CIE 1931 xy color space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ColorPrimaries {
+    /// x and y coordinates of the red primary
+    pub red: (f64, f64),
+    /// x and y coordinates of the green primary
+    pub green: (f64, f64),
+    /// x and y coordinates of the blue primary
+    pub blue: (f64, f64),
+    /// x and y coordinates of the white point
+    pub white: (f64, f64),
+}
+
+/// Information parsed from the EDID of a monitor
+#[derive(Debug, Clone)]
+pub struct Edid {
+    manufacturer_id: String,
+    monitor_name: Option<String>,
+    serial_number: Option<String>,
+    preferred_timing: Option<Mode>,
+    color_primaries: Option<ColorPrimaries>,
+}
+
+impl Edid {
+    /// Parse the raw bytes of an EDID
+    ///
+    /// Returns `None` if the data is not a valid EDID base block.
+    pub fn parse(data: &[u8]) -> Option<Edid> {
+        if data.len() < BLOCK_SIZE || data[..8] != HEADER {
+            return None;
+        }
+        let block = &data[..BLOCK_SIZE];
+        if block.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)) != 0 {
+            return None;
+        }
+
+        let mut monitor_name = None;
+        let mut serial_number = None;
+        let mut preferred_timing = None;
+        for descriptor in
+            block[DESCRIPTORS_OFFSET..DESCRIPTORS_OFFSET + 4 * DESCRIPTOR_SIZE].chunks_exact(DESCRIPTOR_SIZE)
+        {
+            if descriptor[0] != 0 || descriptor[1] != 0 {
+                // the first detailed timing descriptor is the preferred one
+                if preferred_timing.is_none() {
+                    preferred_timing = Some(parse_detailed_timing(descriptor));
+                }
+                continue;
+            }
+
+            match descriptor[3] {
+                DESCRIPTOR_MONITOR_NAME => monitor_name = parse_descriptor_string(descriptor),
+                DESCRIPTOR_SERIAL_NUMBER => serial_number = parse_descriptor_string(descriptor),
+                _ => {}
+            }
+        }
+
+        // fall back to the numeric serial number of the vendor block
+        if serial_number.is_none() {
+            let serial = u32::from_le_bytes([block[12], block[13], block[14], block[15]]);
+            if serial != 0 {
+                serial_number = Some(serial.to_string());
+            }
+        }
+
+        Some(Edid {
+            manufacturer_id: parse_manufacturer_id(block[8], block[9]),
+            monitor_name,
+            serial_number,
+            preferred_timing,
+            color_primaries: parse_color_primaries(&block[25..35]),
+        })
+    }
+
+    /// Three letter PNP id of the manufacturer
+    pub fn manufacturer_id(&self) -> &str {
+        &self.manufacturer_id
+    }
+
+    /// Name of the monitor model, if provided
+    pub fn monitor_name(&self) -> Option<&str> {
+        self.monitor_name.as_deref()
+    }
+
+    /// Serial number of the monitor, if provided
+    pub fn serial_number(&self) -> Option<&str> {
+        self.serial_number.as_deref()
+    }
+
+    /// Preferred mode of the monitor, if provided
+    pub fn preferred_timing(&self) -> Option<Mode> {
+        self.preferred_timing
+    }
+
+    /// Color primaries of the monitor, if provided
+    pub fn color_primaries(&self) -> Option<ColorPrimaries> {
+        self.color_primaries
+    }
+
+    /// Physical properties of an output showing this monitor
+    ///
+    /// The make is set to the manufacturer id and the model to the monitor name,
+    /// or `"Unknown"` if the EDID does not provide one. The size in millimeters
+    /// (e.g. from [`connector::Info::size`](drm::control::connector::Info::size)) and
+    /// subpixel layout are not part of the EDID base block and have to be provided.
+    pub fn physical_properties(&self, size_mm: (u32, u32), subpixel: Subpixel) -> PhysicalProperties {
+        PhysicalProperties {
+            size: (size_mm.0 as i32, size_mm.1 as i32).into(),
+            subpixel,
+            make: self.manufacturer_id.clone(),
+            model: self.monitor_name.clone().unwrap_or_else(|| "Unknown".into()),
+        }
+    }
+}
+
+fn parse_manufacturer_id(high: u8, low: u8) -> String {
+    let id = u16::from_be_bytes([high, low]);
+    [10, 5, 0]
+        .iter()
+        .map(|shift| {
+            let letter = ((id >> shift) & 0x1F) as u8;
+            if (1..=26).contains(&letter) {
+                (b'A' + letter - 1) as char
+            } else {
+                '?'
+            }
+        })
+        .collect()
+}
+
+fn parse_descriptor_string(descriptor: &[u8]) -> Option<String> {
+    let text = &descriptor[5..DESCRIPTOR_SIZE];
+    let text = text.split(|byte| *byte == b'\n').next().unwrap_or(text);
+    let text = String::from_utf8_lossy(text).trim_end().to_string();
+    if text.is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+fn parse_color_primaries(data: &[u8]) -> Option<ColorPrimaries> {
+    if data.iter().all(|byte| *byte == 0) {
+        return None;
+    }
+
+    let coordinate = |high: u8, low_byte: u8, shift: u8| {
+        let value = ((high as u16) << 2) | ((low_byte >> shift) & 0x3) as u16;
+        value as f64 / 1024.0
+    };
+
+    Some(ColorPrimaries {
+        red: (coordinate(data[2], data[0], 6), coordinate(data[3], data[0], 4)),
+        green: (coordinate(data[4], data[0], 2), coordinate(data[5], data[0], 0)),
+        blue: (coordinate(data[6], data[1], 6), coordinate(data[7], data[1], 4)),
+        white: (coordinate(data[8], data[1], 2), coordinate(data[9], data[1], 0)),
+    })
+}
+
+fn parse_detailed_timing(descriptor: &[u8]) -> Mode {
+    let clock = u16::from_le_bytes([descriptor[0], descriptor[1]]) as u32 * 10;
+    let hactive = descriptor[2] as u16 | ((descriptor[4] as u16 >> 4) << 8);
+    let hblank = descriptor[3] as u16 | ((descriptor[4] as u16 & 0xF) << 8);
+    let vactive = descriptor[5] as u16 | ((descriptor[7] as u16 >> 4) << 8);
+    let vblank = descriptor[6] as u16 | ((descriptor[7] as u16 & 0xF) << 8);
+    let hsync_offset = descriptor[8] as u16 | (((descriptor[11] as u16 >> 6) & 0x3) << 8);
+    let hsync_width = descriptor[9] as u16 | (((descriptor[11] as u16 >> 4) & 0x3) << 8);
+    let vsync_offset = (descriptor[10] as u16 >> 4) | (((descriptor[11] as u16 >> 2) & 0x3) << 4);
+    let vsync_width = (descriptor[10] as u16 & 0xF) | ((descriptor[11] as u16 & 0x3) << 4);
+    let features = descriptor[17];
+
+    let htotal = hactive + hblank;
+    let vtotal = vactive + vblank;
+
+    let mut flags = 0;
+    if features & 0x80 != 0 {
+        flags |= DRM_MODE_FLAG_INTERLACE;
+    }
+    // digital separate sync carries the sync polarities
+    if (features >> 3) & 0x3 == 0x3 {
+        flags |= if features & 0x4 != 0 {
+            DRM_MODE_FLAG_PVSYNC
+        } else {
+            DRM_MODE_FLAG_NVSYNC
+        };
+        flags |= if features & 0x2 != 0 {
+            DRM_MODE_FLAG_PHSYNC
+        } else {
+            DRM_MODE_FLAG_NHSYNC
+        };
+    }
+
+    let vrefresh = if htotal != 0 && vtotal != 0 {
+        let pixels = htotal as u64 * vtotal as u64;
+        ((clock as u64 * 1000 + pixels / 2) / pixels) as u32
+    } else {
+        0
+    };
+
+    // SAFETY: drm_mode_modeinfo is a plain C struct, for which all zeros is a valid value
+    let mut info: drm_ffi::drm_mode_modeinfo = unsafe { std::mem::zeroed() };
+    info.clock = clock;
+    info.hdisplay = hactive;
+    info.hsync_start = hactive + hsync_offset;
+    info.hsync_end = hactive + hsync_offset + hsync_width;
+    info.htotal = htotal;
+    info.vdisplay = vactive;
+    info.vsync_start = vactive + vsync_offset;
+    info.vsync_end = vactive + vsync_offset + vsync_width;
+    info.vtotal = vtotal;
+    info.vrefresh = vrefresh;
+    info.flags = flags;
+    info.type_ = DRM_MODE_TYPE_PREFERRED | DRM_MODE_TYPE_DRIVER;
+    let name = format!("{}x{}", hactive, vactive);
+    for (dst, src) in info.name.iter_mut().zip(name.bytes().take(31)) {
+        *dst = src as _;
+    }
+
+    Mode::from(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Edid;
+    use crate::output::Subpixel;
+
+    fn edid() -> Vec<u8> {
+        let mut data = vec![0u8; 128];
+        data[..8].copy_from_slice(&super::HEADER);
+        // "DEL"
+        data[8] = 0x10;
+        data[9] = 0xAC;
+        data[12..16].copy_from_slice(&1234u32.to_le_bytes());
+        // sRGB primaries
+        data[25..35].copy_from_slice(&[0xEE, 0x91, 0xA3, 0x54, 0x4C, 0x99, 0x26, 0x0F, 0x50, 0x54]);
+        // 1920x1080@60, 148.5 MHz
+        data[54..72].copy_from_slice(&[
+            0x02, 0x3A, 0x80, 0x18, 0x71, 0x38, 0x2D, 0x40, 0x58, 0x2C, 0x45, 0x00, 0x00, 0x00, 0x00, 0x00,
+            0x00, 0x1E,
+        ]);
+        data[72..90].copy_from_slice(&[
+            0x00, 0x00, 0x00, 0xFC, 0x00, b'D', b'E', b'L', b'L', b' ', b'U', b'2', b'7', b'2', b'0', b'Q',
+            b'\n', b' ',
+        ]);
+        data[90..108].copy_from_slice(&[
+            0x00, 0x00, 0x00, 0xFF, 0x00, b'A', b'B', b'C', b'1', b'2', b'3', b'\n', b' ', b' ', b' ', b' ',
+            b' ', b' ',
+        ]);
+        let sum = data[..127].iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+        data[127] = 0u8.wrapping_sub(sum);
+        data
+    }
+
+    #[test]
+    fn parse_base_block() {
+        let edid = Edid::parse(&edid()).unwrap();
+        assert_eq!(edid.manufacturer_id(), "DEL");
+        assert_eq!(edid.monitor_name(), Some("DELL U2720Q"));
+        assert_eq!(edid.serial_number(), Some("ABC123"));
+
+        let mode = edid.preferred_timing().unwrap();
+        assert_eq!(mode.size(), (1920, 1080));
+        assert_eq!(mode.clock(), 148500);
+        assert_eq!(mode.vrefresh(), 60);
+
+        let primaries = edid.color_primaries().unwrap();
+        assert!((primaries.red.0 - 0.64).abs() < 0.001);
+        assert!((primaries.white.1 - 0.329).abs() < 0.001);
+
+        let physical = edid.physical_properties((600, 340), Subpixel::Unknown);
+        assert_eq!(physical.make, "DEL");
+        assert_eq!(physical.model, "DELL U2720Q");
+        assert_eq!(physical.size, (600, 340).into());
+    }
+
+    #[test]
+    fn reject_invalid_checksum() {
+        let mut data = edid();
+        data[127] = data[127].wrapping_add(1);
+        assert!(Edid::parse(&data).is_none());
+    }
+}
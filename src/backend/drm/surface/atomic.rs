@@ -17,12 +17,14 @@ use crate::{
             device::atomic::{map_props, Mapping},
             device::DrmDeviceInternal,
             error::Error,
-            plane_type,
+            plane_type, DpmsState,
         },
     },
     utils::DevPath,
 };
 
+use super::PlaneConfig;
+
 use slog::{debug, info, o, trace, warn};
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -732,6 +734,101 @@ impl AtomicDrmSurface {
         Ok(result)
     }
 
+    pub fn test_config(&self, config: &PlaneConfig) -> Result<(), Error> {
+        if !self.active.load(Ordering::SeqCst) {
+            return Err(Error::DeviceInactive);
+        }
+
+        let blob = self
+            .fd
+            .create_property_blob(&config.mode)
+            .map_err(|source| Error::Access {
+                errmsg: "Failed to create Property Blob for mode",
+                dev: self.fd.dev_path(),
+                source,
+            })?;
+
+        let current = self.state.read().unwrap();
+        let pending = self.pending.read().unwrap();
+
+        let mut removed = current.connectors.difference(&pending.connectors);
+        let mut added = pending.connectors.difference(&current.connectors);
+
+        let planes = config
+            .planes
+            .iter()
+            .map(|plane| PlaneInfo {
+                handle: plane.handle,
+                x: plane.position.0,
+                y: plane.position.1,
+                w: plane.size.0,
+                h: plane.size.1,
+            })
+            .collect::<Vec<_>>();
+        let framebuffers = std::iter::once((config.framebuffer, self.plane))
+            .chain(
+                config
+                    .planes
+                    .iter()
+                    .map(|plane| (plane.framebuffer, plane.handle)),
+            )
+            .collect::<Vec<_>>();
+
+        let result = self
+            .build_request(
+                &mut added,
+                &mut removed,
+                self.plane,
+                &planes,
+                Some(framebuffers.iter()),
+                Some(config.mode),
+                Some(blob),
+            )
+            .and_then(|req| {
+                self.fd
+                    .atomic_commit(
+                        AtomicCommitFlags::ALLOW_MODESET | AtomicCommitFlags::TEST_ONLY,
+                        req,
+                    )
+                    .map_err(|source| Error::Access {
+                        errmsg: "Atomic test commit was rejected",
+                        dev: self.fd.dev_path(),
+                        source,
+                    })
+            });
+
+        // the blob was only needed for the test
+        if let Err(err) = self.fd.destroy_property_blob(blob.into()) {
+            warn!(self.logger, "Failed to destroy mode property blob: {}", err);
+        }
+
+        result
+    }
+
+    pub fn set_power_state(&self, state: DpmsState) -> Result<(), Error> {
+        if !self.active.load(Ordering::SeqCst) {
+            return Err(Error::DeviceInactive);
+        }
+
+        let mut req = AtomicModeReq::new();
+        {
+            let prop_mapping = self.prop_mapping.read().unwrap();
+            req.add_property(
+                self.crtc,
+                crtc_prop_handle(&prop_mapping, self.crtc, "ACTIVE")?,
+                property::Value::Boolean(state == DpmsState::On),
+            );
+        }
+
+        self.fd
+            .atomic_commit(AtomicCommitFlags::ALLOW_MODESET, req)
+            .map_err(|source| Error::Access {
+                errmsg: "Failed to change the power state of the crtc",
+                dev: self.fd.dev_path(),
+                source,
+            })
+    }
+
     // If a mode is set a matching blob needs to be set (the inverse is not true)
     #[allow(clippy::too_many_arguments)]
     pub fn build_request<'a>(
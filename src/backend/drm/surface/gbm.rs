@@ -23,6 +23,7 @@ pub struct GbmBufferedSurface<A: Allocator<Buffer = BufferObject<()>> + 'static,
     pending_fb: Option<(Slot<BufferObject<()>>, U)>,
     queued_fb: Option<(Slot<BufferObject<()>>, U)>,
     next_fb: Option<Slot<BufferObject<()>>>,
+    max_queue_depth: Option<usize>,
     swapchain: Swapchain<A>,
     drm: Arc<DrmSurface>,
 }
@@ -77,6 +78,7 @@ where
                         pending_fb: None,
                         queued_fb: None,
                         next_fb: None,
+                        max_queue_depth: None,
                         swapchain,
                         drm,
                     });
@@ -236,7 +238,16 @@ where
     /// Otherwise the underlying swapchain will eventually run out of buffers.
     ///
     /// `user_data` can be used to attach some data to a specific buffer and later retrieved with [`GbmBufferedSurface::frame_submitted`]
+    ///
+    /// If a maximum queue depth was set with [`GbmBufferedSurface::set_max_queue_depth`] and is already reached,
+    /// [`Error::QueueFull`] is returned and the buffer stays the current one of [`GbmBufferedSurface::next_buffer`].
     pub fn queue_buffer(&mut self, user_data: U) -> Result<(), Error<A::Error>> {
+        if let Some(max_queue_depth) = self.max_queue_depth {
+            if self.queue_depth() >= max_queue_depth {
+                return Err(Error::QueueFull);
+            }
+        }
+
         self.queued_fb = self.next_fb.take().map(|fb| {
             self.swapchain.submitted(&fb);
             (fb, user_data)
@@ -267,6 +278,27 @@ where
         }
     }
 
+    /// Limits the number of frames waiting for scan-out.
+    ///
+    /// Frames are counted from [`GbmBufferedSurface::queue_buffer`] until the matching
+    /// [`GbmBufferedSurface::frame_submitted`], this includes the frame of a pending page flip.
+    /// Once `depth` frames are waiting, `queue_buffer` returns [`Error::QueueFull`] instead of
+    /// replacing the queued frame, so the compositor should stop rendering until the next vblank.
+    ///
+    /// A depth of `1` gives the lowest latency, while `2` allows rendering the next frame while
+    /// a page flip is still pending. Smaller values are treated as `1`, larger values do not
+    /// change anything, as at most one frame is queued besides the pending one.
+    ///
+    /// By default the queue depth is not limited and a queued frame is replaced by newer ones.
+    pub fn set_max_queue_depth(&mut self, depth: usize) {
+        self.max_queue_depth = Some(depth.max(1));
+    }
+
+    /// Returns the number of frames waiting for scan-out
+    pub fn queue_depth(&self) -> usize {
+        self.pending_fb.is_some() as usize + self.queued_fb.is_some() as usize
+    }
+
     fn submit(&mut self) -> Result<(), Error<A::Error>> {
         // yes it does not look like it, but both of these lines should be safe in all cases.
         let (slot, user_data) = self.queued_fb.take().unwrap();
@@ -457,6 +489,9 @@ pub enum Error<E: std::error::Error + Send + Sync + 'static> {
     /// The swapchain is exhausted, you need to call `frame_submitted`
     #[error("Failed to allocate a new buffer")]
     NoFreeSlotsError,
+    /// The maximum queue depth is reached, you need to wait for `frame_submitted`
+    #[error("Too many frames are waiting for scan-out")]
+    QueueFull,
     /// Failed to renderer using the given renderer
     #[error("Failed to render test frame")]
     InitialRenderingError,
@@ -478,7 +513,9 @@ impl<E: std::error::Error + Send + Sync + 'static> From<Error<E>> for SwapBuffer
             | x @ Error::NoSupportedRendererFormat
             | x @ Error::FormatsNotCompatible
             | x @ Error::InitialRenderingError => SwapBuffersError::ContextLost(Box::new(x)),
-            x @ Error::NoFreeSlotsError => SwapBuffersError::TemporaryFailure(Box::new(x)),
+            x @ Error::NoFreeSlotsError | x @ Error::QueueFull => {
+                SwapBuffersError::TemporaryFailure(Box::new(x))
+            }
             Error::DrmError(err) => err.into(),
             Error::GbmError(err) => SwapBuffersError::ContextLost(Box::new(err)),
             Error::AsDmabufError(err) => SwapBuffersError::ContextLost(Box::new(err)),
@@ -7,10 +7,17 @@ use std::sync::{
 };
 
 use crate::{
-    backend::drm::{device::legacy::set_connector_state, device::DrmDeviceInternal, error::Error},
+    backend::drm::{
+        device::legacy::{set_connector_dpms, set_connector_state},
+        device::DrmDeviceInternal,
+        error::Error,
+        DpmsState,
+    },
     utils::DevPath,
 };
 
+use super::PlaneConfig;
+
 use slog::{debug, info, o, trace};
 
 #[derive(Debug, PartialEq, Eq, Clone)]
@@ -348,6 +355,34 @@ impl LegacyDrmSurface {
             .is_ok())
     }
 
+    pub fn test_config(&self, config: &PlaneConfig) -> Result<(), Error> {
+        if !self.active.load(Ordering::SeqCst) {
+            return Err(Error::DeviceInactive);
+        }
+
+        if let Some(plane) = config.planes.first() {
+            return Err(Error::NonPrimaryPlane(plane.handle));
+        }
+
+        let pending = self.pending.read().unwrap();
+        for conn in pending.connectors.iter() {
+            if !self.check_connector(*conn, &config.mode)? {
+                return Err(Error::ModeNotSuitable(config.mode));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn set_power_state(&self, state: DpmsState) -> Result<(), Error> {
+        if !self.active.load(Ordering::SeqCst) {
+            return Err(Error::DeviceInactive);
+        }
+
+        let current = self.state.read().unwrap();
+        set_connector_dpms(&*self.fd, current.connectors.iter().copied(), state)
+    }
+
     // we use this function to verify, if a certain connector/mode combination
     // is valid on our crtc. We do this with the most basic information we have:
     // - is there a matching encoder
@@ -22,6 +22,45 @@ use legacy::LegacyDrmSurface;
 
 use slog::trace;
 
+/// Power state of the connectors of a [`DrmSurface`], see [`DrmSurface::set_power_state`]
+///
+/// The values match the ones of the `DPMS` connector property.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DpmsState {
+    /// The display is turned on
+    On = 0,
+    /// The display is in standby
+    Standby = 1,
+    /// The display is suspended
+    Suspend = 2,
+    /// The display is turned off
+    Off = 3,
+}
+
+/// A proposed configuration of a [`DrmSurface`], that can be checked with [`DrmSurface::test_config`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaneConfig {
+    /// The mode the crtc should use
+    pub mode: Mode,
+    /// Framebuffer to be displayed on the primary plane
+    pub framebuffer: framebuffer::Handle,
+    /// Additional *cursor* or *overlay* planes to be used
+    pub planes: Vec<PlaneState>,
+}
+
+/// State of a non-primary plane inside a [`PlaneConfig`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlaneState {
+    /// The plane to be used
+    pub handle: plane::Handle,
+    /// Framebuffer to be displayed on the plane
+    pub framebuffer: framebuffer::Handle,
+    /// The position of the plane
+    pub position: (i32, i32),
+    /// The size of the plane
+    pub size: (u32, u32),
+}
+
 /// An open crtc + plane combination that can be used for scan-out
 #[derive(Debug)]
 pub struct DrmSurface {
@@ -440,6 +479,38 @@ impl DrmSurface {
         }
     }
 
+    /// Tests if a full configuration would be accepted by the driver without applying it.
+    ///
+    /// The configuration is tested together with the pending connectors of this surface,
+    /// which makes this useful to validate a new mode before calling [`DrmSurface::use_mode`].
+    ///
+    /// On atomic devices this issues a test-only commit and returns the error reported by the kernel
+    /// if the configuration was rejected. Legacy devices have no way to test a configuration,
+    /// so only the compatibility of the mode with the pending connectors is checked
+    /// and using additional planes fails with [`Error::NonPrimaryPlane`].
+    pub fn test_config(&self, config: &PlaneConfig) -> Result<(), Error> {
+        match &*self.internal {
+            DrmSurfaceInternal::Atomic(surf) => surf.test_config(config),
+            DrmSurfaceInternal::Legacy(surf) => surf.test_config(config),
+        }
+    }
+
+    /// Change the power state of the connected displays, e.g. to blank them during inactivity.
+    ///
+    /// On atomic devices this toggles the `ACTIVE` property of the crtc, as the `DPMS` property
+    /// of the connectors can not be set atomically. [`DpmsState::Standby`] and [`DpmsState::Suspend`]
+    /// are handled like [`DpmsState::Off`] in this case. Legacy devices set the `DPMS` property
+    /// of the current connectors.
+    ///
+    /// Page flips will fail while the displays are not turned on, rendering should be paused
+    /// until the state is set to [`DpmsState::On`] again.
+    pub fn set_power_state(&self, state: DpmsState) -> Result<(), Error> {
+        match &*self.internal {
+            DrmSurfaceInternal::Atomic(surf) => surf.set_power_state(state),
+            DrmSurfaceInternal::Legacy(surf) => surf.set_power_state(state),
+        }
+    }
+
     /// Re-evaluates the current state of the crtc.
     ///
     /// It is recommended to call this function after this used [`Session`]
@@ -28,6 +28,7 @@ pub struct EGLContext {
     display: EGLDisplay,
     config_id: ffi::egl::types::EGLConfig,
     pixel_format: Option<PixelFormat>,
+    config: Option<(GlAttributes, PixelFormatRequirements)>,
     user_data: Arc<UserDataMap>,
     externally_managed: bool,
 }
@@ -68,6 +69,7 @@ impl EGLContext {
             display,
             config_id,
             pixel_format: Some(pixel_format),
+            config: None,
             user_data: Arc::new(UserDataMap::default()),
             externally_managed: true,
         })
@@ -116,6 +118,22 @@ impl EGLContext {
         Self::new_internal(display, Some(share), Some((attributes, reqs)), log)
     }
 
+    /// Create a new `EGLContext` from the same `EGLDisplay` and configuration as this context
+    ///
+    /// The new context does not share any resources with this context.
+    /// This can be used to replace a context, that was lost (see [`EGLError::ContextLost`](super::EGLError::ContextLost)).
+    ///
+    /// Contexts created with [`EGLContext::from_raw`] cannot be recreated.
+    pub fn recreate<L>(&self, log: L) -> Result<EGLContext, Error>
+    where
+        L: Into<Option<::slog::Logger>>,
+    {
+        if self.externally_managed {
+            return Err(Error::ExternallyManagedContext);
+        }
+        Self::new_internal(&self.display, None, self.config, log)
+    }
+
     fn new_internal<L>(
         display: &EGLDisplay,
         shared: Option<&EGLContext>,
@@ -212,6 +230,7 @@ impl EGLContext {
             display: display.clone(),
             config_id,
             pixel_format,
+            config,
             user_data: if let Some(shared) = shared {
                 shared.user_data.clone()
             } else {
@@ -60,6 +60,18 @@ pub enum Error {
     /// The device does not have the given property
     #[error("The device does not have the given property")]
     EmptyDeviceProperty,
+    /// The context was not created by smithay and cannot be recreated
+    #[error("The context is externally managed and cannot be recreated")]
+    ExternallyManagedContext,
+    /// Failed to create an `EGLSync` fence
+    #[error("Failed to create an `EGLSync` fence")]
+    FenceCreationFailed(#[source] EGLError),
+    /// Failed to wait for an `EGLSync` fence
+    #[error("Failed to wait for an `EGLSync` fence")]
+    FenceWaitFailed(#[source] EGLError),
+    /// Failed to export an `EGLSync` fence as a native fence fd
+    #[error("Failed to export an `EGLSync` fence")]
+    FenceExportFailed(#[source] EGLError),
 }
 
 /// Raw EGL error
@@ -0,0 +1,108 @@
+//! EGL fence objects for explicit synchronization
+//!
+//! An [`EGLFence`] wraps an `EGLSyncKHR` of type `EGL_SYNC_NATIVE_FENCE_ANDROID`.
+//! It can either be imported from a native fence fd (for example the acquire fence
+//! a client attached through `zwp_linux_explicit_synchronization_v1`) to make the
+//! gpu wait for it, or be created at the current position of the command stream
+//! and exported as a native fence fd, to be handed out as a release fence.
+
+use std::{
+    os::unix::io::{AsRawFd, FromRawFd, IntoRawFd, OwnedFd},
+    sync::Arc,
+};
+
+use super::{display::EGLDisplayHandle, ffi, wrap_egl_call, EGLDisplay, Error};
+
+const FENCE_EXTENSIONS: &[&str] = &[
+    "EGL_KHR_fence_sync",
+    "EGL_KHR_wait_sync",
+    "EGL_ANDROID_native_fence_sync",
+];
+
+/// A native fence sync object
+#[derive(Debug)]
+pub struct EGLFence {
+    display: Arc<EGLDisplayHandle>,
+    sync: ffi::egl::types::EGLSyncKHR,
+}
+
+impl EGLFence {
+    /// Returns whether the display supports the extensions required for [`EGLFence`]s
+    pub fn is_supported(display: &EGLDisplay) -> bool {
+        FENCE_EXTENSIONS
+            .iter()
+            .all(|ext| display.extensions().iter().any(|s| s == ext))
+    }
+
+    /// Import a native fence fd
+    ///
+    /// On success the ownership of the fd is transferred to EGL.
+    pub fn import(display: &EGLDisplay, native: OwnedFd) -> Result<EGLFence, Error> {
+        let fence = EGLFence::create_native(display, native.as_raw_fd())?;
+        // EGL took ownership of the fd
+        let _ = native.into_raw_fd();
+        Ok(fence)
+    }
+
+    /// Create a new fence at the current position of the command stream
+    ///
+    /// A context of the display has to be current on the calling thread.
+    /// The fence will be signaled once all previously submitted commands finished.
+    pub fn create(display: &EGLDisplay) -> Result<EGLFence, Error> {
+        EGLFence::create_native(display, ffi::egl::NO_NATIVE_FENCE_FD_ANDROID)
+    }
+
+    fn create_native(display: &EGLDisplay, fd: i32) -> Result<EGLFence, Error> {
+        if !EGLFence::is_supported(display) {
+            return Err(Error::EglExtensionNotSupported(FENCE_EXTENSIONS));
+        }
+
+        let attribs = [
+            ffi::egl::SYNC_NATIVE_FENCE_FD_ANDROID as i32,
+            fd,
+            ffi::egl::NONE as i32,
+        ];
+        let display = display.get_display_handle();
+        let sync = wrap_egl_call(|| unsafe {
+            ffi::egl::CreateSyncKHR(**display, ffi::egl::SYNC_NATIVE_FENCE_ANDROID, attribs.as_ptr())
+        })
+        .map_err(Error::FenceCreationFailed)?;
+        if sync == ffi::egl::NO_SYNC_KHR {
+            return Err(Error::FenceCreationFailed(super::EGLError::BadAlloc));
+        }
+
+        Ok(EGLFence { display, sync })
+    }
+
+    /// Make the gpu wait for this fence
+    ///
+    /// This does not block the calling thread, instead all commands submitted afterwards
+    /// to the context current on the calling thread will only be executed once the fence
+    /// is signaled.
+    pub fn wait(&self) -> Result<(), Error> {
+        wrap_egl_call(|| unsafe { ffi::egl::WaitSyncKHR(**self.display, self.sync, 0) })
+            .map_err(Error::FenceWaitFailed)?;
+        Ok(())
+    }
+
+    /// Export this fence as a native fence fd
+    ///
+    /// For fences created with [`EGLFence::create`] the commands have to be flushed first.
+    pub fn export(&self) -> Result<OwnedFd, Error> {
+        let fd = wrap_egl_call(|| unsafe { ffi::egl::DupNativeFenceFDANDROID(**self.display, self.sync) })
+            .map_err(Error::FenceExportFailed)?;
+        if fd == ffi::egl::NO_NATIVE_FENCE_FD_ANDROID {
+            return Err(Error::FenceExportFailed(super::EGLError::BadParameter));
+        }
+        Ok(unsafe { OwnedFd::from_raw_fd(fd) })
+    }
+}
+
+impl Drop for EGLFence {
+    fn drop(&mut self) {
+        unsafe {
+            // ignore errors on drop
+            ffi::egl::DestroySyncKHR(**self.display, self.sync);
+        }
+    }
+}
@@ -46,10 +46,12 @@ pub mod ffi;
 use self::{display::EGLDisplayHandle, ffi::egl::types::EGLImage};
 
 pub mod display;
+pub mod fence;
 pub mod native;
 pub mod surface;
 pub use self::device::EGLDevice;
 pub use self::display::EGLDisplay;
+pub use self::fence::EGLFence;
 pub use self::surface::EGLSurface;
 
 use std::ffi::CString;
@@ -160,6 +162,12 @@ impl std::convert::From<SwapBuffersError> for GraphicsSwapBuffersError {
 #[error("`eglMakeCurrent` failed: {0}")]
 pub struct MakeCurrentError(#[from] EGLError);
 
+impl MakeCurrentError {
+    pub(crate) fn is_context_lost(&self) -> bool {
+        matches!(self.0, EGLError::ContextLost)
+    }
+}
+
 impl From<MakeCurrentError> for GraphicsSwapBuffersError {
     fn from(err: MakeCurrentError) -> GraphicsSwapBuffersError {
         match err {
@@ -9,6 +9,22 @@ pub use tablet::{
     TabletToolEvent, TabletToolProximityEvent, TabletToolTipEvent, TabletToolTipState, TabletToolType,
 };
 
+#[cfg(feature = "backend_replay")]
+mod replay;
+mod timestamp;
+mod touch;
+
+#[cfg(feature = "backend_replay")]
+pub use replay::{
+    EventRecorder, EventReplayBackend, RecordError, ReplayComplete, ReplayDevice, ReplayError,
+    ReplayGestureBeginEvent, ReplayGestureEndEvent, ReplayGesturePinchUpdateEvent,
+    ReplayGestureSwipeUpdateEvent, ReplayKeyboardKeyEvent, ReplayPointerAxisEvent, ReplayPointerButtonEvent,
+    ReplayPointerMotionAbsoluteEvent, ReplayPointerMotionEvent, ReplayTouchFrameEvent,
+    ReplayTouchPositionEvent, ReplayTouchSlotEvent,
+};
+pub use timestamp::{TimestampNormalizer, TimestampSource};
+pub use touch::TouchSlotMapper;
+
 use crate::utils::{Logical, Point, Raw, Size};
 
 /// Trait for generic functions every input device does provide
@@ -33,6 +49,7 @@ pub trait Device: PartialEq + Eq + std::hash::Hash {
 
 /// Set of input types a device may provide
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 #[allow(missing_docs)] // self explainatory
 pub enum DeviceCapability {
     Keyboard,
@@ -84,6 +101,7 @@ impl<B: InputBackend> Event<B> for UnusedEvent {
 
 /// State of key on a keyboard. Either pressed or released
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum KeyState {
     /// Key is released
     Released,
@@ -140,6 +158,7 @@ pub enum MouseButton {
 
 /// State of a button on a pointer device, like mouse or tablet tool. Either pressed or released
 #[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum ButtonState {
     /// Button is released
     Released,
@@ -302,6 +321,7 @@ pub enum Axis {
 
 /// Source of an axis when scrolling
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AxisSource {
     /// Finger. Mostly used for trackpads.
     ///
@@ -345,6 +365,14 @@ pub trait PointerAxisEvent<B: InputBackend>: Event<B> {
     /// Guaranteed to be `Some` when source returns either [`AxisSource::Wheel`] or [`AxisSource::WheelTilt`].
     fn amount_discrete(&self, axis: Axis) -> Option<f64>;
 
+    /// Amount of scrolling in fractions of 1/120 of a discrete step on the given [`Axis`].
+    ///
+    /// High-resolution scroll wheels report multiple events per step. The default
+    /// implementation derives the value from [`amount_discrete`](PointerAxisEvent::amount_discrete).
+    fn amount_v120(&self, axis: Axis) -> Option<f64> {
+        self.amount_discrete(axis).map(|steps| steps * 120.0)
+    }
+
     /// Source of the scroll event.
     fn source(&self) -> AxisSource;
 }
@@ -501,6 +529,9 @@ impl<B: InputBackend> TouchEvent<B> for UnusedEvent {
 }
 
 /// Trait for touch events starting at a given position.
+///
+/// The [`time`](Event::time) of the event is the timestamp reported by the hardware, e.g. the
+/// libinput backend uses `libinput_event_touch_get_time_usec`, and not the time of dispatch.
 pub trait TouchDownEvent<B: InputBackend>: TouchEvent<B> + AbsolutePositionEvent<B> {}
 impl<B: InputBackend> TouchDownEvent<B> for UnusedEvent {}
 
@@ -0,0 +1,1013 @@
+//! Recording and replaying of input events
+//!
+//! An [`EventRecorder`] wraps the event source of any [`InputBackend`] and writes all events it
+//! emits into a CBOR-encoded recording, while passing them on unchanged. An [`EventReplayBackend`]
+//! reads such a recording and emits the same events again, at the same relative times they were
+//! recorded at. This allows to reproduce input related bugs with the exact same event sequence.
+//!
+//! ```no_run
+//! use smithay::backend::input::{EventRecorder, EventReplayBackend, InputEvent};
+//! # use smithay::backend::libinput::LibinputInputBackend;
+//! # let libinput_backend: LibinputInputBackend = todo!();
+//! # let event_loop = smithay::reexports::calloop::EventLoop::<()>::try_new().unwrap();
+//! # let handle = event_loop.handle();
+//!
+//! // record a session
+//! let recording = std::io::BufWriter::new(std::fs::File::create("input.cbor").unwrap());
+//! let recorder = EventRecorder::new(libinput_backend, recording);
+//! handle.insert_source(recorder, |event, _, _| {
+//!     // process the events like usual
+//! });
+//!
+//! // and replay it later on
+//! let replay = EventReplayBackend::new(std::fs::File::open("input.cbor").unwrap()).unwrap();
+//! handle.insert_source(replay, |event, _, _| match event {
+//!     InputEvent::Special(_) => { /* the recording is exhausted */ }
+//!     event => { /* process the events like usual */ }
+//! });
+//! ```
+//!
+//! Tablet tool events and backend specific [`InputEvent::Special`] events are not recorded.
+
+use std::{
+    collections::{HashSet, VecDeque},
+    fmt,
+    hash::{Hash, Hasher},
+    io::{self, BufRead, BufReader, Read, Write},
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use calloop::{
+    timer::{TimeoutAction, Timer},
+    EventSource, Poll, PostAction, Readiness, Token, TokenFactory,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    AbsolutePositionEvent, Axis, AxisSource, ButtonState, Device, DeviceCapability, Event, GestureBeginEvent,
+    GestureEndEvent, GestureHoldBeginEvent, GestureHoldEndEvent, GesturePinchBeginEvent,
+    GesturePinchEndEvent, GesturePinchUpdateEvent, GestureSwipeBeginEvent, GestureSwipeEndEvent,
+    GestureSwipeUpdateEvent, InputBackend, InputEvent, KeyState, KeyboardKeyEvent, PointerAxisEvent,
+    PointerButtonEvent, PointerMotionAbsoluteEvent, PointerMotionEvent, TouchCancelEvent, TouchDownEvent,
+    TouchEvent, TouchFrameEvent, TouchMotionEvent, TouchSlot, TouchUpEvent, UnusedEvent,
+};
+
+const CAPABILITIES: [DeviceCapability; 7] = [
+    DeviceCapability::Keyboard,
+    DeviceCapability::Pointer,
+    DeviceCapability::Touch,
+    DeviceCapability::TabletTool,
+    DeviceCapability::TabletPad,
+    DeviceCapability::Gesture,
+    DeviceCapability::Switch,
+];
+
+/// Errors thrown by the [`EventReplayBackend`]
+#[derive(Debug, thiserror::Error)]
+pub enum ReplayError {
+    /// Reading the recording failed
+    #[error("Failed to read the recording: {0}")]
+    Io(#[from] io::Error),
+    /// The recording could not be decoded
+    #[error("Failed to decode the recording: {0}")]
+    Decode(#[from] ciborium::de::Error<io::Error>),
+}
+
+/// Errors thrown by the [`EventRecorder`]
+#[derive(Debug, thiserror::Error)]
+pub enum RecordError {
+    /// Writing the recording failed
+    #[error("Failed to write the recording: {0}")]
+    Io(#[from] io::Error),
+    /// An event could not be encoded
+    #[error("Failed to encode an event: {0}")]
+    Encode(#[from] ciborium::ser::Error<io::Error>),
+    /// The wrapped event source failed
+    #[error("The recorded event source failed: {0}")]
+    Source(#[source] Box<dyn std::error::Error + Send + Sync>),
+}
+
+/// Input device of a recording
+///
+/// Devices are identified by their [`id`](Device::id) only.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayDevice {
+    id: String,
+    name: String,
+    capabilities: Vec<DeviceCapability>,
+    usb_id: Option<(u32, u32)>,
+    syspath: Option<PathBuf>,
+}
+
+impl ReplayDevice {
+    fn from_device<D: Device>(device: &D) -> Self {
+        ReplayDevice {
+            id: device.id(),
+            name: device.name(),
+            capabilities: CAPABILITIES
+                .into_iter()
+                .filter(|capability| device.has_capability(*capability))
+                .collect(),
+            usb_id: device.usb_id(),
+            syspath: device.syspath(),
+        }
+    }
+}
+
+impl PartialEq for ReplayDevice {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for ReplayDevice {}
+
+impl Hash for ReplayDevice {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.id.hash(state);
+    }
+}
+
+impl Device for ReplayDevice {
+    fn id(&self) -> String {
+        self.id.clone()
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn has_capability(&self, capability: DeviceCapability) -> bool {
+        self.capabilities.contains(&capability)
+    }
+
+    fn usb_id(&self) -> Option<(u32, u32)> {
+        self.usb_id
+    }
+
+    fn syspath(&self) -> Option<PathBuf> {
+        self.syspath.clone()
+    }
+}
+
+macro_rules! replay_event {
+    ($(#[$attr:meta])* $name:ident { $($field:ident: $ty:ty),* $(,)? }) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, Serialize, Deserialize)]
+        pub struct $name {
+            time: u64,
+            device: ReplayDevice,
+            $($field: $ty,)*
+        }
+
+        impl Event<EventReplayBackend> for $name {
+            fn time(&self) -> u64 {
+                self.time
+            }
+
+            fn device(&self) -> ReplayDevice {
+                self.device.clone()
+            }
+        }
+    };
+}
+
+replay_event!(
+    /// Replayed [`KeyboardKeyEvent`]
+    ReplayKeyboardKeyEvent {
+        key_code: u32,
+        state: KeyState,
+        count: u32,
+    }
+);
+
+impl KeyboardKeyEvent<EventReplayBackend> for ReplayKeyboardKeyEvent {
+    fn key_code(&self) -> u32 {
+        self.key_code
+    }
+
+    fn state(&self) -> KeyState {
+        self.state
+    }
+
+    fn count(&self) -> u32 {
+        self.count
+    }
+}
+
+replay_event!(
+    /// Replayed [`PointerMotionEvent`]
+    ReplayPointerMotionEvent {
+        delta: (f64, f64),
+        delta_unaccel: (f64, f64),
+    }
+);
+
+impl PointerMotionEvent<EventReplayBackend> for ReplayPointerMotionEvent {
+    fn delta_x(&self) -> f64 {
+        self.delta.0
+    }
+
+    fn delta_y(&self) -> f64 {
+        self.delta.1
+    }
+
+    fn delta_x_unaccel(&self) -> f64 {
+        self.delta_unaccel.0
+    }
+
+    fn delta_y_unaccel(&self) -> f64 {
+        self.delta_unaccel.1
+    }
+}
+
+replay_event!(
+    /// Replayed [`PointerMotionAbsoluteEvent`]
+    ///
+    /// The transformed position is recorded for a size of `1x1` and scaled when replayed.
+    ReplayPointerMotionAbsoluteEvent {
+        position: (f64, f64),
+        normalized: (f64, f64),
+    }
+);
+
+impl AbsolutePositionEvent<EventReplayBackend> for ReplayPointerMotionAbsoluteEvent {
+    fn x(&self) -> f64 {
+        self.position.0
+    }
+
+    fn y(&self) -> f64 {
+        self.position.1
+    }
+
+    fn x_transformed(&self, width: i32) -> f64 {
+        self.normalized.0 * width as f64
+    }
+
+    fn y_transformed(&self, height: i32) -> f64 {
+        self.normalized.1 * height as f64
+    }
+}
+
+impl PointerMotionAbsoluteEvent<EventReplayBackend> for ReplayPointerMotionAbsoluteEvent {}
+
+replay_event!(
+    /// Replayed [`PointerButtonEvent`]
+    ReplayPointerButtonEvent {
+        button_code: u32,
+        state: ButtonState,
+    }
+);
+
+impl PointerButtonEvent<EventReplayBackend> for ReplayPointerButtonEvent {
+    fn button_code(&self) -> u32 {
+        self.button_code
+    }
+
+    fn state(&self) -> ButtonState {
+        self.state
+    }
+}
+
+replay_event!(
+    /// Replayed [`PointerAxisEvent`]
+    ///
+    /// The amounts are stored as horizontal and vertical value.
+    ReplayPointerAxisEvent {
+        source: AxisSource,
+        amount: (Option<f64>, Option<f64>),
+        amount_discrete: (Option<f64>, Option<f64>),
+        amount_v120: (Option<f64>, Option<f64>),
+    }
+);
+
+fn axis_value(values: (Option<f64>, Option<f64>), axis: Axis) -> Option<f64> {
+    match axis {
+        Axis::Horizontal => values.0,
+        Axis::Vertical => values.1,
+    }
+}
+
+impl PointerAxisEvent<EventReplayBackend> for ReplayPointerAxisEvent {
+    fn amount(&self, axis: Axis) -> Option<f64> {
+        axis_value(self.amount, axis)
+    }
+
+    fn amount_discrete(&self, axis: Axis) -> Option<f64> {
+        axis_value(self.amount_discrete, axis)
+    }
+
+    fn amount_v120(&self, axis: Axis) -> Option<f64> {
+        axis_value(self.amount_v120, axis)
+    }
+
+    fn source(&self) -> AxisSource {
+        self.source
+    }
+}
+
+replay_event!(
+    /// Replayed [`GestureBeginEvent`] of any gesture
+    ReplayGestureBeginEvent { fingers: u32 }
+);
+
+impl GestureBeginEvent<EventReplayBackend> for ReplayGestureBeginEvent {
+    fn fingers(&self) -> u32 {
+        self.fingers
+    }
+}
+
+impl GestureSwipeBeginEvent<EventReplayBackend> for ReplayGestureBeginEvent {}
+impl GesturePinchBeginEvent<EventReplayBackend> for ReplayGestureBeginEvent {}
+impl GestureHoldBeginEvent<EventReplayBackend> for ReplayGestureBeginEvent {}
+
+replay_event!(
+    /// Replayed [`GestureSwipeUpdateEvent`]
+    ReplayGestureSwipeUpdateEvent { delta: (f64, f64) }
+);
+
+impl GestureSwipeUpdateEvent<EventReplayBackend> for ReplayGestureSwipeUpdateEvent {
+    fn delta_x(&self) -> f64 {
+        self.delta.0
+    }
+
+    fn delta_y(&self) -> f64 {
+        self.delta.1
+    }
+}
+
+replay_event!(
+    /// Replayed [`GesturePinchUpdateEvent`]
+    ReplayGesturePinchUpdateEvent {
+        delta: (f64, f64),
+        scale: f64,
+        rotation: f64,
+    }
+);
+
+impl GesturePinchUpdateEvent<EventReplayBackend> for ReplayGesturePinchUpdateEvent {
+    fn delta_x(&self) -> f64 {
+        self.delta.0
+    }
+
+    fn delta_y(&self) -> f64 {
+        self.delta.1
+    }
+
+    fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    fn rotation(&self) -> f64 {
+        self.rotation
+    }
+}
+
+replay_event!(
+    /// Replayed [`GestureEndEvent`] of any gesture
+    ReplayGestureEndEvent { cancelled: bool }
+);
+
+impl GestureEndEvent<EventReplayBackend> for ReplayGestureEndEvent {
+    fn cancelled(&self) -> bool {
+        self.cancelled
+    }
+}
+
+impl GestureSwipeEndEvent<EventReplayBackend> for ReplayGestureEndEvent {}
+impl GesturePinchEndEvent<EventReplayBackend> for ReplayGestureEndEvent {}
+impl GestureHoldEndEvent<EventReplayBackend> for ReplayGestureEndEvent {}
+
+replay_event!(
+    /// Replayed [`TouchDownEvent`] or [`TouchMotionEvent`]
+    ///
+    /// The transformed position is recorded for a size of `1x1` and scaled when replayed.
+    ReplayTouchPositionEvent {
+        slot: Option<u32>,
+        position: (f64, f64),
+        normalized: (f64, f64),
+    }
+);
+
+impl TouchEvent<EventReplayBackend> for ReplayTouchPositionEvent {
+    fn slot(&self) -> TouchSlot {
+        self.slot.into()
+    }
+}
+
+impl AbsolutePositionEvent<EventReplayBackend> for ReplayTouchPositionEvent {
+    fn x(&self) -> f64 {
+        self.position.0
+    }
+
+    fn y(&self) -> f64 {
+        self.position.1
+    }
+
+    fn x_transformed(&self, width: i32) -> f64 {
+        self.normalized.0 * width as f64
+    }
+
+    fn y_transformed(&self, height: i32) -> f64 {
+        self.normalized.1 * height as f64
+    }
+}
+
+impl TouchDownEvent<EventReplayBackend> for ReplayTouchPositionEvent {}
+impl TouchMotionEvent<EventReplayBackend> for ReplayTouchPositionEvent {}
+
+replay_event!(
+    /// Replayed [`TouchUpEvent`] or [`TouchCancelEvent`]
+    ReplayTouchSlotEvent { slot: Option<u32> }
+);
+
+impl TouchEvent<EventReplayBackend> for ReplayTouchSlotEvent {
+    fn slot(&self) -> TouchSlot {
+        self.slot.into()
+    }
+}
+
+impl TouchUpEvent<EventReplayBackend> for ReplayTouchSlotEvent {}
+impl TouchCancelEvent<EventReplayBackend> for ReplayTouchSlotEvent {}
+
+replay_event!(
+    /// Replayed [`TouchFrameEvent`]
+    ReplayTouchFrameEvent {}
+);
+
+impl TouchFrameEvent<EventReplayBackend> for ReplayTouchFrameEvent {}
+
+/// Event signaling that all events of a recording were replayed
+///
+/// Emitted as [`InputEvent::Special`] by the [`EventReplayBackend`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReplayComplete;
+
+#[derive(Debug, Serialize, Deserialize)]
+enum RecordedEvent {
+    DeviceAdded(ReplayDevice),
+    DeviceRemoved(ReplayDevice),
+    Keyboard(ReplayKeyboardKeyEvent),
+    PointerMotion(ReplayPointerMotionEvent),
+    PointerMotionAbsolute(ReplayPointerMotionAbsoluteEvent),
+    PointerButton(ReplayPointerButtonEvent),
+    PointerAxis(ReplayPointerAxisEvent),
+    GestureSwipeBegin(ReplayGestureBeginEvent),
+    GestureSwipeUpdate(ReplayGestureSwipeUpdateEvent),
+    GestureSwipeEnd(ReplayGestureEndEvent),
+    GesturePinchBegin(ReplayGestureBeginEvent),
+    GesturePinchUpdate(ReplayGesturePinchUpdateEvent),
+    GesturePinchEnd(ReplayGestureEndEvent),
+    GestureHoldBegin(ReplayGestureBeginEvent),
+    GestureHoldEnd(ReplayGestureEndEvent),
+    TouchDown(ReplayTouchPositionEvent),
+    TouchMotion(ReplayTouchPositionEvent),
+    TouchUp(ReplayTouchSlotEvent),
+    TouchCancel(ReplayTouchSlotEvent),
+    TouchFrame(ReplayTouchFrameEvent),
+}
+
+impl From<RecordedEvent> for InputEvent<EventReplayBackend> {
+    fn from(event: RecordedEvent) -> Self {
+        match event {
+            RecordedEvent::DeviceAdded(device) => InputEvent::DeviceAdded { device },
+            RecordedEvent::DeviceRemoved(device) => InputEvent::DeviceRemoved { device },
+            RecordedEvent::Keyboard(event) => InputEvent::Keyboard { event },
+            RecordedEvent::PointerMotion(event) => InputEvent::PointerMotion { event },
+            RecordedEvent::PointerMotionAbsolute(event) => InputEvent::PointerMotionAbsolute { event },
+            RecordedEvent::PointerButton(event) => InputEvent::PointerButton { event },
+            RecordedEvent::PointerAxis(event) => InputEvent::PointerAxis { event },
+            RecordedEvent::GestureSwipeBegin(event) => InputEvent::GestureSwipeBegin { event },
+            RecordedEvent::GestureSwipeUpdate(event) => InputEvent::GestureSwipeUpdate { event },
+            RecordedEvent::GestureSwipeEnd(event) => InputEvent::GestureSwipeEnd { event },
+            RecordedEvent::GesturePinchBegin(event) => InputEvent::GesturePinchBegin { event },
+            RecordedEvent::GesturePinchUpdate(event) => InputEvent::GesturePinchUpdate { event },
+            RecordedEvent::GesturePinchEnd(event) => InputEvent::GesturePinchEnd { event },
+            RecordedEvent::GestureHoldBegin(event) => InputEvent::GestureHoldBegin { event },
+            RecordedEvent::GestureHoldEnd(event) => InputEvent::GestureHoldEnd { event },
+            RecordedEvent::TouchDown(event) => InputEvent::TouchDown { event },
+            RecordedEvent::TouchMotion(event) => InputEvent::TouchMotion { event },
+            RecordedEvent::TouchUp(event) => InputEvent::TouchUp { event },
+            RecordedEvent::TouchCancel(event) => InputEvent::TouchCancel { event },
+            RecordedEvent::TouchFrame(event) => InputEvent::TouchFrame { event },
+        }
+    }
+}
+
+/// A single entry of a recording
+#[derive(Debug, Serialize, Deserialize)]
+struct Record {
+    /// Time since the start of the recording in microseconds
+    offset_us: u64,
+    event: RecordedEvent,
+}
+
+fn touch_slot(slot: TouchSlot) -> Option<u32> {
+    let slot: i32 = slot.into();
+    u32::try_from(slot).ok()
+}
+
+/// Converts an event into its recorded form
+///
+/// Devices not seen before are recorded as added first, as the recording might have been
+/// started after the device was announced.
+fn record_event<B: InputBackend>(event: &InputEvent<B>, devices: &mut HashSet<String>) -> Vec<RecordedEvent> {
+    let mut added = None;
+    let mut device = |device: &B::Device| {
+        let device = ReplayDevice::from_device(device);
+        if devices.insert(device.id.clone()) {
+            added = Some(RecordedEvent::DeviceAdded(device.clone()));
+        }
+        device
+    };
+
+    let recorded = match event {
+        InputEvent::DeviceAdded { device: new_device } => {
+            device(new_device);
+            None
+        }
+        InputEvent::DeviceRemoved { device } => {
+            let device = ReplayDevice::from_device(device);
+            devices.remove(&device.id);
+            Some(RecordedEvent::DeviceRemoved(device))
+        }
+        InputEvent::Keyboard { event } => Some(RecordedEvent::Keyboard(ReplayKeyboardKeyEvent {
+            time: event.time(),
+            device: device(&event.device()),
+            key_code: event.key_code(),
+            state: event.state(),
+            count: event.count(),
+        })),
+        InputEvent::PointerMotion { event } => Some(RecordedEvent::PointerMotion(ReplayPointerMotionEvent {
+            time: event.time(),
+            device: device(&event.device()),
+            delta: (event.delta_x(), event.delta_y()),
+            delta_unaccel: (event.delta_x_unaccel(), event.delta_y_unaccel()),
+        })),
+        InputEvent::PointerMotionAbsolute { event } => Some(RecordedEvent::PointerMotionAbsolute(
+            ReplayPointerMotionAbsoluteEvent {
+                time: event.time(),
+                device: device(&event.device()),
+                position: (event.x(), event.y()),
+                normalized: (event.x_transformed(1), event.y_transformed(1)),
+            },
+        )),
+        InputEvent::PointerButton { event } => Some(RecordedEvent::PointerButton(ReplayPointerButtonEvent {
+            time: event.time(),
+            device: device(&event.device()),
+            button_code: event.button_code(),
+            state: event.state(),
+        })),
+        InputEvent::PointerAxis { event } => Some(RecordedEvent::PointerAxis(ReplayPointerAxisEvent {
+            time: event.time(),
+            device: device(&event.device()),
+            source: event.source(),
+            amount: (event.amount(Axis::Horizontal), event.amount(Axis::Vertical)),
+            amount_discrete: (
+                event.amount_discrete(Axis::Horizontal),
+                event.amount_discrete(Axis::Vertical),
+            ),
+            amount_v120: (
+                event.amount_v120(Axis::Horizontal),
+                event.amount_v120(Axis::Vertical),
+            ),
+        })),
+        InputEvent::GestureSwipeBegin { event } => {
+            Some(RecordedEvent::GestureSwipeBegin(ReplayGestureBeginEvent {
+                time: event.time(),
+                device: device(&event.device()),
+                fingers: event.fingers(),
+            }))
+        }
+        InputEvent::GestureSwipeUpdate { event } => {
+            Some(RecordedEvent::GestureSwipeUpdate(ReplayGestureSwipeUpdateEvent {
+                time: event.time(),
+                device: device(&event.device()),
+                delta: (event.delta_x(), event.delta_y()),
+            }))
+        }
+        InputEvent::GestureSwipeEnd { event } => {
+            Some(RecordedEvent::GestureSwipeEnd(ReplayGestureEndEvent {
+                time: event.time(),
+                device: device(&event.device()),
+                cancelled: event.cancelled(),
+            }))
+        }
+        InputEvent::GesturePinchBegin { event } => {
+            Some(RecordedEvent::GesturePinchBegin(ReplayGestureBeginEvent {
+                time: event.time(),
+                device: device(&event.device()),
+                fingers: event.fingers(),
+            }))
+        }
+        InputEvent::GesturePinchUpdate { event } => {
+            Some(RecordedEvent::GesturePinchUpdate(ReplayGesturePinchUpdateEvent {
+                time: event.time(),
+                device: device(&event.device()),
+                delta: (event.delta_x(), event.delta_y()),
+                scale: event.scale(),
+                rotation: event.rotation(),
+            }))
+        }
+        InputEvent::GesturePinchEnd { event } => {
+            Some(RecordedEvent::GesturePinchEnd(ReplayGestureEndEvent {
+                time: event.time(),
+                device: device(&event.device()),
+                cancelled: event.cancelled(),
+            }))
+        }
+        InputEvent::GestureHoldBegin { event } => {
+            Some(RecordedEvent::GestureHoldBegin(ReplayGestureBeginEvent {
+                time: event.time(),
+                device: device(&event.device()),
+                fingers: event.fingers(),
+            }))
+        }
+        InputEvent::GestureHoldEnd { event } => Some(RecordedEvent::GestureHoldEnd(ReplayGestureEndEvent {
+            time: event.time(),
+            device: device(&event.device()),
+            cancelled: event.cancelled(),
+        })),
+        InputEvent::TouchDown { event } => Some(RecordedEvent::TouchDown(ReplayTouchPositionEvent {
+            time: event.time(),
+            device: device(&event.device()),
+            slot: touch_slot(event.slot()),
+            position: (event.x(), event.y()),
+            normalized: (event.x_transformed(1), event.y_transformed(1)),
+        })),
+        InputEvent::TouchMotion { event } => Some(RecordedEvent::TouchMotion(ReplayTouchPositionEvent {
+            time: event.time(),
+            device: device(&event.device()),
+            slot: touch_slot(event.slot()),
+            position: (event.x(), event.y()),
+            normalized: (event.x_transformed(1), event.y_transformed(1)),
+        })),
+        InputEvent::TouchUp { event } => Some(RecordedEvent::TouchUp(ReplayTouchSlotEvent {
+            time: event.time(),
+            device: device(&event.device()),
+            slot: touch_slot(event.slot()),
+        })),
+        InputEvent::TouchCancel { event } => Some(RecordedEvent::TouchCancel(ReplayTouchSlotEvent {
+            time: event.time(),
+            device: device(&event.device()),
+            slot: touch_slot(event.slot()),
+        })),
+        InputEvent::TouchFrame { event } => Some(RecordedEvent::TouchFrame(ReplayTouchFrameEvent {
+            time: event.time(),
+            device: device(&event.device()),
+        })),
+        InputEvent::TabletToolAxis { .. }
+        | InputEvent::TabletToolProximity { .. }
+        | InputEvent::TabletToolTip { .. }
+        | InputEvent::TabletToolButton { .. }
+        | InputEvent::Special(_) => None,
+    };
+
+    added.into_iter().chain(recorded).collect()
+}
+
+/// Event source wrapping the event source of an [`InputBackend`] and recording its events
+///
+/// All events are passed on unchanged, while being written to the given output.
+/// The recording can be replayed with an [`EventReplayBackend`]. The output is flushed after
+/// every batch of events, so it should be buffered (e.g. with a [`BufWriter`](std::io::BufWriter)).
+#[derive(Debug)]
+pub struct EventRecorder<S, W> {
+    inner: S,
+    output: W,
+    start: Instant,
+    devices: HashSet<String>,
+}
+
+impl<S, W: Write> EventRecorder<S, W> {
+    /// Wrap an input event source, recording its events into `output`
+    ///
+    /// The timestamps of the recording are relative to the creation of the recorder.
+    pub fn new(inner: S, output: W) -> Self {
+        EventRecorder {
+            inner,
+            output,
+            start: Instant::now(),
+            devices: HashSet::new(),
+        }
+    }
+
+    /// Access the wrapped event source
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    /// Mutably access the wrapped event source
+    pub fn inner_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Stop recording, returning the wrapped event source and the output
+    pub fn into_inner(self) -> (S, W) {
+        (self.inner, self.output)
+    }
+}
+
+impl<S, B, W> EventSource for EventRecorder<S, W>
+where
+    S: EventSource<Event = InputEvent<B>, Metadata = (), Ret = ()>,
+    B: InputBackend,
+    W: Write,
+{
+    type Event = InputEvent<B>;
+    type Metadata = ();
+    type Ret = ();
+    type Error = RecordError;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> Result<PostAction, RecordError>
+    where
+        F: FnMut(Self::Event, &mut ()) -> Self::Ret,
+    {
+        let EventRecorder {
+            inner,
+            output,
+            start,
+            devices,
+        } = self;
+
+        let mut error = None;
+        let post_action = inner
+            .process_events(readiness, token, |event, metadata| {
+                if error.is_none() {
+                    let offset_us = start.elapsed().as_micros() as u64;
+                    for event in record_event(&event, devices) {
+                        if let Err(err) =
+                            ciborium::ser::into_writer(&Record { offset_us, event }, &mut *output)
+                        {
+                            error = Some(err);
+                            break;
+                        }
+                    }
+                }
+                callback(event, metadata)
+            })
+            .map_err(|err| RecordError::Source(err.into()))?;
+
+        if let Some(err) = error {
+            return Err(err.into());
+        }
+        output.flush()?;
+        Ok(post_action)
+    }
+
+    fn register(&mut self, poll: &mut Poll, factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.inner.register(poll, factory)
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, factory: &mut TokenFactory) -> calloop::Result<()> {
+        self.inner.reregister(poll, factory)
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        self.inner.unregister(poll)
+    }
+}
+
+/// Input backend replaying a recording of an [`EventRecorder`]
+///
+/// The events are emitted at the same times relative to each other as they were recorded,
+/// starting once the backend is inserted into the event loop. Once all events were emitted,
+/// [`ReplayComplete`] is emitted as [`InputEvent::Special`] and the event source is removed.
+pub struct EventReplayBackend {
+    records: VecDeque<Record>,
+    start: Option<Instant>,
+    timer: Option<Timer>,
+}
+
+impl fmt::Debug for EventReplayBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventReplayBackend")
+            .field("records", &self.records)
+            .field("start", &self.start)
+            .finish_non_exhaustive()
+    }
+}
+
+impl EventReplayBackend {
+    /// Read a recording
+    ///
+    /// The whole recording is read immediately.
+    pub fn new<R: Read>(reader: R) -> Result<EventReplayBackend, ReplayError> {
+        let mut reader = BufReader::new(reader);
+        let mut records = VecDeque::new();
+        while !reader.fill_buf()?.is_empty() {
+            records.push_back(ciborium::de::from_reader(&mut reader)?);
+        }
+
+        Ok(EventReplayBackend {
+            records,
+            start: None,
+            timer: None,
+        })
+    }
+
+    /// Number of events not yet replayed
+    pub fn remaining(&self) -> usize {
+        self.records.len()
+    }
+
+    fn deadline(&self, start: Instant) -> Instant {
+        start
+            + self
+                .records
+                .front()
+                .map(|record| Duration::from_micros(record.offset_us))
+                .unwrap_or_default()
+    }
+}
+
+impl InputBackend for EventReplayBackend {
+    type Device = ReplayDevice;
+    type KeyboardKeyEvent = ReplayKeyboardKeyEvent;
+    type PointerAxisEvent = ReplayPointerAxisEvent;
+    type PointerButtonEvent = ReplayPointerButtonEvent;
+    type PointerMotionEvent = ReplayPointerMotionEvent;
+    type PointerMotionAbsoluteEvent = ReplayPointerMotionAbsoluteEvent;
+
+    type GestureSwipeBeginEvent = ReplayGestureBeginEvent;
+    type GestureSwipeUpdateEvent = ReplayGestureSwipeUpdateEvent;
+    type GestureSwipeEndEvent = ReplayGestureEndEvent;
+    type GesturePinchBeginEvent = ReplayGestureBeginEvent;
+    type GesturePinchUpdateEvent = ReplayGesturePinchUpdateEvent;
+    type GesturePinchEndEvent = ReplayGestureEndEvent;
+    type GestureHoldBeginEvent = ReplayGestureBeginEvent;
+    type GestureHoldEndEvent = ReplayGestureEndEvent;
+
+    type TouchDownEvent = ReplayTouchPositionEvent;
+    type TouchUpEvent = ReplayTouchSlotEvent;
+    type TouchMotionEvent = ReplayTouchPositionEvent;
+    type TouchCancelEvent = ReplayTouchSlotEvent;
+    type TouchFrameEvent = ReplayTouchFrameEvent;
+    type TabletToolAxisEvent = UnusedEvent;
+    type TabletToolProximityEvent = UnusedEvent;
+    type TabletToolTipEvent = UnusedEvent;
+    type TabletToolButtonEvent = UnusedEvent;
+
+    type SpecialEvent = ReplayComplete;
+}
+
+impl EventSource for EventReplayBackend {
+    type Event = InputEvent<EventReplayBackend>;
+    type Metadata = ();
+    type Ret = ();
+    type Error = io::Error;
+
+    fn process_events<F>(
+        &mut self,
+        readiness: Readiness,
+        token: Token,
+        mut callback: F,
+    ) -> io::Result<PostAction>
+    where
+        F: FnMut(Self::Event, &mut ()) -> Self::Ret,
+    {
+        let (timer, start) = match (self.timer.as_mut(), self.start) {
+            (Some(timer), Some(start)) => (timer, start),
+            _ => return Ok(PostAction::Continue),
+        };
+        let records = &mut self.records;
+
+        timer.process_events(readiness, token, |_, _| {
+            let now = Instant::now();
+            while records
+                .front()
+                .map(|record| start + Duration::from_micros(record.offset_us) <= now)
+                .unwrap_or(false)
+            {
+                let record = records.pop_front().unwrap();
+                callback(record.event.into(), &mut ());
+            }
+
+            match records.front() {
+                Some(record) => TimeoutAction::ToInstant(start + Duration::from_micros(record.offset_us)),
+                None => {
+                    callback(InputEvent::Special(ReplayComplete), &mut ());
+                    TimeoutAction::Drop
+                }
+            }
+        })
+    }
+
+    fn register(&mut self, poll: &mut Poll, factory: &mut TokenFactory) -> calloop::Result<()> {
+        let start = *self.start.get_or_insert_with(Instant::now);
+        let mut timer = Timer::from_deadline(self.deadline(start));
+        timer.register(poll, factory)?;
+        self.timer = Some(timer);
+        Ok(())
+    }
+
+    fn reregister(&mut self, poll: &mut Poll, factory: &mut TokenFactory) -> calloop::Result<()> {
+        match self.timer.as_mut() {
+            Some(timer) => timer.reregister(poll, factory),
+            None => self.register(poll, factory),
+        }
+    }
+
+    fn unregister(&mut self, poll: &mut Poll) -> calloop::Result<()> {
+        match self.timer.take() {
+            Some(mut timer) => timer.unregister(poll),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use calloop::EventLoop;
+
+    use super::{
+        record_event, EventReplayBackend, Record, ReplayDevice, ReplayKeyboardKeyEvent, ReplayTouchSlotEvent,
+    };
+    use crate::backend::input::{
+        Device, DeviceCapability, Event, InputEvent, KeyState, KeyboardKeyEvent, TouchEvent, TouchSlot,
+    };
+
+    fn device() -> ReplayDevice {
+        ReplayDevice {
+            id: "test".into(),
+            name: "Test device".into(),
+            capabilities: vec![DeviceCapability::Keyboard, DeviceCapability::Touch],
+            usb_id: None,
+            syspath: None,
+        }
+    }
+
+    #[test]
+    fn record_and_replay() {
+        let events: Vec<InputEvent<EventReplayBackend>> = vec![
+            InputEvent::Keyboard {
+                event: ReplayKeyboardKeyEvent {
+                    time: 1000,
+                    device: device(),
+                    key_code: 30,
+                    state: KeyState::Pressed,
+                    count: 1,
+                },
+            },
+            InputEvent::TouchUp {
+                event: ReplayTouchSlotEvent {
+                    time: 2000,
+                    device: device(),
+                    slot: Some(2),
+                },
+            },
+        ];
+
+        let mut recording = Vec::new();
+        let mut devices = HashSet::new();
+        for (i, event) in events.iter().enumerate() {
+            for event in record_event(event, &mut devices) {
+                let record = Record {
+                    offset_us: i as u64 * 1000,
+                    event,
+                };
+                ciborium::ser::into_writer(&record, &mut recording).unwrap();
+            }
+        }
+
+        let replay = EventReplayBackend::new(&recording[..]).unwrap();
+        // the device is recorded as added before its first event
+        assert_eq!(replay.remaining(), 3);
+
+        let mut event_loop = EventLoop::<Vec<InputEvent<EventReplayBackend>>>::try_new().unwrap();
+        event_loop
+            .handle()
+            .insert_source(replay, |event, _, replayed| replayed.push(event))
+            .unwrap();
+        let mut replayed = Vec::new();
+        while !matches!(replayed.last(), Some(InputEvent::Special(_))) {
+            event_loop.dispatch(None, &mut replayed).unwrap();
+        }
+
+        assert_eq!(replayed.len(), 4);
+        match &replayed[0] {
+            InputEvent::DeviceAdded { device } => assert!(device.has_capability(DeviceCapability::Touch)),
+            event => panic!("unexpected event {:?}", event),
+        }
+        match &replayed[1] {
+            InputEvent::Keyboard { event } => {
+                assert_eq!(event.time(), 1000);
+                assert_eq!(event.key_code(), 30);
+                assert_eq!(event.state(), KeyState::Pressed);
+            }
+            event => panic!("unexpected event {:?}", event),
+        }
+        match &replayed[2] {
+            InputEvent::TouchUp { event } => assert_eq!(event.slot(), TouchSlot::from(Some(2))),
+            event => panic!("unexpected event {:?}", event),
+        }
+    }
+}
@@ -0,0 +1,143 @@
+use std::time::Duration;
+
+use crate::utils::{Clock, Monotonic};
+
+/// Source of the system time used by a [`TimestampNormalizer`]
+pub trait TimestampSource {
+    /// Current time in microseconds, with an undefined base
+    fn now_usec(&self) -> u64;
+}
+
+impl TimestampSource for Clock<Monotonic> {
+    fn now_usec(&self) -> u64 {
+        Duration::from(self.now()).as_micros() as u64
+    }
+}
+
+/// Converts microsecond input event timestamps into wayland timestamps
+///
+/// Input backends like libinput report timestamps in microseconds (see [`Event::time`](super::Event::time)),
+/// while wayland events like `wl_pointer::motion` or `wl_keyboard::key` use
+/// millisecond timestamps stored in an `u32`.
+///
+/// The normalizer records the offset between the event time and the system time
+/// on the first event and converts all following event times relative to that offset.
+/// The resulting timestamps start at the creation of the normalizer instead of the
+/// boot of the system, which delays the wrap-around of the `u32` timestamps (otherwise
+/// reached after ~49 days of uptime), and never decrease.
+#[derive(Debug)]
+pub struct TimestampNormalizer<S = Clock<Monotonic>> {
+    source: S,
+    start_usec: u64,
+    offset_usec: Option<i128>,
+    last_msec: Option<u32>,
+}
+
+impl TimestampNormalizer<Clock<Monotonic>> {
+    /// Create a new normalizer using the monotonic system clock
+    pub fn new() -> std::io::Result<Self> {
+        Ok(Self::with_source(Clock::new()?))
+    }
+}
+
+impl<S: TimestampSource> TimestampNormalizer<S> {
+    /// Create a new normalizer using the provided time source
+    pub fn with_source(source: S) -> Self {
+        let start_usec = source.now_usec();
+        TimestampNormalizer {
+            source,
+            start_usec,
+            offset_usec: None,
+            last_msec: None,
+        }
+    }
+
+    /// Current system time as wayland timestamp
+    ///
+    /// Uses the same base as [`TimestampNormalizer::normalize`], which makes it suitable
+    /// for events generated by the compositor itself.
+    pub fn now_msec(&self) -> u32 {
+        Self::to_msec(self.elapsed_usec() as i128)
+    }
+
+    /// Convert an event timestamp in microseconds into a wayland timestamp in milliseconds
+    ///
+    /// The returned timestamps increase monotonically, event timestamps going backwards
+    /// return the last timestamp again.
+    pub fn normalize(&mut self, time_usec: u64) -> u32 {
+        let offset_usec = match self.offset_usec {
+            Some(offset) => offset,
+            None => {
+                let offset = self.elapsed_usec() as i128 - time_usec as i128;
+                self.offset_usec = Some(offset);
+                offset
+            }
+        };
+
+        let mut msec = Self::to_msec(time_usec as i128 + offset_usec);
+        if let Some(last_msec) = self.last_msec {
+            // compare using wrapping arithmetic to support the eventual wrap-around
+            if msec.wrapping_sub(last_msec) > u32::MAX / 2 {
+                msec = last_msec;
+            }
+        }
+        self.last_msec = Some(msec);
+        msec
+    }
+
+    fn elapsed_usec(&self) -> u64 {
+        self.source.now_usec().saturating_sub(self.start_usec)
+    }
+
+    fn to_msec(usec: i128) -> u32 {
+        (usec.max(0) / 1000) as u32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::cell::Cell;
+
+    use super::{TimestampNormalizer, TimestampSource};
+
+    struct FakeClock<'a>(&'a Cell<u64>);
+
+    impl TimestampSource for FakeClock<'_> {
+        fn now_usec(&self) -> u64 {
+            self.0.get()
+        }
+    }
+
+    #[test]
+    fn normalize_relative_to_first_event() {
+        let now = Cell::new(5_000_000_000);
+        let mut normalizer = TimestampNormalizer::with_source(FakeClock(&now));
+
+        now.set(5_000_002_000);
+        assert_eq!(normalizer.normalize(1_000_000), 2);
+        assert_eq!(normalizer.normalize(1_016_000), 18);
+        assert_eq!(normalizer.now_msec(), 2);
+    }
+
+    #[test]
+    fn normalize_is_monotonic() {
+        let now = Cell::new(0);
+        let mut normalizer = TimestampNormalizer::with_source(FakeClock(&now));
+
+        assert_eq!(normalizer.normalize(50_000), 0);
+        assert_eq!(normalizer.normalize(60_000), 10);
+        assert_eq!(normalizer.normalize(55_000), 10);
+        assert_eq!(normalizer.normalize(70_000), 20);
+    }
+
+    #[test]
+    fn normalize_wraps_around() {
+        let now = Cell::new(0);
+        let mut normalizer = TimestampNormalizer::with_source(FakeClock(&now));
+
+        now.set(u32::MAX as u64 * 1000);
+        assert_eq!(normalizer.normalize(0), u32::MAX);
+        assert_eq!(normalizer.normalize(6000), 5);
+        assert_eq!(normalizer.normalize(4000), 5);
+    }
+}
@@ -0,0 +1,79 @@
+use std::collections::BTreeMap;
+
+use super::TouchSlot;
+
+/// Maps touch slots to stable touch point ids
+///
+/// Input backends like libinput report touch points by their slot index, which is
+/// reused as soon as a touch point is lifted. Clients of the `wl_touch` protocol however
+/// track touch points by their id, so reusing ids can confuse them when a new touch
+/// point starts right after another one ended, e.g. within a gesture.
+///
+/// The mapper assigns a fresh id to every touch point on [`down`](TouchSlotMapper::down)
+/// and keeps it until the touch point is released on [`up`](TouchSlotMapper::up).
+#[derive(Debug, Default)]
+pub struct TouchSlotMapper {
+    ids: BTreeMap<u32, u32>,
+    next_id: u32,
+}
+
+impl TouchSlotMapper {
+    /// Create a new mapper
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Assign a fresh id to a new touch point in the given slot
+    ///
+    /// If the slot was still in use, its previous id is replaced.
+    pub fn down(&mut self, slot: TouchSlot) -> i32 {
+        let id = self.next_id;
+        // wl_touch ids are signed, stay in the positive range
+        self.next_id = (self.next_id + 1) % (i32::MAX as u32 + 1);
+        self.ids.insert(Self::key(slot), id);
+        id as i32
+    }
+
+    /// The id of the touch point currently in the given slot
+    pub fn id(&self, slot: TouchSlot) -> Option<i32> {
+        self.ids.get(&Self::key(slot)).map(|id| *id as i32)
+    }
+
+    /// Release the touch point in the given slot, returning its id
+    pub fn up(&mut self, slot: TouchSlot) -> Option<i32> {
+        self.ids.remove(&Self::key(slot)).map(|id| id as i32)
+    }
+
+    /// Release all touch points
+    pub fn clear(&mut self) {
+        self.ids.clear();
+    }
+
+    fn key(slot: TouchSlot) -> u32 {
+        // Devices without slot support only ever report a single touch point
+        slot.id.unwrap_or(u32::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TouchSlotMapper;
+
+    #[test]
+    fn reused_slots_get_fresh_ids() {
+        let mut mapper = TouchSlotMapper::new();
+
+        let first = mapper.down(Some(0).into());
+        let second = mapper.down(Some(1).into());
+        assert_ne!(first, second);
+        assert_eq!(mapper.id(Some(0).into()), Some(first));
+
+        assert_eq!(mapper.up(Some(0).into()), Some(first));
+        assert_eq!(mapper.id(Some(0).into()), None);
+
+        let third = mapper.down(Some(0).into());
+        assert_ne!(third, first);
+        assert_ne!(third, second);
+        assert_eq!(mapper.id(Some(1).into()), Some(second));
+    }
+}
@@ -0,0 +1,76 @@
+use input as libinput;
+use input::{AccelProfile, DeviceConfigError, ScrollMethod};
+
+/// Configuration helpers for libinput devices
+///
+/// This is implemented for [`libinput::Device`] and offers checked variants of its
+/// `config_*` functions, so compositors can apply and read back user preferences.
+pub trait LibinputDevice {
+    /// Set the pointer acceleration profile of this device
+    fn set_accel_profile(&mut self, profile: AccelProfile) -> Result<(), DeviceConfigError>;
+
+    /// Returns the currently applied pointer acceleration profile
+    ///
+    /// Returns `None` if the device does not support pointer acceleration.
+    fn accel_profile(&self) -> Option<AccelProfile>;
+
+    /// Set the pointer acceleration speed of this device
+    ///
+    /// The speed is clamped to the range `-1.0..=1.0`, where `0.0` is the default speed.
+    fn set_accel_speed(&mut self, speed: f64) -> Result<(), DeviceConfigError>;
+
+    /// Returns the currently applied pointer acceleration speed
+    ///
+    /// Returns `None` if the device does not support pointer acceleration.
+    fn accel_speed(&self) -> Option<f64>;
+
+    /// Set the scroll method of this device
+    ///
+    /// [`ScrollMethod::OnButtonDown`] scrolls while the button set with
+    /// [`set_scroll_button`](LibinputDevice::set_scroll_button) is held down.
+    fn set_scroll_method(&mut self, method: ScrollMethod) -> Result<(), DeviceConfigError>;
+
+    /// Returns the currently applied scroll method
+    ///
+    /// Returns `None` if the device does not support any scroll methods.
+    fn scroll_method(&self) -> Option<ScrollMethod>;
+
+    /// Set the button used for [`ScrollMethod::OnButtonDown`] scrolling
+    ///
+    /// The button is given as linux input event code, e.g. `BTN_MIDDLE`.
+    fn set_scroll_button(&mut self, button: u32) -> Result<(), DeviceConfigError>;
+}
+
+impl LibinputDevice for libinput::Device {
+    fn set_accel_profile(&mut self, profile: AccelProfile) -> Result<(), DeviceConfigError> {
+        self.config_accel_set_profile(profile)
+    }
+
+    fn accel_profile(&self) -> Option<AccelProfile> {
+        self.config_accel_profile()
+    }
+
+    fn set_accel_speed(&mut self, speed: f64) -> Result<(), DeviceConfigError> {
+        if speed.is_nan() {
+            return Err(DeviceConfigError::Invalid);
+        }
+        self.config_accel_set_speed(speed.clamp(-1.0, 1.0))
+    }
+
+    fn accel_speed(&self) -> Option<f64> {
+        self.config_accel_is_available()
+            .then(|| self.config_accel_speed())
+    }
+
+    fn set_scroll_method(&mut self, method: ScrollMethod) -> Result<(), DeviceConfigError> {
+        self.config_scroll_set_method(method)
+    }
+
+    fn scroll_method(&self) -> Option<ScrollMethod> {
+        self.config_scroll_method()
+    }
+
+    fn set_scroll_button(&mut self, button: u32) -> Result<(), DeviceConfigError> {
+        self.config_scroll_set_button(button)
+    }
+}
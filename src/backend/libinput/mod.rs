@@ -11,6 +11,7 @@ use input as libinput;
 use input::event;
 
 use std::{
+    collections::VecDeque,
     io,
     os::unix::io::{AsRawFd, RawFd},
     path::PathBuf,
@@ -25,15 +26,24 @@ use calloop::{EventSource, Interest, Mode, Poll, PostAction, Readiness, Token, T
 
 use slog::{info, o, trace};
 
+mod device;
 mod tablet;
 
+pub use device::LibinputDevice;
+
 /// Libinput based [`InputBackend`].
 ///
 /// Tracks input of all devices given manually or via a udev seat to a provided libinput
 /// context.
+///
+/// Devices already present when the context was assigned to a seat are reported through
+/// [`InputEvent::DeviceAdded`] once the backend is dispatched for the first time, like devices
+/// plugged in later on. Use [`LibinputInputBackend::existing_devices`] to configure them before
+/// starting the event loop.
 #[derive(Debug)]
 pub struct LibinputInputBackend {
     context: libinput::Libinput,
+    queued: VecDeque<libinput::Event>,
     logger: ::slog::Logger,
     token: Option<Token>,
 }
@@ -49,6 +59,7 @@ impl LibinputInputBackend {
         info!(log, "Initializing a libinput backend");
         LibinputInputBackend {
             context,
+            queued: VecDeque::new(),
             logger: log,
             token: None,
         }
@@ -58,6 +69,24 @@ impl LibinputInputBackend {
     pub fn context(&self) -> &libinput::Libinput {
         &self.context
     }
+
+    /// Returns the devices found while the context was assigned to its seat or devices were added to it,
+    /// that were not yet reported through [`InputEvent::DeviceAdded`]
+    ///
+    /// Compositors should call this before starting the event loop, to configure devices that are
+    /// already present. The devices are still reported through [`InputEvent::DeviceAdded`] afterwards.
+    pub fn existing_devices(&mut self) -> Vec<libinput::Device> {
+        self.queued.extend(&mut self.context);
+        self.queued
+            .iter()
+            .filter_map(|event| match event {
+                libinput::Event::Device(event::DeviceEvent::Added(added)) => {
+                    Some(event::EventTrait::device(added))
+                }
+                _ => None,
+            })
+            .collect()
+    }
 }
 
 impl backend::Device for libinput::Device {
@@ -637,7 +666,7 @@ impl EventSource for LibinputInputBackend {
         if Some(token) == self.token {
             self.context.dispatch()?;
 
-            for event in &mut self.context {
+            for event in self.queued.drain(..).chain(&mut self.context) {
                 match event {
                     libinput::Event::Device(device_event) => match device_event {
                         event::DeviceEvent::Added(device_added_event) => {
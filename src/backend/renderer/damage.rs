@@ -54,6 +54,7 @@
 //! #         _: &[Rectangle<i32, Physical>],
 //! #         _: Transform,
 //! #         _: f32,
+//! #         _: smithay::backend::renderer::BlendMode,
 //! #     ) -> Result<(), Self::Error> {
 //! #         unimplemented!()
 //! #     }
@@ -160,14 +161,18 @@
 //! }
 //! ```
 
-use std::collections::{HashMap, VecDeque};
+use std::{
+    collections::{hash_map::DefaultHasher, HashMap, HashSet, VecDeque},
+    hash::{Hash, Hasher},
+    time::Instant,
+};
 
 use indexmap::IndexMap;
 
 use crate::{
     backend::renderer::{element::RenderElementPresentationState, Frame},
     output::Output,
-    utils::{Physical, Rectangle, Scale, Size, Transform},
+    utils::{Physical, Rectangle, Region, Scale, Size, Transform},
 };
 
 use super::{
@@ -193,6 +198,8 @@ impl ElementInstanceState {
 struct ElementState {
     last_commit: CommitCounter,
     last_instances: Vec<ElementInstanceState>,
+    last_opaque_regions: Vec<Rectangle<i32, Physical>>,
+    last_opaque_regions_hash: u64,
 }
 
 impl ElementState {
@@ -203,13 +210,42 @@ impl ElementState {
     }
 }
 
-#[derive(Debug, Default)]
+/// Hash the opaque regions independent of their order
+fn opaque_regions_hash(regions: &[Rectangle<i32, Physical>]) -> u64 {
+    let mut regions = regions
+        .iter()
+        .map(|r| (r.loc.x, r.loc.y, r.size.w, r.size.h))
+        .collect::<Vec<_>>();
+    regions.sort_unstable();
+
+    let mut hasher = DefaultHasher::new();
+    regions.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[derive(Debug, Default, Clone)]
 struct RendererState {
     size: Option<Size<i32, Physical>>,
+    scale: Option<Scale<f64>>,
+    clear_color: Option<[f32; 4]>,
     elements: IndexMap<Id, ElementState>,
     old_damage: VecDeque<Vec<Rectangle<i32, Physical>>>,
 }
 
+/// Snapshot of the damage tracking state of a [`DamageTrackedRenderer`]
+///
+/// Created with [`DamageTrackedRenderer::to_snapshot`] and restored with
+/// [`DamageTrackedRenderer::from_snapshot`].
+///
+/// With the `bincode` feature the snapshot implements `bincode::Encode` and `bincode::Decode`,
+/// e.g. to transfer it to a separate rendering process. See the `Encode` implementation of
+/// [`Id`] for how element ids are matched after decoding.
+#[derive(Debug, Clone)]
+pub struct DamageTrackedRendererSnapshot {
+    state: RendererState,
+    excluded: HashSet<Id>,
+}
+
 /// Mode for the [`DamageTrackedRenderer`]
 #[derive(Debug, Clone)]
 pub enum DamageTrackedRendererMode {
@@ -250,11 +286,114 @@ impl TryInto<(Size<i32, Physical>, Scale<f64>, Transform)> for DamageTrackedRend
     }
 }
 
+/// Time spent in the phases of a single [`DamageTrackedRenderer::render_output`] call
+///
+/// All durations are in microseconds.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameTiming {
+    /// Time spent calculating the damage of the elements
+    pub damage_calc_us: u64,
+    /// Time spent clearing the damaged regions not covered by opaque elements
+    pub clear_us: u64,
+    /// Time spent drawing the elements
+    pub element_draw_us: u64,
+    /// Total time of the call, including finishing the frame
+    pub total_us: u64,
+}
+
+/// Rolling statistics about the damage of the last frames of an output
+///
+/// Intended for debug overlays, feed it the damage returned by
+/// [`DamageTrackedRenderer::render_output`] with [`DamageStats::record_frame`].
+#[derive(Debug, Clone)]
+pub struct DamageStats {
+    capacity: usize,
+    // damaged pixels and output area of the last frames
+    history: VecDeque<(usize, usize)>,
+    frames_skipped: u64,
+    frames_rendered: u64,
+}
+
+impl DamageStats {
+    /// Create new statistics, averaging over the given number of frames
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(1);
+        DamageStats {
+            capacity,
+            history: VecDeque::with_capacity(capacity),
+            frames_skipped: 0,
+            frames_rendered: 0,
+        }
+    }
+
+    /// Record the damage of a frame
+    ///
+    /// `None` damage marks a frame that was skipped because nothing was damaged.
+    pub fn record_frame(&mut self, damage: Option<&[Rectangle<i32, Physical>]>, output_area: usize) {
+        let damaged_pixels = match damage {
+            Some(damage) => {
+                self.frames_rendered += 1;
+                damage
+                    .iter()
+                    .map(|rect| rect.size.w.max(0) as usize * rect.size.h.max(0) as usize)
+                    .sum::<usize>()
+                    .min(output_area)
+            }
+            None => {
+                self.frames_skipped += 1;
+                0
+            }
+        };
+
+        if self.history.len() == self.capacity {
+            self.history.pop_front();
+        }
+        self.history.push_back((damaged_pixels, output_area));
+    }
+
+    /// Average fraction of the output that was damaged over the recorded frames
+    pub fn average_damage_fraction(&self) -> f32 {
+        if self.history.is_empty() {
+            return 0.0;
+        }
+
+        let sum = self
+            .history
+            .iter()
+            .filter(|(_, output_area)| *output_area > 0)
+            .map(|(damaged_pixels, output_area)| *damaged_pixels as f32 / *output_area as f32)
+            .sum::<f32>();
+        sum / self.history.len() as f32
+    }
+
+    /// Highest number of damaged pixels of the recorded frames
+    pub fn peak_damage_pixels(&self) -> usize {
+        self.history
+            .iter()
+            .map(|(damaged_pixels, _)| *damaged_pixels)
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Total number of frames skipped, because nothing was damaged
+    pub fn frames_skipped(&self) -> u64 {
+        self.frames_skipped
+    }
+
+    /// Total number of frames rendered
+    pub fn frames_rendered(&self) -> u64 {
+        self.frames_rendered
+    }
+}
+
 /// Damage tracked renderer for a single output
 #[derive(Debug)]
 pub struct DamageTrackedRenderer {
     mode: DamageTrackedRendererMode,
     last_state: RendererState,
+    profiling: bool,
+    last_frame_timing: Option<FrameTiming>,
+    excluded: HashSet<Id>,
 }
 
 /// Errors thrown by [`DamageTrackedRenderer::render_output`]
@@ -266,6 +405,11 @@ pub enum DamageTrackedRendererError<R: Renderer> {
     /// The given [`Output`] has no mode set
     #[error(transparent)]
     OutputNoMode(#[from] OutputNoMode),
+    /// The context of the provided [`Renderer`] was lost (see [`Renderer::is_context_lost`])
+    ///
+    /// The renderer has to be recovered or recreated before rendering again.
+    #[error("The renderer context was lost: {0}")]
+    ContextLost(R::Error),
 }
 
 impl<R: Renderer> std::fmt::Debug for DamageTrackedRendererError<R> {
@@ -273,6 +417,7 @@ impl<R: Renderer> std::fmt::Debug for DamageTrackedRendererError<R> {
         match self {
             DamageTrackedRendererError::Rendering(err) => std::fmt::Debug::fmt(err, f),
             DamageTrackedRendererError::OutputNoMode(err) => std::fmt::Debug::fmt(err, f),
+            DamageTrackedRendererError::ContextLost(err) => f.debug_tuple("ContextLost").field(err).finish(),
         }
     }
 }
@@ -291,6 +436,9 @@ impl DamageTrackedRenderer {
                 transform,
             },
             last_state: Default::default(),
+            profiling: false,
+            last_frame_timing: None,
+            excluded: HashSet::new(),
         }
     }
 
@@ -303,6 +451,33 @@ impl DamageTrackedRenderer {
         Self {
             mode: DamageTrackedRendererMode::Auto(output.clone()),
             last_state: Default::default(),
+            profiling: false,
+            last_frame_timing: None,
+            excluded: HashSet::new(),
+        }
+    }
+
+    /// Initialize a [`DamageTrackedRenderer`] with the damage tracking state of a snapshot
+    ///
+    /// The element [`Id`]s of the snapshot are only valid within the same process.
+    pub fn from_snapshot(mode: DamageTrackedRendererMode, snapshot: DamageTrackedRendererSnapshot) -> Self {
+        Self {
+            mode,
+            last_state: snapshot.state,
+            profiling: false,
+            last_frame_timing: None,
+            excluded: snapshot.excluded,
+        }
+    }
+
+    /// Capture the damage tracking state of this [`DamageTrackedRenderer`]
+    ///
+    /// This includes the state of the elements of the last frame, the damage of
+    /// previous frames and the excluded elements.
+    pub fn to_snapshot(&self) -> DamageTrackedRendererSnapshot {
+        DamageTrackedRendererSnapshot {
+            state: self.last_state.clone(),
+            excluded: self.excluded.clone(),
         }
     }
 
@@ -311,6 +486,53 @@ impl DamageTrackedRenderer {
         &self.mode
     }
 
+    /// Exclude the element with the given [`Id`] from rendering and damage tracking
+    ///
+    /// Excluded elements are handled as if they were outside of the output,
+    /// which is useful for elements displayed by other means, like a hardware cursor plane.
+    pub fn exclude_element(&mut self, id: Id) {
+        self.excluded.insert(id);
+    }
+
+    /// Include an element previously excluded with [`exclude_element`](DamageTrackedRenderer::exclude_element) again
+    pub fn include_element(&mut self, id: Id) {
+        self.excluded.remove(&id);
+    }
+
+    /// Enable or disable collecting a [`FrameTiming`] for every call to
+    /// [`render_output`](DamageTrackedRenderer::render_output)
+    ///
+    /// Profiling is disabled by default.
+    pub fn set_profiling(&mut self, profiling: bool) {
+        self.profiling = profiling;
+        if !profiling {
+            self.last_frame_timing = None;
+        }
+    }
+
+    /// Returns whether profiling is enabled
+    pub fn profiling(&self) -> bool {
+        self.profiling
+    }
+
+    /// Timing of the last call to [`render_output`](DamageTrackedRenderer::render_output)
+    ///
+    /// Only available if profiling is enabled, see [`set_profiling`](DamageTrackedRenderer::set_profiling).
+    pub fn last_frame_timing(&self) -> Option<&FrameTiming> {
+        self.last_frame_timing.as_ref()
+    }
+
+    /// Damage of the last frame damaged by [`render_output`](DamageTrackedRenderer::render_output)
+    /// or [`damage_output`](DamageTrackedRenderer::damage_output)
+    ///
+    /// Unlike the damage returned by these functions this does not include damage accumulated
+    /// for older buffers, making it suitable for e.g. screencopy or presentation feedback.
+    /// An empty slice means nothing changed in the last frame. Returns `None` if no frame
+    /// was rendered yet or the last rendering failed.
+    pub fn last_frame_damage(&self) -> Option<&[Rectangle<i32, Physical>]> {
+        self.last_state.old_damage.front().map(|damage| &**damage)
+    }
+
     /// Render this output
     pub fn render_output<E, R>(
         &mut self,
@@ -324,8 +546,48 @@ impl DamageTrackedRenderer {
         E: RenderElement<R>,
         R: Renderer,
         <R as Renderer>::TextureId: Texture,
+    {
+        self.render_output_with_hooks(
+            renderer,
+            age,
+            elements,
+            clear_color,
+            log,
+            |_, _, _| {},
+            |_, _, _| {},
+        )
+    }
+
+    /// Render this output, calling the provided hooks around the draw call of every element
+    ///
+    /// `pre_draw` is called right before and `post_draw` right after an element is drawn,
+    /// with the z-index of the element among the rendered elements (`0` being the topmost one),
+    /// the element itself and the current frame.
+    /// This allows to set up and reset renderer specific state, like custom blend equations,
+    /// scissor rects or stencil tests, for individual elements.
+    ///
+    /// Elements without damage are skipped and do not trigger the hooks.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render_output_with_hooks<E, R, Pre, Post>(
+        &mut self,
+        renderer: &mut R,
+        age: usize,
+        elements: &[E],
+        clear_color: [f32; 4],
+        log: impl Into<Option<slog::Logger>>,
+        mut pre_draw: Pre,
+        mut post_draw: Post,
+    ) -> Result<(Option<Vec<Rectangle<i32, Physical>>>, RenderElementStates), DamageTrackedRendererError<R>>
+    where
+        E: RenderElement<R>,
+        R: Renderer,
+        <R as Renderer>::TextureId: Texture,
+        Pre: for<'frame> FnMut(usize, &E, &mut <R as Renderer>::Frame<'frame>),
+        Post: for<'frame> FnMut(usize, &E, &mut <R as Renderer>::Frame<'frame>),
     {
         let log = crate::slog_or_fallback(log);
+        let start = self.profiling.then(Instant::now);
+        let mut timing = FrameTiming::default();
 
         let (output_size, output_scale, output_transform) = self.mode.clone().try_into()?;
         // We have to apply to output transform to the output size so that the intersection
@@ -343,13 +605,21 @@ impl DamageTrackedRenderer {
             &log,
             output_scale,
             output_geo,
+            Some(clear_color),
             &mut damage,
             &mut render_elements,
             &mut opaque_regions,
         );
+        if let Some(start) = start {
+            timing.damage_calc_us = start.elapsed().as_micros() as u64;
+        }
 
         if damage.is_empty() {
             slog::trace!(log, "no damage, skipping rendering");
+            if let Some(start) = start {
+                timing.total_us = start.elapsed().as_micros() as u64;
+                self.last_frame_timing = Some(timing);
+            }
             return Ok((None, states));
         }
 
@@ -363,18 +633,17 @@ impl DamageTrackedRenderer {
         let render_res = (|| {
             let mut frame = renderer.render(output_size, output_transform)?;
 
-            let clear_damage = opaque_regions.iter().flat_map(|(_, regions)| regions).fold(
-                damage.clone(),
-                |damage, region| {
-                    damage
-                        .into_iter()
-                        .flat_map(|geo| geo.subtract_rect(*region))
-                        .collect::<Vec<_>>()
-                },
-            );
+            let mut clear_damage = damage.iter().copied().collect::<Region<_>>();
+            clear_damage.subtract_rects(opaque_regions.iter().flat_map(|(_, regions)| regions).copied());
 
             slog::trace!(log, "clearing damage {:?}", clear_damage);
-            frame.clear(clear_color, &clear_damage)?;
+            let clear_start = start.map(|_| Instant::now());
+            frame.clear(clear_color, clear_damage.rects())?;
+            if let Some(clear_start) = clear_start {
+                timing.clear_us = clear_start.elapsed().as_micros() as u64;
+            }
+
+            let draw_start = start.map(|_| Instant::now());
 
             for (mut z_index, element) in render_elements.iter().rev().enumerate() {
                 // This is necessary because we reversed the render elements to draw
@@ -384,24 +653,19 @@ impl DamageTrackedRenderer {
 
                 let element_id = element.id();
                 let element_geometry = element.geometry(output_scale);
+                let element_visible_geometry = clipped_geometry(*element, output_scale).unwrap_or_default();
 
-                let element_damage = opaque_regions
-                    .iter()
-                    .filter(|(index, _)| *index < z_index)
-                    .flat_map(|(_, regions)| regions)
-                    .fold(
-                        damage
-                            .clone()
-                            .into_iter()
-                            .filter_map(|d| d.intersection(element_geometry))
-                            .collect::<Vec<_>>(),
-                        |damage, region| {
-                            damage
-                                .into_iter()
-                                .flat_map(|geo| geo.subtract_rect(*region))
-                                .collect::<Vec<_>>()
-                        },
-                    )
+                let mut element_damage = damage.iter().copied().collect::<Region<_>>();
+                element_damage.intersect_with_rect(element_visible_geometry);
+                element_damage.subtract_rects(
+                    opaque_regions
+                        .iter()
+                        .filter(|(index, _)| *index < z_index)
+                        .flat_map(|(_, regions)| regions)
+                        .copied(),
+                );
+                let element_damage = element_damage
+                    .into_rects()
                     .into_iter()
                     .map(|mut d| {
                         d.loc -= element_geometry.loc;
@@ -427,7 +691,12 @@ impl DamageTrackedRenderer {
                     element_damage,
                 );
 
+                pre_draw(z_index, element, &mut frame);
                 element.draw(&mut frame, element.src(), element_geometry, &element_damage, &log)?;
+                post_draw(z_index, element, &mut frame);
+            }
+            if let Some(draw_start) = draw_start {
+                timing.element_draw_us = draw_start.elapsed().as_micros() as u64;
             }
 
             Result::<(), R::Error>::Ok(())
@@ -437,9 +706,17 @@ impl DamageTrackedRenderer {
             // if the rendering errors on us, we need to be prepared, that this whole buffer was partially updated and thus now unusable.
             // thus clean our old states before returning
             self.last_state = Default::default();
+            if renderer.is_context_lost() {
+                return Err(DamageTrackedRendererError::ContextLost(err));
+            }
             return Err(DamageTrackedRendererError::Rendering(err));
         }
 
+        if let Some(start) = start {
+            timing.total_us = start.elapsed().as_micros() as u64;
+            self.last_frame_timing = Some(timing);
+        }
+
         Ok((Some(damage), states))
     }
 
@@ -471,6 +748,7 @@ impl DamageTrackedRenderer {
             &log,
             output_scale,
             output_geo,
+            None,
             &mut damage,
             &mut render_elements,
             &mut opaque_regions,
@@ -483,6 +761,62 @@ impl DamageTrackedRenderer {
         }
     }
 
+    /// Compute the damage of this output without updating the tracked state
+    ///
+    /// Works like [`damage_output`](DamageTrackedRenderer::damage_output), but the
+    /// state used for damage tracking is left untouched. This can be used to decide
+    /// whether the output needs to be rendered at all, without committing to the
+    /// provided elements.
+    pub fn peek_damage_output<E>(
+        &self,
+        age: usize,
+        elements: &[E],
+        log: impl Into<Option<slog::Logger>>,
+    ) -> Result<(Option<Vec<Rectangle<i32, Physical>>>, RenderElementStates), OutputNoMode>
+    where
+        E: Element,
+    {
+        let mut renderer = DamageTrackedRenderer {
+            mode: self.mode.clone(),
+            last_state: self.last_state.clone(),
+            profiling: false,
+            last_frame_timing: None,
+            excluded: self.excluded.clone(),
+        };
+        renderer.damage_output(age, elements, log)
+    }
+
+    /// Compute the damage between two sets of elements without updating the tracked state
+    ///
+    /// Starting from an empty state, `before` is used as the previously rendered frame
+    /// and the damage of rendering `after` with the given `age` is returned.
+    /// The damage of an age of `1` only contains the difference between the two sets of elements,
+    /// any other age damages the whole output.
+    ///
+    /// This is mostly useful for test suites comparing the damage of element configurations.
+    pub fn compute_damage_between<E>(
+        &self,
+        before: &[E],
+        after: &[E],
+        age: usize,
+    ) -> Result<Vec<Rectangle<i32, Physical>>, OutputNoMode>
+    where
+        E: Element,
+    {
+        let mut renderer = DamageTrackedRenderer {
+            mode: self.mode.clone(),
+            last_state: Default::default(),
+            profiling: false,
+            last_frame_timing: None,
+            excluded: self.excluded.clone(),
+        };
+        // render `before` twice, so the damage of the initial frame is not part of the result
+        renderer.damage_output(0, before, None)?;
+        renderer.damage_output(1, before, None)?;
+        let (damage, _) = renderer.damage_output(age, after, None)?;
+        Ok(damage.unwrap_or_default())
+    }
+
     #[allow(clippy::too_many_arguments)]
     fn damage_output_internal<'a, E>(
         &mut self,
@@ -491,6 +825,7 @@ impl DamageTrackedRenderer {
         log: &slog::Logger,
         output_scale: Scale<f64>,
         output_geo: Rectangle<i32, Physical>,
+        clear_color: Option<[f32; 4]>,
         damage: &mut Vec<Rectangle<i32, Physical>>,
         render_elements: &mut Vec<&'a E>,
         opaque_regions: &mut Vec<(usize, Vec<Rectangle<i32, Physical>>)>,
@@ -510,25 +845,33 @@ impl DamageTrackedRenderer {
             let element_id = element.id();
             let element_loc = element.geometry(output_scale).loc;
 
+            // Excluded elements are handled like elements outside of the output
+            if self.excluded.contains(element_id) {
+                continue;
+            }
+
             // First test if the element overlaps with the output
             // if not we can skip it
-            let element_output_geometry = match element.geometry(output_scale).intersection(output_geo) {
-                Some(geo) => geo,
-                None => continue,
-            };
+            let element_output_geometry =
+                match clipped_geometry(element, output_scale).and_then(|geo| geo.intersection(output_geo)) {
+                    Some(geo) => geo,
+                    None => continue,
+                };
+            // Damage and opaque regions outside of the clip rect are discarded
+            let element_clip = element
+                .clip_rect(output_scale)
+                .and_then(|clip| clip.intersection(output_geo))
+                .unwrap_or(output_geo);
 
             // Then test if the element is completely hidden behind opaque regions
-            let element_visible_area = opaque_regions
-                .iter()
-                .flat_map(|(_, opaque_regions)| opaque_regions)
-                .fold([element_output_geometry].to_vec(), |geometry, opaque_region| {
-                    geometry
-                        .into_iter()
-                        .flat_map(|g| g.subtract_rect(*opaque_region))
-                        .collect::<Vec<_>>()
-                })
-                .into_iter()
-                .fold(0usize, |acc, item| acc + (item.size.w * item.size.h) as usize);
+            let mut element_visible_region = Region::from(element_output_geometry);
+            element_visible_region.subtract_rects(
+                opaque_regions
+                    .iter()
+                    .flat_map(|(_, opaque_regions)| opaque_regions)
+                    .copied(),
+            );
+            let element_visible_area = element_visible_region.area();
 
             // No need to draw a completely hidden element
             if element_visible_area == 0 {
@@ -552,7 +895,7 @@ impl DamageTrackedRenderer {
                     d.loc += element_loc;
                     d
                 })
-                .filter_map(|geo| geo.intersection(output_geo))
+                .filter_map(|geo| geo.intersection(element_clip))
                 .collect::<Vec<_>>();
             damage.extend(element_output_damage);
 
@@ -563,7 +906,7 @@ impl DamageTrackedRenderer {
                     region.loc += element_loc;
                     region
                 })
-                .filter_map(|geo| geo.intersection(output_geo))
+                .filter_map(|geo| geo.intersection(element_clip))
                 .collect::<Vec<_>>();
             opaque_regions.push((z_index, element_opaque_regions));
             render_elements.push(element);
@@ -590,52 +933,62 @@ impl DamageTrackedRenderer {
             .iter()
             .filter(|(id, _)| !render_elements.iter().any(|e| e.id() == *id))
             .flat_map(|(_, state)| {
-                opaque_regions
+                let mut gone = state
+                    .last_instances
                     .iter()
-                    .filter(|(z_index, _)| state.last_instances.iter().any(|i| *z_index < i.last_z_index))
-                    .flat_map(|(_, opaque_regions)| opaque_regions)
-                    .fold(
-                        state
-                            .last_instances
-                            .iter()
-                            .map(|i| i.last_geometry)
-                            .collect::<Vec<_>>(),
-                        |damage, opaque_region| {
-                            damage
-                                .into_iter()
-                                .flat_map(|damage| damage.subtract_rect(*opaque_region))
-                                .collect::<Vec<_>>()
-                        },
-                    )
+                    .map(|i| i.last_geometry)
+                    .collect::<Region<_>>();
+                gone.subtract_rects(
+                    opaque_regions
+                        .iter()
+                        .filter(|(z_index, _)| state.last_instances.iter().any(|i| *z_index < i.last_z_index))
+                        .flat_map(|(_, opaque_regions)| opaque_regions)
+                        .copied(),
+                );
+                gone.into_rects()
             })
             .collect::<Vec<_>>();
         damage.extend(elements_gone);
 
+        // if the opaque regions of an element changed damage the old and the new
+        // regions, otherwise we might leave stale content behind transparent parts
+        let mut current_opaque_regions: HashMap<&Id, Vec<Rectangle<i32, Physical>>> = HashMap::new();
+        for (element, (_, element_opaque_regions)) in render_elements.iter().zip(opaque_regions.iter()) {
+            current_opaque_regions
+                .entry(element.id())
+                .or_default()
+                .extend(element_opaque_regions.iter().copied());
+        }
+        for (id, regions) in current_opaque_regions {
+            if let Some(state) = self.last_state.elements.get(id) {
+                if state.last_opaque_regions_hash != opaque_regions_hash(&regions) {
+                    damage.extend(state.last_opaque_regions.iter().copied());
+                    damage.extend(regions);
+                }
+            }
+        }
+
         // if the element has been moved or it's z index changed damage it
         for (z_index, element) in render_elements.iter().enumerate() {
-            let element_geometry = element.geometry(output_scale);
+            let element_geometry = clipped_geometry(*element, output_scale).unwrap_or_default();
             let element_last_state = self.last_state.elements.get(element.id());
 
             if element_last_state
                 .map(|s| !s.instance_matches(element_geometry, z_index))
                 .unwrap_or(true)
             {
-                let mut element_damage = vec![element_geometry];
+                let mut element_damage = Region::from(element_geometry);
                 if let Some(state) = element_last_state {
                     element_damage.extend(state.last_instances.iter().map(|i| i.last_geometry));
                 }
-                damage.extend(
+                element_damage.subtract_rects(
                     opaque_regions
                         .iter()
                         .filter(|(index, _)| *index < z_index)
                         .flat_map(|(_, opaque_regions)| opaque_regions)
-                        .fold(element_damage, |damage, opaque_region| {
-                            damage
-                                .into_iter()
-                                .flat_map(|damage| damage.subtract_rect(*opaque_region))
-                                .collect::<Vec<_>>()
-                        }),
+                        .copied(),
                 );
+                damage.extend(element_damage.into_rects());
             }
         }
 
@@ -648,6 +1001,14 @@ impl DamageTrackedRenderer {
             // The output geometry changed, so just damage everything
             slog::trace!(log, "Output geometry changed, damaging whole output geometry. previous geometry: {:?}, current geometry: {:?}", self.last_state.size, output_geo);
             *damage = vec![output_geo];
+        } else if self.last_state.scale != Some(output_scale) {
+            // Everything gets rendered at a different size, so damage everything as well
+            slog::trace!(log, "Output scale changed, damaging whole output geometry. previous scale: {:?}, current scale: {:?}", self.last_state.scale, output_scale);
+            *damage = vec![output_geo];
+        } else if clear_color.is_some() && self.last_state.clear_color != clear_color {
+            // The clear color is visible wherever no opaque element covers the output
+            slog::trace!(log, "Clear color changed, damaging whole output geometry. previous color: {:?}, current color: {:?}", self.last_state.clear_color, clear_color);
+            *damage = vec![output_geo];
         }
 
         // That is all completely new damage, which we need to store for subsequent renders
@@ -697,17 +1058,21 @@ impl DamageTrackedRenderer {
 
         slog::trace!(log, "damage to be rendered: {:#?}", &damage);
 
-        let new_elements_state = render_elements.iter().enumerate().fold(
+        let mut new_elements_state = render_elements.iter().enumerate().fold(
             IndexMap::<Id, ElementState>::with_capacity(render_elements.len()),
             |mut map, (z_index, elem)| {
                 let id = elem.id();
-                let elem_geometry = elem.geometry(output_scale);
+                let elem_geometry = clipped_geometry(*elem, output_scale).unwrap_or_default();
+                let elem_opaque_regions = &opaque_regions[z_index].1;
 
                 if let Some(state) = map.get_mut(id) {
                     state.last_instances.push(ElementInstanceState {
                         last_geometry: elem_geometry,
                         last_z_index: z_index,
                     });
+                    state
+                        .last_opaque_regions
+                        .extend(elem_opaque_regions.iter().copied());
                 } else {
                     let current_commit = elem.current_commit();
                     map.insert(
@@ -718,6 +1083,8 @@ impl DamageTrackedRenderer {
                                 last_geometry: elem_geometry,
                                 last_z_index: z_index,
                             }],
+                            last_opaque_regions: elem_opaque_regions.clone(),
+                            last_opaque_regions_hash: 0,
                         },
                     );
                 }
@@ -725,11 +1092,655 @@ impl DamageTrackedRenderer {
                 map
             },
         );
+        for state in new_elements_state.values_mut() {
+            state.last_opaque_regions_hash = opaque_regions_hash(&state.last_opaque_regions);
+        }
 
         self.last_state.size = Some(output_geo.size);
+        self.last_state.scale = Some(output_scale);
+        if clear_color.is_some() {
+            self.last_state.clear_color = clear_color;
+        }
         self.last_state.elements = new_elements_state;
         self.last_state.old_damage.push_front(new_damage);
 
         element_render_states
     }
 }
+
+/// Geometry of an element restricted to its [`clip_rect`](Element::clip_rect)
+fn clipped_geometry<E: Element>(element: &E, scale: Scale<f64>) -> Option<Rectangle<i32, Physical>> {
+    let geometry = element.geometry(scale);
+    match element.clip_rect(scale) {
+        Some(clip) => geometry.intersection(clip).filter(|geo| !geo.is_empty()),
+        None => Some(geometry),
+    }
+}
+
+/// Coalesce thin damage rectangles that are stacked on top of each other
+///
+/// Sorts the rectangles by their vertical position and merges every rectangle
+/// into its predecessor if the vertical gap between them is less than `max_gap`
+/// pixels, replacing both by their bounding rectangle.
+///
+/// This is especially useful for clients like terminal emulators that
+/// damage many narrow horizontal strips (one per changed line), at the cost
+/// of a bounded amount of over-damage.
+pub fn compress_damage(rects: &mut Vec<Rectangle<i32, Physical>>, max_gap: i32) {
+    if rects.len() < 2 {
+        return;
+    }
+
+    rects.sort_by_key(|rect| (rect.loc.y, rect.loc.x));
+
+    let mut compressed: Vec<Rectangle<i32, Physical>> = Vec::with_capacity(rects.len());
+    for rect in rects.drain(..) {
+        match compressed.last_mut() {
+            Some(last) if rect.loc.y - (last.loc.y + last.size.h) < max_gap => {
+                *last = last.merge(rect);
+            }
+            _ => compressed.push(rect),
+        }
+    }
+
+    *rects = compressed;
+}
+
+#[cfg(feature = "bincode")]
+mod bincode_impls {
+    use bincode::{
+        de::Decoder,
+        enc::Encoder,
+        error::{DecodeError, EncodeError},
+        Decode, Encode,
+    };
+    use indexmap::IndexMap;
+
+    use super::{DamageTrackedRendererSnapshot, ElementInstanceState, ElementState, RendererState};
+
+    impl Encode for ElementInstanceState {
+        fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+            self.last_geometry.encode(encoder)?;
+            self.last_z_index.encode(encoder)
+        }
+    }
+
+    impl Decode for ElementInstanceState {
+        fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+            Ok(ElementInstanceState {
+                last_geometry: Decode::decode(decoder)?,
+                last_z_index: Decode::decode(decoder)?,
+            })
+        }
+    }
+
+    impl Encode for ElementState {
+        fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+            self.last_commit.encode(encoder)?;
+            self.last_instances.encode(encoder)?;
+            self.last_opaque_regions.encode(encoder)?;
+            self.last_opaque_regions_hash.encode(encoder)
+        }
+    }
+
+    impl Decode for ElementState {
+        fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+            Ok(ElementState {
+                last_commit: Decode::decode(decoder)?,
+                last_instances: Decode::decode(decoder)?,
+                last_opaque_regions: Decode::decode(decoder)?,
+                last_opaque_regions_hash: Decode::decode(decoder)?,
+            })
+        }
+    }
+
+    impl Encode for RendererState {
+        fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+            self.size.encode(encoder)?;
+            self.scale.encode(encoder)?;
+            self.clear_color.encode(encoder)?;
+            // the order of the elements is part of the state
+            self.elements.len().encode(encoder)?;
+            for (id, state) in self.elements.iter() {
+                id.encode(encoder)?;
+                state.encode(encoder)?;
+            }
+            self.old_damage.encode(encoder)
+        }
+    }
+
+    impl Decode for RendererState {
+        fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+            let size = Decode::decode(decoder)?;
+            let scale = Decode::decode(decoder)?;
+            let clear_color = Decode::decode(decoder)?;
+            let len = usize::decode(decoder)?;
+            let mut elements = IndexMap::new();
+            for _ in 0..len {
+                elements.insert(Decode::decode(decoder)?, Decode::decode(decoder)?);
+            }
+            Ok(RendererState {
+                size,
+                scale,
+                clear_color,
+                elements,
+                old_damage: Decode::decode(decoder)?,
+            })
+        }
+    }
+
+    impl Encode for DamageTrackedRendererSnapshot {
+        fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+            self.state.encode(encoder)?;
+            self.excluded.encode(encoder)
+        }
+    }
+
+    impl Decode for DamageTrackedRendererSnapshot {
+        fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+            Ok(DamageTrackedRendererSnapshot {
+                state: Decode::decode(decoder)?,
+                excluded: Decode::decode(decoder)?,
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{compress_damage, DamageStats, DamageTrackedRenderer};
+    use crate::{
+        backend::renderer::{
+            element::{texture::TextureRenderElement, Id},
+            test::{TestRenderer, TestTexture},
+            Renderer,
+        },
+        output::{Mode, Output, PhysicalProperties, Scale as OutputScale, Subpixel},
+        utils::{Physical, Rectangle, Transform},
+    };
+
+    fn rect(x: i32, y: i32, w: i32, h: i32) -> Rectangle<i32, Physical> {
+        Rectangle::from_loc_and_size((x, y), (w, h))
+    }
+
+    #[test]
+    fn compress_adjacent_lines() {
+        let mut damage = vec![rect(0, 20, 200, 10), rect(0, 0, 100, 10), rect(10, 10, 50, 10)];
+        compress_damage(&mut damage, 1);
+        assert_eq!(damage, vec![rect(0, 0, 200, 30)]);
+    }
+
+    #[test]
+    fn compress_respects_max_gap() {
+        let mut damage = vec![rect(0, 0, 100, 10), rect(0, 14, 100, 10), rect(0, 40, 100, 10)];
+        compress_damage(&mut damage, 5);
+        assert_eq!(damage, vec![rect(0, 0, 100, 24), rect(0, 40, 100, 10)]);
+    }
+
+    #[test]
+    fn damage_stats_window() {
+        let mut stats = DamageStats::new(2);
+        stats.record_frame(Some(&[rect(0, 0, 10, 10)]), 400);
+        stats.record_frame(None, 400);
+        assert_eq!(stats.peak_damage_pixels(), 100);
+        assert_eq!(stats.average_damage_fraction(), 0.125);
+
+        stats.record_frame(Some(&[rect(0, 0, 20, 10)]), 400);
+        assert_eq!(stats.peak_damage_pixels(), 200);
+        assert_eq!(stats.average_damage_fraction(), 0.25);
+        assert_eq!(stats.frames_rendered(), 2);
+        assert_eq!(stats.frames_skipped(), 1);
+    }
+
+    #[test]
+    fn render_only_damaged_elements() {
+        let mut renderer = TestRenderer::new();
+        let texture = renderer.create_texture((10, 10).into());
+        let element = TextureRenderElement::from_static_texture(
+            Id::new(),
+            renderer.id(),
+            (0.0, 0.0),
+            texture.clone(),
+            1,
+            Transform::Normal,
+            None,
+            None,
+            None,
+            None,
+        );
+        let mut damage_tracked_renderer = DamageTrackedRenderer::new((20, 20), 1.0, Transform::Normal);
+
+        let (damage, _) = damage_tracked_renderer
+            .render_output(
+                &mut renderer,
+                0,
+                std::slice::from_ref(&element),
+                [0.0; 4],
+                None::<slog::Logger>,
+            )
+            .unwrap();
+        assert!(damage.is_some());
+        assert_eq!(renderer.clear_calls().len(), 1);
+        assert_eq!(renderer.draw_calls().len(), 1);
+        let draw_call = &renderer.draw_calls()[0];
+        assert_eq!(draw_call.texture, texture.id());
+        assert_eq!(draw_call.dst, rect(0, 0, 10, 10));
+        assert_eq!(draw_call.damage, vec![rect(0, 0, 10, 10)]);
+
+        // the damage of the previous frame is added for an age of 1
+        let (damage, _) = damage_tracked_renderer
+            .render_output(
+                &mut renderer,
+                1,
+                std::slice::from_ref(&element),
+                [0.0; 4],
+                None::<slog::Logger>,
+            )
+            .unwrap();
+        assert_eq!(damage, Some(vec![rect(0, 0, 20, 20)]));
+
+        let (damage, _) = damage_tracked_renderer
+            .render_output(
+                &mut renderer,
+                1,
+                std::slice::from_ref(&element),
+                [0.0; 4],
+                None::<slog::Logger>,
+            )
+            .unwrap();
+        assert_eq!(damage, None);
+    }
+
+    #[test]
+    fn clear_color_change_damages_whole_output() {
+        let mut renderer = TestRenderer::new();
+        let elements: &[TextureRenderElement<TestTexture>] = &[];
+        let mut damage_tracked_renderer = DamageTrackedRenderer::new((20, 20), 1.0, Transform::Normal);
+
+        for age in [0, 1] {
+            damage_tracked_renderer
+                .render_output(&mut renderer, age, elements, [0.0; 4], None::<slog::Logger>)
+                .unwrap();
+        }
+        // nothing changed, so rendering is skipped entirely
+        let (damage, _) = damage_tracked_renderer
+            .render_output(&mut renderer, 1, elements, [0.0; 4], None::<slog::Logger>)
+            .unwrap();
+        assert_eq!(damage, None);
+
+        let (damage, _) = damage_tracked_renderer
+            .render_output(&mut renderer, 1, elements, [1.0; 4], None::<slog::Logger>)
+            .unwrap();
+        assert_eq!(damage, Some(vec![rect(0, 0, 20, 20)]));
+        assert_eq!(renderer.clear_calls()[0].color, [1.0; 4]);
+    }
+
+    #[test]
+    fn scale_change_damages_whole_output() {
+        let mut renderer = TestRenderer::new();
+        let texture = renderer.create_texture((10, 10).into());
+        let element = TextureRenderElement::from_static_texture(
+            Id::new(),
+            renderer.id(),
+            (0.0, 0.0),
+            texture,
+            1,
+            Transform::Normal,
+            None,
+            None,
+            None,
+            None,
+        );
+        let elements = std::slice::from_ref(&element);
+        let output = Output::new(
+            "test".into(),
+            PhysicalProperties {
+                size: (0, 0).into(),
+                subpixel: Subpixel::Unknown,
+                make: "Smithay".into(),
+                model: "Test".into(),
+            },
+            None,
+        );
+        output.change_current_state(
+            Some(Mode {
+                size: (100, 100).into(),
+                refresh: 60_000,
+            }),
+            None,
+            Some(OutputScale::Integer(1)),
+            None,
+        );
+        let mut damage_tracked_renderer = DamageTrackedRenderer::from_output(&output);
+        damage_tracked_renderer
+            .damage_output(0, elements, None::<slog::Logger>)
+            .unwrap();
+        let (damage, _) = damage_tracked_renderer
+            .damage_output(1, elements, None::<slog::Logger>)
+            .unwrap();
+        assert_eq!(damage, Some(vec![rect(0, 0, 100, 100)]));
+        let (damage, _) = damage_tracked_renderer
+            .damage_output(1, elements, None::<slog::Logger>)
+            .unwrap();
+        assert_eq!(damage, None);
+
+        output.change_current_state(None, None, Some(OutputScale::Fractional(2.0)), None);
+        let (damage, _) = damage_tracked_renderer
+            .damage_output(1, elements, None::<slog::Logger>)
+            .unwrap();
+        assert_eq!(damage, Some(vec![rect(0, 0, 100, 100)]));
+    }
+
+    #[test]
+    fn restore_from_snapshot() {
+        let mut renderer = TestRenderer::new();
+        let texture = renderer.create_texture((10, 10).into());
+        let element = TextureRenderElement::from_static_texture(
+            Id::new(),
+            renderer.id(),
+            (0.0, 0.0),
+            texture,
+            1,
+            Transform::Normal,
+            None,
+            None,
+            None,
+            None,
+        );
+        let elements = std::slice::from_ref(&element);
+        let mut damage_tracked_renderer = DamageTrackedRenderer::new((20, 20), 1.0, Transform::Normal);
+        damage_tracked_renderer
+            .damage_output(0, elements, None::<slog::Logger>)
+            .unwrap();
+        damage_tracked_renderer
+            .damage_output(1, elements, None::<slog::Logger>)
+            .unwrap();
+
+        let mut restored = DamageTrackedRenderer::from_snapshot(
+            damage_tracked_renderer.mode().clone(),
+            damage_tracked_renderer.to_snapshot(),
+        );
+        assert_eq!(restored.last_frame_damage(), Some(&[][..]));
+        let (damage, _) = restored.damage_output(1, elements, None::<slog::Logger>).unwrap();
+        assert_eq!(damage, None);
+    }
+
+    #[cfg(feature = "bincode")]
+    #[test]
+    fn snapshot_bincode_roundtrip() {
+        let mut renderer = TestRenderer::new();
+        let texture = renderer.create_texture((10, 10).into());
+        let element = TextureRenderElement::from_static_texture(
+            Id::new(),
+            renderer.id(),
+            (0.0, 0.0),
+            texture,
+            1,
+            Transform::Normal,
+            None,
+            None,
+            None,
+            None,
+        );
+        let mut damage_tracked_renderer = DamageTrackedRenderer::new((20, 20), 1.0, Transform::Normal);
+        damage_tracked_renderer
+            .damage_output(0, std::slice::from_ref(&element), None::<slog::Logger>)
+            .unwrap();
+
+        let config = bincode::config::standard();
+        let encoded = bincode::encode_to_vec(damage_tracked_renderer.to_snapshot(), config).unwrap();
+        let (snapshot, _): (super::DamageTrackedRendererSnapshot, _) =
+            bincode::decode_from_slice(&encoded, config).unwrap();
+        assert_eq!(bincode::encode_to_vec(&snapshot, config).unwrap(), encoded);
+
+        let restored = DamageTrackedRenderer::from_snapshot(damage_tracked_renderer.mode().clone(), snapshot);
+        assert_eq!(restored.last_frame_damage(), Some(&[rect(0, 0, 20, 20)][..]));
+    }
+
+    #[test]
+    fn last_frame_damage() {
+        let mut renderer = TestRenderer::new();
+        let texture = renderer.create_texture((10, 10).into());
+        let element = TextureRenderElement::from_static_texture(
+            Id::new(),
+            renderer.id(),
+            (0.0, 0.0),
+            texture,
+            1,
+            Transform::Normal,
+            None,
+            None,
+            None,
+            None,
+        );
+        let mut damage_tracked_renderer = DamageTrackedRenderer::new((20, 20), 1.0, Transform::Normal);
+        assert_eq!(damage_tracked_renderer.last_frame_damage(), None);
+
+        damage_tracked_renderer
+            .render_output(
+                &mut renderer,
+                0,
+                std::slice::from_ref(&element),
+                [0.0; 4],
+                None::<slog::Logger>,
+            )
+            .unwrap();
+        assert_eq!(
+            damage_tracked_renderer.last_frame_damage(),
+            Some(&[rect(0, 0, 20, 20)][..])
+        );
+
+        // the damage of older frames is not included
+        let (damage, _) = damage_tracked_renderer
+            .render_output(
+                &mut renderer,
+                1,
+                std::slice::from_ref(&element),
+                [0.0; 4],
+                None::<slog::Logger>,
+            )
+            .unwrap();
+        assert_eq!(damage, Some(vec![rect(0, 0, 20, 20)]));
+        assert_eq!(damage_tracked_renderer.last_frame_damage(), Some(&[][..]));
+    }
+
+    #[test]
+    fn damage_between_moved_element() {
+        let mut renderer = TestRenderer::new();
+        let texture = renderer.create_texture((10, 10).into());
+        let id = Id::new();
+        let element_at = |x: f64, y: f64| {
+            TextureRenderElement::from_static_texture(
+                id.clone(),
+                renderer.id(),
+                (x, y),
+                texture.clone(),
+                1,
+                Transform::Normal,
+                None,
+                None,
+                None,
+                None,
+            )
+        };
+        let before = [element_at(0.0, 0.0)];
+        let after = [element_at(5.0, 5.0)];
+        let damage_tracked_renderer = DamageTrackedRenderer::new((20, 20), 1.0, Transform::Normal);
+
+        let damage = damage_tracked_renderer
+            .compute_damage_between(&before, &before, 1)
+            .unwrap();
+        assert!(damage.is_empty());
+
+        let damage = damage_tracked_renderer
+            .compute_damage_between(&before, &after, 1)
+            .unwrap();
+        assert!(damage.iter().any(|rect| rect.contains((0, 0))));
+        assert!(damage.iter().any(|rect| rect.contains((14, 14))));
+        assert!(!damage.iter().any(|rect| rect.contains((19, 0))));
+        assert_eq!(damage_tracked_renderer.last_frame_damage(), None);
+    }
+}
+
+#[cfg(test)]
+mod proptests {
+    use proptest::prelude::*;
+
+    use super::DamageTrackedRenderer;
+    use crate::{
+        backend::renderer::{
+            element::{Element, Id},
+            utils::CommitCounter,
+        },
+        utils::{Buffer, Physical, Point, Rectangle, Region, Scale, Transform},
+    };
+
+    const OUTPUT_SIZE: i32 = 100;
+
+    #[derive(Debug, Clone)]
+    struct ElementSpec {
+        geometry: Rectangle<i32, Physical>,
+        damage: Vec<Rectangle<i32, Physical>>,
+        opaque_regions: Vec<Rectangle<i32, Physical>>,
+    }
+
+    #[derive(Debug)]
+    struct SpecElement {
+        id: Id,
+        spec: ElementSpec,
+    }
+
+    impl Element for SpecElement {
+        fn id(&self) -> &Id {
+            &self.id
+        }
+
+        fn current_commit(&self) -> CommitCounter {
+            CommitCounter::default()
+        }
+
+        fn src(&self) -> Rectangle<f64, Buffer> {
+            Rectangle::from_loc_and_size(
+                (0.0, 0.0),
+                (self.spec.geometry.size.w as f64, self.spec.geometry.size.h as f64),
+            )
+        }
+
+        fn geometry(&self, _scale: Scale<f64>) -> Rectangle<i32, Physical> {
+            self.spec.geometry
+        }
+
+        fn damage_since(
+            &self,
+            _scale: Scale<f64>,
+            commit: Option<CommitCounter>,
+        ) -> Vec<Rectangle<i32, Physical>> {
+            if commit == Some(self.current_commit()) {
+                Vec::new()
+            } else {
+                self.spec.damage.clone()
+            }
+        }
+
+        fn opaque_regions(&self, _scale: Scale<f64>) -> Vec<Rectangle<i32, Physical>> {
+            self.spec.opaque_regions.clone()
+        }
+    }
+
+    fn rect(max_loc: i32, max_size: i32) -> impl Strategy<Value = Rectangle<i32, Physical>> {
+        (-max_loc..max_loc, -max_loc..max_loc, 1..max_size, 1..max_size)
+            .prop_map(|(x, y, w, h)| Rectangle::from_loc_and_size((x, y), (w, h)))
+    }
+
+    fn element_spec() -> impl Strategy<Value = ElementSpec> {
+        (
+            rect(OUTPUT_SIZE, OUTPUT_SIZE),
+            prop::collection::vec(rect(10, 40), 0..3),
+            prop::collection::vec(rect(10, 40), 0..3),
+        )
+            .prop_map(|(mut geometry, damage, opaque_regions)| {
+                geometry.loc = Point::from((geometry.loc.x.abs(), geometry.loc.y.abs()));
+                // damage and opaque regions are relative to the element and have to be inside of it
+                let bounds = Rectangle::from_loc_and_size((0, 0), geometry.size);
+                let clamp = |rects: Vec<Rectangle<i32, Physical>>| {
+                    rects
+                        .into_iter()
+                        .filter_map(|rect| rect.intersection(bounds))
+                        .filter(|rect| !rect.is_empty())
+                        .collect()
+                };
+                ElementSpec {
+                    geometry,
+                    damage: clamp(damage),
+                    opaque_regions: clamp(opaque_regions),
+                }
+            })
+    }
+
+    // The order of the generated elements defines their z-index, the first element is on top.
+    fn elements(specs: Vec<ElementSpec>) -> Vec<SpecElement> {
+        specs
+            .into_iter()
+            .map(|spec| SpecElement { id: Id::new(), spec })
+            .collect()
+    }
+
+    fn damage_tracked_renderer(elements: &[SpecElement]) -> DamageTrackedRenderer {
+        let mut renderer = DamageTrackedRenderer::new((OUTPUT_SIZE, OUTPUT_SIZE), 1.0, Transform::Normal);
+        renderer.damage_output(0, elements, None).unwrap();
+        renderer
+    }
+
+    proptest! {
+        #[test]
+        fn damage_is_idempotent(specs in prop::collection::vec(element_spec(), 1..6)) {
+            let elements = elements(specs);
+            let mut first = damage_tracked_renderer(&elements);
+            let mut second = damage_tracked_renderer(&elements);
+
+            let (first_damage, _) = first.damage_output(1, &elements, None).unwrap();
+            let (second_damage, _) = second.damage_output(1, &elements, None).unwrap();
+            prop_assert_eq!(first_damage, second_damage);
+
+            // nothing changed since the last frame
+            let (damage, _) = first.damage_output(1, &elements, None).unwrap();
+            prop_assert_eq!(damage, None);
+        }
+
+        #[test]
+        fn removing_element_damages_its_geometry(
+            specs in prop::collection::vec(element_spec(), 1..6),
+            index in any::<prop::sample::Index>(),
+        ) {
+            let mut elements = elements(specs);
+            let mut renderer = damage_tracked_renderer(&elements);
+            renderer.damage_output(1, &elements, None).unwrap();
+
+            let index = index.index(elements.len());
+            let removed = elements.remove(index);
+            let (damage, _) = renderer.damage_output(1, &elements, None).unwrap();
+
+            // the part of the removed element not covered by opaque elements on top of it
+            let output = Rectangle::from_loc_and_size((0, 0), (OUTPUT_SIZE, OUTPUT_SIZE));
+            let mut visible = Region::from(removed.spec.geometry);
+            visible.intersect_with_rect(output);
+            for element in elements.iter().take(index) {
+                let loc = element.spec.geometry.loc;
+                visible.subtract_rects(
+                    element
+                        .spec
+                        .opaque_regions
+                        .iter()
+                        .map(|opaque| Rectangle::from_loc_and_size(opaque.loc + loc, opaque.size)),
+                );
+            }
+
+            if !visible.is_empty() {
+                let damage = damage.unwrap_or_default();
+                prop_assert!(!damage.is_empty());
+                visible.subtract_rects(damage);
+                prop_assert!(visible.is_empty(), "damage does not cover {:?}", visible);
+            }
+        }
+    }
+}
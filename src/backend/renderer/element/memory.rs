@@ -58,6 +58,7 @@
 //! #         _: &[Rectangle<i32, Physical>],
 //! #         _: Transform,
 //! #         _: f32,
+//! #         _: smithay::backend::renderer::BlendMode,
 //! #     ) -> Result<(), Self::Error> {
 //! #         unimplemented!()
 //! #     }
@@ -192,7 +193,7 @@ use slog::{trace, warn};
 use crate::{
     backend::renderer::{
         utils::{CommitCounter, DamageTracker},
-        Frame, ImportMem, Renderer,
+        BlendMode, Frame, ImportMem, Renderer,
     },
     utils::{Buffer, Logical, Physical, Point, Rectangle, Scale, Size, Transform},
 };
@@ -202,6 +203,7 @@ use super::{Element, Id, RenderElement};
 #[derive(Debug)]
 struct MemoryRenderBufferInner {
     mem: Vec<u8>,
+    back: Vec<u8>,
     size: Size<i32, Buffer>,
     scale: i32,
     transform: Transform,
@@ -215,6 +217,7 @@ impl Default for MemoryRenderBufferInner {
     fn default() -> Self {
         MemoryRenderBufferInner {
             mem: Vec::default(),
+            back: Vec::default(),
             size: Size::default(),
             scale: 1,
             transform: Transform::Normal,
@@ -236,8 +239,10 @@ impl MemoryRenderBufferInner {
         // TODO: Allow to specify the format when ImportMem
         // has been changed to allow specifying a format
         let size = size.into();
+        let mem = vec![0; (size.w * 4 * size.h) as usize];
         MemoryRenderBufferInner {
-            mem: vec![0; (size.w * 4 * size.h) as usize],
+            back: mem.clone(),
+            mem,
             size,
             scale,
             transform,
@@ -262,6 +267,7 @@ impl MemoryRenderBufferInner {
 
         MemoryRenderBufferInner {
             mem: mem.to_vec(),
+            back: mem.to_vec(),
             size,
             scale,
             transform,
@@ -277,6 +283,7 @@ impl MemoryRenderBufferInner {
         let mem_size = (size.w * 4 * size.h) as usize;
         if self.mem.len() != mem_size {
             self.mem.resize(mem_size, 0);
+            self.back.resize(mem_size, 0);
             self.renderer_seen.clear();
             self.textures.clear();
             self.damage_tracker.reset();
@@ -285,6 +292,31 @@ impl MemoryRenderBufferInner {
         }
     }
 
+    /// Swap the front and back buffer
+    ///
+    /// Compares the back buffer against the front buffer, adds the
+    /// changed regions to the damage tracker and swaps both buffers.
+    /// Afterwards the new back buffer is brought in sync with the new
+    /// front buffer again, so the next draw can continue incrementally.
+    fn swap_buffers(&mut self) {
+        let damage = buffer_diff(&self.mem, &self.back, self.size);
+        if damage.is_empty() {
+            return;
+        }
+
+        std::mem::swap(&mut self.mem, &mut self.back);
+
+        let stride = self.size.w as usize * 4;
+        for rect in damage.iter() {
+            for row in rect.loc.y as usize..(rect.loc.y + rect.size.h) as usize {
+                let range = row * stride..(row + 1) * stride;
+                self.back[range.clone()].copy_from_slice(&self.mem[range]);
+            }
+        }
+
+        self.damage_tracker.add(&damage);
+    }
+
     fn import_texture<R>(
         &mut self,
         renderer: &mut R,
@@ -384,7 +416,7 @@ impl MemoryRenderBuffer {
         let guard = self.inner.lock().unwrap();
         RenderContext {
             buffer: guard,
-            damage: Vec::new(),
+            modified: false,
             opaque_regions: None,
         }
     }
@@ -403,7 +435,7 @@ impl MemoryRenderBuffer {
 #[derive(Debug)]
 pub struct RenderContext<'a> {
     buffer: MutexGuard<'a, MemoryRenderBufferInner>,
-    damage: Vec<Rectangle<i32, Buffer>>,
+    modified: bool,
     opaque_regions: Option<Option<Vec<Rectangle<i32, Buffer>>>>,
 }
 
@@ -419,16 +451,30 @@ impl<'a> RenderContext<'a> {
 
     /// Draw to the buffer
     ///
-    /// Provided closure has to return updated regions.
+    /// Drawing happens on the back buffer, which always starts out with the
+    /// content of the front buffer. The changes become visible once the
+    /// context is committed with [`RenderContext::commit`] or dropped.
+    ///
+    /// Provided closure has to return updated regions. Regardless of the returned
+    /// regions the buffers are compared on commit and the per-pixel difference is
+    /// used as the damage of the buffer, so changes outside of the reported regions
+    /// or made by a closure returning an error are never lost.
     pub fn draw<F, E>(&mut self, f: F) -> Result<(), E>
     where
         F: FnOnce(&mut [u8]) -> Result<Vec<Rectangle<i32, Buffer>>, E>,
     {
-        let draw_damage = f(&mut self.buffer.mem)?;
-        self.damage.extend(draw_damage);
+        // the back buffer may be modified even if no damage is reported
+        self.modified = true;
+        f(&mut self.buffer.back)?;
         Ok(())
     }
 
+    /// Commit the drawn content
+    ///
+    /// Swaps the front and back buffer and updates the damage.
+    /// This is equivalent to dropping the context.
+    pub fn commit(self) {}
+
     /// Update the opaque regions
     pub fn update_opaque_regions(&mut self, opaque_regions: Option<Vec<Rectangle<i32, Buffer>>>) {
         self.opaque_regions = Some(opaque_regions);
@@ -437,13 +483,98 @@ impl<'a> RenderContext<'a> {
 
 impl<'a> Drop for RenderContext<'a> {
     fn drop(&mut self) {
-        self.buffer.damage_tracker.add(&self.damage);
+        if self.modified {
+            self.buffer.swap_buffers();
+        }
         if let Some(opaque_regions) = self.opaque_regions.take() {
             self.buffer.opaque_regions = opaque_regions;
         }
     }
 }
 
+/// Computes the regions that differ between two buffers
+///
+/// Unchanged rows are skipped with a plain slice comparison, which the
+/// compiler and libc turn into a vectorized memcmp. For changed rows the
+/// horizontal extent is narrowed down by comparing 8 bytes (two pixels)
+/// at a time. Consecutive changed rows are merged into a single rectangle.
+fn buffer_diff(front: &[u8], back: &[u8], size: Size<i32, Buffer>) -> Vec<Rectangle<i32, Buffer>> {
+    let stride = size.w as usize * 4;
+    let mut damage: Vec<Rectangle<i32, Buffer>> = Vec::new();
+    if stride == 0 {
+        return damage;
+    }
+
+    let mut current: Option<(usize, usize, usize, usize)> = None;
+    for (y, (front_row, back_row)) in front
+        .chunks_exact(stride)
+        .zip(back.chunks_exact(stride))
+        .enumerate()
+    {
+        match row_diff(front_row, back_row) {
+            Some((x1, x2)) => {
+                current = Some(match current {
+                    Some((start, _, min_x, max_x)) => (start, y, min_x.min(x1), max_x.max(x2)),
+                    None => (y, y, x1, x2),
+                })
+            }
+            None => {
+                if let Some((start, end, min_x, max_x)) = current.take() {
+                    damage.push(diff_rect(start, end, min_x, max_x));
+                }
+            }
+        }
+    }
+    if let Some((start, end, min_x, max_x)) = current {
+        damage.push(diff_rect(start, end, min_x, max_x));
+    }
+
+    damage
+}
+
+fn diff_rect(start: usize, end: usize, min_x: usize, max_x: usize) -> Rectangle<i32, Buffer> {
+    Rectangle::from_loc_and_size(
+        (min_x as i32, start as i32),
+        ((max_x - min_x + 1) as i32, (end - start + 1) as i32),
+    )
+}
+
+/// Returns the first and last differing pixel of a row
+fn row_diff(front: &[u8], back: &[u8]) -> Option<(usize, usize)> {
+    if front == back {
+        return None;
+    }
+
+    let first = front
+        .chunks(8)
+        .zip(back.chunks(8))
+        .position(|(a, b)| a != b)
+        .map(|chunk| {
+            let offset = chunk * 8;
+            let pixel = front[offset..]
+                .chunks(4)
+                .zip(back[offset..].chunks(4))
+                .position(|(a, b)| a != b)
+                .unwrap_or(0);
+            offset / 4 + pixel
+        })?;
+    let last = front
+        .rchunks(8)
+        .zip(back.rchunks(8))
+        .position(|(a, b)| a != b)
+        .map(|chunk| {
+            let end = front.len() - chunk * 8;
+            let pixel = front[..end]
+                .rchunks(4)
+                .zip(back[..end].rchunks(4))
+                .position(|(a, b)| a != b)
+                .unwrap_or(0);
+            end / 4 - 1 - pixel
+        })?;
+
+    Some((first, last))
+}
+
 /// A render element for [`MemoryRenderBuffer`]
 #[derive(Debug)]
 pub struct MemoryRenderBufferRenderElement<R: Renderer> {
@@ -641,6 +772,74 @@ where
             return Ok(());
         };
 
-        frame.render_texture_from_to(texture, src, dst, damage, transform, self.alpha)
+        frame.render_texture_from_to(
+            texture,
+            src,
+            dst,
+            damage,
+            transform,
+            self.alpha,
+            BlendMode::Premultiplied,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{buffer_diff, MemoryRenderBuffer};
+    use crate::utils::{Rectangle, Size, Transform};
+
+    #[test]
+    fn buffer_diff_single_pixel() {
+        let size = Size::from((5, 4));
+        let front = vec![0u8; 5 * 4 * 4];
+        let mut back = front.clone();
+        assert!(buffer_diff(&front, &back, size).is_empty());
+
+        back[(2 * 5 + 3) * 4 + 1] = 255;
+        assert_eq!(
+            buffer_diff(&front, &back, size),
+            vec![Rectangle::from_loc_and_size((3, 2), (1, 1))]
+        );
+    }
+
+    #[test]
+    fn buffer_diff_merges_rows() {
+        let size = Size::from((5, 4));
+        let front = vec![0u8; 5 * 4 * 4];
+        let mut back = front.clone();
+        back[4 * 4] = 1;
+        back[5 * 4] = 1;
+        back[(3 * 5 + 2) * 4 + 3] = 1;
+        assert_eq!(
+            buffer_diff(&front, &back, size),
+            vec![
+                Rectangle::from_loc_and_size((0, 0), (5, 2)),
+                Rectangle::from_loc_and_size((2, 3), (1, 1)),
+            ]
+        );
+    }
+
+    #[test]
+    fn draw_without_reported_damage_is_committed() {
+        let mut buffer = MemoryRenderBuffer::new((5, 4), 1, Transform::Normal, None);
+        let commit = buffer.current_commit();
+
+        let mut context = buffer.render();
+        context
+            .draw(|mem| {
+                mem[0] = 255;
+                Result::<_, ()>::Ok(vec![])
+            })
+            .unwrap();
+        context.commit();
+
+        let inner = buffer.inner.lock().unwrap();
+        assert_eq!(inner.mem[0], 255);
+        assert_eq!(inner.mem, inner.back);
+        assert_eq!(
+            inner.damage_tracker.damage_since(Some(commit)),
+            Some(vec![Rectangle::from_loc_and_size((0, 0), (1, 1))])
+        );
     }
 }
@@ -47,6 +47,8 @@ enum InnerId {
     #[cfg(feature = "wayland_frontend")]
     WaylandResource(ObjectId),
     External(Arc<ExternalId>),
+    #[cfg(feature = "bincode")]
+    Decoded(u8, u64),
 }
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone)]
@@ -90,6 +92,44 @@ impl<R: Resource> From<&R> for Id {
     }
 }
 
+/// Ids are encoded as their kind and a 64 bit value.
+///
+/// Ids of wayland resources are encoded as a hash of the object id, which is only
+/// stable within the same build of smithay. Decoding an [`Id`] never yields an id
+/// of the decoding process, a decoded id only compares equal to ids decoded from the
+/// same encoded id. Processes receiving element states from another process therefore
+/// have to use decoded ids for their elements as well.
+#[cfg(feature = "bincode")]
+impl bincode::Encode for Id {
+    fn encode<E: bincode::enc::Encoder>(&self, encoder: &mut E) -> Result<(), bincode::error::EncodeError> {
+        let (kind, value): (u8, u64) = match &self.0 {
+            #[cfg(feature = "wayland_frontend")]
+            InnerId::WaylandResource(id) => {
+                use std::hash::{Hash, Hasher};
+                let mut hasher = std::collections::hash_map::DefaultHasher::new();
+                id.hash(&mut hasher);
+                (0, hasher.finish())
+            }
+            InnerId::External(id) => (1, id.0 as u64),
+            InnerId::Decoded(kind, value) => (*kind, *value),
+        };
+        bincode::Encode::encode(&kind, encoder)?;
+        bincode::Encode::encode(&value, encoder)
+    }
+}
+
+#[cfg(feature = "bincode")]
+impl bincode::Decode for Id {
+    fn decode<D: bincode::de::Decoder>(decoder: &mut D) -> Result<Self, bincode::error::DecodeError> {
+        let kind: u8 = bincode::Decode::decode(decoder)?;
+        if kind > 1 {
+            return Err(bincode::error::DecodeError::Other("unknown element id kind"));
+        }
+        let value: u64 = bincode::Decode::decode(decoder)?;
+        Ok(Id(InnerId::Decoded(kind, value)))
+    }
+}
+
 /// The underlying storage for a element
 #[derive(Debug)]
 pub enum UnderlyingStorage<'a, R: Renderer> {
@@ -312,12 +352,19 @@ pub trait Element {
     /// Get the src of the underlying buffer
     fn src(&self) -> Rectangle<f64, BufferCoords>;
     /// Get the transform of the underlying buffer
+    ///
+    /// The transform only describes how [`src`](Element::src) maps onto the element,
+    /// [`damage_since`](Element::damage_since) and [`opaque_regions`](Element::opaque_regions)
+    /// are expected to already have it applied.
     fn transform(&self) -> Transform {
         Transform::Normal
     }
     /// Get the geometry relative to the output
     fn geometry(&self, scale: Scale<f64>) -> Rectangle<i32, Physical>;
     /// Get the damage since the provided commit relative to the element
+    ///
+    /// The damage is in physical coordinates relative to the [`geometry`](Element::geometry) of
+    /// the element, any buffer [`transform`](Element::transform) has to be applied already.
     fn damage_since(
         &self,
         scale: Scale<f64>,
@@ -333,11 +380,26 @@ pub trait Element {
     fn opaque_regions(&self, _scale: Scale<f64>) -> Vec<Rectangle<i32, Physical>> {
         vec![]
     }
+    /// Get the rectangle relative to the output this element is clipped to at the given scale, if any
+    ///
+    /// Only the part of the element inside the clip rect is drawn. Damage outside of it
+    /// is discarded and the element only occludes other elements within it.
+    fn clip_rect(&self, _scale: Scale<f64>) -> Option<Rectangle<i32, Physical>> {
+        None
+    }
 }
 
 /// A single render element
+///
+/// Render elements do not carry a transform of their own. Rotated or flipped content is
+/// described by the buffer [`transform`](Element::transform) of the [`Element`], while damage
+/// and opaque regions are always reported in the (already transformed) element geometry.
 pub trait RenderElement<R: Renderer>: Element {
     /// Draw this element
+    ///
+    /// Implementations are responsible for applying their [`transform`](Element::transform)
+    /// when sampling `src`. `dst` is in output physical coordinates and `damage` is
+    /// relative to `dst`.
     fn draw<'a>(
         &self,
         frame: &mut <R as Renderer>::Frame<'a>,
@@ -409,6 +471,10 @@ where
     fn opaque_regions(&self, scale: Scale<f64>) -> Vec<Rectangle<i32, Physical>> {
         (*self).opaque_regions(scale)
     }
+
+    fn clip_rect(&self, scale: Scale<f64>) -> Option<Rectangle<i32, Physical>> {
+        (*self).clip_rect(scale)
+    }
 }
 
 impl<R, E> RenderElement<R> for &E
@@ -676,6 +742,19 @@ macro_rules! render_elements_internal {
                 Self::_GenericCatcher(_) => unreachable!(),
             }
         }
+
+        fn clip_rect(&self, scale: $crate::utils::Scale<f64>) -> Option<$crate::utils::Rectangle<i32, $crate::utils::Physical>> {
+            match self {
+                $(
+                    #[allow(unused_doc_comments)]
+                    $(
+                        #[$meta]
+                    )*
+                    Self::$body(x) => $crate::render_elements_internal!(@call clip_rect; x, scale)
+                ),*,
+                Self::_GenericCatcher(_) => unreachable!(),
+            }
+        }
     };
     (@draw <$renderer:ty>; $($(#[$meta:meta])* $body:ident=$field:ty $(as <$other_renderer:ty>)?),* $(,)?) => {
         fn draw<'frame>(
@@ -1194,6 +1273,7 @@ macro_rules! render_elements_internal {
 /// #         _: &[Rectangle<i32, Physical>],
 /// #         _: Transform,
 /// #         _: f32,
+/// #         _: smithay::backend::renderer::BlendMode,
 /// #     ) -> Result<(), Self::Error> {
 /// #         unimplemented!()
 /// #     }
@@ -1349,6 +1429,10 @@ where
     fn opaque_regions(&self, scale: Scale<f64>) -> Vec<Rectangle<i32, Physical>> {
         self.0.opaque_regions(scale)
     }
+
+    fn clip_rect(&self, scale: Scale<f64>) -> Option<Rectangle<i32, Physical>> {
+        self.0.clip_rect(scale)
+    }
 }
 
 impl<R, C> RenderElement<R> for Wrap<C>
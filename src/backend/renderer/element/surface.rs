@@ -61,6 +61,7 @@
 //! #         _: &[Rectangle<i32, Physical>],
 //! #         _: Transform,
 //! #         _: f32,
+//! #         _: smithay::backend::renderer::BlendMode,
 //! #     ) -> Result<(), Self::Error> {
 //! #         unimplemented!()
 //! #     }
@@ -199,7 +200,9 @@ use slog::warn;
 use wayland_server::protocol::wl_surface;
 
 use crate::{
-    backend::renderer::{utils::RendererSurfaceStateUserData, Frame, ImportAll, Renderer, Texture},
+    backend::renderer::{
+        utils::RendererSurfaceStateUserData, BlendMode, Frame, ImportAll, Renderer, Texture,
+    },
     utils::{Buffer, Physical, Point, Rectangle, Scale, Size, Transform},
     wayland::compositor::{self, SurfaceData, TraversalAction},
 };
@@ -207,6 +210,11 @@ use crate::{
 use super::{CommitCounter, Element, Id, RenderElement, UnderlyingStorage};
 
 /// Retrieve the [`WaylandSurfaceRenderElement`]s for a surface tree
+///
+/// One element is created for every surface of the tree with a buffer attached,
+/// subsurfaces included. Damage committed by a subsurface is therefore reported by its own
+/// element, positioned relative to the parent, and does not have to be handled by the element
+/// of the parent surface.
 pub fn render_elements_from_surface_tree<R, E>(
     renderer: &mut R,
     surface: &wl_surface::WlSurface,
@@ -279,6 +287,10 @@ where
 }
 
 /// A single surface render element
+///
+/// The element only represents a single surface, its [`damage_since`](Element::damage_since)
+/// does not include the damage of subsurfaces. Use [`render_elements_from_surface_tree`]
+/// to get elements for a whole surface tree.
 pub struct WaylandSurfaceRenderElement<R> {
     id: Id,
     location: Point<f64, Physical>,
@@ -386,6 +398,7 @@ impl<R: Renderer + ImportAll> Element for WaylandSurfaceRenderElement<R> {
         commit: Option<CommitCounter>,
     ) -> Vec<Rectangle<i32, Physical>> {
         let dst_size = self.size(scale);
+        let dst_rect = Rectangle::from_loc_and_size((0, 0), dst_size);
 
         compositor::with_states(&self.surface, |states| {
             let data = states.data_map.get::<RendererSurfaceStateUserData>();
@@ -421,6 +434,8 @@ impl<R: Renderer + ImportAll> Element for WaylandSurfaceRenderElement<R> {
                                         dst_size.to_f64() / surface_view.dst.to_f64().to_physical(scale);
                                     rect.to_physical_precise_up(surface_scale * scale)
                                 })
+                                // rounding up could make the damage exceed the element
+                                .and_then(|rect| rect.intersection(dst_rect))
                         })
                         .collect::<Vec<_>>();
 
@@ -485,7 +500,15 @@ where
                 let data = data.borrow();
 
                 if let Some(texture) = data.texture::<R>(frame.id()) {
-                    frame.render_texture_from_to(texture, src, dst, damage, data.buffer_transform, 1.0f32)?;
+                    frame.render_texture_from_to(
+                        texture,
+                        src,
+                        dst,
+                        damage,
+                        data.buffer_transform,
+                        1.0f32,
+                        BlendMode::Premultiplied,
+                    )?;
                 } else {
                     warn!(log, "trying to render texture from different renderer");
                 }
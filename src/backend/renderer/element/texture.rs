@@ -76,6 +76,7 @@
 //! #         _: &[Rectangle<i32, Physical>],
 //! #         _: Transform,
 //! #         _: f32,
+//! #         _: smithay::backend::renderer::BlendMode,
 //! #     ) -> Result<(), Self::Error> {
 //! #         unimplemented!()
 //! #     }
@@ -207,6 +208,7 @@
 //! #         _: &[Rectangle<i32, Physical>],
 //! #         _: Transform,
 //! #         _: f32,
+//! #         _: smithay::backend::renderer::BlendMode,
 //! #     ) -> Result<(), Self::Error> {
 //! #         unimplemented!()
 //! #     }
@@ -352,11 +354,14 @@ use slog::warn;
 use crate::{
     backend::renderer::{
         utils::{DamageTracker, DamageTrackerSnapshot},
-        Frame, ImportMem, Renderer, Texture,
+        BlendMode, Frame, ImportMem, Renderer, Texture,
     },
     utils::{Buffer, Coordinate, Logical, Physical, Point, Rectangle, Scale, Size, Transform},
 };
 
+#[cfg(feature = "renderer_gl")]
+use crate::backend::renderer::gles2::{Gles2Error, Gles2Renderer, Gles2Texture};
+
 use super::{CommitCounter, Element, Id, RenderElement, UnderlyingStorage};
 
 /// A single texture buffer
@@ -718,6 +723,18 @@ impl<T: Texture> TextureRenderElement<T> {
     }
 }
 
+#[cfg(feature = "renderer_gl")]
+impl TextureRenderElement<Gles2Texture> {
+    /// Generate mipmaps for the texture of this element
+    ///
+    /// Improves the quality of elements drawn considerably smaller than their texture,
+    /// see [`Gles2Renderer::generate_mipmaps`] for details.
+    pub fn with_mipmaps(self, renderer: &mut Gles2Renderer) -> Result<Self, Gles2Error> {
+        renderer.generate_mipmaps(&self.texture)?;
+        Ok(self)
+    }
+}
+
 impl<T> Element for TextureRenderElement<T>
 where
     T: Texture,
@@ -814,7 +831,15 @@ where
             return Ok(());
         }
 
-        frame.render_texture_from_to(&self.texture, src, dst, damage, self.transform, self.alpha)
+        frame.render_texture_from_to(
+            &self.texture,
+            src,
+            dst,
+            damage,
+            self.transform,
+            self.alpha,
+            BlendMode::Premultiplied,
+        )
     }
 
     fn underlying_storage(&self, _renderer: &R) -> Option<UnderlyingStorage<'_, R>> {
@@ -83,6 +83,18 @@ impl<E: Element> Element for RescaleRenderElement<E> {
             .map(|rect| rect.to_f64().upscale(self.scale).to_i32_round())
             .collect::<Vec<_>>()
     }
+
+    fn clip_rect(
+        &self,
+        scale: crate::utils::Scale<f64>,
+    ) -> Option<crate::utils::Rectangle<i32, crate::utils::Physical>> {
+        self.element.clip_rect(scale).map(|mut clip| {
+            clip.loc -= self.origin;
+            clip = clip.to_f64().upscale(self.scale).to_i32_round();
+            clip.loc += self.origin;
+            clip
+        })
+    }
 }
 
 impl<R: Renderer, E: RenderElement<R>> RenderElement<R> for RescaleRenderElement<E> {
@@ -255,6 +267,10 @@ impl<E: Element> Element for CropRenderElement<E> {
             Default::default()
         }
     }
+
+    fn clip_rect(&self, scale: crate::utils::Scale<f64>) -> Option<crate::utils::Rectangle<i32, Physical>> {
+        self.element.clip_rect(scale)
+    }
 }
 
 impl<R: Renderer, E: RenderElement<R>> RenderElement<R> for CropRenderElement<E> {
@@ -348,6 +364,17 @@ impl<E: Element> Element for RelocateRenderElement<E> {
     fn opaque_regions(&self, scale: Scale<f64>) -> Vec<Rectangle<i32, Physical>> {
         self.element.opaque_regions(scale)
     }
+
+    fn clip_rect(&self, scale: Scale<f64>) -> Option<Rectangle<i32, Physical>> {
+        let offset = match self.relocate {
+            Relocate::Absolute => self.location - self.element.location(scale),
+            Relocate::Relative => self.location,
+        };
+        self.element.clip_rect(scale).map(|mut clip| {
+            clip.loc += offset;
+            clip
+        })
+    }
 }
 
 impl<R: Renderer, E: RenderElement<R>> RenderElement<R> for RelocateRenderElement<E> {
@@ -535,3 +562,65 @@ where
         .map(move |e| RelocateRenderElement::from_element(e, offset, Relocate::Relative))
         .filter_map(move |e| CropRenderElement::from_element(e, scale, constrain))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{Relocate, RelocateRenderElement};
+    use crate::{
+        backend::renderer::{
+            element::{Element, Id},
+            utils::CommitCounter,
+        },
+        utils::{Buffer, Logical, Physical, Point, Rectangle, Scale},
+    };
+
+    // an element at (10, 10) in logical space, clipped to its top-left corner
+    struct ClippedElement(Id);
+
+    impl Element for ClippedElement {
+        fn id(&self) -> &Id {
+            &self.0
+        }
+
+        fn current_commit(&self) -> CommitCounter {
+            CommitCounter::default()
+        }
+
+        fn src(&self) -> Rectangle<f64, Buffer> {
+            Rectangle::from_loc_and_size((0.0, 0.0), (10.0, 10.0))
+        }
+
+        fn geometry(&self, scale: Scale<f64>) -> Rectangle<i32, Physical> {
+            Rectangle::from_loc_and_size(
+                Point::<f64, Logical>::from((10.0, 10.0))
+                    .to_physical(scale)
+                    .to_i32_round(),
+                (20, 20),
+            )
+        }
+
+        fn clip_rect(&self, scale: Scale<f64>) -> Option<Rectangle<i32, Physical>> {
+            Some(Rectangle::from_loc_and_size(self.location(scale), (5, 5)))
+        }
+    }
+
+    #[test]
+    fn relocate_clip_rect() {
+        let scale = Scale::from(2.0);
+
+        let absolute =
+            RelocateRenderElement::from_element(ClippedElement(Id::new()), (100, 50), Relocate::Absolute);
+        assert_eq!(absolute.location(scale), (100, 50).into());
+        assert_eq!(
+            absolute.clip_rect(scale),
+            Some(Rectangle::from_loc_and_size((100, 50), (5, 5)))
+        );
+
+        let relative =
+            RelocateRenderElement::from_element(ClippedElement(Id::new()), (100, 50), Relocate::Relative);
+        assert_eq!(
+            relative.clip_rect(scale),
+            Some(Rectangle::from_loc_and_size((120, 70), (5, 5)))
+        );
+    }
+}
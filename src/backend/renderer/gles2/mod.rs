@@ -4,11 +4,12 @@ use cgmath::{prelude::*, Matrix3, Vector2};
 use core::slice;
 use std::{
     borrow::Cow,
+    cell::Cell,
     collections::HashSet,
     convert::TryFrom,
-    ffi::CStr,
+    ffi::{CStr, CString},
     fmt, mem,
-    os::raw::c_char,
+    os::{raw::c_char, unix::io::OwnedFd},
     ptr,
     rc::Rc,
     sync::{
@@ -24,16 +25,17 @@ mod shaders;
 mod version;
 
 use super::{
-    Bind, Blit, ExportDma, ExportMem, Frame, ImportDma, ImportMem, Offscreen, Renderer, Texture,
-    TextureFilter, TextureMapping, Unbind,
+    Bind, BlendMode, Blit, CustomProgramId, ExportDma, ExportMem, Frame, ImportDma, ImportMem, Offscreen,
+    Renderer, RendererProfiler, ShaderUniformType, ShaderUniformValue, Texture, TextureFilter,
+    TextureMapping, Unbind,
 };
 use crate::backend::allocator::{
     dmabuf::{Dmabuf, WeakDmabuf},
-    Format,
+    Format, Fourcc,
 };
 use crate::backend::egl::{
     ffi::egl::{self as ffi_egl, types::EGLImage},
-    EGLContext, EGLSurface, MakeCurrentError,
+    EGLContext, EGLFence, EGLSurface, MakeCurrentError,
 };
 use crate::backend::SwapBuffersError;
 use crate::utils::{Buffer as BufferCoord, Physical, Rectangle, Size, Transform};
@@ -67,6 +69,17 @@ struct Gles2TexProgram {
     attrib_vert_position: ffi::types::GLint,
 }
 
+#[derive(Debug)]
+struct Gles2CustomProgram {
+    name: String,
+    // kept to recompile the program after a context loss
+    fragment_src: String,
+    uniform_names: Vec<String>,
+    // variants for textures with and without alpha
+    variants: [Gles2TexProgram; 2],
+    uniforms: Vec<(ShaderUniformType, [ffi::types::GLint; 2])>,
+}
+
 #[derive(Debug, Clone)]
 struct Gles2SolidProgram {
     program: ffi::types::GLuint,
@@ -104,6 +117,7 @@ impl Gles2Texture {
             y_inverted: false,
             size,
             egl_images: None,
+            mipmapped: Cell::new(false),
             destruction_callback_sender: renderer.destruction_callback_sender.clone(),
         }))
     }
@@ -124,6 +138,7 @@ struct Gles2TextureInternal {
     y_inverted: bool,
     size: Size<i32, BufferCoord>,
     egl_images: Option<Vec<EGLImage>>,
+    mipmapped: Cell<bool>,
     destruction_callback_sender: Sender<CleanupResource>,
 }
 
@@ -265,6 +280,16 @@ impl Drop for Gles2Target {
     }
 }
 
+/// GLSL version used for the internal shaders of a [`Gles2Renderer`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GlslVersion {
+    /// GLSL ES 1.00 (`#version 100`), supported by every OpenGL ES 2.0 context
+    #[default]
+    Es100,
+    /// GLSL ES 3.00 (`#version 300 es`), requires an OpenGL ES 3.0 context
+    Es300,
+}
+
 /// A renderer utilizing OpenGL ES 2
 pub struct Gles2Renderer {
     buffers: Vec<Gles2Buffer>,
@@ -272,11 +297,13 @@ pub struct Gles2Renderer {
     pub(crate) extensions: Vec<String>,
     tex_programs: [Gles2TexProgram; shaders::FRAGMENT_COUNT],
     solid_program: Gles2SolidProgram,
+    custom_programs: Vec<Gles2CustomProgram>,
     dmabuf_cache: std::collections::HashMap<WeakDmabuf, Gles2Texture>,
     egl: EGLContext,
     #[cfg(all(feature = "wayland_frontend", feature = "use_system_lib"))]
     egl_reader: Option<EGLBufferReader>,
     gl_version: version::GlVersion,
+    glsl_version: GlslVersion,
     vbos: [ffi::types::GLuint; 2],
     gl: ffi::Gles2,
     destruction_callback: Receiver<CleanupResource>,
@@ -286,6 +313,8 @@ pub struct Gles2Renderer {
     min_filter: TextureFilter,
     max_filter: TextureFilter,
     supports_instancing: bool,
+    profiler: Option<Box<dyn RendererProfiler>>,
+    context_lost: bool,
     logger_ptr: Option<*mut ::slog::Logger>,
     pub(crate) logger: ::slog::Logger,
     _not_send: *mut (),
@@ -309,6 +338,8 @@ pub struct Gles2Frame<'frame> {
     current_projection: Matrix3<f32>,
     transform: Transform,
     size: Size<i32, Physical>,
+    damage_rects: usize,
+    custom_program: Option<(CustomProgramId, Vec<ShaderUniformValue>)>,
     finished: AtomicBool,
 }
 
@@ -319,6 +350,8 @@ impl<'frame> fmt::Debug for Gles2Frame<'frame> {
             .field("current_projection", &self.current_projection)
             .field("transform", &self.transform)
             .field("size", &self.size)
+            .field("damage_rects", &self.damage_rects)
+            .field("custom_program", &self.custom_program)
             .field("finished", &self.finished)
             .finish_non_exhaustive()
     }
@@ -332,14 +365,17 @@ impl fmt::Debug for Gles2Renderer {
             .field("extensions", &self.extensions)
             .field("tex_programs", &self.tex_programs)
             .field("solid_program", &self.solid_program)
+            .field("custom_programs", &self.custom_programs)
             .field("dmabuf_cache", &self.dmabuf_cache)
             .field("egl", &self.egl)
             .field("gl_version", &self.gl_version)
+            .field("glsl_version", &self.glsl_version)
             // ffi::Gles2 does not implement Debug
             .field("vbos", &self.vbos)
             .field("min_filter", &self.min_filter)
             .field("max_filter", &self.max_filter)
             .field("supports_instancing", &self.supports_instancing)
+            .field("profiler", &self.profiler.is_some())
             .field("logger", &self.logger)
             .finish()
     }
@@ -354,6 +390,12 @@ pub enum Gles2Error {
     /// A program could not be linked
     #[error("Failed to link Program")]
     ProgramLinkError,
+    /// A custom shader program could not be compiled
+    #[error("Failed to compile custom program: {0}")]
+    CustomProgramCompileError(String),
+    /// The custom program is unknown to this renderer or the uniform values do not match its declaration
+    #[error("Unknown custom program or mismatching uniform values")]
+    InvalidCustomProgram,
     /// A framebuffer could not be bound
     #[error("Failed to bind Framebuffer")]
     FramebufferBindingError,
@@ -374,6 +416,9 @@ pub enum Gles2Error {
     /// The underlying egl context could not be activated
     #[error("Failed to active egl context")]
     ContextActivationError(#[from] crate::backend::egl::MakeCurrentError),
+    /// The egl context could not be recreated after it was lost
+    #[error("Failed to recreate the egl context")]
+    ContextRecreationError(#[source] crate::backend::egl::Error),
     ///The given dmabuf could not be converted to an EGLImage for framebuffer use
     #[error("Failed to convert between dmabuf and EGLImage")]
     BindBufferEGLError(#[source] crate::backend::egl::Error),
@@ -401,6 +446,9 @@ pub enum Gles2Error {
     /// The blitting operation was unsuccessful
     #[error("Error blitting between framebuffers")]
     BlitError,
+    /// A fence could not be imported or waited for
+    #[error("Failed to wait for fence")]
+    FenceError(#[source] crate::backend::egl::Error),
 }
 
 impl From<Gles2Error> for SwapBuffersError {
@@ -413,15 +461,19 @@ impl From<Gles2Error> for SwapBuffersError {
             | x @ Gles2Error::GLExtensionNotSupported(_)
             | x @ Gles2Error::EGLExtensionNotSupported(_)
             | x @ Gles2Error::GLVersionNotSupported(_)
+            | x @ Gles2Error::ContextRecreationError(_)
             | x @ Gles2Error::UnconstraintRenderingOperation => SwapBuffersError::ContextLost(Box::new(x)),
             Gles2Error::ContextActivationError(err) => err.into(),
             x @ Gles2Error::FramebufferBindingError
+            | x @ Gles2Error::CustomProgramCompileError(_)
+            | x @ Gles2Error::InvalidCustomProgram
             | x @ Gles2Error::BindBufferEGLError(_)
             | x @ Gles2Error::UnsupportedPixelFormat(_)
             | x @ Gles2Error::BufferAccessError(_)
             | x @ Gles2Error::MappingError
             | x @ Gles2Error::UnexpectedSize
             | x @ Gles2Error::BlitError
+            | x @ Gles2Error::FenceError(_)
             | x @ Gles2Error::EGLBufferAccessError(_) => SwapBuffersError::TemporaryFailure(Box::new(x)),
         }
     }
@@ -434,12 +486,16 @@ impl From<Gles2Error> for SwapBuffersError {
             | x @ Gles2Error::GLExtensionNotSupported(_)
             | x @ Gles2Error::EGLExtensionNotSupported(_)
             | x @ Gles2Error::GLVersionNotSupported(_)
+            | x @ Gles2Error::ContextRecreationError(_)
             | x @ Gles2Error::UnconstraintRenderingOperation => SwapBuffersError::ContextLost(Box::new(x)),
             Gles2Error::ContextActivationError(err) => err.into(),
             x @ Gles2Error::FramebufferBindingError
+            | x @ Gles2Error::CustomProgramCompileError(_)
+            | x @ Gles2Error::InvalidCustomProgram
             | x @ Gles2Error::MappingError
             | x @ Gles2Error::UnexpectedSize
             | x @ Gles2Error::BlitError
+            | x @ Gles2Error::FenceError(_)
             | x @ Gles2Error::BindBufferEGLError(_) => SwapBuffersError::TemporaryFailure(Box::new(x)),
         }
     }
@@ -469,11 +525,58 @@ extern "system" fn gl_debug_log(
     });
 }
 
+unsafe fn object_label(
+    gl: &ffi::Gles2,
+    identifier: ffi::types::GLenum,
+    name: ffi::types::GLuint,
+    label: &str,
+) {
+    gl.ObjectLabel(
+        identifier,
+        name,
+        label.len() as ffi::types::GLsizei,
+        label.as_ptr() as *const ffi::types::GLchar,
+    );
+}
+
+unsafe fn set_blend_mode(gl: &ffi::Gles2, blend_mode: BlendMode) {
+    match blend_mode {
+        BlendMode::Premultiplied => {
+            gl.Enable(ffi::BLEND);
+            gl.BlendFunc(ffi::ONE, ffi::ONE_MINUS_SRC_ALPHA);
+        }
+        BlendMode::Straight => {
+            gl.Enable(ffi::BLEND);
+            gl.BlendFuncSeparate(
+                ffi::SRC_ALPHA,
+                ffi::ONE_MINUS_SRC_ALPHA,
+                ffi::ONE,
+                ffi::ONE_MINUS_SRC_ALPHA,
+            );
+        }
+        BlendMode::Additive => {
+            gl.Enable(ffi::BLEND);
+            gl.BlendFunc(ffi::ONE, ffi::ONE);
+        }
+        BlendMode::Replace => {
+            gl.Disable(ffi::BLEND);
+        }
+    }
+}
+
 unsafe fn compile_shader(
     gl: &ffi::Gles2,
     variant: ffi::types::GLuint,
     src: &'static str,
 ) -> Result<ffi::types::GLuint, Gles2Error> {
+    compile_shader_source(gl, variant, src).ok_or(Gles2Error::ShaderCompileError(src))
+}
+
+unsafe fn compile_shader_source(
+    gl: &ffi::Gles2,
+    variant: ffi::types::GLuint,
+    src: &str,
+) -> Option<ffi::types::GLuint> {
     let shader = gl.CreateShader(variant);
     gl.ShaderSource(
         shader,
@@ -487,10 +590,10 @@ unsafe fn compile_shader(
     gl.GetShaderiv(shader, ffi::COMPILE_STATUS, &mut status as *mut _);
     if status == ffi::FALSE as i32 {
         gl.DeleteShader(shader);
-        return Err(Gles2Error::ShaderCompileError(src));
+        return None;
     }
 
-    Ok(shader)
+    Some(shader)
 }
 
 unsafe fn link_program(
@@ -499,7 +602,21 @@ unsafe fn link_program(
     frag_src: &'static str,
 ) -> Result<ffi::types::GLuint, Gles2Error> {
     let vert = compile_shader(gl, ffi::VERTEX_SHADER, vert_src)?;
-    let frag = compile_shader(gl, ffi::FRAGMENT_SHADER, frag_src)?;
+    let frag = match compile_shader(gl, ffi::FRAGMENT_SHADER, frag_src) {
+        Ok(frag) => frag,
+        Err(err) => {
+            gl.DeleteShader(vert);
+            return Err(err);
+        }
+    };
+    link_shaders(gl, vert, frag)
+}
+
+unsafe fn link_shaders(
+    gl: &ffi::Gles2,
+    vert: ffi::types::GLuint,
+    frag: ffi::types::GLuint,
+) -> Result<ffi::types::GLuint, Gles2Error> {
     let program = gl.CreateProgram();
     gl.AttachShader(program, vert);
     gl.AttachShader(program, frag);
@@ -519,9 +636,55 @@ unsafe fn link_program(
     Ok(program)
 }
 
-unsafe fn texture_program(gl: &ffi::Gles2, frag: &'static str) -> Result<Gles2TexProgram, Gles2Error> {
-    let program = link_program(gl, shaders::VERTEX_SHADER, frag)?;
+unsafe fn texture_program(
+    gl: &ffi::Gles2,
+    vert: &'static str,
+    frag: &'static str,
+) -> Result<Gles2TexProgram, Gles2Error> {
+    let program = link_program(gl, vert, frag)?;
+    Ok(texture_program_locations(gl, program))
+}
+
+unsafe fn texture_programs(
+    gl: &ffi::Gles2,
+    glsl_version: GlslVersion,
+    exts: &[String],
+) -> Result<[Gles2TexProgram; shaders::FRAGMENT_COUNT], Gles2Error> {
+    let sources = match glsl_version {
+        GlslVersion::Es100 => [
+            (shaders::VERTEX_SHADER, shaders::FRAGMENT_SHADER_ABGR),
+            (shaders::VERTEX_SHADER, shaders::FRAGMENT_SHADER_XBGR),
+            (shaders::VERTEX_SHADER, shaders::FRAGMENT_SHADER_EXTERNAL),
+        ],
+        GlslVersion::Es300 => [
+            (shaders::VERTEX_SHADER_300, shaders::FRAGMENT_SHADER_ABGR_300),
+            (shaders::VERTEX_SHADER_300, shaders::FRAGMENT_SHADER_XBGR_300),
+            // samplerExternalOES needs a separate extension in GLSL ES 3.00
+            if exts.iter().any(|ext| ext == "GL_OES_EGL_image_external_essl3") {
+                (shaders::VERTEX_SHADER_300, shaders::FRAGMENT_SHADER_EXTERNAL_300)
+            } else {
+                (shaders::VERTEX_SHADER, shaders::FRAGMENT_SHADER_EXTERNAL)
+            },
+        ],
+    };
+
+    let mut programs = Vec::with_capacity(shaders::FRAGMENT_COUNT);
+    for (vert, frag) in sources {
+        match texture_program(gl, vert, frag) {
+            Ok(program) => programs.push(program),
+            Err(err) => {
+                // don't leak the programs compiled so far
+                for program in programs {
+                    gl.DeleteProgram(program.program);
+                }
+                return Err(err);
+            }
+        }
+    }
+    Ok(programs.try_into().expect("one program per fragment shader"))
+}
 
+unsafe fn texture_program_locations(gl: &ffi::Gles2, program: ffi::types::GLuint) -> Gles2TexProgram {
     let vert = CStr::from_bytes_with_nul(b"vert\0").expect("NULL terminated");
     let vert_position = CStr::from_bytes_with_nul(b"vert_position\0").expect("NULL terminated");
     let tex = CStr::from_bytes_with_nul(b"tex\0").expect("NULL terminated");
@@ -529,7 +692,7 @@ unsafe fn texture_program(gl: &ffi::Gles2, frag: &'static str) -> Result<Gles2Te
     let tex_matrix = CStr::from_bytes_with_nul(b"tex_matrix\0").expect("NULL terminated");
     let alpha = CStr::from_bytes_with_nul(b"alpha\0").expect("NULL terminated");
 
-    Ok(Gles2TexProgram {
+    Gles2TexProgram {
         program,
         uniform_tex: gl.GetUniformLocation(program, tex.as_ptr() as *const ffi::types::GLchar),
         uniform_matrix: gl.GetUniformLocation(program, matrix.as_ptr() as *const ffi::types::GLchar),
@@ -538,11 +701,77 @@ unsafe fn texture_program(gl: &ffi::Gles2, frag: &'static str) -> Result<Gles2Te
         attrib_vert: gl.GetAttribLocation(program, vert.as_ptr() as *const ffi::types::GLchar),
         attrib_vert_position: gl
             .GetAttribLocation(program, vert_position.as_ptr() as *const ffi::types::GLchar),
+    }
+}
+
+unsafe fn custom_program(
+    gl: &ffi::Gles2,
+    name: &str,
+    fragment_src: &str,
+    uniforms: &[(&str, ShaderUniformType)],
+) -> Result<Gles2CustomProgram, Gles2Error> {
+    let uniform_names = uniforms
+        .iter()
+        .map(|(uniform, _)| {
+            CString::new(*uniform).map_err(|_| Gles2Error::CustomProgramCompileError(name.to_string()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut variants = Vec::with_capacity(2);
+    for defines in ["", "#define NO_ALPHA\n"] {
+        let src = format!("#version 100\n{}{}", defines, fragment_src);
+        let program = compile_shader(gl, ffi::VERTEX_SHADER, shaders::VERTEX_SHADER).and_then(|vert| {
+            match compile_shader_source(gl, ffi::FRAGMENT_SHADER, &src) {
+                Some(frag) => link_shaders(gl, vert, frag),
+                None => {
+                    gl.DeleteShader(vert);
+                    Err(Gles2Error::CustomProgramCompileError(name.to_string()))
+                }
+            }
+        });
+        match program {
+            Ok(program) => variants.push(texture_program_locations(gl, program)),
+            Err(err) => {
+                for variant in variants {
+                    gl.DeleteProgram(variant.program);
+                }
+                return Err(err);
+            }
+        }
+    }
+
+    let uniforms = uniforms
+        .iter()
+        .zip(uniform_names.iter())
+        .map(|((_, ty), uniform)| {
+            let locations = [
+                gl.GetUniformLocation(variants[0].program, uniform.as_ptr() as *const ffi::types::GLchar),
+                gl.GetUniformLocation(variants[1].program, uniform.as_ptr() as *const ffi::types::GLchar),
+            ];
+            (*ty, locations)
+        })
+        .collect();
+
+    let xbgr = variants.pop().unwrap();
+    let abgr = variants.pop().unwrap();
+    Ok(Gles2CustomProgram {
+        name: name.to_string(),
+        fragment_src: fragment_src.to_string(),
+        uniform_names: uniforms.iter().map(|(uniform, _)| uniform.to_string()).collect(),
+        variants: [abgr, xbgr],
+        uniforms,
     })
 }
 
-unsafe fn solid_program(gl: &ffi::Gles2) -> Result<Gles2SolidProgram, Gles2Error> {
-    let program = link_program(gl, shaders::VERTEX_SHADER_SOLID, shaders::FRAGMENT_SHADER_SOLID)?;
+unsafe fn solid_program(gl: &ffi::Gles2, glsl_version: GlslVersion) -> Result<Gles2SolidProgram, Gles2Error> {
+    let program = match glsl_version {
+        GlslVersion::Es100 => link_program(gl, shaders::VERTEX_SHADER_SOLID, shaders::FRAGMENT_SHADER_SOLID)?,
+        GlslVersion::Es300 => link_program(
+            gl,
+            shaders::VERTEX_SHADER_SOLID_300,
+            shaders::FRAGMENT_SHADER_SOLID_300,
+        )?,
+    };
 
     let matrix = CStr::from_bytes_with_nul(b"matrix\0").expect("NULL terminated");
     let color = CStr::from_bytes_with_nul(b"color\0").expect("NULL terminated");
@@ -646,12 +875,8 @@ impl Gles2Renderer {
             (gl, gl_version, exts, logger, supports_instancing)
         };
 
-        let tex_programs = [
-            texture_program(&gl, shaders::FRAGMENT_SHADER_ABGR)?,
-            texture_program(&gl, shaders::FRAGMENT_SHADER_XBGR)?,
-            texture_program(&gl, shaders::FRAGMENT_SHADER_EXTERNAL)?,
-        ];
-        let solid_program = solid_program(&gl)?;
+        let tex_programs = texture_programs(&gl, GlslVersion::Es100, &exts)?;
+        let solid_program = solid_program(&gl, GlslVersion::Es100)?;
 
         // Initialize vertices based on drawing methodology.
         let vertices: &[ffi::types::GLfloat] = if supports_instancing {
@@ -682,8 +907,10 @@ impl Gles2Renderer {
             egl_reader: None,
             extensions: exts,
             gl_version,
+            glsl_version: GlslVersion::Es100,
             tex_programs,
             solid_program,
+            custom_programs: Vec::new(),
             target: None,
             buffers: Vec::new(),
             dmabuf_cache: std::collections::HashMap::new(),
@@ -693,32 +920,46 @@ impl Gles2Renderer {
             min_filter: TextureFilter::Linear,
             max_filter: TextureFilter::Linear,
             supports_instancing,
+            profiler: None,
+            context_lost: false,
             logger_ptr,
             logger: log,
             _not_send: std::ptr::null_mut(),
         };
+        renderer.label_programs();
         renderer.egl.unbind()?;
         Ok(renderer)
     }
 
+    // remembers a lost context, `eglGetError` only reports it once
+    fn record_context_loss<T>(&mut self, res: Result<T, MakeCurrentError>) -> Result<T, MakeCurrentError> {
+        if let Err(ref err) = res {
+            if err.is_context_lost() {
+                self.context_lost = true;
+            }
+        }
+        res
+    }
+
     pub(crate) fn make_current(&mut self) -> Result<(), MakeCurrentError> {
         unsafe {
-            if let Some(&Gles2Target::Surface(ref surface)) = self.target.as_ref() {
-                self.egl.make_current_with_surface(surface)?;
+            let res = if let Some(&Gles2Target::Surface(ref surface)) = self.target.as_ref() {
+                self.egl.make_current_with_surface(surface)
             } else {
-                self.egl.make_current()?;
-                match self.target.as_ref() {
-                    Some(&Gles2Target::Image { ref buf, .. }) => {
-                        self.gl.BindFramebuffer(ffi::FRAMEBUFFER, buf.fbo)
-                    }
-                    Some(&Gles2Target::Texture { ref fbo, .. }) => {
-                        self.gl.BindFramebuffer(ffi::FRAMEBUFFER, *fbo)
-                    }
-                    Some(&Gles2Target::Renderbuffer { ref fbo, .. }) => {
-                        self.gl.BindFramebuffer(ffi::FRAMEBUFFER, *fbo)
-                    }
-                    _ => {}
+                self.egl.make_current()
+            };
+            self.record_context_loss(res)?;
+            match self.target.as_ref() {
+                Some(&Gles2Target::Image { ref buf, .. }) => {
+                    self.gl.BindFramebuffer(ffi::FRAMEBUFFER, buf.fbo)
+                }
+                Some(&Gles2Target::Texture { ref fbo, .. }) => {
+                    self.gl.BindFramebuffer(ffi::FRAMEBUFFER, *fbo)
                 }
+                Some(&Gles2Target::Renderbuffer { ref fbo, .. }) => {
+                    self.gl.BindFramebuffer(ffi::FRAMEBUFFER, *fbo)
+                }
+                _ => {}
             }
         }
         // delayed destruction until the next frame rendering.
@@ -838,6 +1079,7 @@ impl ImportMemWl for Gles2Renderer {
                             y_inverted: false,
                             size: (width, height).into(),
                             egl_images: None,
+                            mipmapped: Cell::new(false),
                             destruction_callback_sender: self.destruction_callback_sender.clone(),
                         });
                         if let Some(surface) = surface {
@@ -853,6 +1095,11 @@ impl ImportMemWl for Gles2Renderer {
                     }),
             );
 
+            let upload_size = Size::from((width, height));
+            if let Some(profiler) = self.profiler.as_mut() {
+                profiler.on_texture_upload_start(upload_size);
+            }
+
             unsafe {
                 self.gl.BindTexture(ffi::TEXTURE_2D, texture.0.texture);
                 self.gl
@@ -898,6 +1145,11 @@ impl ImportMemWl for Gles2Renderer {
                 self.gl.PixelStorei(ffi::UNPACK_ROW_LENGTH, 0);
                 self.gl.BindTexture(ffi::TEXTURE_2D, 0);
             }
+            texture.0.mipmapped.set(false);
+
+            if let Some(profiler) = self.profiler.as_mut() {
+                profiler.on_texture_upload_end(upload_size);
+            }
 
             Ok(texture)
         })
@@ -927,6 +1179,10 @@ impl ImportMem for Gles2Renderer {
             return Err(Gles2Error::UnexpectedSize);
         }
 
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.on_texture_upload_start(size);
+        }
+
         let texture = Gles2Texture(Rc::new({
             let mut tex = 0;
             unsafe {
@@ -957,10 +1213,15 @@ impl ImportMem for Gles2Renderer {
                 y_inverted: flipped,
                 size,
                 egl_images: None,
+                mipmapped: Cell::new(false),
                 destruction_callback_sender: self.destruction_callback_sender.clone(),
             }
         }));
 
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.on_texture_upload_end(size);
+        }
+
         Ok(texture)
     }
 
@@ -976,6 +1237,10 @@ impl ImportMem for Gles2Renderer {
             return Err(Gles2Error::UnexpectedSize);
         }
 
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.on_texture_upload_start(region.size);
+        }
+
         unsafe {
             self.gl.BindTexture(ffi::TEXTURE_2D, texture.0.texture);
             self.gl
@@ -1001,6 +1266,11 @@ impl ImportMem for Gles2Renderer {
             self.gl.PixelStorei(ffi::UNPACK_SKIP_ROWS, 0);
             self.gl.BindTexture(ffi::TEXTURE_2D, 0);
         }
+        texture.0.mipmapped.set(false);
+
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.on_texture_upload_end(region.size);
+        }
 
         Ok(())
     }
@@ -1072,6 +1342,7 @@ impl ImportEgl for Gles2Renderer {
             y_inverted: egl.y_inverted,
             size: egl.size,
             egl_images: Some(egl.into_images()),
+            mipmapped: Cell::new(false),
             destruction_callback_sender: self.destruction_callback_sender.clone(),
         }));
 
@@ -1107,6 +1378,7 @@ impl ImportDma for Gles2Renderer {
                 y_inverted: buffer.y_inverted(),
                 size: buffer.size(),
                 egl_images: Some(vec![image]),
+                mipmapped: Cell::new(false),
                 destruction_callback_sender: self.destruction_callback_sender.clone(),
             }));
             self.dmabuf_cache.insert(buffer.weak(), texture.clone());
@@ -1120,9 +1392,46 @@ impl ImportDma for Gles2Renderer {
 }
 
 #[cfg(feature = "wayland_frontend")]
-impl ImportDmaWl for Gles2Renderer {}
+impl ImportDmaWl for Gles2Renderer {
+    fn import_dma_buffer(
+        &mut self,
+        buffer: &wl_buffer::WlBuffer,
+        surface: Option<&crate::wayland::compositor::SurfaceData>,
+        damage: &[Rectangle<i32, BufferCoord>],
+    ) -> Result<Gles2Texture, Gles2Error> {
+        let dmabuf = crate::wayland::dmabuf::get_dmabuf(buffer)
+            .expect("import_dma_buffer without checking buffer type?");
+        let texture = self.import_dmabuf(&dmabuf, Some(damage))?;
+
+        // the client may still be writing to the buffer, so all following draws have to wait
+        let acquire_fence = surface.and_then(|states| {
+            states
+                .cached_state
+                .current::<crate::wayland::explicit_sync::ExplicitSyncCachedState>()
+                .acquire_fence
+                .take()
+        });
+        if let Some(fence) = acquire_fence {
+            self.wait_for_fence(fence)?;
+        }
+
+        Ok(texture)
+    }
+}
 
 impl Gles2Renderer {
+    /// Make the gpu wait for the given native fence fd
+    ///
+    /// The calling thread is not blocked, instead all commands submitted afterwards, including any
+    /// following frames, are only executed once the fence is signaled. This is used to wait for the
+    /// acquire fences of clients using `zwp_linux_explicit_synchronization_v1`, which happens
+    /// automatically when importing their buffers.
+    pub fn wait_for_fence(&mut self, fence: OwnedFd) -> Result<(), Gles2Error> {
+        self.make_current()?;
+        let fence = EGLFence::import(self.egl.display(), fence).map_err(Gles2Error::FenceError)?;
+        fence.wait().map_err(Gles2Error::FenceError)
+    }
+
     fn existing_dmabuf_texture(&self, buffer: &Dmabuf) -> Result<Option<Gles2Texture>, Gles2Error> {
         let existing_texture = self
             .dmabuf_cache
@@ -1699,20 +2008,15 @@ impl Gles2Renderer {
             return Err(Gles2Error::GLVersionNotSupported(version::GLES_3_0));
         }
 
-        match (src_target, dst_target) {
+        let res = match (src_target, dst_target) {
             (&Gles2Target::Surface(ref src), &Gles2Target::Surface(ref dst)) => unsafe {
-                self.egl.make_current_with_draw_and_read_surface(dst, src)?;
-            },
-            (&Gles2Target::Surface(ref src), _) => unsafe {
-                self.egl.make_current_with_surface(src)?;
-            },
-            (_, &Gles2Target::Surface(ref dst)) => unsafe {
-                self.egl.make_current_with_surface(dst)?;
+                self.egl.make_current_with_draw_and_read_surface(dst, src)
             },
-            (_, _) => unsafe {
-                self.egl.make_current()?;
-            },
-        }
+            (&Gles2Target::Surface(ref src), _) => unsafe { self.egl.make_current_with_surface(src) },
+            (_, &Gles2Target::Surface(ref dst)) => unsafe { self.egl.make_current_with_surface(dst) },
+            (_, _) => unsafe { self.egl.make_current() },
+        };
+        self.record_context_loss(res)?;
 
         match src_target {
             Gles2Target::Image { ref buf, .. } => unsafe {
@@ -1775,9 +2079,8 @@ impl Gles2Renderer {
 
 impl Unbind for Gles2Renderer {
     fn unbind(&mut self) -> Result<(), <Self as Renderer>::Error> {
-        unsafe {
-            self.egl.make_current()?;
-        }
+        let res = unsafe { self.egl.make_current() };
+        self.record_context_loss(res)?;
         unsafe { self.gl.BindFramebuffer(ffi::FRAMEBUFFER, 0) };
         self.target = None;
         self.egl.unbind()?;
@@ -1794,6 +2097,11 @@ impl Drop for Gles2Renderer {
                     self.gl.DeleteProgram(program.program);
                 }
                 self.gl.DeleteProgram(self.solid_program.program);
+                for program in &self.custom_programs {
+                    for variant in &program.variants {
+                        self.gl.DeleteProgram(variant.program);
+                    }
+                }
                 self.gl.DeleteBuffers(self.vbos.len() as i32, self.vbos.as_ptr());
 
                 if self.extensions.iter().any(|ext| ext == "GL_KHR_debug") {
@@ -1824,6 +2132,271 @@ impl Gles2Renderer {
         &self.egl
     }
 
+    /// Returns the format modifiers dmabufs of the given format can be imported with
+    ///
+    /// The modifiers are queried through `EGL_EXT_image_dma_buf_import_modifiers`. If the driver
+    /// does not report any explicit modifiers for the format, only the implicit modifier
+    /// (`DRM_FORMAT_MOD_INVALID`) is returned. An empty list means the format cannot be imported.
+    ///
+    /// Dmabufs with an explicit modifier are imported with that modifier by [`ImportDma::import_dmabuf`].
+    pub fn supported_modifiers(&self, fourcc: Fourcc) -> Vec<u64> {
+        let mut modifiers = self
+            .egl
+            .dmabuf_texture_formats()
+            .iter()
+            .filter(|format| format.code == fourcc)
+            .map(|format| Into::<u64>::into(format.modifier))
+            .collect::<Vec<_>>();
+        modifiers.sort_unstable();
+        modifiers
+    }
+
+    /// Generate mipmaps for the given texture
+    ///
+    /// Once generated, the texture is minified using the mipmap levels, which avoids aliasing
+    /// when it is drawn considerably smaller than its size (e.g. for thumbnails or overview modes).
+    /// The [`TextureFilter`] set by [`Renderer::downscale_filter`] is used to filter within and
+    /// between the levels.
+    ///
+    /// Mipmaps are not updated automatically. Uploading new contents (e.g. through
+    /// [`ImportMem::update_memory`]) discards them until this is called again.
+    /// External textures do not support mipmaps and are left untouched.
+    ///
+    /// Textures with dimensions which are not a power of two require OpenGL ES 3.0
+    /// or the `GL_OES_texture_npot` extension.
+    pub fn generate_mipmaps(&mut self, texture: &Gles2Texture) -> Result<(), Gles2Error> {
+        if texture.0.is_external {
+            debug!(self.logger, "Skipping mipmap generation for external texture");
+            return Ok(());
+        }
+        let size = texture.0.size;
+        let power_of_two = (size.w as u32).is_power_of_two() && (size.h as u32).is_power_of_two();
+        if !power_of_two
+            && self.gl_version < version::GLES_3_0
+            && !self.extensions.iter().any(|ext| ext == "GL_OES_texture_npot")
+        {
+            return Err(Gles2Error::GLExtensionNotSupported(&["GL_OES_texture_npot"]));
+        }
+
+        self.make_current()?;
+        unsafe {
+            self.gl.BindTexture(ffi::TEXTURE_2D, texture.0.texture);
+            self.gl.GenerateMipmap(ffi::TEXTURE_2D);
+            self.gl.BindTexture(ffi::TEXTURE_2D, 0);
+        }
+        texture.0.mipmapped.set(true);
+        Ok(())
+    }
+
+    /// Recover from a lost context
+    ///
+    /// Replaces the underlying [`EGLContext`] with a newly created one using the same configuration
+    /// (see [`EGLContext::recreate`]) and re-creates all internal GL state including the programs
+    /// registered with [`Gles2Renderer::register_custom_program`], which keep their ids.
+    /// Any bound target is unbound.
+    ///
+    /// Textures created before are not valid anymore and need to be imported again.
+    /// As the new context also results in a new [`Renderer::id`], textures cached by smithay
+    /// for a renderer id (e.g. of surfaces or [`MemoryRenderBuffer`](super::element::memory::MemoryRenderBuffer)s)
+    /// are re-imported automatically.
+    ///
+    /// On success the renderer is no longer considered lost (see [`Renderer::is_context_lost`]).
+    pub fn recover(&mut self) -> Result<(), Gles2Error> {
+        let context = self
+            .egl
+            .recreate(self.logger.clone())
+            .map_err(Gles2Error::ContextRecreationError)?;
+        let mut renderer = unsafe { Gles2Renderer::new(context, self.logger.clone())? };
+        for program in &self.custom_programs {
+            let uniforms = program
+                .uniform_names
+                .iter()
+                .zip(program.uniforms.iter())
+                .map(|(name, (ty, _))| (name.as_str(), *ty))
+                .collect::<Vec<_>>();
+            renderer.register_custom_program(&program.name, &program.fragment_src, &uniforms)?;
+        }
+        if let Err(err) = renderer.set_glsl_version(self.glsl_version) {
+            warn!(
+                self.logger,
+                "Failed to restore GLSL version {:?}: {}", self.glsl_version, err
+            );
+        }
+        renderer.min_filter = self.min_filter;
+        renderer.max_filter = self.max_filter;
+        renderer.profiler = self.profiler.take();
+        #[cfg(all(feature = "wayland_frontend", feature = "use_system_lib"))]
+        {
+            renderer.egl_reader = self.egl_reader.take();
+        }
+
+        renderer.context_lost = false;
+        info!(self.logger, "Recovered from context loss");
+        // GL resources of the lost context cannot be freed anymore,
+        // dropping the old renderer will just destroy the context.
+        *self = renderer;
+        Ok(())
+    }
+
+    /// Select the GLSL version of the internal shaders
+    ///
+    /// Renderers start out with [`GlslVersion::Es100`]. Selecting [`GlslVersion::Es300`]
+    /// recompiles the internal shaders with high precision floats. If the context does not
+    /// support OpenGL ES 3.0, the renderer keeps using [`GlslVersion::Es100`],
+    /// check [`Gles2Renderer::glsl_version`] for the version in use.
+    ///
+    /// The new shaders are compiled before the old ones are replaced, if compiling them fails
+    /// the renderer keeps using the previous version.
+    ///
+    /// Custom programs (see [`Gles2Renderer::register_custom_program`]) always use `#version 100`.
+    pub fn set_glsl_version(&mut self, glsl_version: GlslVersion) -> Result<(), Gles2Error> {
+        let glsl_version = if glsl_version == GlslVersion::Es300 && self.gl_version < version::GLES_3_0 {
+            warn!(
+                self.logger,
+                "GLSL ES 3.00 requires OpenGL ES 3.0, falling back to GLSL ES 1.00"
+            );
+            GlslVersion::Es100
+        } else {
+            glsl_version
+        };
+        if glsl_version == self.glsl_version {
+            return Ok(());
+        }
+
+        self.make_current()?;
+        unsafe {
+            let tex_programs = texture_programs(&self.gl, glsl_version, &self.extensions)?;
+            let solid_program = match solid_program(&self.gl, glsl_version) {
+                Ok(program) => program,
+                Err(err) => {
+                    for program in &tex_programs {
+                        self.gl.DeleteProgram(program.program);
+                    }
+                    return Err(err);
+                }
+            };
+
+            for program in &self.tex_programs {
+                self.gl.DeleteProgram(program.program);
+            }
+            self.gl.DeleteProgram(self.solid_program.program);
+            self.tex_programs = tex_programs;
+            self.solid_program = solid_program;
+        }
+        self.glsl_version = glsl_version;
+        self.label_programs();
+        debug!(self.logger, "Using GLSL version {:?}", glsl_version);
+
+        Ok(())
+    }
+
+    /// GLSL version of the internal shaders
+    pub fn glsl_version(&self) -> GlslVersion {
+        self.glsl_version
+    }
+
+    /// Set a [`RendererProfiler`] to be notified about texture uploads and frames
+    ///
+    /// Replaces any previously set profiler.
+    pub fn set_profiler(&mut self, profiler: Box<dyn RendererProfiler>) {
+        self.profiler = Some(profiler);
+    }
+
+    /// Compile and link a custom fragment shader, that elements can request to be drawn with
+    /// through [`RenderElement::custom_program`](super::element::RenderElement::custom_program).
+    ///
+    /// The shader is used in place of the default texture shader and has access to the same inputs:
+    /// `uniform sampler2D tex`, `uniform float alpha` and `varying vec2 v_tex_coords`.
+    /// It must not contain a `#version` directive, `#version 100` is prepended by the renderer.
+    /// Additionally `NO_ALPHA` is defined when drawing textures without an alpha channel.
+    /// External textures are always drawn with the default shader.
+    ///
+    /// `uniforms` declares additional uniforms of the shader, values for them are supplied per draw
+    /// in the same order.
+    pub fn register_custom_program(
+        &mut self,
+        name: &str,
+        fragment_src: &str,
+        uniforms: &[(&str, ShaderUniformType)],
+    ) -> Result<CustomProgramId, Gles2Error> {
+        self.make_current()?;
+        let program = unsafe { custom_program(&self.gl, name, fragment_src, uniforms)? };
+        debug!(self.logger, "Registered custom program {:?}", program.name);
+        self.custom_programs.push(program);
+        self.label_programs();
+        Ok(CustomProgramId(self.custom_programs.len() - 1))
+    }
+
+    fn supports_debug(&self) -> bool {
+        self.extensions.iter().any(|ext| ext == "GL_KHR_debug")
+    }
+
+    // label the internal programs for debugging tools, the context has to be current
+    fn label_programs(&self) {
+        if !self.supports_debug() {
+            return;
+        }
+
+        const TEXTURE_PROGRAMS: [&str; shaders::FRAGMENT_COUNT] = [
+            "smithay texture program (abgr)",
+            "smithay texture program (xbgr)",
+            "smithay texture program (external)",
+        ];
+        unsafe {
+            for (program, label) in self.tex_programs.iter().zip(TEXTURE_PROGRAMS) {
+                object_label(&self.gl, ffi::PROGRAM, program.program, label);
+            }
+            object_label(
+                &self.gl,
+                ffi::PROGRAM,
+                self.solid_program.program,
+                "smithay solid program",
+            );
+            for program in &self.custom_programs {
+                for (variant, kind) in program.variants.iter().zip(["abgr", "xbgr"]) {
+                    let label = format!("smithay custom program {} ({})", program.name, kind);
+                    object_label(&self.gl, ffi::PROGRAM, variant.program, &label);
+                }
+            }
+        }
+    }
+
+    /// Enable or disable forwarding of `GL_KHR_debug` messages to the logger of this renderer
+    ///
+    /// Debug output is enabled by default, if the `GL_KHR_debug` extension is supported.
+    /// Independently of this setting the internal programs of the renderer are labeled,
+    /// which makes them identifiable in GPU debuggers like RenderDoc.
+    /// See [`Gles2Renderer::set_texture_label`] to label textures.
+    pub fn enable_debug_logging(&mut self, enabled: bool) -> Result<(), Gles2Error> {
+        if !self.supports_debug() {
+            return Err(Gles2Error::GLExtensionNotSupported(&["GL_KHR_debug"]));
+        }
+
+        self.make_current()?;
+        unsafe {
+            if enabled {
+                self.gl.Enable(ffi::DEBUG_OUTPUT);
+            } else {
+                self.gl.Disable(ffi::DEBUG_OUTPUT);
+            }
+        }
+        Ok(())
+    }
+
+    /// Label a texture for GPU debuggers like RenderDoc, e.g. with the [`Id`](super::element::Id)
+    /// of the element it belongs to
+    ///
+    /// Requires the `GL_KHR_debug` extension.
+    pub fn set_texture_label(&mut self, texture: &Gles2Texture, label: &str) -> Result<(), Gles2Error> {
+        if !self.supports_debug() {
+            return Err(Gles2Error::GLExtensionNotSupported(&["GL_KHR_debug"]));
+        }
+
+        self.make_current()?;
+        unsafe { object_label(&self.gl, ffi::TEXTURE, texture.0.texture, label) };
+        Ok(())
+    }
+
     /// Run custom code in the GL context owned by this renderer.
     ///
     /// The OpenGL state of the renderer is considered an implementation detail
@@ -1867,6 +2440,10 @@ impl Renderer for Gles2Renderer {
         self.egl.user_data().get::<RendererId>().unwrap().0
     }
 
+    fn is_context_lost(&self) -> bool {
+        self.context_lost
+    }
+
     fn downscale_filter(&mut self, filter: TextureFilter) -> Result<(), Self::Error> {
         self.min_filter = filter;
         Ok(())
@@ -1883,6 +2460,10 @@ impl Renderer for Gles2Renderer {
     ) -> Result<Gles2Frame<'_>, Self::Error> {
         self.make_current()?;
 
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.on_frame_start();
+        }
+
         unsafe {
             self.gl.Viewport(0, 0, output_size.w, output_size.h);
 
@@ -1924,6 +2505,8 @@ impl Renderer for Gles2Renderer {
             current_projection: flip180 * transform.matrix() * renderer,
             transform,
             size: output_size,
+            damage_rects: 0,
+            custom_program: None,
             finished: AtomicBool::new(false),
         })
     }
@@ -1988,6 +2571,7 @@ impl<'frame> Frame for Gles2Frame<'frame> {
         if at.is_empty() {
             return Ok(());
         }
+        self.damage_rects += at.len();
 
         let mut mat = Matrix3::<f32>::identity();
         mat = self.current_projection * mat;
@@ -2112,7 +2696,10 @@ impl<'frame> Frame for Gles2Frame<'frame> {
         damage: &[Rectangle<i32, Physical>],
         transform: Transform,
         alpha: f32,
+        blend_mode: BlendMode,
     ) -> Result<(), Gles2Error> {
+        self.damage_rects += damage.len();
+
         let mut mat = Matrix3::<f32>::identity();
 
         // dest position and scale
@@ -2186,7 +2773,18 @@ impl<'frame> Frame for Gles2Frame<'frame> {
             })
             .collect::<Vec<_>>();
 
-        self.render_texture(texture, tex_mat, mat, Some(&instances), alpha)
+        if blend_mode == BlendMode::Premultiplied {
+            return self.render_texture(texture, tex_mat, mat, Some(&instances), alpha);
+        }
+
+        unsafe {
+            set_blend_mode(&self.renderer.gl, blend_mode);
+        }
+        let result = self.render_texture(texture, tex_mat, mat, Some(&instances), alpha);
+        unsafe {
+            set_blend_mode(&self.renderer.gl, BlendMode::Premultiplied);
+        }
+        result
     }
 
     fn transformation(&self) -> Transform {
@@ -2199,6 +2797,66 @@ impl<'frame> Frame for Gles2Frame<'frame> {
 }
 
 impl<'frame> Gles2Frame<'frame> {
+    /// Use a custom program for the following texture draw calls, until it is reset by passing `None`.
+    ///
+    /// The program has to be registered with [`Gles2Renderer::register_custom_program`] on the
+    /// renderer of this frame. The uniform values have to be given in the order the uniforms were
+    /// declared when registering the program.
+    ///
+    /// Elements requesting a custom program usually set it in their
+    /// [`RenderElement::draw`](crate::backend::renderer::element::RenderElement::draw) implementation
+    /// and reset it after drawing.
+    pub fn set_custom_program(
+        &mut self,
+        program: Option<(CustomProgramId, &[ShaderUniformValue])>,
+    ) -> Result<(), Gles2Error> {
+        if let Some((id, values)) = program {
+            let custom = self
+                .renderer
+                .custom_programs
+                .get(id.0)
+                .ok_or(Gles2Error::InvalidCustomProgram)?;
+            if custom.uniforms.len() != values.len()
+                || custom
+                    .uniforms
+                    .iter()
+                    .zip(values)
+                    .any(|((ty, _), value)| *ty != value.uniform_type())
+            {
+                return Err(Gles2Error::InvalidCustomProgram);
+            }
+            self.custom_program = Some((id, values.to_vec()));
+        } else {
+            self.custom_program = None;
+        }
+        Ok(())
+    }
+
+    /// Create a native fence fd, that is signaled once all commands submitted so far for this frame completed
+    ///
+    /// The fence can be used to defer the release of buffers or handed to the kernel, e.g. as an
+    /// `IN_FENCE_FD` of a drm plane, instead of waiting on the cpu.
+    ///
+    /// Returns `None` if the display does not support native fences (see [`EGLFence::is_supported`])
+    /// or the fence could not be created.
+    pub fn completion_fence(&mut self) -> Option<OwnedFd> {
+        let fence = match EGLFence::create(self.renderer.egl.display()) {
+            Ok(fence) => fence,
+            Err(err) => {
+                debug!(self.renderer.logger, "Failed to create completion fence: {}", err);
+                return None;
+            }
+        };
+        // the fence has to be flushed, before it can be exported
+        unsafe {
+            self.renderer.gl.Flush();
+        }
+        fence
+            .export()
+            .map_err(|err| debug!(self.renderer.logger, "Failed to export completion fence: {}", err))
+            .ok()
+    }
+
     fn finish_internal(&mut self) -> Result<(), Gles2Error> {
         if self.finished.swap(true, Ordering::SeqCst) {
             return Ok(());
@@ -2222,6 +2880,13 @@ impl<'frame> Gles2Frame<'frame> {
             // https://gitlab.freedesktop.org/mesa/kmscube/-/blob/9f63f359fab1b5d8e862508e4e51c9dfe339ccb0/drm-atomic.c#L235
             self.renderer.gl.Finish();
             self.renderer.gl.Disable(ffi::BLEND);
+            if ffi_egl::GetError() as u32 == ffi_egl::CONTEXT_LOST {
+                self.renderer.context_lost = true;
+            }
+        }
+
+        if let Some(profiler) = self.renderer.profiler.as_mut() {
+            profiler.on_frame_end(self.damage_rects);
         }
         Ok(())
     }
@@ -2260,6 +2925,17 @@ impl<'frame> Gles2Frame<'frame> {
             ffi::TEXTURE_2D
         };
 
+        // custom programs only have variants for non-external textures
+        let custom = self
+            .custom_program
+            .as_ref()
+            .filter(|_| !tex.0.is_external)
+            .map(|(id, values)| (&self.renderer.custom_programs[id.0], values));
+        let program = match custom {
+            Some((custom, _)) => &custom.variants[tex.0.texture_kind],
+            None => &self.renderer.tex_programs[tex.0.texture_kind],
+        };
+
         // render
         let gl = &self.renderer.gl;
         unsafe {
@@ -2268,9 +2944,11 @@ impl<'frame> Gles2Frame<'frame> {
             gl.TexParameteri(
                 target,
                 ffi::TEXTURE_MIN_FILTER,
-                match self.renderer.min_filter {
-                    TextureFilter::Nearest => ffi::NEAREST as i32,
-                    TextureFilter::Linear => ffi::LINEAR as i32,
+                match (self.renderer.min_filter, tex.0.mipmapped.get()) {
+                    (TextureFilter::Nearest, false) => ffi::NEAREST as i32,
+                    (TextureFilter::Linear, false) => ffi::LINEAR as i32,
+                    (TextureFilter::Nearest, true) => ffi::NEAREST_MIPMAP_NEAREST as i32,
+                    (TextureFilter::Linear, true) => ffi::LINEAR_MIPMAP_LINEAR as i32,
                 },
             );
             gl.TexParameteri(
@@ -2281,27 +2959,25 @@ impl<'frame> Gles2Frame<'frame> {
                     TextureFilter::Linear => ffi::LINEAR as i32,
                 },
             );
-            gl.UseProgram(self.renderer.tex_programs[tex.0.texture_kind].program);
-
-            gl.Uniform1i(self.renderer.tex_programs[tex.0.texture_kind].uniform_tex, 0);
-            gl.UniformMatrix3fv(
-                self.renderer.tex_programs[tex.0.texture_kind].uniform_matrix,
-                1,
-                ffi::FALSE,
-                matrix.as_ptr(),
-            );
-            gl.UniformMatrix3fv(
-                self.renderer.tex_programs[tex.0.texture_kind].uniform_tex_matrix,
-                1,
-                ffi::FALSE,
-                tex_matrix.as_ptr(),
-            );
-            gl.Uniform1f(
-                self.renderer.tex_programs[tex.0.texture_kind].uniform_alpha,
-                alpha,
-            );
+            gl.UseProgram(program.program);
+
+            gl.Uniform1i(program.uniform_tex, 0);
+            gl.UniformMatrix3fv(program.uniform_matrix, 1, ffi::FALSE, matrix.as_ptr());
+            gl.UniformMatrix3fv(program.uniform_tex_matrix, 1, ffi::FALSE, tex_matrix.as_ptr());
+            gl.Uniform1f(program.uniform_alpha, alpha);
+            if let Some((custom, values)) = custom {
+                for ((_, locations), value) in custom.uniforms.iter().zip(values) {
+                    let location = locations[tex.0.texture_kind];
+                    match *value {
+                        ShaderUniformValue::Float(x) => gl.Uniform1f(location, x),
+                        ShaderUniformValue::Vec2([x, y]) => gl.Uniform2f(location, x, y),
+                        ShaderUniformValue::Vec3([x, y, z]) => gl.Uniform3f(location, x, y, z),
+                        ShaderUniformValue::Vec4([x, y, z, w]) => gl.Uniform4f(location, x, y, z, w),
+                    }
+                }
+            }
 
-            gl.EnableVertexAttribArray(self.renderer.tex_programs[tex.0.texture_kind].attrib_vert as u32);
+            gl.EnableVertexAttribArray(program.attrib_vert as u32);
             gl.BindBuffer(ffi::ARRAY_BUFFER, self.renderer.vbos[0]);
             gl.VertexAttribPointer(
                 self.renderer.solid_program.attrib_vert as u32,
@@ -2327,9 +3003,7 @@ impl<'frame> Gles2Frame<'frame> {
             };
 
             // vert_position
-            gl.EnableVertexAttribArray(
-                self.renderer.tex_programs[tex.0.texture_kind].attrib_vert_position as u32,
-            );
+            gl.EnableVertexAttribArray(program.attrib_vert_position as u32);
             gl.BindBuffer(ffi::ARRAY_BUFFER, self.renderer.vbos[1]);
             gl.BufferData(
                 ffi::ARRAY_BUFFER,
@@ -2339,7 +3013,7 @@ impl<'frame> Gles2Frame<'frame> {
             );
 
             gl.VertexAttribPointer(
-                self.renderer.tex_programs[tex.0.texture_kind].attrib_vert_position as u32,
+                program.attrib_vert_position as u32,
                 4,
                 ffi::FLOAT,
                 ffi::FALSE,
@@ -2349,14 +3023,8 @@ impl<'frame> Gles2Frame<'frame> {
 
             let damage_len = (damage.len() / 4) as i32;
             if self.renderer.supports_instancing {
-                gl.VertexAttribDivisor(
-                    self.renderer.tex_programs[tex.0.texture_kind].attrib_vert as u32,
-                    0,
-                );
-                gl.VertexAttribDivisor(
-                    self.renderer.tex_programs[tex.0.texture_kind].attrib_vert_position as u32,
-                    1,
-                );
+                gl.VertexAttribDivisor(program.attrib_vert as u32, 0);
+                gl.VertexAttribDivisor(program.attrib_vert_position as u32, 1);
 
                 gl.DrawArraysInstanced(ffi::TRIANGLE_STRIP, 0, 4, damage_len);
             } else {
@@ -2383,10 +3051,8 @@ impl<'frame> Gles2Frame<'frame> {
 
             gl.BindBuffer(ffi::ARRAY_BUFFER, 0);
             gl.BindTexture(target, 0);
-            gl.DisableVertexAttribArray(self.renderer.tex_programs[tex.0.texture_kind].attrib_vert as u32);
-            gl.DisableVertexAttribArray(
-                self.renderer.tex_programs[tex.0.texture_kind].attrib_vert_position as u32,
-            );
+            gl.DisableVertexAttribArray(program.attrib_vert as u32);
+            gl.DisableVertexAttribArray(program.attrib_vert_position as u32);
         }
 
         Ok(())
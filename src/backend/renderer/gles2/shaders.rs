@@ -101,3 +101,103 @@ void main() {
     gl_FragColor = color;
 }
 "#;
+
+/*
+ * OpenGL ES 3.0 variants of the shaders, see `GlslVersion::Es300`
+ */
+pub const VERTEX_SHADER_300: &str = r#"#version 300 es
+uniform mat3 matrix;
+uniform mat3 tex_matrix;
+
+in vec2 vert;
+in vec4 vert_position;
+
+out vec2 v_tex_coords;
+
+mat2 scale(vec2 scale_vec){
+    return mat2(
+        scale_vec.x, 0.0,
+        0.0, scale_vec.y
+    );
+}
+
+void main() {
+    vec2 vert_transform_translation = vert_position.xy;
+    vec2 vert_transform_scale = vert_position.zw;
+    vec3 position = vec3(vert * scale(vert_transform_scale) + vert_transform_translation, 1.0);
+    v_tex_coords = (tex_matrix * position).xy;
+    gl_Position = vec4(matrix * position, 1.0);
+}
+"#;
+
+pub const FRAGMENT_SHADER_ABGR_300: &str = r#"#version 300 es
+
+precision highp float;
+uniform sampler2D tex;
+uniform float alpha;
+in vec2 v_tex_coords;
+out vec4 frag_color;
+
+void main() {
+    frag_color = texture(tex, v_tex_coords) * alpha;
+}
+"#;
+
+pub const FRAGMENT_SHADER_XBGR_300: &str = r#"#version 300 es
+
+precision highp float;
+uniform sampler2D tex;
+uniform float alpha;
+in vec2 v_tex_coords;
+out vec4 frag_color;
+
+void main() {
+    frag_color = vec4(texture(tex, v_tex_coords).rgb, 1.0) * alpha;
+}
+"#;
+
+pub const FRAGMENT_SHADER_EXTERNAL_300: &str = r#"#version 300 es
+#extension GL_OES_EGL_image_external_essl3 : require
+
+precision highp float;
+uniform samplerExternalOES tex;
+uniform float alpha;
+in vec2 v_tex_coords;
+out vec4 frag_color;
+
+void main() {
+    frag_color = texture(tex, v_tex_coords) * alpha;
+}
+"#;
+
+pub const VERTEX_SHADER_SOLID_300: &str = r#"#version 300 es
+
+uniform mat3 matrix;
+in vec2 vert;
+in vec4 position;
+
+mat2 scale(vec2 scale_vec){
+    return mat2(
+        scale_vec.x, 0.0,
+        0.0, scale_vec.y
+    );
+}
+
+void main() {
+    vec2 transform_translation = position.xy;
+    vec2 transform_scale = position.zw;
+    vec3 position = vec3(vert * scale(transform_scale) + transform_translation, 1.0);
+    gl_Position = vec4(matrix * position, 1.0);
+}
+"#;
+
+pub const FRAGMENT_SHADER_SOLID_300: &str = r#"#version 300 es
+
+precision highp float;
+uniform vec4 color;
+out vec4 frag_color;
+
+void main() {
+    frag_color = color;
+}
+"#;
@@ -13,8 +13,8 @@ use crate::{
         allocator::{dmabuf::Dmabuf, Format},
         egl::EGLContext,
         renderer::{
-            gles2::*, Bind, Blit, ExportDma, ExportMem, ImportDma, ImportMem, Offscreen, Renderer,
-            TextureFilter, Unbind,
+            gles2::*, Bind, Blit, CustomProgramId, ExportDma, ExportMem, ImportDma, ImportMem, Offscreen,
+            Renderer, ShaderUniformValue, TextureFilter, Unbind,
         },
     },
     utils::{Buffer as BufferCoord, Physical, Rectangle, Size, Transform},
@@ -30,7 +30,7 @@ use std::{
     sync::Arc,
 };
 
-use super::Frame;
+use super::{BlendMode, Frame};
 
 #[derive(Debug)]
 /// A renderer utilizing OpenGL ES 2 and [`glow`] on top for easier custom rendering.
@@ -170,6 +170,10 @@ impl Renderer for GlowRenderer {
         self.gl.id()
     }
 
+    fn is_context_lost(&self) -> bool {
+        self.gl.is_context_lost()
+    }
+
     fn downscale_filter(&mut self, filter: TextureFilter) -> Result<(), Self::Error> {
         self.gl.downscale_filter(filter)
     }
@@ -212,11 +216,17 @@ impl<'frame> Frame for GlowFrame<'frame> {
         damage: &[Rectangle<i32, Physical>],
         src_transform: Transform,
         alpha: f32,
+        blend_mode: BlendMode,
     ) -> Result<(), Self::Error> {
-        self.frame
-            .as_mut()
-            .unwrap()
-            .render_texture_from_to(texture, src, dst, damage, src_transform, alpha)
+        self.frame.as_mut().unwrap().render_texture_from_to(
+            texture,
+            src,
+            dst,
+            damage,
+            src_transform,
+            alpha,
+            blend_mode,
+        )
     }
 
     fn transformation(&self) -> Transform {
@@ -250,6 +260,16 @@ impl<'frame> Frame for GlowFrame<'frame> {
 }
 
 impl<'frame> GlowFrame<'frame> {
+    /// Use a custom program for the following texture draw calls, until it is reset by passing `None`.
+    ///
+    /// See [`Gles2Frame::set_custom_program`].
+    pub fn set_custom_program(
+        &mut self,
+        program: Option<(CustomProgramId, &[ShaderUniformValue])>,
+    ) -> Result<(), Gles2Error> {
+        self.frame.as_mut().unwrap().set_custom_program(program)
+    }
+
     fn finish_internal(&mut self) -> Result<(), Gles2Error> {
         if let Some(frame) = self.frame.take() {
             frame.finish()
@@ -344,7 +364,16 @@ impl ImportDma for GlowRenderer {
 }
 
 #[cfg(feature = "wayland_frontend")]
-impl ImportDmaWl for GlowRenderer {}
+impl ImportDmaWl for GlowRenderer {
+    fn import_dma_buffer(
+        &mut self,
+        buffer: &wl_buffer::WlBuffer,
+        surface: Option<&crate::wayland::compositor::SurfaceData>,
+        damage: &[Rectangle<i32, BufferCoord>],
+    ) -> Result<Gles2Texture, Gles2Error> {
+        self.gl.import_dma_buffer(buffer, surface, damage)
+    }
+}
 
 impl ExportMem for GlowRenderer {
     type TextureMapping = Gles2Mapping;
@@ -6,6 +6,8 @@
 //! Supported rendering apis:
 //!
 //! - Raw OpenGL ES 2
+//! - Software rasterization using tiny-skia
+//! - wgpu (Vulkan, Metal, Dx12)
 
 use std::collections::HashSet;
 use std::error::Error;
@@ -37,6 +39,15 @@ use crate::backend::egl::{
 #[cfg(feature = "renderer_multi")]
 pub mod multigpu;
 
+#[cfg(feature = "renderer_software")]
+pub mod software;
+
+#[cfg(feature = "renderer_wgpu")]
+pub mod wgpu;
+
+#[cfg(any(test, feature = "renderer_test"))]
+pub mod test;
+
 pub mod utils;
 
 pub mod element;
@@ -121,6 +132,20 @@ pub trait Texture {
     fn height(&self) -> u32;
 }
 
+/// Blending applied when rendering a texture onto the current target
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BlendMode {
+    /// Source-over blending of a texture with premultiplied alpha
+    #[default]
+    Premultiplied,
+    /// Source-over blending of a texture with straight (non-premultiplied) alpha
+    Straight,
+    /// The texture is added onto the contents of the target
+    Additive,
+    /// The texture replaces the contents of the target, including the alpha channel
+    Replace,
+}
+
 /// A downloaded texture buffer
 pub trait TextureMapping: Texture {
     /// Returns if the mapped buffer is flipped on the y-axis
@@ -175,12 +200,17 @@ pub trait Frame {
             damage,
             src_transform,
             alpha,
+            BlendMode::Premultiplied,
         )
     }
 
     /// Render part of a texture as given by src to the current target into the rectangle described by dst
     /// as a flat 2d-plane after applying the inverse of the given transformation.
     /// (Meaning `src_transform` should match the orientation of surface being rendered).
+    ///
+    /// The texture is blended onto the target as described by `blend_mode`. Textures of
+    /// wayland clients are premultiplied, so most callers want [`BlendMode::Premultiplied`].
+    #[allow(clippy::too_many_arguments)]
     fn render_texture_from_to(
         &mut self,
         texture: &Self::TextureId,
@@ -189,6 +219,7 @@ pub trait Frame {
         damage: &[Rectangle<i32, Physical>],
         src_transform: Transform,
         alpha: f32,
+        blend_mode: BlendMode,
     ) -> Result<(), Self::Error>;
 
     /// Output transformation that is applied to this frame
@@ -222,6 +253,14 @@ pub trait Renderer {
     /// `TextureId`s originating from any of these renderers.
     fn id(&self) -> usize;
 
+    /// Returns `true` if the underlying context of this renderer was lost, e.g. by a gpu reset.
+    ///
+    /// A renderer with a lost context will fail all further rendering operations and
+    /// needs to be recovered or recreated. The default implementation returns `false`.
+    fn is_context_lost(&self) -> bool {
+        false
+    }
+
     /// Set the filter method to be used when rendering a texture into a smaller area than its size
     fn downscale_filter(&mut self, filter: TextureFilter) -> Result<(), Self::Error>;
     /// Set the filter method to be used when rendering a texture into a larger area than its size
@@ -637,6 +676,71 @@ where
     ) -> Result<(), <Self as Renderer>::Error>;
 }
 
+/// Hooks to measure the cost of operations of a renderer
+///
+/// Can be used to connect renderers to external profiling tools or telemetry.
+/// All methods do nothing by default.
+pub trait RendererProfiler {
+    /// Called before uploading pixel data of the given size to a texture
+    fn on_texture_upload_start(&mut self, size: Size<i32, BufferCoord>) {
+        let _ = size;
+    }
+    /// Called after uploading pixel data of the given size to a texture
+    fn on_texture_upload_end(&mut self, size: Size<i32, BufferCoord>) {
+        let _ = size;
+    }
+    /// Called when the renderer starts a new frame
+    fn on_frame_start(&mut self) {}
+    /// Called when a frame was finished, with the number of damage rectangles drawn during the frame
+    fn on_frame_end(&mut self, damage_rects: usize) {
+        let _ = damage_rects;
+    }
+}
+
+/// Handle to a custom shader program registered on a renderer
+///
+/// A handle is only valid for the renderer it was created with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CustomProgramId(pub(crate) usize);
+
+/// Type of an uniform of a custom shader program
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ShaderUniformType {
+    /// `float`
+    Float,
+    /// `vec2`
+    Vec2,
+    /// `vec3`
+    Vec3,
+    /// `vec4`
+    Vec4,
+}
+
+/// Value of an uniform of a custom shader program
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ShaderUniformValue {
+    /// `float`
+    Float(f32),
+    /// `vec2`
+    Vec2([f32; 2]),
+    /// `vec3`
+    Vec3([f32; 3]),
+    /// `vec4`
+    Vec4([f32; 4]),
+}
+
+impl ShaderUniformValue {
+    /// Type of this uniform value
+    pub fn uniform_type(&self) -> ShaderUniformType {
+        match self {
+            ShaderUniformValue::Float(_) => ShaderUniformType::Float,
+            ShaderUniformValue::Vec2(_) => ShaderUniformType::Vec2,
+            ShaderUniformValue::Vec3(_) => ShaderUniformType::Vec3,
+            ShaderUniformValue::Vec4(_) => ShaderUniformType::Vec4,
+        }
+    }
+}
+
 #[cfg(feature = "wayland_frontend")]
 #[non_exhaustive]
 /// Buffer type of a given wl_buffer, if managed by smithay
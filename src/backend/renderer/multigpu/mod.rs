@@ -869,6 +869,10 @@ where
         self.render.renderer().id()
     }
 
+    fn is_context_lost(&self) -> bool {
+        self.render.renderer().is_context_lost()
+    }
+
     fn downscale_filter(&mut self, filter: TextureFilter) -> Result<(), Self::Error> {
         self.render
             .renderer_mut()
@@ -1025,6 +1029,7 @@ where
                             &damage,
                             Transform::Normal,
                             1.0,
+                            BlendMode::Premultiplied,
                         )
                         .map_err(Error::Target)?;
                     frame.finish().map_err(Error::Target)?;
@@ -1090,7 +1095,15 @@ where
                         let damage = &[Rectangle::from_loc_and_size((0, 0), dst.size)];
                         frame.clear([0.0, 0.0, 0.0, 0.0], &[dst]).map_err(Error::Target)?;
                         frame
-                            .render_texture_from_to(&texture, src, dst, damage, Transform::Normal, 1.0)
+                            .render_texture_from_to(
+                                &texture,
+                                src,
+                                dst,
+                                damage,
+                                Transform::Normal,
+                                1.0,
+                                BlendMode::Premultiplied,
+                            )
                             .map_err(Error::Target)?;
                     }
                     frame.finish().map_err(Error::Target)?;
@@ -1322,6 +1335,7 @@ where
         damage: &[Rectangle<i32, Physical>],
         src_transform: Transform,
         alpha: f32,
+        blend_mode: BlendMode,
     ) -> Result<(), Error<R, T>> {
         if let Some(texture) = texture.get::<R>(&self.node) {
             self.damage.extend(damage.iter().copied().map(|mut rect| {
@@ -1331,7 +1345,7 @@ where
             self.frame
                 .as_mut()
                 .unwrap()
-                .render_texture_from_to(&texture, src, dst, damage, src_transform, alpha)
+                .render_texture_from_to(&texture, src, dst, damage, src_transform, alpha, blend_mode)
                 .map_err(Error::Render)
         } else {
             slog::warn!(
@@ -0,0 +1,502 @@
+//! Implementation of the rendering traits using CPU rasterization with [`tiny_skia`]
+//!
+//! The [`SoftwareRenderer`] does not require any GPU or display server, which makes it suitable
+//! for headless compositors and for running a compositor in CI.
+//!
+//! Rendering happens into a framebuffer in memory, that is (re-)allocated by [`Renderer::render`]
+//! to match the requested output size. Its contents can be accessed through
+//! [`SoftwareRenderer::framebuffer`] after the frame was finished.
+//! Pixel data is stored as premultiplied RGBA8, which is also the format expected by
+//! [`ImportMem`].
+
+use std::{cell::RefCell, fmt, rc::Rc};
+
+use tiny_skia::{
+    BlendMode as SkiaBlendMode, ClipMask, Color, FillRule, FilterQuality, Paint, PathBuilder, Pixmap,
+    PixmapPaint, Rect, Transform as SkTransform,
+};
+
+use super::{BlendMode, Frame, ImportMem, Renderer, Texture, TextureFilter};
+use crate::utils::{Buffer as BufferCoord, Physical, Rectangle, Size, Transform};
+
+crate::utils::ids::id_gen!(next_renderer_id, RENDERER_ID, RENDERER_IDS);
+
+/// Error returned during rendering using the [`SoftwareRenderer`]
+#[derive(thiserror::Error, Debug)]
+pub enum SoftwareRendererError {
+    /// The requested size is empty or too large
+    #[error("Invalid size: {0:?}")]
+    InvalidSize(Size<i32, BufferCoord>),
+    /// The provided buffer's size did not match the requested one.
+    #[error("Error reading buffer, size is too small for the given dimensions")]
+    UnexpectedSize,
+    /// The updated region is not inside the bounds of the texture
+    #[error("Region {0:?} is out of bounds of the texture")]
+    InvalidRegion(Rectangle<i32, BufferCoord>),
+}
+
+/// A handle to a texture of the [`SoftwareRenderer`]
+///
+/// Textures are stored in main memory and can be used with any [`SoftwareRenderer`].
+#[derive(Debug, Clone)]
+pub struct SoftwareTexture(Rc<RefCell<Pixmap>>);
+
+impl Texture for SoftwareTexture {
+    fn width(&self) -> u32 {
+        self.0.borrow().width()
+    }
+    fn height(&self) -> u32 {
+        self.0.borrow().height()
+    }
+}
+
+/// A renderer rasterizing on the CPU using [`tiny_skia`]
+pub struct SoftwareRenderer {
+    id: usize,
+    framebuffer: Option<Pixmap>,
+    min_filter: TextureFilter,
+    max_filter: TextureFilter,
+    logger: ::slog::Logger,
+}
+
+impl fmt::Debug for SoftwareRenderer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SoftwareRenderer")
+            .field("id", &self.id)
+            .field("framebuffer", &self.framebuffer)
+            .field("min_filter", &self.min_filter)
+            .field("max_filter", &self.max_filter)
+            .field("logger", &self.logger)
+            .finish()
+    }
+}
+
+impl SoftwareRenderer {
+    /// Creates a new software renderer
+    ///
+    /// Texture filtering starts with Linear-downscaling and Linear-upscaling.
+    pub fn new<L>(logger: L) -> SoftwareRenderer
+    where
+        L: Into<Option<::slog::Logger>>,
+    {
+        let logger = crate::slog_or_fallback(logger).new(slog::o!("smithay_module" => "renderer_software"));
+        SoftwareRenderer {
+            id: next_renderer_id(),
+            framebuffer: None,
+            min_filter: TextureFilter::Linear,
+            max_filter: TextureFilter::Linear,
+            logger,
+        }
+    }
+
+    /// Contents of the framebuffer as premultiplied RGBA8
+    ///
+    /// Returns `None` if nothing was rendered yet.
+    pub fn framebuffer(&self) -> Option<&[u8]> {
+        self.framebuffer.as_ref().map(|pixmap| pixmap.data())
+    }
+
+    /// Size of the framebuffer, which matches the `output_size` of the last [`Renderer::render`] call
+    pub fn framebuffer_size(&self) -> Option<Size<i32, Physical>> {
+        self.framebuffer
+            .as_ref()
+            .map(|pixmap| (pixmap.width() as i32, pixmap.height() as i32).into())
+    }
+}
+
+impl Drop for SoftwareRenderer {
+    fn drop(&mut self) {
+        RENDERER_IDS.lock().unwrap().remove(&self.id);
+    }
+}
+
+fn copy_rows(pixmap: &mut Pixmap, data: &[u8], region: Rectangle<i32, BufferCoord>, flipped: bool) {
+    let stride = pixmap.width() as usize * 4;
+    let row_len = region.size.w as usize * 4;
+    let height = pixmap.height() as usize;
+    let dst = pixmap.data_mut();
+    for row in 0..region.size.h as usize {
+        let y = region.loc.y as usize + row;
+        let y = if flipped { height - 1 - y } else { y };
+        let offset = y * stride + region.loc.x as usize * 4;
+        dst[offset..offset + row_len].copy_from_slice(&data[row * row_len..(row + 1) * row_len]);
+    }
+}
+
+impl ImportMem for SoftwareRenderer {
+    fn import_memory(
+        &mut self,
+        data: &[u8],
+        size: Size<i32, BufferCoord>,
+        flipped: bool,
+    ) -> Result<SoftwareTexture, SoftwareRendererError> {
+        if size.w <= 0 || size.h <= 0 {
+            return Err(SoftwareRendererError::InvalidSize(size));
+        }
+        if data.len() < (size.w * size.h * 4) as usize {
+            return Err(SoftwareRendererError::UnexpectedSize);
+        }
+        let mut pixmap =
+            Pixmap::new(size.w as u32, size.h as u32).ok_or(SoftwareRendererError::InvalidSize(size))?;
+        copy_rows(
+            &mut pixmap,
+            data,
+            Rectangle::from_loc_and_size((0, 0), size),
+            flipped,
+        );
+        Ok(SoftwareTexture(Rc::new(RefCell::new(pixmap))))
+    }
+
+    fn update_memory(
+        &mut self,
+        texture: &SoftwareTexture,
+        data: &[u8],
+        region: Rectangle<i32, BufferCoord>,
+    ) -> Result<(), SoftwareRendererError> {
+        let mut pixmap = texture.0.borrow_mut();
+        let bounds = Rectangle::from_loc_and_size((0, 0), (pixmap.width() as i32, pixmap.height() as i32));
+        if region.loc.x < 0 || region.loc.y < 0 || !bounds.contains_rect(region) {
+            return Err(SoftwareRendererError::InvalidRegion(region));
+        }
+        if data.len() < (region.size.w * region.size.h * 4) as usize {
+            return Err(SoftwareRendererError::UnexpectedSize);
+        }
+        copy_rows(&mut pixmap, data, region, false);
+        Ok(())
+    }
+}
+
+impl Renderer for SoftwareRenderer {
+    type Error = SoftwareRendererError;
+    type TextureId = SoftwareTexture;
+    type Frame<'frame> = SoftwareFrame<'frame>;
+
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn downscale_filter(&mut self, filter: TextureFilter) -> Result<(), Self::Error> {
+        self.min_filter = filter;
+        Ok(())
+    }
+    fn upscale_filter(&mut self, filter: TextureFilter) -> Result<(), Self::Error> {
+        self.max_filter = filter;
+        Ok(())
+    }
+
+    fn render(
+        &mut self,
+        output_size: Size<i32, Physical>,
+        transform: Transform,
+    ) -> Result<SoftwareFrame<'_>, Self::Error> {
+        let invalid_size = || SoftwareRendererError::InvalidSize((output_size.w, output_size.h).into());
+        if output_size.w <= 0 || output_size.h <= 0 {
+            return Err(invalid_size());
+        }
+
+        let matches = self
+            .framebuffer
+            .as_ref()
+            .map(|pixmap| pixmap.width() == output_size.w as u32 && pixmap.height() == output_size.h as u32)
+            .unwrap_or(false);
+        if !matches {
+            slog::debug!(self.logger, "Allocating framebuffer of size {:?}", output_size);
+            self.framebuffer =
+                Some(Pixmap::new(output_size.w as u32, output_size.h as u32).ok_or_else(invalid_size)?);
+        }
+
+        let size = transform.transform_size(output_size);
+        Ok(SoftwareFrame {
+            renderer: self,
+            transform,
+            output_transform: output_transform(transform, size),
+            size,
+        })
+    }
+}
+
+/// Transformation mapping a point of the transformed output space into the framebuffer
+///
+/// Matches [`Transform::transform_point_in`] for an area of the given size.
+fn output_transform(transform: Transform, size: Size<i32, Physical>) -> SkTransform {
+    let (w, h) = (size.w as f32, size.h as f32);
+    match transform {
+        Transform::Normal => SkTransform::identity(),
+        Transform::_90 => SkTransform::from_row(0.0, 1.0, -1.0, 0.0, h, 0.0),
+        Transform::_180 => SkTransform::from_row(-1.0, 0.0, 0.0, -1.0, w, h),
+        Transform::_270 => SkTransform::from_row(0.0, -1.0, 1.0, 0.0, 0.0, w),
+        Transform::Flipped => SkTransform::from_row(-1.0, 0.0, 0.0, 1.0, w, 0.0),
+        Transform::Flipped90 => SkTransform::from_row(0.0, 1.0, 1.0, 0.0, 0.0, 0.0),
+        Transform::Flipped180 => SkTransform::from_row(1.0, 0.0, 0.0, -1.0, 0.0, h),
+        Transform::Flipped270 => SkTransform::from_row(0.0, -1.0, -1.0, 0.0, h, w),
+    }
+}
+
+/// Handle to the currently rendered frame during [`SoftwareRenderer::render`](Renderer::render).
+pub struct SoftwareFrame<'frame> {
+    renderer: &'frame mut SoftwareRenderer,
+    transform: Transform,
+    output_transform: SkTransform,
+    size: Size<i32, Physical>,
+}
+
+impl<'frame> fmt::Debug for SoftwareFrame<'frame> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SoftwareFrame")
+            .field("renderer", &self.renderer)
+            .field("transform", &self.transform)
+            .field("output_transform", &self.output_transform)
+            .field("size", &self.size)
+            .finish()
+    }
+}
+
+impl<'frame> SoftwareFrame<'frame> {
+    /// Clip mask covering the given rectangles of the output space
+    fn clip_mask(&self, rects: impl Iterator<Item = Rectangle<i32, Physical>>) -> Option<ClipMask> {
+        let framebuffer = self.renderer.framebuffer.as_ref()?;
+        let mut builder = PathBuilder::new();
+        for rect in rects {
+            builder.push_rect(
+                rect.loc.x as f32,
+                rect.loc.y as f32,
+                rect.size.w as f32,
+                rect.size.h as f32,
+            );
+        }
+        let path = builder.finish()?.transform(self.output_transform)?;
+        let mut mask = ClipMask::new();
+        mask.set_path(
+            framebuffer.width(),
+            framebuffer.height(),
+            &path,
+            FillRule::Winding,
+            false,
+        )?;
+        Some(mask)
+    }
+}
+
+impl<'frame> Frame for SoftwareFrame<'frame> {
+    type Error = SoftwareRendererError;
+    type TextureId = SoftwareTexture;
+
+    fn id(&self) -> usize {
+        self.renderer.id
+    }
+
+    fn clear(&mut self, color: [f32; 4], at: &[Rectangle<i32, Physical>]) -> Result<(), Self::Error> {
+        let color = match Color::from_rgba(color[0], color[1], color[2], color[3]) {
+            Some(color) => color,
+            None => {
+                slog::warn!(self.renderer.logger, "Invalid clear color {:?}", color);
+                return Ok(());
+            }
+        };
+        let mut paint = Paint {
+            blend_mode: SkiaBlendMode::Source,
+            anti_alias: false,
+            ..Default::default()
+        };
+        paint.set_color(color);
+
+        let output_transform = self.output_transform;
+        let framebuffer = self.renderer.framebuffer.as_mut().unwrap();
+        for rect in at {
+            if let Some(rect) = Rect::from_xywh(
+                rect.loc.x as f32,
+                rect.loc.y as f32,
+                rect.size.w as f32,
+                rect.size.h as f32,
+            ) {
+                framebuffer.fill_rect(rect, &paint, output_transform, None);
+            }
+        }
+        Ok(())
+    }
+
+    fn render_texture_from_to(
+        &mut self,
+        texture: &SoftwareTexture,
+        src: Rectangle<f64, BufferCoord>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        src_transform: Transform,
+        alpha: f32,
+        blend_mode: BlendMode,
+    ) -> Result<(), Self::Error> {
+        if src.size.w <= 0. || src.size.h <= 0. || dst.size.w <= 0 || dst.size.h <= 0 {
+            slog::warn!(self.renderer.logger, "Texture/Src is zero sized");
+            return Ok(());
+        }
+
+        let clip_mask = match self.clip_mask(damage.iter().map(|rect| {
+            let mut rect = *rect;
+            rect.loc += dst.loc;
+            rect
+        })) {
+            Some(clip_mask) => clip_mask,
+            None => return Ok(()),
+        };
+
+        // `src_transform` describes the orientation of the buffer, so its inverse has to be applied
+        let buffer_transform = if src_transform.flipped() {
+            src_transform
+        } else {
+            src_transform.invert()
+        };
+        let src_size = Size::<f64, BufferCoord>::from((src.size.w, src.size.h));
+        let transformed_src_size = buffer_transform.transform_size(src_size);
+
+        let transform = self
+            .output_transform
+            .pre_translate(dst.loc.x as f32, dst.loc.y as f32)
+            .pre_scale(
+                (dst.size.w as f64 / transformed_src_size.w) as f32,
+                (dst.size.h as f64 / transformed_src_size.h) as f32,
+            )
+            .pre_concat(output_transform(
+                buffer_transform,
+                (src_size.w.round() as i32, src_size.h.round() as i32).into(),
+            ))
+            .pre_translate(-src.loc.x as f32, -src.loc.y as f32);
+
+        let upscale =
+            dst.size.w as f64 > transformed_src_size.w || dst.size.h as f64 > transformed_src_size.h;
+        let filter = if upscale {
+            self.renderer.max_filter
+        } else {
+            self.renderer.min_filter
+        };
+        let paint = PixmapPaint {
+            opacity: alpha.clamp(0.0, 1.0),
+            blend_mode: match blend_mode {
+                // straight alpha textures are premultiplied below
+                BlendMode::Premultiplied | BlendMode::Straight => SkiaBlendMode::SourceOver,
+                BlendMode::Additive => SkiaBlendMode::Plus,
+                BlendMode::Replace => SkiaBlendMode::Source,
+            },
+            quality: match filter {
+                TextureFilter::Linear => FilterQuality::Bilinear,
+                TextureFilter::Nearest => FilterQuality::Nearest,
+            },
+        };
+
+        let pixmap = texture.0.borrow();
+        // pixmaps are always composited as premultiplied, so straight alpha has to be converted first
+        let premultiplied;
+        let pixmap = if blend_mode == BlendMode::Straight {
+            premultiplied = premultiply(&pixmap);
+            &premultiplied
+        } else {
+            &*pixmap
+        };
+        self.renderer.framebuffer.as_mut().unwrap().draw_pixmap(
+            0,
+            0,
+            pixmap.as_ref(),
+            &paint,
+            transform,
+            Some(&clip_mask),
+        );
+        Ok(())
+    }
+
+    fn transformation(&self) -> Transform {
+        self.transform
+    }
+
+    fn finish(self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+fn premultiply(pixmap: &Pixmap) -> Pixmap {
+    let mut pixmap = pixmap.clone();
+    for pixel in pixmap.data_mut().chunks_exact_mut(4) {
+        let alpha = pixel[3] as u16;
+        for channel in &mut pixel[..3] {
+            *channel = ((*channel as u16 * alpha + 127) / 255) as u8;
+        }
+    }
+    pixmap
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pixel(renderer: &SoftwareRenderer, x: usize, y: usize) -> [u8; 4] {
+        let size = renderer.framebuffer_size().unwrap();
+        let offset = (y * size.w as usize + x) * 4;
+        renderer.framebuffer().unwrap()[offset..offset + 4]
+            .try_into()
+            .unwrap()
+    }
+
+    #[test]
+    fn clear_damaged_region() {
+        let mut renderer = SoftwareRenderer::new(None);
+        let mut frame = renderer.render((4, 4).into(), Transform::Normal).unwrap();
+        frame
+            .clear(
+                [1.0, 0.0, 0.0, 1.0],
+                &[Rectangle::from_loc_and_size((0, 0), (2, 4))],
+            )
+            .unwrap();
+        frame.finish().unwrap();
+
+        assert_eq!(pixel(&renderer, 0, 0), [255, 0, 0, 255]);
+        assert_eq!(pixel(&renderer, 1, 3), [255, 0, 0, 255]);
+        assert_eq!(pixel(&renderer, 2, 0), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn render_texture_to_damage() {
+        let mut renderer = SoftwareRenderer::new(None);
+        let data = [0u8, 255, 0, 255].repeat(4);
+        let texture = renderer.import_memory(&data, (2, 2).into(), false).unwrap();
+
+        let mut frame = renderer.render((4, 4).into(), Transform::Normal).unwrap();
+        frame
+            .render_texture_from_to(
+                &texture,
+                Rectangle::from_loc_and_size((0., 0.), (2., 2.)),
+                Rectangle::from_loc_and_size((2, 2), (2, 2)),
+                &[Rectangle::from_loc_and_size((0, 0), (1, 2))],
+                Transform::Normal,
+                1.0,
+                BlendMode::Premultiplied,
+            )
+            .unwrap();
+        frame.finish().unwrap();
+
+        assert_eq!(pixel(&renderer, 2, 2), [0, 255, 0, 255]);
+        assert_eq!(pixel(&renderer, 2, 3), [0, 255, 0, 255]);
+        assert_eq!(pixel(&renderer, 3, 2), [0, 0, 0, 0]);
+        assert_eq!(pixel(&renderer, 1, 1), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn render_straight_alpha_texture() {
+        let mut renderer = SoftwareRenderer::new(None);
+        let data = [255u8, 0, 0, 128];
+        let texture = renderer.import_memory(&data, (1, 1).into(), false).unwrap();
+
+        let mut frame = renderer.render((1, 1).into(), Transform::Normal).unwrap();
+        frame
+            .render_texture_from_to(
+                &texture,
+                Rectangle::from_loc_and_size((0., 0.), (1., 1.)),
+                Rectangle::from_loc_and_size((0, 0), (1, 1)),
+                &[Rectangle::from_loc_and_size((0, 0), (1, 1))],
+                Transform::Normal,
+                1.0,
+                BlendMode::Straight,
+            )
+            .unwrap();
+        frame.finish().unwrap();
+
+        assert_eq!(pixel(&renderer, 0, 0), [128, 0, 0, 128]);
+    }
+}
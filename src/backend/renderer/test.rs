@@ -0,0 +1,230 @@
+//! Renderer recording draw calls instead of rendering, for testing
+//!
+//! The [`TestRenderer`] does not touch any pixels. Instead every [`Frame::clear`] and
+//! [`Frame::render_texture_from_to`] call of a frame is recorded as a [`ClearCall`] or [`DrawCall`].
+//! After a frame was rendered, e.g. by [`DamageTrackedRenderer::render_output`](super::damage::DamageTrackedRenderer::render_output),
+//! the recorded calls can be inspected through [`TestRenderer::clear_calls`] and [`TestRenderer::draw_calls`]
+//! to check which elements were drawn with which damage, without requiring a gpu.
+
+use std::{convert::Infallible, fmt};
+
+use super::{BlendMode, Frame, ImportMem, Renderer, Texture, TextureFilter};
+use crate::utils::{Buffer as BufferCoord, Physical, Rectangle, Size, Transform};
+
+crate::utils::ids::id_gen!(next_renderer_id, RENDERER_ID, RENDERER_IDS);
+
+/// A texture of the [`TestRenderer`]
+///
+/// Textures only store their size and an id unique to the renderer that created them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestTexture {
+    id: usize,
+    size: Size<i32, BufferCoord>,
+}
+
+impl TestTexture {
+    /// Id of this texture, unique to the renderer that created it
+    pub fn id(&self) -> usize {
+        self.id
+    }
+}
+
+impl Texture for TestTexture {
+    fn width(&self) -> u32 {
+        self.size.w as u32
+    }
+    fn height(&self) -> u32 {
+        self.size.h as u32
+    }
+}
+
+/// A recorded [`Frame::clear`] call
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClearCall {
+    /// Color used for clearing
+    pub color: [f32; 4],
+    /// Cleared regions
+    pub damage: Vec<Rectangle<i32, Physical>>,
+}
+
+/// A recorded [`Frame::render_texture_from_to`] call
+#[derive(Debug, Clone, PartialEq)]
+pub struct DrawCall {
+    /// Id of the drawn texture, see [`TestTexture::id`]
+    pub texture: usize,
+    /// Source rectangle in the texture
+    pub src: Rectangle<f64, BufferCoord>,
+    /// Destination rectangle in the output
+    pub dst: Rectangle<i32, Physical>,
+    /// Damaged regions, relative to `dst`
+    pub damage: Vec<Rectangle<i32, Physical>>,
+    /// Transformation of the texture
+    pub src_transform: Transform,
+    /// Alpha value used for drawing
+    pub alpha: f32,
+    /// Blend mode used for drawing
+    pub blend_mode: BlendMode,
+}
+
+/// A renderer recording the calls of each frame
+pub struct TestRenderer {
+    id: usize,
+    next_texture_id: usize,
+    clear_calls: Vec<ClearCall>,
+    draw_calls: Vec<DrawCall>,
+}
+
+impl fmt::Debug for TestRenderer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TestRenderer")
+            .field("id", &self.id)
+            .field("clear_calls", &self.clear_calls)
+            .field("draw_calls", &self.draw_calls)
+            .finish()
+    }
+}
+
+impl Default for TestRenderer {
+    fn default() -> Self {
+        TestRenderer::new()
+    }
+}
+
+impl TestRenderer {
+    /// Creates a new test renderer
+    pub fn new() -> TestRenderer {
+        TestRenderer {
+            id: next_renderer_id(),
+            next_texture_id: 0,
+            clear_calls: Vec::new(),
+            draw_calls: Vec::new(),
+        }
+    }
+
+    /// [`Frame::clear`] calls of the last frame
+    pub fn clear_calls(&self) -> &[ClearCall] {
+        &self.clear_calls
+    }
+
+    /// [`Frame::render_texture_from_to`] calls of the last frame, in drawing order
+    pub fn draw_calls(&self) -> &[DrawCall] {
+        &self.draw_calls
+    }
+
+    /// Create a texture of the given size
+    pub fn create_texture(&mut self, size: Size<i32, BufferCoord>) -> TestTexture {
+        let id = self.next_texture_id;
+        self.next_texture_id += 1;
+        TestTexture { id, size }
+    }
+}
+
+impl Drop for TestRenderer {
+    fn drop(&mut self) {
+        RENDERER_IDS.lock().unwrap().remove(&self.id);
+    }
+}
+
+impl ImportMem for TestRenderer {
+    fn import_memory(
+        &mut self,
+        _data: &[u8],
+        size: Size<i32, BufferCoord>,
+        _flipped: bool,
+    ) -> Result<TestTexture, Infallible> {
+        Ok(self.create_texture(size))
+    }
+
+    fn update_memory(
+        &mut self,
+        _texture: &TestTexture,
+        _data: &[u8],
+        _region: Rectangle<i32, BufferCoord>,
+    ) -> Result<(), Infallible> {
+        Ok(())
+    }
+}
+
+impl Renderer for TestRenderer {
+    type Error = Infallible;
+    type TextureId = TestTexture;
+    type Frame<'frame> = TestFrame<'frame>;
+
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn downscale_filter(&mut self, _filter: TextureFilter) -> Result<(), Self::Error> {
+        Ok(())
+    }
+    fn upscale_filter(&mut self, _filter: TextureFilter) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn render(
+        &mut self,
+        _output_size: Size<i32, Physical>,
+        transform: Transform,
+    ) -> Result<TestFrame<'_>, Self::Error> {
+        self.clear_calls.clear();
+        self.draw_calls.clear();
+        Ok(TestFrame {
+            renderer: self,
+            transform,
+        })
+    }
+}
+
+/// Handle to the currently rendered frame during [`TestRenderer::render`](Renderer::render).
+#[derive(Debug)]
+pub struct TestFrame<'frame> {
+    renderer: &'frame mut TestRenderer,
+    transform: Transform,
+}
+
+impl<'frame> Frame for TestFrame<'frame> {
+    type Error = Infallible;
+    type TextureId = TestTexture;
+
+    fn id(&self) -> usize {
+        self.renderer.id
+    }
+
+    fn clear(&mut self, color: [f32; 4], at: &[Rectangle<i32, Physical>]) -> Result<(), Self::Error> {
+        self.renderer.clear_calls.push(ClearCall {
+            color,
+            damage: at.to_vec(),
+        });
+        Ok(())
+    }
+
+    fn render_texture_from_to(
+        &mut self,
+        texture: &TestTexture,
+        src: Rectangle<f64, BufferCoord>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        src_transform: Transform,
+        alpha: f32,
+        blend_mode: BlendMode,
+    ) -> Result<(), Self::Error> {
+        self.renderer.draw_calls.push(DrawCall {
+            texture: texture.id,
+            src,
+            dst,
+            damage: damage.to_vec(),
+            src_transform,
+            alpha,
+            blend_mode,
+        });
+        Ok(())
+    }
+
+    fn transformation(&self) -> Transform {
+        self.transform
+    }
+
+    fn finish(self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
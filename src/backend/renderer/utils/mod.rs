@@ -16,6 +16,7 @@ pub use self::wayland::*;
 /// It provides an easy way to obtain the distance
 /// between two instances of a [`CommitCounter`].
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[cfg_attr(feature = "bincode", derive(bincode::Encode, bincode::Decode))]
 pub struct CommitCounter(usize);
 
 impl CommitCounter {
@@ -1,12 +1,16 @@
 use crate::{
     backend::renderer::{buffer_dimensions, buffer_has_alpha, element::RenderElement, ImportAll, Renderer},
-    utils::{Buffer as BufferCoord, Coordinate, Logical, Physical, Point, Rectangle, Scale, Size, Transform},
+    utils::{
+        Buffer as BufferCoord, Coordinate, Logical, Physical, Point, Rectangle, Region, Scale, Size,
+        Transform,
+    },
     wayland::{
         compositor::{
             self, add_destruction_hook, is_sync_subsurface, with_surface_tree_downward,
             with_surface_tree_upward, BufferAssignment, Damage, RectangleKind, SubsurfaceCachedState,
             SurfaceAttributes, SurfaceData, TraversalAction,
         },
+        explicit_sync::{ExplicitBufferRelease, ExplicitSyncCachedState},
         viewporter,
     },
 };
@@ -14,6 +18,8 @@ use std::{
     any::TypeId,
     cell::RefCell,
     collections::{hash_map::Entry, HashMap},
+    os::unix::io::OwnedFd,
+    sync::Arc,
 };
 
 use wayland_server::protocol::{wl_buffer::WlBuffer, wl_surface::WlSurface};
@@ -43,6 +49,8 @@ pub struct RendererSurfaceState {
     pub(crate) textures: HashMap<(TypeId, usize), Box<dyn std::any::Any>>,
     pub(crate) surface_view: Option<SurfaceView>,
     pub(crate) opaque_regions: Vec<Rectangle<i32, Logical>>,
+    pub(crate) buffer_release: Option<ExplicitBufferRelease>,
+    pub(crate) release_fence: Option<Arc<OwnedFd>>,
 
     accumulated_buffer_delta: Point<i32, Logical>,
 }
@@ -74,6 +82,12 @@ impl RendererSurfaceState {
                         old_buffer.release();
                     }
                 }
+                self.send_explicit_release();
+                self.buffer_release = states
+                    .cached_state
+                    .current::<ExplicitSyncCachedState>()
+                    .release
+                    .take();
                 self.textures.clear();
 
                 let surface_size = self
@@ -128,35 +142,15 @@ impl RendererSurfaceState {
 
                             (kind, rect)
                         })
-                        .fold(
-                            std::mem::take(&mut self.opaque_regions),
-                            |mut new_regions, (kind, rect)| {
-                                match kind {
-                                    RectangleKind::Add => {
-                                        let added_regions = new_regions
-                                            .iter()
-                                            .filter(|region| region.overlaps(rect))
-                                            .fold(vec![rect], |new_regions, existing_region| {
-                                                new_regions
-                                                    .into_iter()
-                                                    .flat_map(|region| region.subtract_rect(*existing_region))
-                                                    .collect::<Vec<_>>()
-                                            });
-                                        new_regions.extend(added_regions);
-                                    }
-                                    RectangleKind::Subtract => {
-                                        new_regions = new_regions
-                                            .into_iter()
-                                            .flat_map(|r| r.subtract_rect(rect))
-                                            .collect::<Vec<_>>();
-                                    }
-                                }
-
-                                new_regions
-                            },
-                        );
-
-                    self.opaque_regions = opaque_regions;
+                        .fold(Region::new(), |mut new_regions, (kind, rect)| {
+                            match kind {
+                                RectangleKind::Add => new_regions.add(rect),
+                                RectangleKind::Subtract => new_regions.subtract(rect),
+                            }
+                            new_regions
+                        });
+
+                    self.opaque_regions = opaque_regions.into_rects();
                 }
             }
             Some(BufferAssignment::Removed) => {
@@ -165,6 +159,7 @@ impl RendererSurfaceState {
                 if let Some(buffer) = self.buffer.take() {
                     buffer.release();
                 };
+                self.send_explicit_release();
                 self.textures.clear();
                 self.damage.reset();
                 self.surface_view = None;
@@ -175,6 +170,29 @@ impl RendererSurfaceState {
         }
     }
 
+    fn send_explicit_release(&mut self) {
+        let fence = self.release_fence.take();
+        if let Some(release) = self.buffer_release.take() {
+            match fence {
+                Some(fence) => release.fenced_release(&*fence),
+                None => release.immediate_release(),
+            }
+        }
+    }
+
+    /// Set the fence the release of the current buffer has to wait for
+    ///
+    /// Clients using `zwp_linux_explicit_synchronization_v1` may request to be told when the
+    /// compositor is done with the buffer of a commit. This happens once the buffer is replaced
+    /// or removed, and the client is asked to wait for the last fence set through this method
+    /// before reusing the buffer. If no fence was set, the buffer is released immediately.
+    ///
+    /// The fence should be signaled once the last frame sampling the buffer is done, for example
+    /// the completion fence of that frame with the gles renderer, after it was committed to KMS.
+    pub fn set_release_fence(&mut self, fence: Arc<OwnedFd>) {
+        self.release_fence = Some(fence);
+    }
+
     /// Get the current commit position of this surface
     ///
     /// The position should be saved after calling [`damage_since`](RendererSurfaceState::damage_since) and
@@ -297,12 +315,12 @@ pub fn on_commit_buffer_handler(surface: &WlSurface) {
         );
         for surf in &new_surfaces {
             add_destruction_hook(surf, |data| {
-                if let Some(buffer) = data
-                    .data_map
-                    .get::<RendererSurfaceStateUserData>()
-                    .and_then(|s| s.borrow_mut().buffer.take())
-                {
-                    buffer.release();
+                if let Some(state) = data.data_map.get::<RendererSurfaceStateUserData>() {
+                    let mut state = state.borrow_mut();
+                    if let Some(buffer) = state.buffer.take() {
+                        buffer.release();
+                    }
+                    state.send_explicit_release();
                 }
             });
         }
@@ -502,31 +520,19 @@ where
         let element_geometry = element.geometry(scale);
 
         // Then test if the element is completely hidden behind opaque regions
-        let is_hidden = opaque_regions
-            .iter()
-            .fold([element_geometry].to_vec(), |geometry, opaque_region| {
-                geometry
-                    .into_iter()
-                    .flat_map(|g| g.subtract_rect(*opaque_region))
-                    .collect::<Vec<_>>()
-            })
-            .is_empty();
+        let mut visible = Region::from(element_geometry);
+        visible.subtract_rects(opaque_regions.iter().copied());
+        let is_hidden = visible.is_empty();
 
         if is_hidden {
             // No need to draw a completely hidden element
             continue;
         }
 
-        let damage = opaque_regions
-            .iter()
-            .fold(damage.to_vec(), |damage, opaque_region| {
-                damage
-                    .into_iter()
-                    .flat_map(|damage| damage.subtract_rect(*opaque_region))
-                    .collect::<Vec<_>>()
-            });
+        let mut visible_damage = damage.iter().copied().collect::<Region<_>>();
+        visible_damage.subtract_rects(opaque_regions.iter().copied());
 
-        render_damage.extend(damage);
+        render_damage.extend(visible_damage.into_rects());
 
         opaque_regions.extend(element.opaque_regions(scale).into_iter().map(|mut region| {
             region.loc += element_geometry.loc;
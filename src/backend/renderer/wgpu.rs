@@ -0,0 +1,968 @@
+//! Implementation of the rendering traits using [`wgpu`]
+//!
+//! The [`WgpuRenderer`] runs on any of the native graphics apis supported by wgpu
+//! (Vulkan, Metal, Dx12), which allows running compositor integration tests on
+//! machines without EGL, e.g. macOS or Windows CI runners.
+//!
+//! Rendering happens into an offscreen framebuffer, that is (re-)allocated by [`Renderer::render`]
+//! to match the requested output size. It can be accessed through [`WgpuRenderer::framebuffer`]
+//! or downloaded with [`WgpuRenderer::read_framebuffer`] after the frame was finished.
+//! Pixel data is stored as premultiplied RGBA8, which is also the format expected by
+//! [`ImportMem`].
+
+use std::{borrow::Cow, fmt, num::NonZeroU32, sync::Arc};
+
+use cgmath::{Matrix3, SquareMatrix};
+use wgpu::util::DeviceExt;
+
+use super::{BlendMode, Frame, ImportMem, Renderer, Texture, TextureFilter};
+use crate::utils::{Buffer as BufferCoord, Physical, Rectangle, Size, Transform};
+
+crate::utils::ids::id_gen!(next_renderer_id, RENDERER_ID, RENDERER_IDS);
+
+const SHADER: &str = r#"
+struct Uniforms {
+    matrix: mat4x4<f32>,
+    tex_matrix: mat4x4<f32>,
+    color: vec4<f32>,
+    alpha: f32,
+};
+
+@group(0) @binding(0) var<uniform> uniforms: Uniforms;
+@group(0) @binding(1) var tex: texture_2d<f32>;
+@group(0) @binding(2) var tex_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+};
+
+@vertex
+fn vs_main(@location(0) vert: vec2<f32>) -> VertexOutput {
+    var out: VertexOutput;
+    let position = vec4<f32>(vert, 0.0, 1.0);
+    out.position = vec4<f32>((uniforms.matrix * position).xy, 0.0, 1.0);
+    out.tex_coords = (uniforms.tex_matrix * position).xy;
+    return out;
+}
+
+@fragment
+fn fs_texture(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(tex, tex_sampler, in.tex_coords) * uniforms.alpha;
+}
+
+@fragment
+fn fs_texture_straight(in: VertexOutput) -> @location(0) vec4<f32> {
+    let color = textureSample(tex, tex_sampler, in.tex_coords);
+    return vec4<f32>(color.rgb * color.a, color.a) * uniforms.alpha;
+}
+
+@fragment
+fn fs_solid(in: VertexOutput) -> @location(0) vec4<f32> {
+    return uniforms.color;
+}
+"#;
+
+/// Size of the `Uniforms` struct of the shader, including trailing padding
+const UNIFORMS_SIZE: usize = 160;
+
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8Unorm;
+
+/// Error returned during rendering using the [`WgpuRenderer`]
+#[derive(thiserror::Error, Debug)]
+pub enum WgpuRendererError {
+    /// No adapter supporting any of the requested backends was found
+    #[error("No suitable adapter found")]
+    NoAdapter,
+    /// The device could not be created
+    #[error("Failed to create the device: {0}")]
+    RequestDevice(#[from] wgpu::RequestDeviceError),
+    /// The requested size is empty or too large
+    #[error("Invalid size: {0:?}")]
+    InvalidSize(Size<i32, BufferCoord>),
+    /// The provided buffer's size did not match the requested one.
+    #[error("Error reading buffer, size is too small for the given dimensions")]
+    UnexpectedSize,
+    /// The updated region is not inside the bounds of the texture
+    #[error("Region {0:?} is out of bounds of the texture")]
+    InvalidRegion(Rectangle<i32, BufferCoord>),
+    /// Nothing was rendered yet
+    #[error("No framebuffer was allocated yet")]
+    NoFramebuffer,
+    /// Mapping the framebuffer contents for reading failed
+    #[error("Failed to map the framebuffer: {0}")]
+    BufferMap(#[from] wgpu::BufferAsyncError),
+}
+
+#[derive(Debug)]
+struct WgpuTextureInner {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    size: Size<i32, BufferCoord>,
+    flipped: bool,
+}
+
+/// A handle to a texture of the [`WgpuRenderer`]
+///
+/// Textures can only be used with the renderer, that created them.
+#[derive(Debug, Clone)]
+pub struct WgpuTexture(Arc<WgpuTextureInner>);
+
+impl WgpuTexture {
+    /// Underlying wgpu texture
+    pub fn texture(&self) -> &wgpu::Texture {
+        &self.0.texture
+    }
+}
+
+impl Texture for WgpuTexture {
+    fn width(&self) -> u32 {
+        self.0.size.w as u32
+    }
+    fn height(&self) -> u32 {
+        self.0.size.h as u32
+    }
+    fn size(&self) -> Size<i32, BufferCoord> {
+        self.0.size
+    }
+}
+
+/// Texture pipelines for every [`BlendMode`]
+struct TexturePipelines {
+    premultiplied: wgpu::RenderPipeline,
+    straight: wgpu::RenderPipeline,
+    additive: wgpu::RenderPipeline,
+    replace: wgpu::RenderPipeline,
+}
+
+impl TexturePipelines {
+    fn get(&self, blend_mode: BlendMode) -> &wgpu::RenderPipeline {
+        match blend_mode {
+            BlendMode::Premultiplied => &self.premultiplied,
+            BlendMode::Straight => &self.straight,
+            BlendMode::Additive => &self.additive,
+            BlendMode::Replace => &self.replace,
+        }
+    }
+}
+
+struct Framebuffer {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    size: Size<i32, Physical>,
+}
+
+/// A renderer using [`wgpu`]
+pub struct WgpuRenderer {
+    id: usize,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    bind_group_layout: wgpu::BindGroupLayout,
+    texture_pipelines: TexturePipelines,
+    solid_pipeline: wgpu::RenderPipeline,
+    linear_sampler: wgpu::Sampler,
+    nearest_sampler: wgpu::Sampler,
+    // bound instead of a texture when drawing solid colors
+    blank: wgpu::TextureView,
+    framebuffer: Option<Framebuffer>,
+    min_filter: TextureFilter,
+    max_filter: TextureFilter,
+    logger: ::slog::Logger,
+}
+
+impl fmt::Debug for WgpuRenderer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WgpuRenderer")
+            .field("id", &self.id)
+            .field("device", &self.device)
+            .field(
+                "framebuffer_size",
+                &self.framebuffer.as_ref().map(|framebuffer| framebuffer.size),
+            )
+            .field("min_filter", &self.min_filter)
+            .field("max_filter", &self.max_filter)
+            .field("logger", &self.logger)
+            .finish()
+    }
+}
+
+impl WgpuRenderer {
+    /// Creates a new wgpu renderer using the first adapter supporting any of the given `backends`
+    ///
+    /// Use e.g. `wgpu::Backends::PRIMARY` to select between Vulkan, Metal and Dx12,
+    /// depending on the platform.
+    ///
+    /// Texture filtering starts with Linear-downscaling and Linear-upscaling.
+    pub fn new<L>(backends: wgpu::Backends, logger: L) -> Result<WgpuRenderer, WgpuRendererError>
+    where
+        L: Into<Option<::slog::Logger>>,
+    {
+        let logger = crate::slog_or_fallback(logger);
+
+        let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+            backends,
+            ..Default::default()
+        });
+        let adapter = pollster::block_on(instance.request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::default(),
+            force_fallback_adapter: false,
+            compatible_surface: None,
+        }))
+        .ok_or(WgpuRendererError::NoAdapter)?;
+        let info = adapter.get_info();
+        slog::info!(logger, "Using adapter {} ({:?})", info.name, info.backend; "smithay_module" => "renderer_wgpu");
+
+        let (device, queue) = pollster::block_on(adapter.request_device(
+            &wgpu::DeviceDescriptor {
+                label: Some("smithay"),
+                features: wgpu::Features::empty(),
+                limits: wgpu::Limits::downlevel_defaults().using_resolution(adapter.limits()),
+            },
+            None,
+        ))?;
+
+        Ok(WgpuRenderer::from_device(device, queue, logger))
+    }
+
+    /// Creates a new wgpu renderer from an existing device
+    pub fn from_device<L>(device: wgpu::Device, queue: wgpu::Queue, logger: L) -> WgpuRenderer
+    where
+        L: Into<Option<::slog::Logger>>,
+    {
+        let logger = crate::slog_or_fallback(logger).new(slog::o!("smithay_module" => "renderer_wgpu"));
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("smithay shader"),
+            source: wgpu::ShaderSource::Wgsl(Cow::Borrowed(SHADER)),
+        });
+        let bind_group_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("smithay bind group layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX_FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("smithay pipeline layout"),
+            bind_group_layouts: &[&bind_group_layout],
+            push_constant_ranges: &[],
+        });
+
+        let texture_pipeline =
+            |blend| create_pipeline(&device, &pipeline_layout, &shader, "fs_texture", Some(blend));
+        let additive = wgpu::BlendComponent {
+            src_factor: wgpu::BlendFactor::One,
+            dst_factor: wgpu::BlendFactor::One,
+            operation: wgpu::BlendOperation::Add,
+        };
+        let texture_pipelines = TexturePipelines {
+            premultiplied: texture_pipeline(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+            // the shader premultiplies straight alpha, so blending is the same as for premultiplied textures
+            straight: create_pipeline(
+                &device,
+                &pipeline_layout,
+                &shader,
+                "fs_texture_straight",
+                Some(wgpu::BlendState::PREMULTIPLIED_ALPHA_BLENDING),
+            ),
+            additive: texture_pipeline(wgpu::BlendState {
+                color: additive,
+                alpha: additive,
+            }),
+            replace: texture_pipeline(wgpu::BlendState::REPLACE),
+        };
+        let solid_pipeline = create_pipeline(&device, &pipeline_layout, &shader, "fs_solid", None);
+
+        let linear_sampler = create_sampler(&device, wgpu::FilterMode::Linear);
+        let nearest_sampler = create_sampler(&device, wgpu::FilterMode::Nearest);
+        let blank = device
+            .create_texture(&texture_descriptor(
+                (1, 1).into(),
+                wgpu::TextureUsages::TEXTURE_BINDING,
+            ))
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        WgpuRenderer {
+            id: next_renderer_id(),
+            device,
+            queue,
+            bind_group_layout,
+            texture_pipelines,
+            solid_pipeline,
+            linear_sampler,
+            nearest_sampler,
+            blank,
+            framebuffer: None,
+            min_filter: TextureFilter::Linear,
+            max_filter: TextureFilter::Linear,
+            logger,
+        }
+    }
+
+    /// Device used by this renderer
+    pub fn device(&self) -> &wgpu::Device {
+        &self.device
+    }
+
+    /// Queue used by this renderer
+    pub fn queue(&self) -> &wgpu::Queue {
+        &self.queue
+    }
+
+    /// Framebuffer texture rendered into by [`Renderer::render`]
+    ///
+    /// Returns `None` if nothing was rendered yet.
+    pub fn framebuffer(&self) -> Option<&wgpu::Texture> {
+        self.framebuffer.as_ref().map(|framebuffer| &framebuffer.texture)
+    }
+
+    /// Size of the framebuffer, which matches the `output_size` of the last [`Renderer::render`] call
+    pub fn framebuffer_size(&self) -> Option<Size<i32, Physical>> {
+        self.framebuffer.as_ref().map(|framebuffer| framebuffer.size)
+    }
+
+    /// Download the contents of the framebuffer as premultiplied RGBA8
+    ///
+    /// This blocks until all submitted frames are finished.
+    pub fn read_framebuffer(&self) -> Result<Vec<u8>, WgpuRendererError> {
+        let framebuffer = self
+            .framebuffer
+            .as_ref()
+            .ok_or(WgpuRendererError::NoFramebuffer)?;
+        let row_len = framebuffer.size.w as u32 * 4;
+        // copies into buffers need an aligned stride
+        let align = wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let stride = (row_len + align - 1) / align * align;
+
+        let buffer = self.device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("smithay framebuffer download"),
+            size: stride as u64 * framebuffer.size.h as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("smithay framebuffer download"),
+            });
+        encoder.copy_texture_to_buffer(
+            framebuffer.texture.as_image_copy(),
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: NonZeroU32::new(stride),
+                    rows_per_image: None,
+                },
+            },
+            extent(framebuffer.size.w, framebuffer.size.h),
+        );
+        self.queue.submit(Some(encoder.finish()));
+
+        let slice = buffer.slice(..);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        slice.map_async(wgpu::MapMode::Read, move |result| {
+            let _ = sender.send(result);
+        });
+        self.device.poll(wgpu::Maintain::Wait);
+        receiver
+            .recv()
+            .expect("map_async callback was not called after waiting for the device")?;
+
+        let data = slice
+            .get_mapped_range()
+            .chunks(stride as usize)
+            .flat_map(|row| &row[..row_len as usize])
+            .copied()
+            .collect();
+        buffer.unmap();
+        Ok(data)
+    }
+
+    fn write_texture(&self, texture: &wgpu::Texture, data: &[u8], region: Rectangle<i32, BufferCoord>) {
+        self.queue.write_texture(
+            wgpu::ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d {
+                    x: region.loc.x as u32,
+                    y: region.loc.y as u32,
+                    z: 0,
+                },
+                aspect: wgpu::TextureAspect::All,
+            },
+            &data[..(region.size.w * region.size.h * 4) as usize],
+            wgpu::ImageDataLayout {
+                offset: 0,
+                bytes_per_row: NonZeroU32::new(region.size.w as u32 * 4),
+                rows_per_image: None,
+            },
+            extent(region.size.w, region.size.h),
+        );
+    }
+}
+
+impl Drop for WgpuRenderer {
+    fn drop(&mut self) {
+        RENDERER_IDS.lock().unwrap().remove(&self.id);
+    }
+}
+
+fn create_pipeline(
+    device: &wgpu::Device,
+    layout: &wgpu::PipelineLayout,
+    shader: &wgpu::ShaderModule,
+    fragment_entry_point: &str,
+    blend: Option<wgpu::BlendState>,
+) -> wgpu::RenderPipeline {
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(fragment_entry_point),
+        layout: Some(layout),
+        vertex: wgpu::VertexState {
+            module: shader,
+            entry_point: "vs_main",
+            buffers: &[wgpu::VertexBufferLayout {
+                array_stride: 2 * std::mem::size_of::<f32>() as wgpu::BufferAddress,
+                step_mode: wgpu::VertexStepMode::Vertex,
+                attributes: &wgpu::vertex_attr_array![0 => Float32x2],
+            }],
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: shader,
+            entry_point: fragment_entry_point,
+            targets: &[Some(wgpu::ColorTargetState {
+                format: FORMAT,
+                blend,
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+        }),
+        primitive: wgpu::PrimitiveState::default(),
+        depth_stencil: None,
+        multisample: wgpu::MultisampleState::default(),
+        multiview: None,
+    })
+}
+
+fn create_sampler(device: &wgpu::Device, filter: wgpu::FilterMode) -> wgpu::Sampler {
+    device.create_sampler(&wgpu::SamplerDescriptor {
+        label: Some("smithay sampler"),
+        mag_filter: filter,
+        min_filter: filter,
+        ..Default::default()
+    })
+}
+
+fn texture_descriptor(
+    size: Size<i32, BufferCoord>,
+    usage: wgpu::TextureUsages,
+) -> wgpu::TextureDescriptor<'static> {
+    wgpu::TextureDescriptor {
+        label: None,
+        size: extent(size.w, size.h),
+        mip_level_count: 1,
+        sample_count: 1,
+        dimension: wgpu::TextureDimension::D2,
+        format: FORMAT,
+        usage,
+        view_formats: &[],
+    }
+}
+
+fn extent(width: i32, height: i32) -> wgpu::Extent3d {
+    wgpu::Extent3d {
+        width: width as u32,
+        height: height as u32,
+        depth_or_array_layers: 1,
+    }
+}
+
+impl ImportMem for WgpuRenderer {
+    fn import_memory(
+        &mut self,
+        data: &[u8],
+        size: Size<i32, BufferCoord>,
+        flipped: bool,
+    ) -> Result<WgpuTexture, WgpuRendererError> {
+        let max_size = self.device.limits().max_texture_dimension_2d as i32;
+        if size.w <= 0 || size.h <= 0 || size.w > max_size || size.h > max_size {
+            return Err(WgpuRendererError::InvalidSize(size));
+        }
+        if data.len() < (size.w * size.h * 4) as usize {
+            return Err(WgpuRendererError::UnexpectedSize);
+        }
+        let texture = self.device.create_texture(&texture_descriptor(
+            size,
+            wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+        ));
+        self.write_texture(&texture, data, Rectangle::from_loc_and_size((0, 0), size));
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        Ok(WgpuTexture(Arc::new(WgpuTextureInner {
+            texture,
+            view,
+            size,
+            flipped,
+        })))
+    }
+
+    fn update_memory(
+        &mut self,
+        texture: &WgpuTexture,
+        data: &[u8],
+        region: Rectangle<i32, BufferCoord>,
+    ) -> Result<(), WgpuRendererError> {
+        let bounds = Rectangle::from_loc_and_size((0, 0), texture.0.size);
+        if region.loc.x < 0 || region.loc.y < 0 || !bounds.contains_rect(region) {
+            return Err(WgpuRendererError::InvalidRegion(region));
+        }
+        if data.len() < (region.size.w * region.size.h * 4) as usize {
+            return Err(WgpuRendererError::UnexpectedSize);
+        }
+        self.write_texture(&texture.0.texture, data, region);
+        Ok(())
+    }
+}
+
+impl Renderer for WgpuRenderer {
+    type Error = WgpuRendererError;
+    type TextureId = WgpuTexture;
+    type Frame<'frame> = WgpuFrame<'frame>;
+
+    fn id(&self) -> usize {
+        self.id
+    }
+
+    fn downscale_filter(&mut self, filter: TextureFilter) -> Result<(), Self::Error> {
+        self.min_filter = filter;
+        Ok(())
+    }
+    fn upscale_filter(&mut self, filter: TextureFilter) -> Result<(), Self::Error> {
+        self.max_filter = filter;
+        Ok(())
+    }
+
+    fn render(
+        &mut self,
+        output_size: Size<i32, Physical>,
+        transform: Transform,
+    ) -> Result<WgpuFrame<'_>, Self::Error> {
+        let max_size = self.device.limits().max_texture_dimension_2d as i32;
+        if output_size.w <= 0 || output_size.h <= 0 || output_size.w > max_size || output_size.h > max_size {
+            return Err(WgpuRendererError::InvalidSize(
+                (output_size.w, output_size.h).into(),
+            ));
+        }
+
+        let matches = self
+            .framebuffer
+            .as_ref()
+            .map(|framebuffer| framebuffer.size == output_size)
+            .unwrap_or(false);
+        if !matches {
+            slog::debug!(self.logger, "Allocating framebuffer of size {:?}", output_size);
+            let texture = self.device.create_texture(&texture_descriptor(
+                (output_size.w, output_size.h).into(),
+                wgpu::TextureUsages::RENDER_ATTACHMENT
+                    | wgpu::TextureUsages::TEXTURE_BINDING
+                    | wgpu::TextureUsages::COPY_SRC,
+            ));
+            let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+            self.framebuffer = Some(Framebuffer {
+                texture,
+                view,
+                size: output_size,
+            });
+        }
+
+        let size = transform.transform_size(output_size);
+        // maps framebuffer pixels to normalized device coordinates
+        let ortho = affine(
+            2.0 / output_size.w as f32,
+            0.0,
+            0.0,
+            -2.0 / output_size.h as f32,
+            -1.0,
+            1.0,
+        );
+        let encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("smithay frame"),
+            });
+        Ok(WgpuFrame {
+            renderer: self,
+            encoder: Some(encoder),
+            transform,
+            projection: ortho * output_transform(transform, size),
+        })
+    }
+}
+
+/// Affine transformation from the coefficients of the mapping
+/// `x' = sx * x + kx * y + tx` and `y' = ky * x + sy * y + ty`
+fn affine(sx: f32, ky: f32, kx: f32, sy: f32, tx: f32, ty: f32) -> Matrix3<f32> {
+    Matrix3::new(sx, ky, 0.0, kx, sy, 0.0, tx, ty, 1.0)
+}
+
+/// Transformation mapping a point of the transformed output space into the framebuffer
+///
+/// Matches [`Transform::transform_point_in`] for an area of the given size.
+fn output_transform(transform: Transform, size: Size<i32, Physical>) -> Matrix3<f32> {
+    let (w, h) = (size.w as f32, size.h as f32);
+    match transform {
+        Transform::Normal => Matrix3::identity(),
+        Transform::_90 => affine(0.0, 1.0, -1.0, 0.0, h, 0.0),
+        Transform::_180 => affine(-1.0, 0.0, 0.0, -1.0, w, h),
+        Transform::_270 => affine(0.0, -1.0, 1.0, 0.0, 0.0, w),
+        Transform::Flipped => affine(-1.0, 0.0, 0.0, 1.0, w, 0.0),
+        Transform::Flipped90 => affine(0.0, 1.0, 1.0, 0.0, 0.0, 0.0),
+        Transform::Flipped180 => affine(1.0, 0.0, 0.0, -1.0, 0.0, h),
+        Transform::Flipped270 => affine(0.0, -1.0, -1.0, 0.0, h, w),
+    }
+}
+
+/// Embed a 2d affine transformation into the `mat4x4` used by the shader
+fn uniform_matrix(matrix: Matrix3<f32>) -> [f32; 16] {
+    [
+        matrix.x.x, matrix.x.y, 0.0, matrix.x.z, //
+        matrix.y.x, matrix.y.y, 0.0, matrix.y.z, //
+        0.0, 0.0, 1.0, 0.0, //
+        matrix.z.x, matrix.z.y, 0.0, matrix.z.z,
+    ]
+}
+
+/// Handle to the currently rendered frame during [`WgpuRenderer::render`](Renderer::render).
+///
+/// Draw calls are recorded and submitted to the queue once the frame is finished or dropped.
+pub struct WgpuFrame<'frame> {
+    renderer: &'frame mut WgpuRenderer,
+    encoder: Option<wgpu::CommandEncoder>,
+    transform: Transform,
+    projection: Matrix3<f32>,
+}
+
+impl<'frame> fmt::Debug for WgpuFrame<'frame> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WgpuFrame")
+            .field("renderer", &self.renderer)
+            .field("transform", &self.transform)
+            .field("projection", &self.projection)
+            .finish()
+    }
+}
+
+impl<'frame> WgpuFrame<'frame> {
+    /// Record a draw of the given rectangles, which are in the transformed output space
+    ///
+    /// Without a texture the rectangles are filled with `color` instead.
+    fn draw(
+        &mut self,
+        texture: Option<(&wgpu::TextureView, TextureFilter, BlendMode)>,
+        tex_matrix: Matrix3<f32>,
+        color: [f32; 4],
+        alpha: f32,
+        rects: impl Iterator<Item = Rectangle<i32, Physical>>,
+    ) {
+        let vertices = rects
+            .flat_map(|rect| {
+                let (x1, y1) = (rect.loc.x as f32, rect.loc.y as f32);
+                let (x2, y2) = (x1 + rect.size.w as f32, y1 + rect.size.h as f32);
+                [x1, y1, x2, y1, x1, y2, x1, y2, x2, y1, x2, y2]
+            })
+            .collect::<Vec<f32>>();
+        if vertices.is_empty() {
+            return;
+        }
+
+        let uniforms = uniform_matrix(self.projection)
+            .into_iter()
+            .chain(uniform_matrix(tex_matrix))
+            .chain(color)
+            .chain([alpha])
+            .flat_map(f32::to_ne_bytes)
+            .chain(std::iter::repeat(0))
+            .take(UNIFORMS_SIZE)
+            .collect::<Vec<u8>>();
+
+        let renderer = &*self.renderer;
+        let (pipeline, view, sampler) = match texture {
+            Some((view, filter, blend_mode)) => (
+                renderer.texture_pipelines.get(blend_mode),
+                view,
+                match filter {
+                    TextureFilter::Linear => &renderer.linear_sampler,
+                    TextureFilter::Nearest => &renderer.nearest_sampler,
+                },
+            ),
+            None => (
+                &renderer.solid_pipeline,
+                &renderer.blank,
+                &renderer.nearest_sampler,
+            ),
+        };
+
+        let device = &renderer.device;
+        let uniform_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("smithay uniforms"),
+            contents: &uniforms,
+            usage: wgpu::BufferUsages::UNIFORM,
+        });
+        let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("smithay vertices"),
+            contents: &vertices.iter().flat_map(|v| v.to_ne_bytes()).collect::<Vec<u8>>(),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: None,
+            layout: &renderer.bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+        });
+
+        let framebuffer = renderer.framebuffer.as_ref().unwrap();
+        let encoder = self.encoder.as_mut().unwrap();
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: None,
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &framebuffer.view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: true,
+                },
+            })],
+            depth_stencil_attachment: None,
+        });
+        pass.set_pipeline(pipeline);
+        pass.set_bind_group(0, &bind_group, &[]);
+        pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+        pass.draw(0..(vertices.len() / 2) as u32, 0..1);
+    }
+
+    fn submit(&mut self) {
+        if let Some(encoder) = self.encoder.take() {
+            self.renderer.queue.submit(Some(encoder.finish()));
+        }
+    }
+}
+
+impl<'frame> Frame for WgpuFrame<'frame> {
+    type Error = WgpuRendererError;
+    type TextureId = WgpuTexture;
+
+    fn id(&self) -> usize {
+        self.renderer.id
+    }
+
+    fn clear(&mut self, color: [f32; 4], at: &[Rectangle<i32, Physical>]) -> Result<(), Self::Error> {
+        self.draw(None, Matrix3::identity(), color, 1.0, at.iter().copied());
+        Ok(())
+    }
+
+    fn render_texture_from_to(
+        &mut self,
+        texture: &WgpuTexture,
+        src: Rectangle<f64, BufferCoord>,
+        dst: Rectangle<i32, Physical>,
+        damage: &[Rectangle<i32, Physical>],
+        src_transform: Transform,
+        alpha: f32,
+        blend_mode: BlendMode,
+    ) -> Result<(), Self::Error> {
+        if src.size.w <= 0. || src.size.h <= 0. || dst.size.w <= 0 || dst.size.h <= 0 {
+            slog::warn!(self.renderer.logger, "Texture/Src is zero sized");
+            return Ok(());
+        }
+
+        // `src_transform` describes the orientation of the buffer, so its inverse has to be applied
+        let buffer_transform = if src_transform.flipped() {
+            src_transform
+        } else {
+            src_transform.invert()
+        };
+        let src_size = Size::<f64, BufferCoord>::from((src.size.w, src.size.h));
+        let transformed_src_size = buffer_transform.transform_size(src_size);
+
+        // maps the texture into the output space
+        let texture_to_output = Matrix3::from_translation((dst.loc.x as f32, dst.loc.y as f32).into())
+            * Matrix3::from_nonuniform_scale(
+                (dst.size.w as f64 / transformed_src_size.w) as f32,
+                (dst.size.h as f64 / transformed_src_size.h) as f32,
+            )
+            * output_transform(
+                buffer_transform,
+                (src_size.w.round() as i32, src_size.h.round() as i32).into(),
+            )
+            * Matrix3::from_translation((-src.loc.x as f32, -src.loc.y as f32).into());
+        let mut tex_matrix =
+            Matrix3::from_nonuniform_scale(1.0 / texture.0.size.w as f32, 1.0 / texture.0.size.h as f32)
+                * texture_to_output
+                    .invert()
+                    .expect("texture transformation is invertible");
+        if texture.0.flipped {
+            tex_matrix = affine(1.0, 0.0, 0.0, -1.0, 0.0, 1.0) * tex_matrix;
+        }
+
+        let upscale =
+            dst.size.w as f64 > transformed_src_size.w || dst.size.h as f64 > transformed_src_size.h;
+        let filter = if upscale {
+            self.renderer.max_filter
+        } else {
+            self.renderer.min_filter
+        };
+
+        let dst_bounds = Rectangle::from_loc_and_size((0, 0), dst.size);
+        self.draw(
+            Some((&texture.0.view, filter, blend_mode)),
+            tex_matrix,
+            [0.0; 4],
+            alpha.clamp(0.0, 1.0),
+            damage
+                .iter()
+                .filter_map(|rect| rect.intersection(dst_bounds))
+                .map(|mut rect| {
+                    rect.loc += dst.loc;
+                    rect
+                }),
+        );
+        Ok(())
+    }
+
+    fn transformation(&self) -> Transform {
+        self.transform
+    }
+
+    fn finish(mut self) -> Result<(), Self::Error> {
+        self.submit();
+        Ok(())
+    }
+}
+
+impl<'frame> Drop for WgpuFrame<'frame> {
+    fn drop(&mut self) {
+        self.submit();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clear_and_render_texture() {
+        let mut renderer = match WgpuRenderer::new(wgpu::Backends::all(), None) {
+            Ok(renderer) => renderer,
+            // nothing to test on machines without any gpu
+            Err(WgpuRendererError::NoAdapter) => return,
+            Err(err) => panic!("{}", err),
+        };
+        let data = [0u8, 255, 0, 255].repeat(4);
+        let texture = renderer.import_memory(&data, (2, 2).into(), false).unwrap();
+
+        let mut frame = renderer.render((4, 4).into(), Transform::Normal).unwrap();
+        frame
+            .clear(
+                [1.0, 0.0, 0.0, 1.0],
+                &[Rectangle::from_loc_and_size((0, 0), (4, 4))],
+            )
+            .unwrap();
+        frame
+            .render_texture_from_to(
+                &texture,
+                Rectangle::from_loc_and_size((0., 0.), (2., 2.)),
+                Rectangle::from_loc_and_size((2, 2), (2, 2)),
+                &[Rectangle::from_loc_and_size((0, 0), (2, 2))],
+                Transform::Normal,
+                1.0,
+                BlendMode::Premultiplied,
+            )
+            .unwrap();
+        frame.finish().unwrap();
+
+        let pixels = renderer.read_framebuffer().unwrap();
+        let pixel = |x: usize, y: usize| &pixels[(y * 4 + x) * 4..(y * 4 + x) * 4 + 4];
+        assert_eq!(pixel(0, 0), [255, 0, 0, 255]);
+        assert_eq!(pixel(3, 3), [0, 255, 0, 255]);
+    }
+
+    #[test]
+    fn render_straight_alpha_texture() {
+        let mut renderer = match WgpuRenderer::new(wgpu::Backends::all(), None) {
+            Ok(renderer) => renderer,
+            // nothing to test on machines without any gpu
+            Err(WgpuRendererError::NoAdapter) => return,
+            Err(err) => panic!("{}", err),
+        };
+        // red at 50% alpha, not premultiplied
+        let data = [255u8, 0, 0, 128];
+        let texture = renderer.import_memory(&data, (1, 1).into(), false).unwrap();
+
+        let mut frame = renderer.render((1, 1).into(), Transform::Normal).unwrap();
+        frame
+            .clear(
+                [0.0, 0.0, 0.0, 0.0],
+                &[Rectangle::from_loc_and_size((0, 0), (1, 1))],
+            )
+            .unwrap();
+        frame
+            .render_texture_from_to(
+                &texture,
+                Rectangle::from_loc_and_size((0., 0.), (1., 1.)),
+                Rectangle::from_loc_and_size((0, 0), (1, 1)),
+                &[Rectangle::from_loc_and_size((0, 0), (1, 1))],
+                Transform::Normal,
+                0.5,
+                BlendMode::Straight,
+            )
+            .unwrap();
+        frame.finish().unwrap();
+
+        // the color is only scaled by the texture alpha once, followed by the frame alpha
+        let pixels = renderer.read_framebuffer().unwrap();
+        let expected = [64u8, 0, 0, 64];
+        assert!(
+            pixels[..4]
+                .iter()
+                .zip(expected)
+                .all(|(value, expected)| (*value as i32 - expected as i32).abs() <= 1),
+            "{:?} != {:?}",
+            &pixels[..4],
+            expected
+        );
+    }
+}
@@ -32,6 +32,7 @@ use crate::{
             Bind,
         },
     },
+    output::{Mode, Output},
     utils::{Logical, Physical, Rectangle, Size},
 };
 use std::{cell::RefCell, rc::Rc, sync::Arc, time::Instant};
@@ -114,6 +115,7 @@ pub struct WinitEventLoop {
     initialized: bool,
     size: Rc<RefCell<WindowSize>>,
     resize_notification: Rc<Cell<Option<Size<i32, Physical>>>>,
+    output: Option<Output>,
     /// Whether winit is using Wayland or X11 as it's backend.
     is_x11: bool,
 }
@@ -257,6 +259,7 @@ where
             initialized: false,
             logger: log.new(o!("smithay_winit_component" => "event_loop")),
             size,
+            output: None,
             is_x11,
         },
     ))
@@ -367,6 +370,16 @@ where
     }
 }
 
+fn update_output_mode(output: &Option<Output>, size: Size<i32, Physical>) {
+    if let Some(output) = output {
+        let mode = Mode {
+            size,
+            refresh: 60_000,
+        };
+        output.change_current_state(Some(mode), None, None, None);
+    }
+}
+
 /// Errors that may happen when driving a [`WinitEventLoop`]
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, thiserror::Error)]
 pub enum WinitError {
@@ -378,6 +391,14 @@ pub enum WinitError {
 }
 
 impl WinitEventLoop {
+    /// Set the [`Output`] representing the window
+    ///
+    /// The current mode of the output is updated to the new window size, whenever
+    /// the window is resized, before [`WinitEvent::Resized`] is emitted.
+    pub fn set_output(&mut self, output: Option<Output>) {
+        self.output = output;
+    }
+
     /// Processes new events of the underlying event loop and calls the provided callback.
     ///
     /// You need to periodically call this function to keep the underlying event loop and
@@ -410,6 +431,7 @@ impl WinitEventLoop {
             let resize_notification = &self.resize_notification;
             let logger = &self.logger;
             let window_size = &self.size;
+            let output = &self.output;
             let is_x11 = self.is_x11;
 
             if !self.initialized {
@@ -440,6 +462,7 @@ impl WinitEventLoop {
                                 wsize.scale_factor = scale_factor;
 
                                 resize_notification.set(Some(wsize.physical_size));
+                                update_output_mode(output, wsize.physical_size);
 
                                 callback(WinitEvent::Resized {
                                     size: wsize.physical_size,
@@ -458,10 +481,12 @@ impl WinitEventLoop {
                                 wsize.scale_factor = scale_factor;
 
                                 let (pw, ph): (u32, u32) = (*new_psize).into();
-                                resize_notification.set(Some((pw as i32, ph as i32).into()));
+                                let size = (pw as i32, ph as i32).into();
+                                resize_notification.set(Some(size));
+                                update_output_mode(output, size);
 
                                 callback(WinitEvent::Resized {
-                                    size: (pw as i32, ph as i32).into(),
+                                    size,
                                     scale_factor: wsize.scale_factor,
                                 });
                             }
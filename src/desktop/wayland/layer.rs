@@ -3,7 +3,10 @@ use crate::{
     desktop::{utils::*, PopupManager},
     input::{
         keyboard::{KeyboardTarget, KeysymHandle, ModifiersState},
-        pointer::{AxisFrame, ButtonEvent, MotionEvent, PointerTarget, RelativeMotionEvent},
+        pointer::{
+            AxisFrame, ButtonEvent, GesturePinchEvent, GestureSwipeEvent, MotionEvent, PointerTarget,
+            RelativeMotionEvent,
+        },
         Seat, SeatHandler,
     },
     output::{Output, WeakOutput},
@@ -656,7 +659,13 @@ impl<D: SeatHandler + 'static> PointerTarget<D> for LayerSurface {
             new_event.location -= loc.to_f64();
             if let Some(old_surface) = self.0.focused_surface.lock().unwrap().replace(surface.clone()) {
                 if old_surface != surface {
-                    PointerTarget::<D>::leave(&old_surface, seat, data, event.serial, event.time);
+                    PointerTarget::<D>::leave(
+                        &old_surface,
+                        seat,
+                        data,
+                        event.serial,
+                        event.time.as_millis_u32(),
+                    );
                     PointerTarget::<D>::enter(&surface, seat, data, &new_event);
                 } else {
                     PointerTarget::<D>::motion(&surface, seat, data, &new_event)
@@ -684,6 +693,16 @@ impl<D: SeatHandler + 'static> PointerTarget<D> for LayerSurface {
             PointerTarget::<D>::axis(surface, seat, data, frame)
         }
     }
+    fn gesture_swipe(&self, seat: &Seat<D>, data: &mut D, event: &GestureSwipeEvent) {
+        if let Some(surface) = self.0.focused_surface.lock().unwrap().as_ref() {
+            PointerTarget::<D>::gesture_swipe(surface, seat, data, event)
+        }
+    }
+    fn gesture_pinch(&self, seat: &Seat<D>, data: &mut D, event: &GesturePinchEvent) {
+        if let Some(surface) = self.0.focused_surface.lock().unwrap().as_ref() {
+            PointerTarget::<D>::gesture_pinch(surface, seat, data, event)
+        }
+    }
     fn leave(&self, seat: &Seat<D>, data: &mut D, serial: Serial, time: u32) {
         if let Some(surface) = self.0.focused_surface.lock().unwrap().take() {
             PointerTarget::<D>::leave(&surface, seat, data, serial, time)
@@ -1,6 +1,15 @@
 use std::{
     fmt,
-    sync::{Arc, Mutex},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use calloop::{
+    timer::{TimeoutAction, Timer},
+    LoopHandle, RegistrationToken,
 };
 
 use wayland_server::{protocol::wl_surface::WlSurface, Resource};
@@ -18,7 +27,7 @@ use crate::{
         },
         SeatHandler,
     },
-    utils::{DeadResource, IsAlive, Logical, Point, Serial},
+    utils::{DeadResource, IsAlive, Logical, Point, Serial, SERIAL_COUNTER},
     wayland::{compositor::get_role, seat::WaylandFocus, shell::xdg::XDG_POPUP_ROLE},
 };
 
@@ -113,6 +122,11 @@ impl PopupGrabInner {
         guard.active_grabs.iter().rev().find(|p| p.alive()).cloned()
     }
 
+    fn depth(&self) -> usize {
+        let guard = self.internal.lock().unwrap();
+        guard.active_grabs.iter().filter(|p| p.alive()).count()
+    }
+
     pub(super) fn cleanup(&self) {
         let mut guard = self.internal.lock().unwrap();
         guard.cleanup();
@@ -226,6 +240,7 @@ where
     <D as SeatHandler>::PointerFocus: From<<D as SeatHandler>::KeyboardFocus> + WaylandFocus,
 {
     root: <D as SeatHandler>::KeyboardFocus,
+    previous_focus: Option<<D as SeatHandler>::KeyboardFocus>,
     serial: Serial,
     previous_serial: Option<Serial>,
     toplevel_grab: PopupGrabInner,
@@ -243,6 +258,7 @@ where
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("PopupGrab")
             .field("root", &self.root)
+            .field("previous_focus", &self.previous_focus)
             .field("serial", &self.serial)
             .field("previous_serial", &self.previous_serial)
             .field("keyboard_handle", &self.keyboard_handle)
@@ -261,6 +277,7 @@ where
     fn clone(&self) -> Self {
         PopupGrab {
             root: self.root.clone(),
+            previous_focus: self.previous_focus.clone(),
             serial: self.serial,
             previous_serial: self.previous_serial,
             toplevel_grab: self.toplevel_grab.clone(),
@@ -286,6 +303,9 @@ where
     ) -> Self {
         PopupGrab {
             root: root.clone(),
+            previous_focus: keyboard_handle
+                .as_ref()
+                .and_then(|keyboard| keyboard.current_focus()),
             serial,
             previous_serial,
             toplevel_grab: toplevel_popups,
@@ -331,15 +351,39 @@ where
         !self.root.alive() || !self.toplevel_grab.active()
     }
 
+    /// Returns the keyboard focus at the time the grab was obtained
+    pub fn previous_focus(&self) -> Option<&<D as SeatHandler>::KeyboardFocus> {
+        self.previous_focus.as_ref()
+    }
+
     /// Returns the current grabbed [`WlSurface`].
     ///
-    /// If the grab has ended this will return the root surface
-    /// so that the client expected focus can be restored
+    /// If the grab has ended this will return the focus to restore,
+    /// see [`PopupGrab::restore_focus`]
     pub fn current_grab(&self) -> Option<<D as SeatHandler>::KeyboardFocus> {
         self.toplevel_grab
             .current_grab()
             .map(From::from)
-            .or_else(|| Some(self.root.clone()))
+            .or_else(|| Some(self.restore_focus()))
+    }
+
+    /// Returns the keyboard focus to restore once the grab has ended
+    ///
+    /// This is the focus the keyboard had when the grab was obtained, as long as
+    /// it is still alive and not a popup itself (which is the case for nested grabs).
+    /// Otherwise the root of the grab is returned.
+    pub fn restore_focus(&self) -> <D as SeatHandler>::KeyboardFocus {
+        self.previous_focus
+            .as_ref()
+            .filter(|focus| {
+                focus.alive()
+                    && focus
+                        .wl_surface()
+                        .map(|surface| get_role(&surface) != Some(XDG_POPUP_ROLE))
+                        .unwrap_or(true)
+            })
+            .cloned()
+            .unwrap_or_else(|| self.root.clone())
     }
 
     /// Ungrab and dismiss a popup
@@ -374,14 +418,27 @@ where
         &self.pointer_grab_start_data
     }
 
+    /// Number of currently grabbed (nested) popups
+    fn depth(&self) -> usize {
+        self.toplevel_grab.depth()
+    }
+
+    fn has_keyboard_grab(&self) -> bool {
+        self.keyboard_handle
+            .as_ref()
+            .map(|keyboard| {
+                keyboard.is_grabbed()
+                    && (keyboard.has_grab(self.serial)
+                        || keyboard.has_grab(self.previous_serial.unwrap_or(self.serial)))
+            })
+            .unwrap_or(false)
+    }
+
     fn unset_keyboard_grab(&self, data: &mut D, serial: Serial) {
         if let Some(keyboard) = self.keyboard_handle.as_ref() {
-            if keyboard.is_grabbed()
-                && (keyboard.has_grab(self.serial)
-                    || keyboard.has_grab(self.previous_serial.unwrap_or(self.serial)))
-            {
+            if self.has_keyboard_grab() {
                 keyboard.unset_grab();
-                keyboard.set_focus(data, Some(self.root.clone()), serial);
+                keyboard.set_focus(data, Some(self.restore_focus()), serial);
             }
         }
     }
@@ -391,8 +448,11 @@ where
 ///
 /// The [`PopupKeyboardGrab`] will keep the focus of the keyboard
 /// on the topmost popup until the grab has ended. If the
-/// grab has ended it will restore the focus on the root of the grab
-/// and unset the [`KeyboardGrab`]
+/// grab has ended it will restore the focus the keyboard had before
+/// the grab (see [`PopupGrab::restore_focus`]) and unset the [`KeyboardGrab`]
+///
+/// Optionally the grab can be released automatically, if the user does not interact
+/// with the popup in time, see [`PopupKeyboardGrab::with_timeout`].
 pub struct PopupKeyboardGrab<D>
 where
     D: SeatHandler + 'static,
@@ -400,6 +460,14 @@ where
     <D as SeatHandler>::PointerFocus: From<<D as SeatHandler>::KeyboardFocus> + WaylandFocus,
 {
     popup_grab: PopupGrab<D>,
+    timeout: Option<PopupGrabTimeout<D>>,
+}
+
+struct PopupGrabTimeout<D: 'static> {
+    loop_handle: LoopHandle<'static, D>,
+    token: RegistrationToken,
+    // set once the timer fired, the source is removed automatically in that case
+    fired: Arc<AtomicBool>,
 }
 
 impl<D> fmt::Debug for PopupKeyboardGrab<D>
@@ -425,8 +493,66 @@ where
     pub fn new(popup_grab: &PopupGrab<D>) -> Self {
         PopupKeyboardGrab {
             popup_grab: popup_grab.clone(),
+            timeout: None,
         }
     }
+
+    fn cancel_timeout(&mut self) {
+        if let Some(timeout) = self.timeout.take() {
+            if !timeout.fired.load(Ordering::SeqCst) {
+                timeout.loop_handle.remove(timeout.token);
+            }
+        }
+    }
+}
+
+impl<D> PopupKeyboardGrab<D>
+where
+    D: SeatHandler + 'static,
+    <D as SeatHandler>::KeyboardFocus: WaylandFocus + From<PopupKind>,
+    <D as SeatHandler>::PointerFocus: From<<D as SeatHandler>::KeyboardFocus> + WaylandFocus,
+{
+    /// Automatically release the grab after the given timeout
+    ///
+    /// If no key input reaches the grab before the timeout expires, all popups
+    /// of the grab are dismissed, the keyboard focus is restored and the grab is unset,
+    /// like with [`PopupKeyboardGrab::release`]. Any key input cancels the timeout.
+    pub fn with_timeout(mut self, duration: Duration, loop_handle: &LoopHandle<'static, D>) -> Self {
+        self.cancel_timeout();
+
+        let mut popup_grab = self.popup_grab.clone();
+        let fired = Arc::new(AtomicBool::new(false));
+        let timer_fired = fired.clone();
+        let token = loop_handle.insert_source(Timer::from_duration(duration), move |_, _, data| {
+            timer_fired.store(true, Ordering::SeqCst);
+            // the grab might have been replaced or ended in the meantime
+            if !popup_grab.has_ended() && popup_grab.has_keyboard_grab() {
+                let _ = popup_grab.ungrab(PopupUngrabStrategy::All);
+                popup_grab.unset_keyboard_grab(data, SERIAL_COUNTER.next_serial());
+            }
+            TimeoutAction::Drop
+        });
+        self.timeout = token.ok().map(|token| PopupGrabTimeout {
+            loop_handle: loop_handle.clone(),
+            token,
+            fired,
+        });
+        self
+    }
+
+    /// Release the grab
+    ///
+    /// This dismisses all popups of the grab, sending `xdg_popup.popup_done`
+    /// to each of them, restores the keyboard focus the keyboard had before the grab
+    /// and unsets the [`KeyboardGrab`]. The popup receives `wl_keyboard.leave`
+    /// before the restored focus receives `wl_keyboard.enter`.
+    ///
+    /// This can be used by decorating grabs to end the grab early.
+    pub fn release(&mut self, data: &mut D, handle: &mut KeyboardInnerHandle<'_, D>, serial: Serial) {
+        let _ = self.popup_grab.ungrab(PopupUngrabStrategy::All);
+        handle.set_focus(data, self.popup_grab.current_grab(), serial);
+        handle.unset_grab(data, serial, false);
+    }
 }
 
 impl<D> KeyboardGrab<D> for PopupKeyboardGrab<D>
@@ -445,6 +571,9 @@ where
         serial: Serial,
         time: u32,
     ) {
+        // The user interacted with the popup
+        self.cancel_timeout();
+
         // Check if the grab changed and update the focus
         // If the grab has ended this will return the root
         // surface to restore the client expected focus.
@@ -486,6 +615,17 @@ where
     }
 }
 
+impl<D> Drop for PopupKeyboardGrab<D>
+where
+    D: SeatHandler + 'static,
+    <D as SeatHandler>::KeyboardFocus: WaylandFocus,
+    <D as SeatHandler>::PointerFocus: From<<D as SeatHandler>::KeyboardFocus> + WaylandFocus,
+{
+    fn drop(&mut self) {
+        self.cancel_timeout();
+    }
+}
+
 /// Default implementation of a [`PointerGrab`] for [`PopupGrab`]
 ///
 /// The [`PopupPointerGrab`] will make sure that the pointer focus
@@ -506,6 +646,9 @@ where
     <D as SeatHandler>::PointerFocus: From<<D as SeatHandler>::KeyboardFocus> + WaylandFocus,
 {
     popup_grab: PopupGrab<D>,
+    // number of pointer focus targets this and previous grabs of the
+    // same popup grab pushed onto the focus stack
+    focus_depth: usize,
 }
 
 impl<D> fmt::Debug for PopupPointerGrab<D>
@@ -517,6 +660,7 @@ where
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("PopupPointerGrab")
             .field("popup_grab", &self.popup_grab)
+            .field("focus_depth", &self.focus_depth)
             .finish()
     }
 }
@@ -528,9 +672,33 @@ where
     <D as SeatHandler>::PointerFocus: From<<D as SeatHandler>::KeyboardFocus> + WaylandFocus,
 {
     /// Create a [`PopupPointerGrab`] for the provided [`PopupGrab`]
+    ///
+    /// The pointer focus at the time the grab starts is saved and restored
+    /// once the grabbed popup is dismissed. For nested grabs this means dismissing
+    /// a child popup restores the focus of its parent popup.
     pub fn new(popup_grab: &PopupGrab<D>) -> Self {
         PopupPointerGrab {
             popup_grab: popup_grab.clone(),
+            // focus targets of parent popups were already saved by their grabs
+            focus_depth: popup_grab.depth().saturating_sub(1),
+        }
+    }
+
+    fn sync_focus_stack(
+        &mut self,
+        data: &mut D,
+        handle: &mut PointerInnerHandle<'_, D>,
+        serial: Serial,
+        time: u32,
+    ) {
+        let depth = self.popup_grab.depth();
+        while self.focus_depth < depth {
+            handle.push_focus();
+            self.focus_depth += 1;
+        }
+        while self.focus_depth > depth.max(1) {
+            handle.pop_focus(data, serial, time);
+            self.focus_depth -= 1;
         }
     }
 }
@@ -549,10 +717,11 @@ where
         event: &MotionEvent,
     ) {
         if self.popup_grab.has_ended() {
-            handle.unset_grab(data, event.serial, event.time);
+            handle.unset_grab(data, event.serial, event.time.as_millis_u32());
             self.popup_grab.unset_keyboard_grab(data, event.serial);
             return;
         }
+        self.sync_focus_stack(data, handle, event.serial, event.time.as_millis_u32());
 
         // Check that the focus is of the same client as the grab
         // If yes allow it, if not unset the focus.
@@ -585,7 +754,7 @@ where
 
     fn button(&mut self, data: &mut D, handle: &mut PointerInnerHandle<'_, D>, event: &ButtonEvent) {
         let serial = event.serial;
-        let time = event.time;
+        let time = event.time.as_millis_u32();
         let state = event.state;
 
         if self.popup_grab.has_ended() {
@@ -594,6 +763,7 @@ where
             self.popup_grab.unset_keyboard_grab(data, serial);
             return;
         }
+        self.sync_focus_stack(data, handle, serial, time);
 
         // Check if the the client of the focused surface is still equal to the grabbed surface client
         // if not the popup will be dismissed
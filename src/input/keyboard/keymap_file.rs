@@ -50,6 +50,11 @@ impl KeymapFile {
         self.keymap = keymap;
     }
 
+    /// The keymap in the `KEYMAP_FORMAT_TEXT_V1` format
+    pub(crate) fn as_str(&self) -> &str {
+        &self.keymap
+    }
+
     #[cfg(feature = "wayland_frontend")]
     /// Run a closure with the file descriptor to ensure safety
     pub fn with_fd<F>(&self, supports_sealed: bool, cb: F) -> Result<(), std::io::Error>
@@ -1,7 +1,7 @@
 //! Keyboard-related types for smithay's input abstraction
 
 use crate::backend::input::KeyState;
-use crate::utils::{IsAlive, Serial};
+use crate::utils::{IsAlive, Serial, SERIAL_COUNTER};
 use slog::{debug, error, info, o, trace};
 use std::collections::HashSet;
 use std::{
@@ -59,9 +59,16 @@ pub(crate) struct KbdInternal<D: SeatHandler> {
     pub(crate) focus: Option<(<D as SeatHandler>::KeyboardFocus, Serial)>,
     pending_focus: Option<<D as SeatHandler>::KeyboardFocus>,
     pub(crate) pressed_keys: HashSet<u32>,
+    // keycodes whose next release is not forwarded
+    suppressed_keys: HashSet<u32>,
+    // repeated key presses are not forwarded to the current focus
+    repeat_inhibited: bool,
     pub(crate) mods_state: ModifiersState,
     pub(crate) keymap: xkb::Keymap,
     pub(crate) state: xkb::State,
+    compose: Option<xkb::compose::State>,
+    // keycode and result of the last key fed into the compose state
+    last_compose: Option<(u32, ComposeResult)>,
     pub(crate) repeat_rate: i32,
     pub(crate) repeat_delay: i32,
     grab: GrabStatus<D>,
@@ -77,9 +84,13 @@ where
             .field("focus", &self.focus)
             .field("pending_focus", &self.pending_focus)
             .field("pressed_keys", &self.pressed_keys)
+            .field("suppressed_keys", &self.suppressed_keys)
+            .field("repeat_inhibited", &self.repeat_inhibited)
             .field("mods_state", &self.mods_state)
             .field("keymap", &self.keymap.get_raw_ptr())
             .field("state", &self.state.get_raw_ptr())
+            .field("compose", &self.compose.is_some())
+            .field("last_compose", &self.last_compose)
             .field("repeat_rate", &self.repeat_rate)
             .field("repeat_delay", &self.repeat_delay)
             .finish()
@@ -114,9 +125,13 @@ impl<D: SeatHandler + 'static> KbdInternal<D> {
             focus: None,
             pending_focus: None,
             pressed_keys: HashSet::new(),
+            suppressed_keys: HashSet::new(),
+            repeat_inhibited: false,
             mods_state: ModifiersState::default(),
             keymap,
             state,
+            compose: None,
+            last_compose: None,
             repeat_rate,
             repeat_delay,
             grab: GrabStatus::None,
@@ -150,6 +165,31 @@ impl<D: SeatHandler + 'static> KbdInternal<D> {
         }
     }
 
+    // feed a key press into the compose state, if compose is enabled
+    fn compose_input(&mut self, keycode: u32) -> Option<ComposeResult> {
+        let keysym = self.state.key_get_one_sym(keycode + 8);
+        let compose = self.compose.as_mut()?;
+        if let xkb::compose::FeedResult::Ignored = compose.feed(keysym) {
+            return None;
+        }
+
+        let status = ComposeStatus::from(compose.status());
+        let composed_keysym = match status {
+            ComposeStatus::Composed => compose.keysym(),
+            _ => None,
+        };
+        Some(ComposeResult {
+            status,
+            composed_keysym,
+        })
+    }
+
+    fn compose_result(&self, keycode: u32) -> Option<ComposeResult> {
+        self.last_compose
+            .filter(|(code, _)| *code == keycode)
+            .map(|(_, result)| result)
+    }
+
     fn with_grab<F>(&mut self, seat: &Seat<D>, f: F, logger: ::slog::Logger)
     where
         F: FnOnce(KeyboardInnerHandle<'_, D>, &mut dyn KeyboardGrab<D>),
@@ -210,6 +250,9 @@ pub enum Error {
     /// Smithay could not create a tempfile to share the keymap with clients
     #[error("Failed to create tempfile to share the keymap: {0}")]
     IoError(io::Error),
+    /// libxkbcommon could not load a compose table for the specified locale
+    #[error("Libxkbcommon could not load a compose table for locale {0}")]
+    BadComposeTable(String),
 }
 
 pub(crate) struct KbdRc<D: SeatHandler> {
@@ -219,6 +262,9 @@ pub(crate) struct KbdRc<D: SeatHandler> {
     pub(crate) logger: ::slog::Logger,
     #[cfg(feature = "wayland_frontend")]
     pub(crate) known_kbds: Mutex<Vec<wayland_server::protocol::wl_keyboard::WlKeyboard>>,
+    #[cfg(feature = "wayland_frontend")]
+    pub(crate) client_keymaps:
+        Mutex<std::collections::HashMap<wayland_server::backend::ClientId, KeymapFile>>,
 }
 
 #[cfg(not(feature = "wayland_frontend"))]
@@ -245,15 +291,50 @@ where
             .field("keymap", &self.keymap)
             .field("logger", &self.logger)
             .field("known_kbds", &self.known_kbds)
+            .field("client_keymaps", &self.client_keymaps)
             .finish()
     }
 }
 
+/// State of a compose sequence, see [`KeyboardHandle::enable_compose`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComposeStatus {
+    /// No sequence is in progress
+    Nothing,
+    /// A sequence was started, but is not complete yet
+    Composing,
+    /// A sequence was completed
+    Composed,
+    /// A sequence was aborted by a key, that does not continue it
+    Cancelled,
+}
+
+impl From<xkb::compose::Status> for ComposeStatus {
+    fn from(status: xkb::compose::Status) -> ComposeStatus {
+        match status {
+            xkb::compose::Status::Nothing => ComposeStatus::Nothing,
+            xkb::compose::Status::Composing => ComposeStatus::Composing,
+            xkb::compose::Status::Composed => ComposeStatus::Composed,
+            xkb::compose::Status::Cancelled => ComposeStatus::Cancelled,
+        }
+    }
+}
+
+/// Result of feeding a key press into the compose state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ComposeResult {
+    /// Status of the compose sequence after this key press
+    pub status: ComposeStatus,
+    /// The resulting keysym, if the sequence was completed by this key press
+    pub composed_keysym: Option<Keysym>,
+}
+
 /// Handle to the underlying keycode to allow for different conversions
 pub struct KeysymHandle<'a> {
     keycode: u32,
     keymap: &'a xkb::Keymap,
     state: &'a xkb::State,
+    compose: Option<ComposeResult>,
 }
 
 impl<'a> fmt::Debug for KeysymHandle<'a> {
@@ -288,6 +369,14 @@ impl<'a> KeysymHandle<'a> {
     pub fn raw_code(&'a self) -> u32 {
         self.keycode
     }
+
+    /// Returns how this key press advanced the compose state
+    ///
+    /// This is `None` if compose is not enabled (see [`KeyboardHandle::enable_compose`]),
+    /// for key releases and for keys ignored by compose sequences, like modifiers.
+    pub fn compose(&self) -> Option<ComposeResult> {
+        self.compose
+    }
 }
 
 /// Result for key input filtering (see [`KeyboardHandle::input`])
@@ -432,6 +521,8 @@ impl<D: SeatHandler + 'static> KeyboardHandle<D> {
                 logger: log,
                 #[cfg(feature = "wayland_frontend")]
                 known_kbds: Mutex::new(Vec::new()),
+                #[cfg(feature = "wayland_frontend")]
+                client_keymaps: Mutex::new(std::collections::HashMap::new()),
             }),
         })
     }
@@ -447,7 +538,16 @@ impl<D: SeatHandler + 'static> KeyboardHandle<D> {
         use std::os::unix::io::AsRawFd;
         use wayland_server::{protocol::wl_keyboard::KeymapFormat, Resource};
         let known_kbds = &self.arc.known_kbds;
+        let client_keymaps = self.arc.client_keymaps.lock().unwrap();
         for kbd in &*known_kbds.lock().unwrap() {
+            // clients with their own keymap keep it
+            if kbd
+                .client()
+                .map(|client| client_keymaps.contains_key(&client.id()))
+                .unwrap_or(false)
+            {
+                continue;
+            }
             let res = keymap_file.with_fd(kbd.version() >= 7, |fd, size| {
                 kbd.keymap(KeymapFormat::XkbV1, fd.as_raw_fd(), size as u32)
             });
@@ -460,6 +560,133 @@ impl<D: SeatHandler + 'static> KeyboardHandle<D> {
         }
     }
 
+    /// Set a keymap only used for the given client
+    ///
+    /// The keymap is compiled from the provided [`XkbConfig`] and immediately sent to all
+    /// existing `wl_keyboard` objects of the client, keyboards created later by the client
+    /// will receive it as well. This does not change the keymap used by the compositor to
+    /// interpret key events, which is useful e.g. for remote desktop sessions, where every
+    /// client may be operated with a different layout.
+    ///
+    /// The keymap is kept until [`KeyboardHandle::unset_keymap_for_client`] is called or the last
+    /// `wl_keyboard` of the client is destroyed, e.g. because the client disconnected.
+    #[cfg(feature = "wayland_frontend")]
+    pub fn set_keymap_for_client(
+        &self,
+        client: &wayland_server::backend::ClientId,
+        xkb_config: XkbConfig<'_>,
+    ) -> Result<(), Error> {
+        use wayland_server::Resource;
+
+        let logger = &self.arc.logger;
+        let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+        let keymap = xkb::Keymap::new_from_names(
+            &context,
+            &xkb_config.rules,
+            &xkb_config.model,
+            &xkb_config.layout,
+            &xkb_config.variant,
+            xkb_config.options,
+            xkb::KEYMAP_COMPILE_NO_FLAGS,
+        )
+        .ok_or_else(|| {
+            debug!(logger, "Loading client keymap failed");
+            Error::BadKeymap
+        })?;
+        let keymap_file = KeymapFile::new(&keymap, logger.clone());
+
+        for kbd in &*self.arc.known_kbds.lock().unwrap() {
+            if kbd.client().map(|c| c.id()).as_ref() == Some(client) {
+                keymap_file.send(kbd).map_err(Error::IoError)?;
+            }
+        }
+
+        self.arc
+            .client_keymaps
+            .lock()
+            .unwrap()
+            .insert(client.clone(), keymap_file);
+        Ok(())
+    }
+
+    /// Remove a keymap previously set with [`KeyboardHandle::set_keymap_for_client`]
+    ///
+    /// The existing `wl_keyboard` objects of the client will receive the keymap of the keyboard again.
+    #[cfg(feature = "wayland_frontend")]
+    pub fn unset_keymap_for_client(&self, client: &wayland_server::backend::ClientId) {
+        use slog::warn;
+        use wayland_server::Resource;
+
+        if self.arc.client_keymaps.lock().unwrap().remove(client).is_none() {
+            return;
+        }
+
+        let keymap_file = self.arc.keymap.lock().unwrap();
+        for kbd in &*self.arc.known_kbds.lock().unwrap() {
+            if kbd.client().map(|c| c.id()).as_ref() != Some(client) {
+                continue;
+            }
+            if let Err(e) = keymap_file.send(kbd) {
+                warn!(self.arc.logger,
+                    "Failed to send keymap to client";
+                    "err" => format!("{:?}", e)
+                );
+            }
+        }
+    }
+
+    /// Enable handling of compose sequences using the compose table of the given locale
+    ///
+    /// Every key press is fed into the compose state afterwards, the result is available
+    /// through [`KeysymHandle::compose`]. Sequences like `Compose + " + a` or dead keys
+    /// can then be resolved to a single keysym, e.g. `ä`.
+    ///
+    /// The locale is usually taken from the `LC_ALL`, `LC_CTYPE` or `LANG` environment variables.
+    pub fn enable_compose(&self, locale: &str) -> Result<(), Error> {
+        let logger = &self.arc.logger;
+        let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+        let table = xkb::compose::Table::new_from_locale(
+            &context,
+            std::ffi::OsStr::new(locale),
+            xkb::compose::COMPILE_NO_FLAGS,
+        )
+        .map_err(|_| {
+            debug!(logger, "Loading compose table failed"; "locale" => locale);
+            Error::BadComposeTable(locale.to_owned())
+        })?;
+        let state = xkb::compose::State::new(&table, xkb::compose::STATE_NO_FLAGS);
+
+        let mut guard = self.arc.internal.lock().unwrap();
+        guard.compose = Some(state);
+        guard.last_compose = None;
+        Ok(())
+    }
+
+    /// The keymap of this keyboard in the xkb text format
+    ///
+    /// This is the keymap sent to clients, which is kept up to date whenever the keymap
+    /// changes, e.g. by a virtual keyboard. Comparing the returned strings allows to detect
+    /// keymap changes without holding onto any xkb objects.
+    /// Keymaps set with [`KeyboardHandle::set_keymap_for_client`] are not taken into account.
+    pub fn keymap_as_string(&self) -> String {
+        #[cfg(feature = "wayland_frontend")]
+        {
+            self.arc.keymap.lock().unwrap().as_str().to_owned()
+        }
+        #[cfg(not(feature = "wayland_frontend"))]
+        {
+            let internal = self.arc.internal.lock().unwrap();
+            internal.keymap.get_as_string(xkb::KEYMAP_FORMAT_TEXT_V1)
+        }
+    }
+
+    /// Disable handling of compose sequences, see [`KeyboardHandle::enable_compose`]
+    pub fn disable_compose(&self) {
+        let mut guard = self.arc.internal.lock().unwrap();
+        guard.compose = None;
+        guard.last_compose = None;
+    }
+
     /// Change the current grab on this keyboard to the provided grab
     ///
     /// Overwrites any current grab.
@@ -522,14 +749,25 @@ impl<D: SeatHandler + 'static> KeyboardHandle<D> {
         F: FnOnce(&mut D, &ModifiersState, KeysymHandle<'_>) -> FilterResult<T>,
     {
         trace!(self.arc.logger, "Handling keystroke"; "keycode" => keycode, "state" => format_args!("{:?}", state));
+        let seat = self.get_seat(data);
+        #[cfg(feature = "wayland_frontend")]
+        crate::wayland::idle_notify::notify_activity(&seat);
         let mut guard = self.arc.internal.lock().unwrap();
+        // backends report auto-repeat as presses of an already pressed key
+        let is_repeat = state == KeyState::Pressed && guard.pressed_keys.contains(&keycode);
         let mods_changed = guard.key_input(keycode, state);
+        let compose = match state {
+            KeyState::Pressed => guard.compose_input(keycode),
+            KeyState::Released => None,
+        };
+        guard.last_compose = compose.map(|result| (keycode, result));
         let key_handle = KeysymHandle {
             // Offset the keycode by 8, as the evdev XKB rules reflect X's
             // broken keycode system, which starts at 8.
             keycode: keycode + 8,
             state: &guard.state,
             keymap: &guard.keymap,
+            compose,
         };
 
         trace!(self.arc.logger, "Calling input filter";
@@ -542,8 +780,24 @@ impl<D: SeatHandler + 'static> KeyboardHandle<D> {
             return Some(val);
         }
 
+        if state == KeyState::Released && guard.suppressed_keys.remove(&keycode) {
+            trace!(self.arc.logger, "Key release was suppressed");
+            // the client still needs to know about modifiers being released
+            if mods_changed {
+                let mods_state = guard.mods_state;
+                if let Some((focus, _)) = guard.focus.as_ref() {
+                    focus.modifiers(&seat, data, mods_state, serial);
+                }
+            }
+            return None;
+        }
+
+        if is_repeat && guard.repeat_inhibited {
+            trace!(self.arc.logger, "Key repeat was inhibited");
+            return None;
+        }
+
         // forward to client if no keybinding is triggered
-        let seat = self.get_seat(data);
         let modifiers = mods_changed.then_some(guard.mods_state);
         guard.with_grab(
             &seat,
@@ -561,6 +815,41 @@ impl<D: SeatHandler + 'static> KeyboardHandle<D> {
         None
     }
 
+    /// Do not forward the next release of the given key to the client
+    ///
+    /// This is useful when the press of the key was intercepted by the filter of [`KeyboardHandle::input`],
+    /// e.g. because it triggered a shortcut, so the client does not receive a release for a key
+    /// it never saw being pressed. The release is still passed to the filter and changes to the
+    /// modifiers caused by it are still forwarded.
+    pub fn suppress_next_key_up(&self, keycode: u32) {
+        self.arc.internal.lock().unwrap().suppressed_keys.insert(keycode);
+    }
+
+    /// Inhibit forwarding repeated key presses to the current keyboard focus
+    ///
+    /// Some backends (e.g. winit) report auto-repeat as additional presses of an already
+    /// pressed key. While inhibited, these are still passed to the filter of
+    /// [`KeyboardHandle::input`], but not forwarded to the client. This is useful for clients
+    /// tracking held keys themselves, like games.
+    ///
+    /// The inhibition only applies to the focus at the time of the call and ends once the focus changes.
+    /// The repeat info sent to clients is not changed, see [`KeyboardHandle::change_repeat_info`].
+    pub fn set_repeat_inhibited(&self, inhibited: bool) {
+        self.arc.internal.lock().unwrap().repeat_inhibited = inhibited;
+    }
+
+    /// Inject a synthetic keystroke, e.g. from an on-screen keyboard
+    ///
+    /// The keystroke is handled like one passed to [`KeyboardHandle::input`] without any filter:
+    /// the state of the keymap is updated and the key as well as any changed modifiers are
+    /// forwarded to the focused client, through the active grab if any.
+    pub fn inject_key(&self, data: &mut D, keycode: u32, state: KeyState, time: u32) {
+        let serial = SERIAL_COUNTER.next_serial();
+        self.input::<(), _>(data, keycode, state, serial, time, |_, _, _| {
+            FilterResult::Forward
+        });
+    }
+
     /// Set the current focus of this keyboard
     ///
     /// If the new focus is different from the previous one, any previous focus
@@ -595,6 +884,7 @@ impl<D: SeatHandler + 'static> KeyboardHandle<D> {
                     keycode: code + 8,
                     state: &guard.state,
                     keymap: &guard.keymap,
+                    compose: None,
                 })
                 .collect::<Vec<_>>();
             f(handles);
@@ -703,6 +993,7 @@ impl<'a, D: SeatHandler + 'static> KeyboardInnerHandle<'a, D> {
             keycode: keycode + 8,
             state: &self.inner.state,
             keymap: &self.inner.keymap,
+            compose: None,
         }
     }
 
@@ -729,6 +1020,10 @@ impl<'a, D: SeatHandler + 'static> KeyboardInnerHandle<'a, D> {
                 keycode: keycode + 8,
                 state: &self.inner.state,
                 keymap: &self.inner.keymap,
+                compose: match key_state {
+                    KeyState::Pressed => self.inner.compose_result(keycode),
+                    KeyState::Released => None,
+                },
             };
 
             focus.key(self.seat, data, key, key_state, serial, time);
@@ -781,6 +1076,7 @@ impl<'a, D: SeatHandler + 'static> KeyboardInnerHandle<'a, D> {
 
             // set new focus
             self.inner.focus = focus.map(|f| (f, serial));
+            self.inner.repeat_inhibited = false;
             if let Some((focus, _)) = self.inner.focus.as_mut() {
                 let keys = self
                     .inner
@@ -793,6 +1089,7 @@ impl<'a, D: SeatHandler + 'static> KeyboardInnerHandle<'a, D> {
                             keycode: keycode + 8,
                             state: &self.inner.state,
                             keymap: &self.inner.keymap,
+                            compose: None,
                         }
                     })
                     .collect();
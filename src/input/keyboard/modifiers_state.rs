@@ -23,6 +23,11 @@ pub struct ModifiersState {
     /// The "Num lock" key
     pub num_lock: bool,
 
+    /// Index of the effective keyboard layout
+    ///
+    /// Changes when switching between the layouts of a keymap with multiple layouts.
+    pub layout: u32,
+
     /// Serialized modifier state, as send e.g. by the wl_keyboard protocol
     pub serialized: SerializedMods,
 }
@@ -36,8 +41,103 @@ impl ModifiersState {
         self.caps_lock = state.mod_name_is_active(&xkb::MOD_NAME_CAPS, xkb::STATE_MODS_EFFECTIVE);
         self.logo = state.mod_name_is_active(&xkb::MOD_NAME_LOGO, xkb::STATE_MODS_EFFECTIVE);
         self.num_lock = state.mod_name_is_active(&xkb::MOD_NAME_NUM, xkb::STATE_MODS_EFFECTIVE);
+        self.layout = state.serialize_layout(xkb::STATE_LAYOUT_EFFECTIVE);
         self.serialized = serialize_modifiers(state);
     }
+
+    /// Keysyms of the active modifiers
+    ///
+    /// Each active modifier is mapped to the keysym of its left-hand key (e.g.
+    /// `KEY_Control_L` for `ctrl`, `KEY_Super_L` for `logo`). The keysyms are returned
+    /// in the order the fields of this struct are declared.
+    pub fn active_modifier_keysyms(&self) -> Vec<xkb::Keysym> {
+        [
+            (self.ctrl, xkb::keysyms::KEY_Control_L),
+            (self.alt, xkb::keysyms::KEY_Alt_L),
+            (self.shift, xkb::keysyms::KEY_Shift_L),
+            (self.caps_lock, xkb::keysyms::KEY_Caps_Lock),
+            (self.logo, xkb::keysyms::KEY_Super_L),
+            (self.num_lock, xkb::keysyms::KEY_Num_Lock),
+        ]
+        .into_iter()
+        .filter_map(|(active, keysym)| active.then_some(keysym))
+        .collect()
+    }
+
+    /// Check the modifiers of a keyboard shortcut
+    ///
+    /// `Some(value)` requires the modifier to be in the given state, while `None` accepts
+    /// either state. Lock modifiers (caps lock and num lock) are never taken into account.
+    ///
+    /// ```
+    /// # use smithay::input::keyboard::ModifiersState;
+    /// let modifiers = ModifiersState {
+    ///     ctrl: true,
+    ///     shift: true,
+    ///     caps_lock: true,
+    ///     ..Default::default()
+    /// };
+    /// // ctrl+shift, regardless of alt
+    /// assert!(modifiers.matches_shortcut(Some(true), None, Some(true), Some(false)));
+    /// ```
+    pub fn matches_shortcut(
+        &self,
+        ctrl: Option<bool>,
+        alt: Option<bool>,
+        shift: Option<bool>,
+        logo: Option<bool>,
+    ) -> bool {
+        [
+            (ctrl, self.ctrl),
+            (alt, self.alt),
+            (shift, self.shift),
+            (logo, self.logo),
+        ]
+        .into_iter()
+        .all(|(expected, active)| expected.map(|expected| expected == active).unwrap_or(true))
+    }
+}
+
+/// Create a `(ModifiersState, Keysym)` pair describing a keyboard shortcut
+///
+/// The modifiers are given as the names of the [`ModifiersState`] fields to set, followed by the
+/// name of the keysym (as understood by `xkb_keysym_from_name`). An unknown keysym name results in
+/// [`keysyms::KEY_NoSymbol`](crate::input::keyboard::keysyms::KEY_NoSymbol).
+///
+/// Only the listed modifiers are set, so the returned state should be checked with
+/// [`ModifiersState::matches_shortcut`] instead of being compared to the current modifiers directly,
+/// which also contain lock modifiers and the serialized state.
+///
+/// ```
+/// # use smithay::{input::keyboard::{keysyms, ModifiersState}, shortcut};
+/// let (mods, keysym) = shortcut!(ctrl, shift; "Return");
+/// assert!(mods.ctrl && mods.shift && !mods.alt);
+/// assert_eq!(keysym, keysyms::KEY_Return);
+///
+/// let current = ModifiersState { ctrl: true, shift: true, num_lock: true, ..Default::default() };
+/// assert!(current.matches_shortcut(Some(mods.ctrl), Some(mods.alt), Some(mods.shift), Some(mods.logo)));
+///
+/// let (mods, keysym) = shortcut!("Escape");
+/// assert_eq!(mods, ModifiersState::default());
+/// assert_eq!(keysym, keysyms::KEY_Escape);
+/// ```
+#[macro_export]
+macro_rules! shortcut {
+    ($($modifier:ident),* ; $keysym:expr) => {
+        (
+            $crate::input::keyboard::ModifiersState {
+                $($modifier: true,)*
+                ..::std::default::Default::default()
+            },
+            $crate::input::keyboard::xkb::keysym_from_name(
+                $keysym,
+                $crate::input::keyboard::xkb::KEYSYM_NO_FLAGS,
+            ),
+        )
+    };
+    ($keysym:expr) => {
+        $crate::shortcut!(; $keysym)
+    };
 }
 
 #[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
@@ -61,3 +161,48 @@ fn serialize_modifiers(state: &xkb::State) -> SerializedMods {
         layout_effective,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::ModifiersState;
+    use xkbcommon::xkb::{self, keysyms};
+
+    #[test]
+    fn layout_follows_effective_layout() {
+        let context = xkb::Context::new(xkb::CONTEXT_NO_FLAGS);
+        let keymap =
+            xkb::Keymap::new_from_names(&context, "", "", "us,de", "", None, xkb::KEYMAP_COMPILE_NO_FLAGS)
+                .unwrap();
+        let mut state = xkb::State::new(&keymap);
+        let mut mods = ModifiersState::default();
+
+        mods.update_with(&state);
+        assert_eq!(mods.layout, 0);
+
+        state.update_mask(0, 0, 0, 0, 0, 1);
+        mods.update_with(&state);
+        assert_eq!(mods.layout, 1);
+        assert_eq!(mods.serialized.layout_effective, 1);
+
+        state.update_mask(0, 0, 0, 0, 0, 0);
+        mods.update_with(&state);
+        assert_eq!(mods.layout, 0);
+    }
+
+    #[test]
+    fn shortcut_matching() {
+        let (mods, keysym) = crate::shortcut!(ctrl, alt; "t");
+        assert_eq!(keysym, keysyms::KEY_t);
+
+        let current = ModifiersState {
+            ctrl: true,
+            alt: true,
+            caps_lock: true,
+            ..Default::default()
+        };
+        assert!(current.matches_shortcut(Some(mods.ctrl), Some(mods.alt), Some(mods.shift), Some(mods.logo)));
+        assert!(current.matches_shortcut(Some(true), None, None, None));
+        assert!(!current.matches_shortcut(Some(true), Some(true), Some(true), None));
+        assert!(!current.matches_shortcut(None, Some(false), None, None));
+    }
+}
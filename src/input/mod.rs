@@ -97,6 +97,11 @@
 //! These methods return handles that can be cloned and sent across thread, so you can keep one around
 //! in your event-handling code to forward inputs to your clients.
 //!
+//! A seat has at most one pointer, as every `wl_pointer` a client creates for a `wl_seat`
+//! represents the same pointer of that seat. Compositors wanting multiple independent pointers,
+//! e.g. for multi-user setups, should create a [`Seat`] per pointer with [`SeatState::new_seat`]
+//! and route the events of each input device to its seat.
+//!
 
 use std::{
     fmt,
@@ -126,12 +131,19 @@ pub trait SeatHandler: Sized {
 
     /// Callback that will be notified whenever a client requests to set a custom cursor image.
     fn cursor_image(&mut self, _seat: &Seat<Self>, _image: CursorImageStatus) {}
+
+    /// Callback that will be notified whenever the next frame of an animated cursor is due.
+    ///
+    /// See [`PointerHandle::set_animated_cursor`].
+    fn cursor_frame(&mut self, _seat: &Seat<Self>, _frame: &pointer::CursorImage) {}
 }
 /// Delegate type for all [Seat] globals.
 ///
 /// Events will be forwarded to an instance of the Seat global.
 pub struct SeatState<D: SeatHandler> {
     pub(crate) seats: Vec<Seat<D>>,
+    #[cfg(feature = "wayland_frontend")]
+    pub(crate) session_lock: Option<Arc<crate::wayland::session_lock::SessionLockFilter>>,
 }
 
 impl<D: SeatHandler> fmt::Debug for SeatState<D>
@@ -189,6 +201,8 @@ pub(crate) struct Inner<D: SeatHandler> {
     pub(crate) global: Option<wayland_server::backend::GlobalId>,
     #[cfg(feature = "wayland_frontend")]
     pub(crate) known_seats: Vec<wayland_server::protocol::wl_seat::WlSeat>,
+    #[cfg(feature = "wayland_frontend")]
+    pub(crate) added_caps: wayland_server::protocol::wl_seat::Capability,
 }
 
 #[cfg(not(feature = "wayland_frontend"))]
@@ -218,6 +232,7 @@ where
             .field("touch", &self.touch)
             .field("global", &self.global)
             .field("known_seats", &self.known_seats)
+            .field("added_caps", &self.added_caps)
             .finish()
     }
 }
@@ -262,7 +277,11 @@ impl<D: SeatHandler> Default for SeatState<D> {
 impl<D: SeatHandler> SeatState<D> {
     /// Create new delegate SeatState
     pub fn new() -> Self {
-        Self { seats: Vec::new() }
+        Self {
+            seats: Vec::new(),
+            #[cfg(feature = "wayland_frontend")]
+            session_lock: None,
+        }
     }
 
     /// Create a new seat
@@ -288,6 +307,8 @@ impl<D: SeatHandler> SeatState<D> {
                 global: None,
                 #[cfg(feature = "wayland_frontend")]
                 known_seats: Vec::new(),
+                #[cfg(feature = "wayland_frontend")]
+                added_caps: wayland_server::protocol::wl_seat::Capability::empty(),
             }),
             user_data_map: UserDataMap::new(),
             log,
@@ -0,0 +1,100 @@
+use std::time::Duration;
+
+use calloop::{
+    timer::{TimeoutAction, Timer},
+    InsertError, LoopHandle,
+};
+
+use crate::{
+    input::SeatHandler,
+    utils::{Buffer, Point, Size},
+};
+
+use super::PointerHandle;
+
+/// Shortest time a single frame of an animated cursor is shown for
+///
+/// Themes may contain frames with a delay of zero, which would otherwise make the
+/// animation timer fire continuously.
+const MIN_FRAME_DURATION: Duration = Duration::from_millis(1);
+
+/// A single image of a compositor provided cursor
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CursorImage {
+    /// Size of the image
+    pub size: Size<i32, Buffer>,
+    /// Location of the hotspot in the image
+    pub hotspot: Point<i32, Buffer>,
+    /// Pixels of the image, in RGBA8888 format
+    pub pixels_rgba: Vec<u8>,
+}
+
+/// A cursor made up of multiple images, as found in animated XCursor themes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnimatedCursor {
+    /// Images of the animation, each with the duration it should be shown for
+    pub frames: Vec<(CursorImage, Duration)>,
+}
+
+impl<D: SeatHandler + 'static> PointerHandle<D> {
+    /// Start animating the given cursor
+    ///
+    /// A timer is inserted into the given event loop, which passes each frame to
+    /// [`SeatHandler::cursor_frame`] once its deadline is reached. After the last frame
+    /// the animation starts over with the first one.
+    ///
+    /// The animation replaces any previously started animation of this pointer and runs until
+    /// [`PointerHandle::stop_animated_cursor`] is called. It is *not* stopped when a client sets
+    /// a cursor image, it is up to the compositor to decide which cursor to display.
+    ///
+    /// Frames are shown for at least one millisecond, shorter durations are clamped.
+    pub fn set_animated_cursor(
+        &self,
+        mut cursor: AnimatedCursor,
+        event_loop: &LoopHandle<'static, D>,
+    ) -> Result<(), InsertError<Timer>> {
+        let generation = {
+            let mut inner = self.inner.lock().unwrap();
+            inner.cursor_animation = inner.cursor_animation.wrapping_add(1);
+            inner.cursor_animation
+        };
+        if cursor.frames.is_empty() {
+            return Ok(());
+        }
+        for (_, duration) in cursor.frames.iter_mut() {
+            *duration = (*duration).max(MIN_FRAME_DURATION);
+        }
+
+        let pointer = self.clone();
+        let mut index = 0;
+        event_loop.insert_source(Timer::immediate(), move |deadline, _, data| {
+            if pointer.inner.lock().unwrap().cursor_animation != generation {
+                return TimeoutAction::Drop;
+            }
+            let seat = match data
+                .seat_state()
+                .seats
+                .iter()
+                .find(|seat| seat.get_pointer().as_ref() == Some(&pointer))
+                .cloned()
+            {
+                Some(seat) => seat,
+                // the pointer was removed from its seat
+                None => return TimeoutAction::Drop,
+            };
+
+            let (frame, duration) = &cursor.frames[index];
+            data.cursor_frame(&seat, frame);
+            index = (index + 1) % cursor.frames.len();
+            TimeoutAction::ToInstant(deadline + *duration)
+        })?;
+
+        Ok(())
+    }
+
+    /// Stop the animation started by [`PointerHandle::set_animated_cursor`], if any
+    pub fn stop_animated_cursor(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.cursor_animation = inner.cursor_animation.wrapping_add(1);
+    }
+}
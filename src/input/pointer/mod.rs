@@ -9,9 +9,12 @@ use crate::{
     backend::input::{Axis, AxisSource, ButtonState},
     input::{Seat, SeatHandler},
     utils::Serial,
-    utils::{IsAlive, Logical, Point},
+    utils::{IsAlive, Logical, Point, Time, Unspecified},
 };
 
+mod animated_cursor;
+pub use animated_cursor::{AnimatedCursor, CursorImage};
+
 mod cursor_image;
 pub use cursor_image::{CursorImageAttributes, CursorImageStatus, CursorImageSurfaceData};
 
@@ -34,6 +37,10 @@ pub struct PointerHandle<D: SeatHandler> {
     pub(crate) known_pointers: Arc<Mutex<Vec<wayland_server::protocol::wl_pointer::WlPointer>>>,
     #[cfg(feature = "wayland_frontend")]
     pub(crate) known_relative_pointers: Arc<Mutex<Vec<wayland_protocols::wp::relative_pointer::zv1::server::zwp_relative_pointer_v1::ZwpRelativePointerV1>>>,
+    #[cfg(feature = "wayland_frontend")]
+    pub(crate) known_swipe_gestures: Arc<Mutex<Vec<wayland_protocols::wp::pointer_gestures::zv1::server::zwp_pointer_gesture_swipe_v1::ZwpPointerGestureSwipeV1>>>,
+    #[cfg(feature = "wayland_frontend")]
+    pub(crate) known_pinch_gestures: Arc<Mutex<Vec<wayland_protocols::wp::pointer_gestures::zv1::server::zwp_pointer_gesture_pinch_v1::ZwpPointerGesturePinchV1>>>,
 }
 
 #[cfg(not(feature = "wayland_frontend"))]
@@ -58,6 +65,8 @@ where
             .field("inner", &self.inner)
             .field("known_pointers", &self.known_pointers)
             .field("known_relative_pointers", &self.known_relative_pointers)
+            .field("known_swipe_gestures", &self.known_swipe_gestures)
+            .field("known_pinch_gestures", &self.known_pinch_gestures)
             .finish()
     }
 }
@@ -70,6 +79,10 @@ impl<D: SeatHandler> Clone for PointerHandle<D> {
             known_pointers: self.known_pointers.clone(),
             #[cfg(feature = "wayland_frontend")]
             known_relative_pointers: self.known_relative_pointers.clone(),
+            #[cfg(feature = "wayland_frontend")]
+            known_swipe_gestures: self.known_swipe_gestures.clone(),
+            #[cfg(feature = "wayland_frontend")]
+            known_pinch_gestures: self.known_pinch_gestures.clone(),
         }
     }
 }
@@ -95,6 +108,18 @@ where
     fn button(&self, seat: &Seat<D>, data: &mut D, event: &ButtonEvent);
     /// A pointer of a given seat scrolled on an axis
     fn axis(&self, seat: &Seat<D>, data: &mut D, frame: AxisFrame);
+    /// A pointer of a given seat performed a swipe gesture over this handler
+    ///
+    /// The default implementation ignores the gesture.
+    fn gesture_swipe(&self, seat: &Seat<D>, data: &mut D, event: &GestureSwipeEvent) {
+        let _ = (seat, data, event);
+    }
+    /// A pointer of a given seat performed a pinch gesture over this handler
+    ///
+    /// The default implementation ignores the gesture.
+    fn gesture_pinch(&self, seat: &Seat<D>, data: &mut D, event: &GesturePinchEvent) {
+        let _ = (seat, data, event);
+    }
     /// A pointer of a given seat left this handler
     fn leave(&self, seat: &Seat<D>, data: &mut D, serial: Serial, time: u32);
 }
@@ -107,6 +132,10 @@ impl<D: SeatHandler + 'static> PointerHandle<D> {
             known_pointers: Arc::new(Mutex::new(Vec::new())),
             #[cfg(feature = "wayland_frontend")]
             known_relative_pointers: Arc::new(Mutex::new(Vec::new())),
+            #[cfg(feature = "wayland_frontend")]
+            known_swipe_gestures: Arc::new(Mutex::new(Vec::new())),
+            #[cfg(feature = "wayland_frontend")]
+            known_pinch_gestures: Arc::new(Mutex::new(Vec::new())),
         }
     }
 
@@ -173,6 +202,8 @@ impl<D: SeatHandler + 'static> PointerHandle<D> {
         let mut inner = self.inner.lock().unwrap();
         inner.pending_focus = focus.clone();
         let seat = self.get_seat(data);
+        #[cfg(feature = "wayland_frontend")]
+        crate::wayland::idle_notify::notify_activity(&seat);
         inner.with_grab(&seat, move |mut handle, grab| {
             grab.motion(data, &mut handle, focus, event);
         });
@@ -192,6 +223,8 @@ impl<D: SeatHandler + 'static> PointerHandle<D> {
         let mut inner = self.inner.lock().unwrap();
         inner.pending_focus = focus.clone();
         let seat = self.get_seat(data);
+        #[cfg(feature = "wayland_frontend")]
+        crate::wayland::idle_notify::notify_activity(&seat);
         inner.with_grab(&seat, move |mut handle, grab| {
             grab.relative_motion(data, &mut handle, focus, event);
         });
@@ -212,6 +245,8 @@ impl<D: SeatHandler + 'static> PointerHandle<D> {
             }
         }
         let seat = self.get_seat(data);
+        #[cfg(feature = "wayland_frontend")]
+        crate::wayland::idle_notify::notify_activity(&seat);
         inner.with_grab(&seat, |mut handle, grab| {
             grab.button(data, &mut handle, event);
         });
@@ -220,13 +255,111 @@ impl<D: SeatHandler + 'static> PointerHandle<D> {
     /// Start an axis frame
     ///
     /// A single frame will group multiple scroll events as if they happened in the same instance.
-    pub fn axis(&self, data: &mut D, details: AxisFrame) {
+    ///
+    /// If smooth scroll conversion was enabled with [`set_smooth_scroll_threshold`](PointerHandle::set_smooth_scroll_threshold),
+    /// discrete steps are added to continuous frames without any.
+    pub fn axis(&self, data: &mut D, mut details: AxisFrame) {
         let seat = self.get_seat(data);
-        self.inner.lock().unwrap().with_grab(&seat, |mut handle, grab| {
+        #[cfg(feature = "wayland_frontend")]
+        crate::wayland::idle_notify::notify_activity(&seat);
+        let mut inner = self.inner.lock().unwrap();
+        if let Some((horizontal, vertical)) = inner.smooth_scroll.as_mut() {
+            if details.discrete.is_none()
+                && matches!(
+                    details.source,
+                    Some(AxisSource::Finger) | Some(AxisSource::Continuous)
+                )
+            {
+                let steps = (
+                    horizontal.accumulate(details.axis.0),
+                    vertical.accumulate(details.axis.1),
+                );
+                if steps != (0, 0) {
+                    details.discrete = Some(steps);
+                }
+            }
+            if details.stop.0 {
+                horizontal.reset();
+            }
+            if details.stop.1 {
+                vertical.reset();
+            }
+        }
+        inner.axis_consumed = false;
+        inner.with_grab(&seat, |mut handle, grab| {
             grab.axis(data, &mut handle, details);
         });
     }
 
+    /// Enable or disable the conversion of continuous scrolling into discrete steps
+    ///
+    /// With a threshold set, [`axis`](PointerHandle::axis) frames of a [`AxisSource::Finger`]
+    /// or [`AxisSource::Continuous`] source get a discrete step added for every `threshold_px`
+    /// scrolled, for clients only reacting to discrete scroll events.
+    /// `None` disables the conversion, which is the default.
+    pub fn set_smooth_scroll_threshold(&self, threshold_px: Option<f64>) {
+        self.inner.lock().unwrap().smooth_scroll = threshold_px.map(|threshold_px| {
+            (
+                SmoothScrollAccumulator::new(threshold_px),
+                SmoothScrollAccumulator::new(threshold_px),
+            )
+        });
+    }
+
+    /// Notify about a touchpad swipe gesture
+    ///
+    /// The [`GesturePhase::Begin`] event is sent to the currently focused surface,
+    /// all following events of the same gesture are sent to that surface as well,
+    /// even if the pointer focus changes in the meantime. Events that are not part
+    /// of a started gesture are ignored.
+    ///
+    /// Gesture events are not intercepted by pointer grabs.
+    pub fn send_swipe_gesture(&self, data: &mut D, event: &GestureSwipeEvent) {
+        let seat = self.get_seat(data);
+        #[cfg(feature = "wayland_frontend")]
+        crate::wayland::idle_notify::notify_activity(&seat);
+        let mut inner = self.inner.lock().unwrap();
+        let target = match event.phase {
+            GesturePhase::Begin => {
+                inner.swipe_focus = inner.focus.as_ref().map(|(focus, _)| focus.clone());
+                inner.swipe_focus.clone()
+            }
+            GesturePhase::Update => inner.swipe_focus.clone(),
+            GesturePhase::End => inner.swipe_focus.take(),
+        };
+        drop(inner);
+        if let Some(target) = target {
+            target.gesture_swipe(&seat, data, event);
+        }
+    }
+
+    /// Notify about a touchpad pinch gesture
+    ///
+    /// The [`GesturePhase::Begin`] event is sent to the currently focused surface,
+    /// all following events of the same gesture are sent to that surface as well,
+    /// even if the pointer focus changes in the meantime. Events that are not part
+    /// of a started gesture are ignored.
+    ///
+    /// Gesture events are not intercepted by pointer grabs.
+    pub fn send_pinch_gesture(&self, data: &mut D, event: &GesturePinchEvent) {
+        let seat = self.get_seat(data);
+        #[cfg(feature = "wayland_frontend")]
+        crate::wayland::idle_notify::notify_activity(&seat);
+        let mut inner = self.inner.lock().unwrap();
+        let target = match event.phase {
+            GesturePhase::Begin => {
+                inner.pinch_focus = inner.focus.as_ref().map(|(focus, _)| focus.clone());
+                inner.pinch_focus.clone()
+            }
+            GesturePhase::Update => inner.pinch_focus.clone(),
+            GesturePhase::End => inner.pinch_focus.take(),
+        };
+        drop(inner);
+        if let Some(target) = target {
+            target.gesture_pinch(&seat, data, event);
+        }
+    }
+
     /// Access the current location of this pointer in the global space
     pub fn current_location(&self) -> Point<f64, Logical> {
         self.inner.lock().unwrap().location
@@ -312,6 +445,41 @@ impl<'a, D: SeatHandler + 'static> PointerInnerHandle<'a, D> {
         &self.inner.pressed_buttons
     }
 
+    /// Save the current focus of this pointer on the focus stack
+    ///
+    /// Grabs can use this to remember the focus before they redirect it, e.g. when
+    /// nesting popup grabs, and restore it later with [`pop_focus`](PointerInnerHandle::pop_focus).
+    /// The focus stack is cleared once the grab is unset.
+    pub fn push_focus(&mut self) {
+        let focus = self.inner.focus.clone();
+        self.inner.focus_stack.push(focus);
+    }
+
+    /// Restore the last focus saved with [`push_focus`](PointerInnerHandle::push_focus)
+    ///
+    /// If the saved focus target is no longer alive, the pointer loses its focus.
+    /// Returns the restored focus, if any. Does nothing if the focus stack is empty.
+    pub fn pop_focus(
+        &mut self,
+        data: &mut D,
+        serial: Serial,
+        time: u32,
+    ) -> Option<<D as SeatHandler>::PointerFocus> {
+        let focus = self.inner.focus_stack.pop()?.filter(|(focus, _)| focus.alive());
+        let location = self.inner.location;
+        self.inner.motion(
+            data,
+            self.seat,
+            focus.clone(),
+            &MotionEvent {
+                location,
+                serial,
+                time: Time::from_millis(time),
+            },
+        );
+        focus.map(|(focus, _)| focus)
+    }
+
     /// Notify that the pointer moved
     ///
     /// You provide the new location of the pointer, in the form of:
@@ -360,19 +528,41 @@ impl<'a, D: SeatHandler + 'static> PointerInnerHandle<'a, D> {
     ///
     /// This will internally send the appropriate axis events to the client
     /// objects matching with the currently focused surface.
+    ///
+    /// Does nothing if the current axis frame was consumed with
+    /// [`consume_axis`](PointerInnerHandle::consume_axis).
     pub fn axis(&mut self, data: &mut D, details: AxisFrame) {
+        if self.inner.axis_consumed {
+            return;
+        }
         if let Some((focused, _)) = self.inner.focus.as_mut() {
             focused.axis(self.seat, data, details);
         }
     }
+
+    /// Mark the current axis frame as handled by the grab
+    ///
+    /// Grabs handling scroll events themselves (e.g. for a compositor-drawn overlay) can use this
+    /// to make sure the frame is not forwarded to the focused client, even if it is passed on to
+    /// another grab logic afterwards. Any later [`axis`](PointerInnerHandle::axis) call for the
+    /// same frame does nothing. The flag is reset with the next [`PointerHandle::axis`] call.
+    pub fn consume_axis(&mut self) {
+        self.inner.axis_consumed = true;
+    }
 }
 
 pub(crate) struct PointerInternal<D: SeatHandler> {
     pub(crate) focus: Option<(<D as SeatHandler>::PointerFocus, Point<i32, Logical>)>,
     pending_focus: Option<(<D as SeatHandler>::PointerFocus, Point<i32, Logical>)>,
+    focus_stack: Vec<Option<(<D as SeatHandler>::PointerFocus, Point<i32, Logical>)>>,
     location: Point<f64, Logical>,
     grab: GrabStatus<D>,
     pressed_buttons: Vec<u32>,
+    cursor_animation: usize,
+    swipe_focus: Option<<D as SeatHandler>::PointerFocus>,
+    pinch_focus: Option<<D as SeatHandler>::PointerFocus>,
+    smooth_scroll: Option<(SmoothScrollAccumulator, SmoothScrollAccumulator)>,
+    axis_consumed: bool,
 }
 
 // image_callback does not implement debug, so we have to impl Debug manually
@@ -384,9 +574,15 @@ where
         f.debug_struct("PointerInternal")
             .field("focus", &self.focus)
             .field("pending_focus", &self.pending_focus)
+            .field("focus_stack", &self.focus_stack)
             .field("location", &self.location)
             .field("grab", &self.grab)
             .field("pressed_buttons", &self.pressed_buttons)
+            .field("cursor_animation", &self.cursor_animation)
+            .field("swipe_focus", &self.swipe_focus)
+            .field("pinch_focus", &self.pinch_focus)
+            .field("smooth_scroll", &self.smooth_scroll)
+            .field("axis_consumed", &self.axis_consumed)
             .field("image_callback", &"...")
             .finish()
     }
@@ -397,9 +593,15 @@ impl<D: SeatHandler + 'static> PointerInternal<D> {
         Self {
             focus: None,
             pending_focus: None,
+            focus_stack: Vec::new(),
             location: (0.0, 0.0).into(),
             grab: GrabStatus::None,
             pressed_buttons: Vec::new(),
+            cursor_animation: 0,
+            swipe_focus: None,
+            pinch_focus: None,
+            smooth_scroll: None,
+            axis_consumed: false,
         }
     }
 
@@ -422,7 +624,7 @@ impl<D: SeatHandler + 'static> PointerInternal<D> {
                 &MotionEvent {
                     location,
                     serial,
-                    time: 0,
+                    time: Time::from_millis(0),
                 },
             );
         }
@@ -430,6 +632,7 @@ impl<D: SeatHandler + 'static> PointerInternal<D> {
 
     fn unset_grab(&mut self, data: &mut D, seat: &Seat<D>, serial: Serial, time: u32) {
         self.grab = GrabStatus::None;
+        self.focus_stack.clear();
         // restore the focus
         let location = self.location;
         let focus = self.pending_focus.clone();
@@ -440,7 +643,7 @@ impl<D: SeatHandler + 'static> PointerInternal<D> {
             &MotionEvent {
                 location,
                 serial,
-                time,
+                time: Time::from_millis(time),
             },
         );
     }
@@ -464,7 +667,7 @@ impl<D: SeatHandler + 'static> PointerInternal<D> {
         }
         if leave {
             if let Some((focused, _)) = self.focus.as_mut() {
-                focused.leave(seat, data, event.serial, event.time);
+                focused.leave(seat, data, event.serial, event.time.as_millis_u32());
             }
             self.focus = None;
             data.cursor_image(seat, CursorImageStatus::Default);
@@ -515,6 +718,7 @@ impl<D: SeatHandler + 'static> PointerInternal<D> {
                 if let Some((ref focus, _)) = handler.start_data().focus {
                     if !focus.alive() {
                         self.grab = GrabStatus::None;
+                        self.focus_stack.clear();
                         f(PointerInnerHandle { inner: self, seat }, &mut DefaultGrab);
                         return;
                     }
@@ -549,8 +753,8 @@ pub struct MotionEvent {
     pub location: Point<f64, Logical>,
     /// Serial of the event
     pub serial: Serial,
-    /// Timestamp of the event, with millisecond granularity
-    pub time: u32,
+    /// Timestamp of the event
+    pub time: Time<Unspecified>,
 }
 
 /// Relative pointer motion event
@@ -564,6 +768,59 @@ pub struct RelativeMotionEvent {
     pub utime: u64,
 }
 
+/// Phase of a touchpad gesture
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GesturePhase {
+    /// The gesture started
+    Begin,
+    /// The gesture changed
+    Update,
+    /// The gesture ended or was cancelled
+    End,
+}
+
+/// Touchpad swipe gesture event
+#[derive(Debug, Clone, Copy)]
+pub struct GestureSwipeEvent {
+    /// Phase of the gesture this event belongs to
+    pub phase: GesturePhase,
+    /// Serial of the event, only used for the begin and end phases
+    pub serial: Serial,
+    /// Timestamp with millisecond granularity, with an undefined base.
+    pub time: u32,
+    /// Number of fingers, only used for the begin phase
+    pub fingers: u32,
+    /// Delta of the x coordinate, only used for the update phase
+    pub dx: f64,
+    /// Delta of the y coordinate, only used for the update phase
+    pub dy: f64,
+    /// Whether the gesture was cancelled, only used for the end phase
+    pub cancelled: bool,
+}
+
+/// Touchpad pinch gesture event
+#[derive(Debug, Clone, Copy)]
+pub struct GesturePinchEvent {
+    /// Phase of the gesture this event belongs to
+    pub phase: GesturePhase,
+    /// Serial of the event, only used for the begin and end phases
+    pub serial: Serial,
+    /// Timestamp with millisecond granularity, with an undefined base.
+    pub time: u32,
+    /// Number of fingers, only used for the begin phase
+    pub fingers: u32,
+    /// Delta of the x coordinate, only used for the update phase
+    pub dx: f64,
+    /// Delta of the y coordinate, only used for the update phase
+    pub dy: f64,
+    /// Scale relative to the initial finger position, only used for the update phase
+    pub scale: f64,
+    /// Angle in degrees clockwise relative to the previous event, only used for the update phase
+    pub rotation: f64,
+    /// Whether the gesture was cancelled, only used for the end phase
+    pub cancelled: bool,
+}
+
 /// Pointer button event
 
 /// Mouse button click and release notifications.
@@ -572,8 +829,8 @@ pub struct RelativeMotionEvent {
 pub struct ButtonEvent {
     /// Serial of the event
     pub serial: Serial,
-    /// Timestamp with millisecond granularity, with an undefined base.
-    pub time: u32,
+    /// Timestamp of the event
+    pub time: Time<Unspecified>,
     /// Button that produced the event
     ///
     /// The button is a button code as defined in the
@@ -602,11 +859,13 @@ pub struct AxisFrame {
     /// Source of the axis event, if known
     pub source: Option<AxisSource>,
     /// Time of the axis event
-    pub time: u32,
+    pub time: Time<Unspecified>,
     /// Raw scroll value per axis of the event
     pub axis: (f64, f64),
     /// Discrete representation of scroll value per axis, if available
     pub discrete: Option<(i32, i32)>,
+    /// High-resolution scroll value per axis in fractions of 1/120 of a discrete step, if available
+    pub high_res: Option<(i32, i32)>,
     /// If the axis is considered having stoped movement
     ///
     /// Only useful in conjunction of AxisSource::Finger events
@@ -615,12 +874,13 @@ pub struct AxisFrame {
 
 impl AxisFrame {
     /// Create a new frame of axis events
-    pub fn new(time: u32) -> Self {
+    pub fn new(time: Time<Unspecified>) -> Self {
         AxisFrame {
             source: None,
             time,
             axis: (0.0, 0.0),
             discrete: None,
+            high_res: None,
             stop: (false, false),
         }
     }
@@ -655,6 +915,24 @@ impl AxisFrame {
         self
     }
 
+    /// Specify the high-resolution scroll value additionally to the computed value.
+    ///
+    /// The value is given in fractions of 1/120 of a discrete step, a value of 120 equals
+    /// one step as given to [`discrete`](AxisFrame::discrete). Clients supporting
+    /// `wl_pointer` version 8 receive this value instead of the discrete steps.
+    pub fn value120(mut self, axis: Axis, value: i32) -> Self {
+        let high_res = self.high_res.get_or_insert_with(Default::default);
+        match axis {
+            Axis::Horizontal => {
+                high_res.0 = value;
+            }
+            Axis::Vertical => {
+                high_res.1 = value;
+            }
+        };
+        self
+    }
+
     /// The actual scroll value. This event is the only required one, but can also
     /// be send multiple times. The values off one frame will be accumulated by the client.
     pub fn value(mut self, axis: Axis, value: f64) -> Self {
@@ -685,3 +963,58 @@ impl AxisFrame {
         self
     }
 }
+
+/// Converts continuous scroll values into discrete scroll steps
+///
+/// Scroll values are accumulated until they cross the threshold,
+/// every full threshold crossed results in one discrete step.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SmoothScrollAccumulator {
+    threshold_px: f64,
+    accumulated: f64,
+}
+
+impl SmoothScrollAccumulator {
+    /// Create a new accumulator emitting one step for every `threshold_px` scrolled
+    pub fn new(threshold_px: f64) -> Self {
+        SmoothScrollAccumulator {
+            threshold_px,
+            accumulated: 0.0,
+        }
+    }
+
+    /// Add a scroll value and return the number of discrete steps it resulted in
+    ///
+    /// The remainder not making up a full step is kept for the next call.
+    pub fn accumulate(&mut self, delta: f64) -> i32 {
+        self.accumulated += delta;
+        if self.threshold_px <= 0.0 || self.accumulated.abs() < self.threshold_px {
+            return 0;
+        }
+        let steps = (self.accumulated / self.threshold_px).trunc();
+        self.accumulated -= steps * self.threshold_px;
+        steps as i32
+    }
+
+    /// Discard the accumulated scroll value, e.g. when scrolling stopped
+    pub fn reset(&mut self) {
+        self.accumulated = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SmoothScrollAccumulator;
+
+    #[test]
+    fn smooth_scroll_accumulation() {
+        let mut accumulator = SmoothScrollAccumulator::new(10.0);
+        assert_eq!(accumulator.accumulate(6.0), 0);
+        assert_eq!(accumulator.accumulate(6.0), 1);
+        assert_eq!(accumulator.accumulate(25.0), 2);
+        assert_eq!(accumulator.accumulate(-4.0), 0);
+        assert_eq!(accumulator.accumulate(-13.0), -1);
+        accumulator.reset();
+        assert_eq!(accumulator.accumulate(9.0), 0);
+    }
+}
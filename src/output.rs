@@ -60,7 +60,10 @@ use slog::{info, o};
 #[cfg(feature = "wayland_frontend")]
 use crate::wayland::output::xdg::XdgOutput;
 #[cfg(feature = "wayland_frontend")]
-use wayland_server::{backend::WeakHandle, protocol::wl_output::WlOutput};
+use wayland_server::{
+    backend::WeakHandle,
+    protocol::{wl_output::WlOutput, wl_surface::WlSurface},
+};
 
 use crate::utils::{user_data::UserDataMap, Logical, Physical, Point, Raw, Size, Transform};
 
@@ -177,6 +180,8 @@ pub(crate) struct Inner {
     pub(crate) handle: Option<WeakHandle>,
     #[cfg(feature = "wayland_frontend")]
     pub(crate) xdg_output: Option<XdgOutput>,
+    #[cfg(feature = "wayland_frontend")]
+    pub(crate) surfaces: Vec<wayland_server::Weak<WlSurface>>,
 
     #[allow(dead_code)]
     pub(crate) log: ::slog::Logger,
@@ -230,6 +235,8 @@ impl Output {
                 preferred_mode: None,
                 #[cfg(feature = "wayland_frontend")]
                 xdg_output: None,
+                #[cfg(feature = "wayland_frontend")]
+                surfaces: Vec::new(),
                 log,
             }),
             UserDataMap::default(),
@@ -264,8 +271,20 @@ impl Output {
     }
 
     /// Returns the preferred mode of the output
+    ///
+    /// This is the mode set with [`Output::set_preferred`], usually the mode marked
+    /// as preferred by the display (e.g. `DRM_MODE_TYPE_PREFERRED` for drm connectors).
+    /// If no mode was set as preferred, the mode with the highest resolution and refresh rate
+    /// (by the product of width, height and refresh rate) among all known modes is returned.
     pub fn preferred_mode(&self) -> Option<Mode> {
-        self.inner.0.lock().unwrap().preferred_mode
+        let inner = self.inner.0.lock().unwrap();
+        inner.preferred_mode.or_else(|| {
+            inner
+                .modes
+                .iter()
+                .copied()
+                .max_by_key(|mode| mode.size.w as i64 * mode.size.h as i64 * mode.refresh as i64)
+        })
     }
 
     /// Returns the currently advertised transformation of the output
@@ -298,6 +317,23 @@ impl Output {
         self.inner.0.lock().unwrap().physical.clone()
     }
 
+    /// Change the physical size of the output, in millimeters
+    ///
+    /// If the size changed, an updated `geometry` event is sent to all bound `wl_output`s.
+    pub fn set_physical_size(&self, width_mm: i32, height_mm: i32) {
+        let size = Size::from((width_mm, height_mm));
+        {
+            let mut inner = self.inner.0.lock().unwrap();
+            if inner.physical.size == size {
+                return;
+            }
+            inner.physical.size = size;
+        }
+
+        #[cfg(feature = "wayland_frontend")]
+        self.wl_send_geometry();
+    }
+
     /// Returns the currently advertised modes of the output
     pub fn modes(&self) -> Vec<Mode> {
         self.inner.0.lock().unwrap().modes.clone()
@@ -1,4 +1,9 @@
-use std::{cmp::Ordering, marker::PhantomData, mem::MaybeUninit, time::Duration};
+use std::{
+    cmp::Ordering,
+    marker::PhantomData,
+    mem::MaybeUninit,
+    time::{Duration, Instant},
+};
 
 /// Marker for clock source that never returns a negative [`Time`]
 pub trait NonNegativeClockSource: ClockSource {}
@@ -37,6 +42,13 @@ impl ClockSource for Realtime {
     }
 }
 
+/// Clock with an unspecified base
+///
+/// Used for hardware timestamps, e.g. of input events, which can only be compared
+/// with other timestamps of the same source. No [`Clock`] can be created for it.
+#[derive(Debug)]
+pub struct Unspecified;
+
 /// Id for a clock according to unix clockid_t
 pub trait ClockSource {
     /// Gets the id of the clock source
@@ -74,6 +86,9 @@ impl<Kind: ClockSource> Clock<Kind> {
     }
 }
 
+/// A point in time of the [`Monotonic`] clock
+pub type MonotonicTime = Time<Monotonic>;
+
 /// A point in time for a clock with a specific kind
 pub struct Time<Kind> {
     tp: libc::timespec,
@@ -85,6 +100,59 @@ impl<Kind> Time<Kind> {
     pub fn duration_since(&self, later: Time<Kind>) -> Duration {
         saturating_sub_timespec(later.tp, self.tp).unwrap_or(Duration::ZERO)
     }
+
+    /// Gets the duration between an earlier time and self
+    pub fn elapsed_since(&self, earlier: Time<Kind>) -> Duration {
+        earlier.duration_since(*self)
+    }
+
+    /// Returns the time in milliseconds, truncated to 32 bits like the timestamps of wayland events
+    pub fn as_millis_u32(&self) -> u32 {
+        self.as_micros_u64().wrapping_div(1000) as u32
+    }
+
+    /// Returns the time in microseconds
+    pub fn as_micros_u64(&self) -> u64 {
+        (self.tp.tv_sec as u64)
+            .wrapping_mul(1_000_000)
+            .wrapping_add(self.tp.tv_nsec as u64 / 1000)
+    }
+}
+
+impl Time<Unspecified> {
+    /// Creates a timestamp from microseconds with an unspecified base
+    pub fn from_micros(micros: u64) -> Self {
+        timespec_from_duration(Duration::from_micros(micros)).into()
+    }
+
+    /// Creates a timestamp from milliseconds with an unspecified base
+    pub fn from_millis(millis: u32) -> Self {
+        timespec_from_duration(Duration::from_millis(millis as u64)).into()
+    }
+}
+
+impl From<Time<Monotonic>> for Time<Unspecified> {
+    fn from(time: Time<Monotonic>) -> Self {
+        time.tp.into()
+    }
+}
+
+impl TryFrom<Instant> for Time<Monotonic> {
+    type Error = std::io::Error;
+
+    fn try_from(instant: Instant) -> Result<Self, Self::Error> {
+        // `Instant` is based on the monotonic clock, but offers no way to access its value,
+        // so we have to compute it relative to the current time.
+        let now = Instant::now();
+        let tp = clock_get_time(libc::CLOCK_MONOTONIC)?;
+        let now_tp = Duration::new(tp.tv_sec as u64, tp.tv_nsec as u32);
+        let tp = if instant <= now {
+            now_tp.saturating_sub(now - instant)
+        } else {
+            now_tp.saturating_add(instant - now)
+        };
+        Ok(tp.into())
+    }
 }
 
 impl<Kind> Clone for Time<Kind> {
@@ -143,17 +211,17 @@ impl<Kind> Ord for Time<Kind> {
 
 impl<Kind: NonNegativeClockSource> From<Duration> for Time<Kind> {
     fn from(tp: Duration) -> Self {
-        let tp = libc::timespec {
-            tv_sec: tp.as_secs() as libc::time_t,
-            #[cfg(all(target_arch = "x86_64", target_pointer_width = "32"))]
-            tv_nsec: tp.subsec_nanos() as i64,
-            #[cfg(not(all(target_arch = "x86_64", target_pointer_width = "32")))]
-            tv_nsec: tp.subsec_nanos() as std::os::raw::c_long,
-        };
-        Time {
-            tp,
-            _kind: PhantomData,
-        }
+        timespec_from_duration(tp).into()
+    }
+}
+
+fn timespec_from_duration(tp: Duration) -> libc::timespec {
+    libc::timespec {
+        tv_sec: tp.as_secs() as libc::time_t,
+        #[cfg(all(target_arch = "x86_64", target_pointer_width = "32"))]
+        tv_nsec: tp.subsec_nanos() as i64,
+        #[cfg(not(all(target_arch = "x86_64", target_pointer_width = "32")))]
+        tv_nsec: tp.subsec_nanos() as std::os::raw::c_long,
     }
 }
 
@@ -204,9 +272,9 @@ fn clock_get_time(clk_id: libc::clockid_t) -> Result<libc::timespec, std::io::Er
 
 #[cfg(test)]
 mod test {
-    use std::time::Duration;
+    use std::time::{Duration, Instant};
 
-    use crate::utils::{Boottime, Clock, Monotonic, Time};
+    use crate::utils::{Boottime, Clock, Monotonic, MonotonicTime, Time, Unspecified};
 
     #[test]
     fn monotonic() {
@@ -223,4 +291,21 @@ mod test {
         let zero = Time::<Boottime>::from(Duration::ZERO);
         assert_eq!(zero.duration_since(now), now.into());
     }
+
+    #[test]
+    fn from_instant() {
+        let clock_source: Clock<Monotonic> = Clock::new().unwrap();
+        let before = clock_source.now();
+        let time = MonotonicTime::try_from(Instant::now()).unwrap();
+        let after = clock_source.now();
+        assert!(before <= time && time <= after);
+        assert!(time.elapsed_since(before) <= after.elapsed_since(before));
+    }
+
+    #[test]
+    fn unspecified_millis() {
+        let time = Time::<Unspecified>::from_millis(u32::MAX);
+        assert_eq!(time.as_millis_u32(), u32::MAX);
+        assert_eq!(time.as_micros_u64(), u32::MAX as u64 * 1000);
+    }
 }
@@ -1612,6 +1612,84 @@ impl From<Transform> for WlTransform {
     }
 }
 
+#[cfg(feature = "bincode")]
+mod bincode_impls {
+    use bincode::{
+        de::Decoder,
+        enc::Encoder,
+        error::{DecodeError, EncodeError},
+        Decode, Encode,
+    };
+
+    use super::{Coordinate, Point, Rectangle, Scale, Size};
+
+    impl<N: Coordinate + Encode> Encode for Scale<N> {
+        fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+            self.x.encode(encoder)?;
+            self.y.encode(encoder)
+        }
+    }
+
+    impl<N: Coordinate + Decode> Decode for Scale<N> {
+        fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+            Ok(Scale {
+                x: N::decode(decoder)?,
+                y: N::decode(decoder)?,
+            })
+        }
+    }
+
+    impl<N: Encode, Kind> Encode for Point<N, Kind> {
+        fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+            self.x.encode(encoder)?;
+            self.y.encode(encoder)
+        }
+    }
+
+    impl<N: Decode, Kind> Decode for Point<N, Kind> {
+        fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+            Ok(Point {
+                x: N::decode(decoder)?,
+                y: N::decode(decoder)?,
+                _kind: std::marker::PhantomData,
+            })
+        }
+    }
+
+    impl<N: Encode, Kind> Encode for Size<N, Kind> {
+        fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+            self.w.encode(encoder)?;
+            self.h.encode(encoder)
+        }
+    }
+
+    impl<N: Decode, Kind> Decode for Size<N, Kind> {
+        fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+            Ok(Size {
+                w: N::decode(decoder)?,
+                h: N::decode(decoder)?,
+                _kind: std::marker::PhantomData,
+            })
+        }
+    }
+
+    impl<N: Encode, Kind> Encode for Rectangle<N, Kind> {
+        fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+            self.loc.encode(encoder)?;
+            self.size.encode(encoder)
+        }
+    }
+
+    impl<N: Decode, Kind> Decode for Rectangle<N, Kind> {
+        fn decode<D: Decoder>(decoder: &mut D) -> Result<Self, DecodeError> {
+            Ok(Rectangle {
+                loc: Point::decode(decoder)?,
+                size: Size::decode(decoder)?,
+            })
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::{Logical, Rectangle, Size, Transform};
@@ -22,11 +22,15 @@ pub use self::geometry::{
     Buffer, Coordinate, Logical, Physical, Point, Raw, Rectangle, Scale, Size, Transform,
 };
 
+mod region;
+pub use self::region::Region;
+
 mod serial;
 pub use serial::*;
 
 mod clock;
 pub use clock::*;
+pub mod time;
 
 /// This resource is not managed by Smithay
 #[derive(Debug)]
@@ -0,0 +1,191 @@
+use std::fmt;
+
+use super::Rectangle;
+
+/// An area described by a set of non-overlapping rectangles
+///
+/// The rectangles are kept sorted from top to bottom and left to right.
+pub struct Region<C> {
+    rects: Vec<Rectangle<i32, C>>,
+}
+
+impl<C> Region<C> {
+    /// Create an empty region
+    pub fn new() -> Self {
+        Region { rects: Vec::new() }
+    }
+
+    /// Add a rectangle to this region
+    pub fn add(&mut self, rect: Rectangle<i32, C>) {
+        if is_empty(&rect) {
+            return;
+        }
+
+        let mut added = vec![rect];
+        for existing in self.rects.iter().filter(|existing| existing.overlaps(rect)) {
+            added = added
+                .into_iter()
+                .flat_map(|added| added.subtract_rect(*existing))
+                .collect();
+            if added.is_empty() {
+                return;
+            }
+        }
+        self.rects.extend(added);
+        self.sort();
+    }
+
+    /// Subtract a rectangle from this region
+    pub fn subtract(&mut self, rect: Rectangle<i32, C>) {
+        if is_empty(&rect) {
+            return;
+        }
+
+        self.rects = self
+            .rects
+            .drain(..)
+            .flat_map(|existing| existing.subtract_rect(rect))
+            .collect();
+        self.sort();
+    }
+
+    /// Subtract a set of rectangles from this region
+    pub fn subtract_rects(&mut self, rects: impl IntoIterator<Item = Rectangle<i32, C>>) {
+        for rect in rects {
+            if self.is_empty() {
+                return;
+            }
+            self.subtract(rect);
+        }
+    }
+
+    /// Reduce this region to the part inside the given rectangle
+    pub fn intersect_with_rect(&mut self, rect: Rectangle<i32, C>) {
+        self.rects = self
+            .rects
+            .drain(..)
+            .filter_map(|existing| existing.intersection(rect))
+            .filter(|intersection| !is_empty(intersection))
+            .collect();
+    }
+
+    /// Returns whether this region is empty
+    pub fn is_empty(&self) -> bool {
+        self.rects.is_empty()
+    }
+
+    /// The area covered by this region
+    pub fn area(&self) -> usize {
+        self.rects
+            .iter()
+            .map(|rect| rect.size.w as usize * rect.size.h as usize)
+            .sum()
+    }
+
+    /// The rectangles describing this region
+    pub fn rects(&self) -> &[Rectangle<i32, C>] {
+        &self.rects
+    }
+
+    /// Convert this region into its rectangles
+    pub fn into_rects(self) -> Vec<Rectangle<i32, C>> {
+        self.rects
+    }
+
+    fn sort(&mut self) {
+        self.rects.sort_by_key(|rect| (rect.loc.y, rect.loc.x));
+    }
+}
+
+fn is_empty<C>(rect: &Rectangle<i32, C>) -> bool {
+    rect.size.w <= 0 || rect.size.h <= 0
+}
+
+impl<C> Default for Region<C> {
+    fn default() -> Self {
+        Region::new()
+    }
+}
+
+impl<C> Clone for Region<C> {
+    fn clone(&self) -> Self {
+        Region {
+            rects: self.rects.clone(),
+        }
+    }
+}
+
+impl<C> PartialEq for Region<C> {
+    fn eq(&self, other: &Self) -> bool {
+        self.rects == other.rects
+    }
+}
+
+impl<C> fmt::Debug for Region<C>
+where
+    Rectangle<i32, C>: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Region").field("rects", &self.rects).finish()
+    }
+}
+
+impl<C> From<Rectangle<i32, C>> for Region<C> {
+    fn from(rect: Rectangle<i32, C>) -> Self {
+        let mut region = Region::new();
+        region.add(rect);
+        region
+    }
+}
+
+impl<C> FromIterator<Rectangle<i32, C>> for Region<C> {
+    fn from_iter<T: IntoIterator<Item = Rectangle<i32, C>>>(iter: T) -> Self {
+        let mut region = Region::new();
+        region.extend(iter);
+        region
+    }
+}
+
+impl<C> Extend<Rectangle<i32, C>> for Region<C> {
+    fn extend<T: IntoIterator<Item = Rectangle<i32, C>>>(&mut self, iter: T) {
+        for rect in iter {
+            self.add(rect);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Region;
+    use crate::utils::{Logical, Rectangle};
+
+    fn rect(x: i32, y: i32, w: i32, h: i32) -> Rectangle<i32, Logical> {
+        Rectangle::from_loc_and_size((x, y), (w, h))
+    }
+
+    #[test]
+    fn add_overlapping() {
+        let mut region = Region::from(rect(0, 0, 10, 10));
+        region.add(rect(5, 5, 10, 10));
+        region.add(rect(2, 2, 2, 2));
+        assert_eq!(region.area(), 175);
+        for (i, a) in region.rects().iter().enumerate() {
+            for b in &region.rects()[i + 1..] {
+                assert!(a.intersection(*b).map(|i| i.is_empty()).unwrap_or(true));
+            }
+        }
+    }
+
+    #[test]
+    fn subtract_and_intersect() {
+        let mut region = Region::from(rect(0, 0, 10, 10));
+        region.subtract(rect(0, 0, 10, 5));
+        assert_eq!(region.rects(), &[rect(0, 5, 10, 5)]);
+
+        region.intersect_with_rect(rect(5, 0, 10, 10));
+        assert_eq!(region.rects(), &[rect(5, 5, 5, 5)]);
+
+        region.subtract(rect(0, 0, 20, 20));
+        assert!(region.is_empty());
+    }
+}
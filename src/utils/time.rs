@@ -0,0 +1,7 @@
+//! Timestamps used throughout smithay
+//!
+//! [`MonotonicTime`] is a point in time of the monotonic clock, which [`Instant`](std::time::Instant)s
+//! convert into. Input events carry a [`Time<Unspecified>`](Time), as the timestamps reported by input
+//! devices usually have an unspecified base. Monotonic times convert into it.
+
+pub use super::clock::{MonotonicTime, Time, Unspecified};
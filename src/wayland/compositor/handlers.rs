@@ -7,6 +7,7 @@ use wayland_server::{
     protocol::{
         wl_callback::{self, WlCallback},
         wl_compositor::{self, WlCompositor},
+        wl_display,
         wl_region::{self, WlRegion},
         wl_subcompositor::{self, WlSubcompositor},
         wl_subsurface::{self, WlSubsurface},
@@ -15,19 +16,23 @@ use wayland_server::{
     DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource, WEnum,
 };
 
-use crate::utils::{
-    alive_tracker::{AliveTracker, IsAlive},
-    Logical, Point,
+use crate::{
+    backend::renderer::buffer_dimensions,
+    utils::{
+        alive_tracker::{AliveTracker, IsAlive},
+        Logical, Point, Size,
+    },
+    wayland::viewporter::ViewportCachedState,
 };
 
 use super::{
     cache::Cacheable,
     tree::{Location, PrivateSurfaceData},
     AlreadyHasRole, BufferAssignment, CompositorHandler, CompositorState, Damage, Rectangle, RectangleKind,
-    RegionAttributes, SurfaceAttributes,
+    RegionAttributes, SurfaceAttributes, SurfaceData,
 };
 
-use slog::trace;
+use slog::{debug, trace};
 
 /*
  * wl_compositor
@@ -43,14 +48,17 @@ where
     D: 'static,
 {
     fn bind(
-        _state: &mut D,
+        state: &mut D,
         _handle: &DisplayHandle,
-        _client: &wayland_server::Client,
+        client: &wayland_server::Client,
         resource: New<WlCompositor>,
         _global_data: &(),
         data_init: &mut DataInit<'_, D>,
     ) {
-        data_init.init(resource, ());
+        let compositor = data_init.init(resource, ());
+        state
+            .compositor_state()
+            .compositor_bound(client.id(), compositor.version());
     }
 }
 
@@ -98,6 +106,16 @@ where
             _ => unreachable!(),
         }
     }
+
+    fn destroyed(
+        state: &mut D,
+        client: wayland_server::backend::ClientId,
+        _resource: wayland_server::backend::ObjectId,
+        _data: &(),
+    ) {
+        // wl_compositor has no destructor, so it is only destroyed once the client disconnects
+        state.compositor_state().compositor_destroyed(&client);
+    }
 }
 
 /*
@@ -109,6 +127,7 @@ impl Cacheable for SurfaceAttributes {
         SurfaceAttributes {
             buffer: self.buffer.take(),
             buffer_delta: self.buffer_delta.take(),
+            buffer_age: 0,
             buffer_scale: self.buffer_scale,
             buffer_transform: self.buffer_transform,
             damage: std::mem::take(&mut self.damage),
@@ -118,6 +137,12 @@ impl Cacheable for SurfaceAttributes {
         }
     }
     fn merge_into(self, into: &mut Self, _dh: &DisplayHandle) {
+        into.buffer_age = match self.buffer {
+            Some(BufferAssignment::NewBuffer(_)) => 1,
+            Some(BufferAssignment::Removed) => 0,
+            None if into.buffer_age == 0 => 0,
+            None => into.buffer_age.saturating_add(1),
+        };
         if self.buffer.is_some() {
             if let Some(BufferAssignment::NewBuffer(buffer)) =
                 std::mem::replace(&mut into.buffer, self.buffer)
@@ -142,6 +167,55 @@ impl Cacheable for SurfaceAttributes {
     }
 }
 
+/// Clip the pending damage of a surface to the extent of its buffer
+///
+/// Damage exceeding the buffer would otherwise be carried forward and applied to later buffers.
+fn clip_pending_damage(states: &SurfaceData, log: &slog::Logger) {
+    let current_buffer_size = match states.cached_state.current::<SurfaceAttributes>().buffer {
+        Some(BufferAssignment::NewBuffer(ref buffer)) => buffer_dimensions(buffer),
+        _ => None,
+    };
+    let viewport_size = states.cached_state.pending::<ViewportCachedState>().size();
+
+    let mut attributes = states.cached_state.pending::<SurfaceAttributes>();
+    if attributes.damage.is_empty() {
+        return;
+    }
+    let buffer_size = match attributes.buffer {
+        Some(BufferAssignment::NewBuffer(ref buffer)) => buffer_dimensions(buffer),
+        Some(BufferAssignment::Removed) => None,
+        None => current_buffer_size,
+    };
+    let Some(buffer_size) = buffer_size else {
+        // unknown buffer type or no buffer at all, nothing to validate against
+        return;
+    };
+    let surface_size: Size<i32, Logical> = viewport_size.unwrap_or_else(|| {
+        buffer_size.to_logical(attributes.buffer_scale, attributes.buffer_transform.into())
+    });
+
+    let buffer_rect = Rectangle::from_loc_and_size((0, 0), buffer_size);
+    let surface_rect = Rectangle::from_loc_and_size((0, 0), surface_size);
+    let mut clipped = false;
+    attributes.damage.retain_mut(|damage| {
+        let intersection = match damage {
+            Damage::Buffer(rect) => rect.intersection(buffer_rect).map(|intersection| {
+                clipped |= intersection != *rect;
+                *rect = intersection;
+            }),
+            Damage::Surface(rect) => rect.intersection(surface_rect).map(|intersection| {
+                clipped |= intersection != *rect;
+                *rect = intersection;
+            }),
+        };
+        clipped |= intersection.is_none();
+        intersection.is_some()
+    });
+    if clipped {
+        debug!(log, "Clipped damage exceeding the attached buffer"; "buffer_size" => ?buffer_size);
+    }
+}
+
 /// User data for WlSurface
 #[derive(Debug)]
 pub struct SurfaceUserData {
@@ -241,6 +315,10 @@ where
                 });
             }
             wl_surface::Request::Commit => {
+                PrivateSurfaceData::with_states(surface, |states| {
+                    clip_pending_damage(states, &state.compositor_state().log)
+                });
+
                 PrivateSurfaceData::invoke_pre_commit_hooks(handle, surface);
 
                 PrivateSurfaceData::commit(surface, handle);
@@ -294,12 +372,13 @@ where
     }
 
     fn destroyed(
-        _state: &mut D,
+        state: &mut D,
         _client_id: wayland_server::backend::ClientId,
         object_id: wayland_server::backend::ObjectId,
         data: &SurfaceUserData,
     ) {
         data.alive_tracker.destroy_notify();
+        state.compositor_state().surface_destroyed(&object_id, data);
         PrivateSurfaceData::cleanup(data, object_id);
     }
 }
@@ -329,7 +408,7 @@ where
     fn request(
         _state: &mut D,
         _client: &wayland_server::Client,
-        _resource: &WlRegion,
+        resource: &WlRegion,
         request: wl_region::Request,
         data: &RegionUserData,
         _dhandle: &DisplayHandle,
@@ -337,14 +416,16 @@ where
     ) {
         let mut guard = data.inner.lock().unwrap();
         match request {
-            wl_region::Request::Add { x, y, width, height } => guard.rects.push((
-                RectangleKind::Add,
-                Rectangle::from_loc_and_size((x, y), (width, height)),
-            )),
-            wl_region::Request::Subtract { x, y, width, height } => guard.rects.push((
-                RectangleKind::Subtract,
-                Rectangle::from_loc_and_size((x, y), (width, height)),
-            )),
+            wl_region::Request::Add { x, y, width, height } => {
+                if let Some(rect) = region_rect(resource, x, y, width, height) {
+                    guard.rects.push((RectangleKind::Add, rect));
+                }
+            }
+            wl_region::Request::Subtract { x, y, width, height } => {
+                if let Some(rect) = region_rect(resource, x, y, width, height) {
+                    guard.rects.push((RectangleKind::Subtract, rect));
+                }
+            }
             wl_region::Request::Destroy => {
                 // all is handled by our destructor
             }
@@ -353,6 +434,30 @@ where
     }
 }
 
+/// Validate a rectangle of a `wl_region` request
+///
+/// Rectangles whose far edge does not fit into an `i32` would silently wrap
+/// around when their bounds are computed, so they are a protocol error.
+fn region_rect(
+    region: &WlRegion,
+    x: i32,
+    y: i32,
+    width: i32,
+    height: i32,
+) -> Option<Rectangle<i32, Logical>> {
+    if x.checked_add(width).is_none() || y.checked_add(height).is_none() {
+        region.post_error(
+            wl_display::Error::InvalidObject,
+            format!(
+                "region rectangle {}x{} at ({}, {}) overflows the coordinate space",
+                width, height, x, y
+            ),
+        );
+        return None;
+    }
+    Some(Rectangle::from_loc_and_size((x, y), (width, height)))
+}
+
 /*
  * wl_subcompositor
  */
@@ -89,7 +89,8 @@
 //!    if the surface is a sync subsurface, its current state will note have changed as
 //!    the result of that commit. You can check if it is using [`is_sync_subsurface`].
 //! 5. If the surface is destroyed, destruction hooks are invoked. Such hooks can be registered
-//!    using the [`add_destruction_hook`] function. They are typically used to cleanup associated
+//!    using the [`add_destruction_hook`] function, or for all surfaces using
+//!    [`CompositorState::add_destruction_hook`]. They are typically used to cleanup associated
 //!    state.
 //!
 //! ### Surface roles
@@ -109,8 +110,9 @@ pub use self::cache::{Cacheable, MultiCache};
 pub use self::handlers::{RegionUserData, SubsurfaceCachedState, SubsurfaceUserData, SurfaceUserData};
 use self::tree::PrivateSurfaceData;
 pub use self::tree::{AlreadyHasRole, TraversalAction};
-use crate::utils::{user_data::UserDataMap, Buffer, Logical, Point, Rectangle};
-use wayland_server::backend::GlobalId;
+use crate::utils::{user_data::UserDataMap, Buffer, Logical, Point, Rectangle, Transform};
+use std::{collections::HashMap, fmt, sync::Mutex};
+use wayland_server::backend::{ClientId, GlobalId, ObjectId};
 use wayland_server::protocol::wl_compositor::WlCompositor;
 use wayland_server::protocol::wl_subcompositor::WlSubcompositor;
 use wayland_server::protocol::{wl_buffer, wl_callback, wl_output, wl_region, wl_surface::WlSurface};
@@ -163,6 +165,58 @@ pub struct SurfaceData {
     pub cached_state: MultiCache,
 }
 
+impl SurfaceData {
+    /// The preferred fractional scale of this surface
+    ///
+    /// Returns the scale last sent to the client through `wp_fractional_scale_v1`,
+    /// or `1.0` if the surface has no fractional scale object or none was set yet.
+    /// Renderers can use this to pick the scale a buffer of this surface was rendered at.
+    pub fn preferred_scale(&self) -> f64 {
+        crate::wayland::fractional_scale::with_fractional_scale(self, |state| state.preferred_scale())
+            .flatten()
+            .unwrap_or(1.0)
+    }
+
+    /// Number of commits since the current buffer of this surface was attached
+    ///
+    /// See [`SurfaceAttributes::buffer_age`].
+    pub fn buffer_age(&self) -> u32 {
+        self.cached_state.current::<SurfaceAttributes>().buffer_age
+    }
+
+    /// Set the buffer transform the client should prefer for this surface
+    ///
+    /// This is the hint of `wl_surface.preferred_buffer_transform`, which
+    /// [`Output::enter`](crate::output::Output::enter) sets to the inverse of the output
+    /// transform, so buffers rendered with it can be scanned out without rotation.
+    ///
+    /// The event was added in version 6 of `wl_compositor`, which is not yet provided by the
+    /// `wayland-server` version in use. Until then the hint is only stored and can be
+    /// queried with [`SurfaceData::preferred_buffer_transform`].
+    pub fn set_preferred_buffer_transform(&self, transform: Transform) {
+        self.data_map
+            .insert_if_missing_threadsafe(|| PreferredBufferTransform(Mutex::new(None)));
+        let mut preferred = self
+            .data_map
+            .get::<PreferredBufferTransform>()
+            .unwrap()
+            .0
+            .lock()
+            .unwrap();
+        *preferred = Some(transform);
+    }
+
+    /// The buffer transform last set with [`SurfaceData::set_preferred_buffer_transform`]
+    pub fn preferred_buffer_transform(&self) -> Option<Transform> {
+        self.data_map
+            .get::<PreferredBufferTransform>()
+            .and_then(|preferred| *preferred.0.lock().unwrap())
+    }
+}
+
+#[derive(Debug)]
+struct PreferredBufferTransform(Mutex<Option<Transform>>);
+
 /// New buffer assignation for a surface
 #[derive(Debug)]
 pub enum BufferAssignment {
@@ -197,6 +251,13 @@ pub struct SurfaceAttributes {
     /// You are free to set this field to `None` to avoid processing it several times.
     pub buffer_delta: Option<Point<i32, Logical>>,
 
+    /// Number of commits since the current buffer was attached
+    ///
+    /// This is `1` for the commit attaching a new buffer and increases with every commit
+    /// not attaching a buffer. It is `0` if no buffer is attached, and only meaningful
+    /// in the current state of the surface.
+    pub buffer_age: u32,
+
     /// Scale of the contents of the buffer, for higher-resolution contents.
     ///
     /// If it matches the one of the output displaying this surface, no change
@@ -243,6 +304,7 @@ impl Default for SurfaceAttributes {
         SurfaceAttributes {
             buffer: None,
             buffer_delta: None,
+            buffer_age: 0,
             buffer_scale: 1,
             buffer_transform: wl_output::Transform::Normal,
             opaque_region: None,
@@ -423,6 +485,41 @@ pub fn add_destruction_hook(surface: &WlSurface, hook: fn(&SurfaceData)) {
     PrivateSurfaceData::add_destruction_hook(surface, hook)
 }
 
+/// Send the pending frame callbacks of the given surfaces and their subsurfaces at once
+///
+/// `wl_callback.done` is sent with the given `time` (in milliseconds) for all frame callbacks
+/// of the surfaces, followed by a single flush of all clients, so every client is woken up
+/// at the same time. This should be called once per frame and output after the page flip,
+/// with the surfaces visible on the output.
+///
+/// See `desktop::utils::send_frames_surface_tree` for throttled frame callbacks
+/// based on the primary scan-out output of a surface.
+pub fn batch_frame_callbacks<'a>(
+    dh: &mut DisplayHandle,
+    time: u32,
+    surfaces: impl IntoIterator<Item = &'a WlSurface>,
+) -> std::io::Result<()> {
+    for surface in surfaces {
+        with_surface_tree_downward(
+            surface,
+            (),
+            |_, _, &()| TraversalAction::DoChildren(()),
+            |_, states, &()| {
+                for callback in states
+                    .cached_state
+                    .current::<SurfaceAttributes>()
+                    .frame_callbacks
+                    .drain(..)
+                {
+                    callback.done(time);
+                }
+            },
+            |_, _, &()| true,
+        );
+    }
+    dh.flush_clients()
+}
+
 /// Handler trait for compositor
 pub trait CompositorHandler {
     /// [CompositorState] getter
@@ -432,12 +529,27 @@ pub trait CompositorHandler {
     fn commit(&mut self, surface: &WlSurface);
 }
 
+type SurfaceDestructionHook = Box<dyn Fn(&ObjectId, &SurfaceData) + Send>;
+
 /// State of a compositor
-#[derive(Debug)]
 pub struct CompositorState {
     log: slog::Logger,
     compositor: GlobalId,
     subcompositor: GlobalId,
+    destruction_hooks: Vec<SurfaceDestructionHook>,
+    client_versions: HashMap<ClientId, u32>,
+}
+
+impl fmt::Debug for CompositorState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CompositorState")
+            .field("log", &self.log)
+            .field("compositor", &self.compositor)
+            .field("subcompositor", &self.subcompositor)
+            .field("destruction_hooks", &self.destruction_hooks.len())
+            .field("client_versions", &self.client_versions)
+            .finish()
+    }
 }
 
 #[doc(hidden)]
@@ -461,9 +573,51 @@ impl CompositorState {
             log,
             compositor,
             subcompositor,
+            destruction_hooks: Vec::new(),
+            client_versions: HashMap::new(),
         }
     }
 
+    /// Register a hook to be invoked on the destruction of any surface
+    ///
+    /// Unlike [`add_destruction_hook`], which is registered per surface, this hook is invoked for
+    /// every destroyed surface (either explicitly by the client or on client disconnect). It is
+    /// called with the id of the surface (matching [`Resource::id`] of the [`WlSurface`]) and its
+    /// data, before the state of the surface is cleaned up, to allow cleaning up data stored by the
+    /// compositor for this surface.
+    pub fn add_destruction_hook(&mut self, hook: Box<dyn Fn(&ObjectId, &SurfaceData) + Send>) {
+        self.destruction_hooks.push(hook);
+    }
+
+    pub(crate) fn surface_destroyed(&self, surface_id: &ObjectId, data: &SurfaceUserData) {
+        if self.destruction_hooks.is_empty() {
+            return;
+        }
+        PrivateSurfaceData::with_public_data(data, |surface_data| {
+            for hook in &self.destruction_hooks {
+                hook(surface_id, surface_data);
+            }
+        });
+    }
+
+    /// Returns the version of `wl_compositor` the given client bound
+    ///
+    /// This can be used to check if version-specific events may be sent to the surfaces of a client.
+    /// If the client bound the global multiple times, the highest version is returned.
+    /// Returns `0` if the client did not bind the global (yet), which is never a valid version.
+    pub fn client_compositor_version(&self, client: &ClientId) -> u32 {
+        self.client_versions.get(client).copied().unwrap_or(0)
+    }
+
+    pub(crate) fn compositor_bound(&mut self, client: ClientId, version: u32) {
+        let entry = self.client_versions.entry(client).or_insert(version);
+        *entry = (*entry).max(version);
+    }
+
+    pub(crate) fn compositor_destroyed(&mut self, client: &ClientId) {
+        self.client_versions.remove(client);
+    }
+
     /// Get id of compositor global
     pub fn compositor_global(&self) -> GlobalId {
         self.compositor.clone()
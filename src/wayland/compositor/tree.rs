@@ -110,6 +110,12 @@ impl PrivateSurfaceData {
         my_data.children.push(surface.clone());
     }
 
+    /// Access the public data of a surface from its user data
+    pub fn with_public_data<T>(surface_data: &SurfaceUserData, f: impl FnOnce(&SurfaceData) -> T) -> T {
+        let guard = surface_data.inner.lock().unwrap();
+        f(&guard.public_data)
+    }
+
     /// Cleans the `as_ref().user_data` of that surface, must be called when it is destroyed
     pub fn cleanup(surface_data: &SurfaceUserData, surface_id: ObjectId) {
         let my_data_mutex = &surface_data.inner;
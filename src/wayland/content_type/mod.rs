@@ -0,0 +1,287 @@
+//! Utilities for handling the `wp_content_type` protocol
+//!
+//! This protocol allows clients to describe the kind of content a surface
+//! displays (photos, videos, games), so the compositor can optimize its
+//! behavior for it, for example by preferring a low-latency presentation path
+//! for games.
+//!
+//! ## How to use it
+//!
+//! ### Initialization
+//!
+//! To initialize this implementation, create [`ContentTypeState`], store it in your `State` struct and
+//! implement the [`ContentTypeHandler`], as shown in this example:
+//!
+//! ```
+//! use smithay::delegate_content_type;
+//! use smithay::wayland::content_type::{ContentTypeHandler, ContentTypeState};
+//!
+//! # struct State { content_type_state: ContentTypeState }
+//! # let mut display = wayland_server::Display::<State>::new().unwrap();
+//! // Create the content type state:
+//! let content_type_state = ContentTypeState::new::<State, _>(
+//!     &display.handle(), // the display
+//!     None // provide a logger, if you want
+//! );
+//!
+//! // insert the ContentTypeState into your state
+//! // ..
+//!
+//! // implement the necessary traits
+//! impl ContentTypeHandler for State {
+//!     fn content_type_state(&mut self) -> &mut ContentTypeState {
+//!         &mut self.content_type_state
+//!     }
+//! }
+//! delegate_content_type!(State);
+//!
+//! // You're now ready to go!
+//! ```
+//!
+//! ### Use the content type
+//!
+//! The [`content type`](ContentTypeSurfaceCachedState) is double-buffered and
+//! can be accessed by using the [`with_states`] function
+//!
+//! ```no_compile
+//! let content_type = with_states(surface, |states| {
+//!     states.cached_state.current::<ContentTypeSurfaceCachedState>().content_type
+//! });
+//! ```
+
+use std::cell::RefCell;
+
+use wayland_protocols::wp::content_type::v1::server::{wp_content_type_manager_v1, wp_content_type_v1};
+use wayland_server::{
+    backend::GlobalId, protocol::wl_surface, Dispatch, DisplayHandle, GlobalDispatch, Resource, WEnum, Weak,
+};
+
+use super::compositor::{with_states, Cacheable};
+
+/// State of the wp_content_type_manager_v1 Global
+#[derive(Debug)]
+pub struct ContentTypeState {
+    global: GlobalId,
+}
+
+impl ContentTypeState {
+    /// Create new [`wp_content_type_manager`](wayland_protocols::wp::content_type::v1::server::wp_content_type_manager_v1) global.
+    pub fn new<D, L>(display: &DisplayHandle, log: L) -> ContentTypeState
+    where
+        D: GlobalDispatch<wp_content_type_manager_v1::WpContentTypeManagerV1, slog::Logger>
+            + Dispatch<wp_content_type_manager_v1::WpContentTypeManagerV1, slog::Logger>
+            + Dispatch<wp_content_type_v1::WpContentTypeV1, Weak<wl_surface::WlSurface>>
+            + 'static,
+        D: ContentTypeHandler,
+        L: Into<Option<slog::Logger>>,
+    {
+        ContentTypeState {
+            global: display
+                .create_global::<D, wp_content_type_manager_v1::WpContentTypeManagerV1, slog::Logger>(
+                    1,
+                    crate::slog_or_fallback(log).new(slog::o!("smithay_module" => "wp_content_type_manager")),
+                ),
+        }
+    }
+
+    /// Returns the content type manager global.
+    pub fn global(&self) -> GlobalId {
+        self.global.clone()
+    }
+}
+
+/// Handler trait for wp_content_type
+pub trait ContentTypeHandler {
+    /// [ContentTypeState] getter
+    fn content_type_state(&mut self) -> &mut ContentTypeState;
+}
+
+impl<D> GlobalDispatch<wp_content_type_manager_v1::WpContentTypeManagerV1, slog::Logger, D>
+    for ContentTypeState
+where
+    D: GlobalDispatch<wp_content_type_manager_v1::WpContentTypeManagerV1, slog::Logger>
+        + Dispatch<wp_content_type_manager_v1::WpContentTypeManagerV1, slog::Logger>
+        + Dispatch<wp_content_type_v1::WpContentTypeV1, Weak<wl_surface::WlSurface>>,
+    D: ContentTypeHandler,
+{
+    fn bind(
+        _state: &mut D,
+        _handle: &DisplayHandle,
+        _client: &wayland_server::Client,
+        resource: wayland_server::New<wp_content_type_manager_v1::WpContentTypeManagerV1>,
+        global_data: &slog::Logger,
+        data_init: &mut wayland_server::DataInit<'_, D>,
+    ) {
+        data_init.init(resource, global_data.clone());
+    }
+}
+
+impl<D> Dispatch<wp_content_type_manager_v1::WpContentTypeManagerV1, slog::Logger, D> for ContentTypeState
+where
+    D: GlobalDispatch<wp_content_type_manager_v1::WpContentTypeManagerV1, slog::Logger>
+        + Dispatch<wp_content_type_manager_v1::WpContentTypeManagerV1, slog::Logger>
+        + Dispatch<wp_content_type_v1::WpContentTypeV1, Weak<wl_surface::WlSurface>>,
+    D: ContentTypeHandler,
+{
+    fn request(
+        _state: &mut D,
+        _client: &wayland_server::Client,
+        resource: &wp_content_type_manager_v1::WpContentTypeManagerV1,
+        request: <wp_content_type_manager_v1::WpContentTypeManagerV1 as Resource>::Request,
+        _data: &slog::Logger,
+        _dhandle: &DisplayHandle,
+        data_init: &mut wayland_server::DataInit<'_, D>,
+    ) {
+        match request {
+            wp_content_type_manager_v1::Request::Destroy => {
+                // All is already handled by our destructor
+            }
+            wp_content_type_manager_v1::Request::GetSurfaceContentType { id, surface } => {
+                let already_has_content_type = with_states(&surface, |states| {
+                    states
+                        .data_map
+                        .get::<RefCell<Option<ContentTypeMarker>>>()
+                        .map(|v| v.borrow().is_some())
+                        .unwrap_or(false)
+                });
+
+                if already_has_content_type {
+                    resource.post_error(
+                        wp_content_type_manager_v1::Error::AlreadyConstructed as u32,
+                        "the surface already has a content type object associated".to_string(),
+                    );
+                    return;
+                }
+
+                data_init.init(id, surface.downgrade());
+                with_states(&surface, |states| {
+                    if !states
+                        .data_map
+                        .insert_if_missing(|| RefCell::new(Some(ContentTypeMarker)))
+                    {
+                        *states
+                            .data_map
+                            .get::<RefCell<Option<ContentTypeMarker>>>()
+                            .unwrap()
+                            .borrow_mut() = Some(ContentTypeMarker);
+                    }
+                });
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<wp_content_type_v1::WpContentTypeV1, Weak<wl_surface::WlSurface>, D> for ContentTypeState
+where
+    D: GlobalDispatch<wp_content_type_manager_v1::WpContentTypeManagerV1, slog::Logger>
+        + Dispatch<wp_content_type_manager_v1::WpContentTypeManagerV1, slog::Logger>
+        + Dispatch<wp_content_type_v1::WpContentTypeV1, Weak<wl_surface::WlSurface>>,
+    D: ContentTypeHandler,
+{
+    fn request(
+        _state: &mut D,
+        _client: &wayland_server::Client,
+        _resource: &wp_content_type_v1::WpContentTypeV1,
+        request: <wp_content_type_v1::WpContentTypeV1 as Resource>::Request,
+        data: &Weak<wl_surface::WlSurface>,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut wayland_server::DataInit<'_, D>,
+    ) {
+        let surface = match data.upgrade() {
+            Ok(surface) => surface,
+            Err(_) => return,
+        };
+
+        match request {
+            wp_content_type_v1::Request::SetContentType { content_type } => {
+                let content_type = match content_type {
+                    WEnum::Value(content_type) => content_type.into(),
+                    WEnum::Unknown(_) => ContentType::None,
+                };
+                with_states(&surface, |states| {
+                    states
+                        .cached_state
+                        .pending::<ContentTypeSurfaceCachedState>()
+                        .content_type = content_type;
+                });
+            }
+            wp_content_type_v1::Request::Destroy => {
+                // The content type is reset to none on the next commit
+                with_states(&surface, |states| {
+                    states
+                        .data_map
+                        .get::<RefCell<Option<ContentTypeMarker>>>()
+                        .and_then(|v| v.borrow_mut().take());
+                    *states.cached_state.pending::<ContentTypeSurfaceCachedState>() =
+                        ContentTypeSurfaceCachedState::default();
+                });
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+struct ContentTypeMarker;
+
+/// Kind of content displayed by a surface
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContentType {
+    /// No content type was set or the content doesn't fit any other category
+    #[default]
+    None,
+    /// Digital photos or still images
+    Photo,
+    /// Video or animation content
+    Video,
+    /// Game content, which usually benefits from low-latency presentation
+    Game,
+}
+
+impl From<wp_content_type_v1::Type> for ContentType {
+    fn from(content_type: wp_content_type_v1::Type) -> Self {
+        match content_type {
+            wp_content_type_v1::Type::Photo => ContentType::Photo,
+            wp_content_type_v1::Type::Video => ContentType::Video,
+            wp_content_type_v1::Type::Game => ContentType::Game,
+            _ => ContentType::None,
+        }
+    }
+}
+
+/// Represents the double-buffered content type
+/// state of a [`WlSurface`](wl_surface::WlSurface)
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ContentTypeSurfaceCachedState {
+    /// The content type hint of the surface
+    pub content_type: ContentType,
+}
+
+impl Cacheable for ContentTypeSurfaceCachedState {
+    fn commit(&mut self, _dh: &DisplayHandle) -> Self {
+        *self
+    }
+
+    fn merge_into(self, into: &mut Self, _dh: &DisplayHandle) {
+        *into = self;
+    }
+}
+
+/// Macro to delegate implementation of the content type protocol
+///
+/// You must also implement [`ContentTypeHandler`] to use this.
+#[macro_export]
+macro_rules! delegate_content_type {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        $crate::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::reexports::wayland_protocols::wp::content_type::v1::server::wp_content_type_manager_v1::WpContentTypeManagerV1: slog::Logger
+        ] => $crate::wayland::content_type::ContentTypeState);
+
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::reexports::wayland_protocols::wp::content_type::v1::server::wp_content_type_manager_v1::WpContentTypeManagerV1: slog::Logger
+        ] => $crate::wayland::content_type::ContentTypeState);
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::reexports::wayland_protocols::wp::content_type::v1::server::wp_content_type_v1::WpContentTypeV1: $crate::reexports::wayland_server::Weak<$crate::reexports::wayland_server::protocol::wl_surface::WlSurface>
+        ] => $crate::wayland::content_type::ContentTypeState);
+    };
+}
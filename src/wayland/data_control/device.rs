@@ -0,0 +1,75 @@
+use wayland_protocols_wlr::data_control::v1::server::{
+    zwlr_data_control_device_v1::{self, ZwlrDataControlDeviceV1},
+    zwlr_data_control_source_v1::ZwlrDataControlSourceV1,
+};
+use wayland_server::{protocol::wl_seat::WlSeat, Client, DataInit, Dispatch, DisplayHandle, Resource};
+
+use crate::{
+    input::Seat,
+    wayland::{data_device, primary_selection},
+};
+
+use super::{DataControlHandler, DataControlSourceUserData, DataControlState, SelectionTarget};
+
+#[doc(hidden)]
+#[derive(Debug)]
+pub struct DataControlDeviceUserData {
+    pub(crate) wl_seat: WlSeat,
+}
+
+impl<D> Dispatch<ZwlrDataControlDeviceV1, DataControlDeviceUserData, D> for DataControlState
+where
+    D: Dispatch<ZwlrDataControlDeviceV1, DataControlDeviceUserData>,
+    D: DataControlHandler,
+    D: 'static,
+{
+    fn request(
+        handler: &mut D,
+        _client: &Client,
+        resource: &ZwlrDataControlDeviceV1,
+        request: zwlr_data_control_device_v1::Request,
+        data: &DataControlDeviceUserData,
+        dh: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        if let Some(seat) = Seat::<D>::from_resource(&data.wl_seat) {
+            match request {
+                zwlr_data_control_device_v1::Request::SetSelection { source } => {
+                    if !mark_used(resource, source.as_ref()) {
+                        return;
+                    }
+                    handler.new_data_control_selection(source.clone(), SelectionTarget::Clipboard);
+                    data_device::set_data_control_selection(dh, &seat, source);
+                }
+                zwlr_data_control_device_v1::Request::SetPrimarySelection { source } => {
+                    if !mark_used(resource, source.as_ref()) {
+                        return;
+                    }
+                    handler.new_data_control_selection(source.clone(), SelectionTarget::Primary);
+                    primary_selection::set_data_control_primary_selection(dh, &seat, source);
+                }
+                zwlr_data_control_device_v1::Request::Destroy => {
+                    // Clean up the known devices
+                    data_device::remove_data_control_device(&seat, resource);
+                    primary_selection::remove_data_control_device(&seat, resource);
+                }
+                _ => unreachable!(),
+            }
+        }
+    }
+}
+
+/// A source may only be used for a single selection
+fn mark_used(device: &ZwlrDataControlDeviceV1, source: Option<&ZwlrDataControlSourceV1>) -> bool {
+    let used = source
+        .and_then(|source| source.data::<DataControlSourceUserData>())
+        .map(|data| !data.mark_used())
+        .unwrap_or(false);
+    if used {
+        device.post_error(
+            zwlr_data_control_device_v1::Error::UsedSource,
+            "source was already used for a selection",
+        );
+    }
+    !used
+}
@@ -0,0 +1,266 @@
+//! Utilities for clipboard managers using the `wlr-data-control` protocol
+//!
+//! The `zwlr_data_control_manager_v1` global allows privileged clients, like clipboard managers,
+//! to observe and set the clipboard and primary selection of a seat, regardless of the keyboard
+//! focus. Every selection set through the [data device](super::data_device) or the
+//! [primary selection](super::primary_selection) is mirrored to the data control devices of the
+//! seat, and selections set by data control clients are offered to the focused client through
+//! the data device and primary selection.
+//!
+//! As data control clients can read everything that is copied, you should only expose the global
+//! to trusted clients, using the filter given to [`DataControlState::new`].
+//!
+//! ## Initialization
+//!
+//! To initialize this implementation, create the [`DataControlState`], store it inside your `State`
+//! struct and implement the [`DataControlHandler`], alongside the [`DataDeviceHandler`] and
+//! [`PrimarySelectionHandler`], as shown in this example:
+//!
+//! ```
+//! # extern crate wayland_server;
+//! # #[macro_use] extern crate smithay;
+//! use smithay::{delegate_data_control, delegate_data_device, delegate_primary_selection};
+//! use smithay::wayland::data_control::{DataControlHandler, DataControlState};
+//! use smithay::wayland::data_device::{ClientDndGrabHandler, DataDeviceHandler, DataDeviceState, ServerDndGrabHandler};
+//! use smithay::wayland::primary_selection::{PrimarySelectionHandler, PrimarySelectionState};
+//! # use smithay::input::{Seat, SeatState, SeatHandler, pointer::CursorImageStatus};
+//! # use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+//!
+//! # struct State {
+//! #     data_device_state: DataDeviceState,
+//! #     primary_selection_state: PrimarySelectionState,
+//! #     data_control_state: DataControlState,
+//! # }
+//! # let mut display = wayland_server::Display::<State>::new().unwrap();
+//! // Create the data control state
+//! let data_control_state = DataControlState::new::<State, _, _>(
+//!     &display.handle(),
+//!     |_client| true, // only allow trusted clients here
+//!     None // We don't add a logger in this example
+//! );
+//!
+//! // insert the DataControlState into your state
+//! // ..
+//!
+//! // implement the necessary traits
+//! # impl SeatHandler for State {
+//! #     type KeyboardFocus = WlSurface;
+//! #     type PointerFocus = WlSurface;
+//! #     fn seat_state(&mut self) -> &mut SeatState<Self> { unimplemented!() }
+//! #     fn focus_changed(&mut self, seat: &Seat<Self>, focused: Option<&WlSurface>) { unimplemented!() }
+//! #     fn cursor_image(&mut self, seat: &Seat<Self>, image: CursorImageStatus) { unimplemented!() }
+//! # }
+//! # impl ClientDndGrabHandler for State {}
+//! # impl ServerDndGrabHandler for State {}
+//! # impl DataDeviceHandler for State {
+//! #     fn data_device_state(&self) -> &DataDeviceState { &self.data_device_state }
+//! # }
+//! # impl PrimarySelectionHandler for State {
+//! #     fn primary_selection_state(&self) -> &PrimarySelectionState { &self.primary_selection_state }
+//! # }
+//! # delegate_data_device!(State);
+//! # delegate_primary_selection!(State);
+//! impl DataControlHandler for State {
+//!     fn data_control_state(&self) -> &DataControlState { &self.data_control_state }
+//!     // ... override default implementations here to customize handling ...
+//! }
+//! delegate_data_control!(State);
+//!
+//! // You're now ready to go!
+//! ```
+
+use wayland_protocols_wlr::data_control::v1::server::{
+    zwlr_data_control_manager_v1::ZwlrDataControlManagerV1,
+    zwlr_data_control_source_v1::ZwlrDataControlSourceV1,
+};
+use wayland_server::{backend::GlobalId, Client, DisplayHandle, GlobalDispatch};
+
+use super::{data_device::DataDeviceHandler, primary_selection::PrimarySelectionHandler};
+
+mod device;
+mod offer;
+mod source;
+
+pub use device::DataControlDeviceUserData;
+pub use source::{with_source_metadata, DataControlSourceUserData, SourceMetadata};
+
+pub(crate) use offer::{clear_selection, offer_selection};
+
+const MANAGER_VERSION: u32 = 2;
+
+/// The selection a data control client interacts with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SelectionTarget {
+    /// The clipboard selection of the data device
+    Clipboard,
+    /// The primary selection
+    Primary,
+}
+
+/// Events that are generated by interactions of data control clients
+pub trait DataControlHandler: DataDeviceHandler + PrimarySelectionHandler {
+    /// [DataControlState] getter
+    fn data_control_state(&self) -> &DataControlState;
+
+    /// A data control client has set the clipboard or primary selection
+    ///
+    /// The new selection is offered to the other clients afterwards.
+    #[allow(unused_variables)]
+    fn new_data_control_selection(
+        &mut self,
+        source: Option<ZwlrDataControlSourceV1>,
+        target: SelectionTarget,
+    ) {
+    }
+}
+
+/// State of the data control global
+#[derive(Debug)]
+pub struct DataControlState {
+    log: slog::Logger,
+    manager_global: GlobalId,
+}
+
+/// Data associated with a data control manager global
+#[allow(missing_debug_implementations)]
+pub struct DataControlManagerGlobalData {
+    filter: Box<dyn for<'c> Fn(&'c Client) -> bool + Send + Sync>,
+}
+
+impl DataControlState {
+    /// Register a new [ZwlrDataControlManagerV1] global
+    ///
+    /// The global is only advertised to clients for which `filter` returns `true`.
+    pub fn new<D, F, L>(display: &DisplayHandle, filter: F, logger: L) -> Self
+    where
+        D: GlobalDispatch<ZwlrDataControlManagerV1, DataControlManagerGlobalData> + 'static,
+        D: DataControlHandler,
+        F: for<'c> Fn(&'c Client) -> bool + Send + Sync + 'static,
+        L: Into<Option<::slog::Logger>>,
+    {
+        let log = crate::slog_or_fallback(logger).new(slog::o!("smithay_module" => "data_control_mgr"));
+
+        let data = DataControlManagerGlobalData {
+            filter: Box::new(filter),
+        };
+        let manager_global = display.create_global::<D, ZwlrDataControlManagerV1, _>(MANAGER_VERSION, data);
+
+        Self { log, manager_global }
+    }
+
+    /// [ZwlrDataControlManagerV1] GlobalId getter
+    pub fn global(&self) -> GlobalId {
+        self.manager_global.clone()
+    }
+}
+
+mod handlers {
+    use slog::error;
+    use wayland_protocols_wlr::data_control::v1::server::{
+        zwlr_data_control_device_v1::ZwlrDataControlDeviceV1,
+        zwlr_data_control_manager_v1::{self, ZwlrDataControlManagerV1},
+        zwlr_data_control_source_v1::ZwlrDataControlSourceV1,
+    };
+    use wayland_server::{Client, Dispatch, DisplayHandle, GlobalDispatch};
+
+    use crate::{
+        input::Seat,
+        wayland::{data_device, primary_selection},
+    };
+
+    use super::{device::DataControlDeviceUserData, source::DataControlSourceUserData};
+    use super::{DataControlHandler, DataControlManagerGlobalData, DataControlState};
+
+    impl<D> GlobalDispatch<ZwlrDataControlManagerV1, DataControlManagerGlobalData, D> for DataControlState
+    where
+        D: GlobalDispatch<ZwlrDataControlManagerV1, DataControlManagerGlobalData>,
+        D: Dispatch<ZwlrDataControlManagerV1, ()>,
+        D: Dispatch<ZwlrDataControlSourceV1, DataControlSourceUserData>,
+        D: Dispatch<ZwlrDataControlDeviceV1, DataControlDeviceUserData>,
+        D: DataControlHandler,
+        D: 'static,
+    {
+        fn bind(
+            _state: &mut D,
+            _handle: &DisplayHandle,
+            _client: &Client,
+            resource: wayland_server::New<ZwlrDataControlManagerV1>,
+            _global_data: &DataControlManagerGlobalData,
+            data_init: &mut wayland_server::DataInit<'_, D>,
+        ) {
+            data_init.init(resource, ());
+        }
+
+        fn can_view(client: Client, global_data: &DataControlManagerGlobalData) -> bool {
+            (global_data.filter)(&client)
+        }
+    }
+
+    impl<D> Dispatch<ZwlrDataControlManagerV1, (), D> for DataControlState
+    where
+        D: Dispatch<ZwlrDataControlManagerV1, ()>,
+        D: Dispatch<ZwlrDataControlSourceV1, DataControlSourceUserData>,
+        D: Dispatch<ZwlrDataControlDeviceV1, DataControlDeviceUserData>,
+        D: DataControlHandler,
+        D: 'static,
+    {
+        fn request(
+            state: &mut D,
+            _client: &Client,
+            _resource: &ZwlrDataControlManagerV1,
+            request: zwlr_data_control_manager_v1::Request,
+            _data: &(),
+            dh: &DisplayHandle,
+            data_init: &mut wayland_server::DataInit<'_, D>,
+        ) {
+            let data_control_state = state.data_control_state();
+
+            match request {
+                zwlr_data_control_manager_v1::Request::CreateDataSource { id } => {
+                    data_init.init(id, DataControlSourceUserData::new());
+                }
+                zwlr_data_control_manager_v1::Request::GetDataDevice { id, seat: wl_seat } => {
+                    match Seat::<D>::from_resource(&wl_seat) {
+                        Some(seat) => {
+                            let device = data_init.init(id, DataControlDeviceUserData { wl_seat });
+
+                            // sends the current selections to the new device
+                            data_device::add_data_control_device(dh, &seat, device.clone());
+                            primary_selection::add_data_control_device(dh, &seat, device);
+                        }
+                        None => {
+                            error!(
+                                &data_control_state.log,
+                                "Unmanaged seat given to a data control device."
+                            );
+                        }
+                    }
+                }
+                zwlr_data_control_manager_v1::Request::Destroy => {}
+                _ => unreachable!(),
+            }
+        }
+    }
+}
+
+/// Macro to delegate implementation of the wlr data control protocol
+///
+/// You must also implement [`DataControlHandler`] to use this.
+#[macro_export]
+macro_rules! delegate_data_control {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        $crate::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::reexports::wayland_protocols_wlr::data_control::v1::server::zwlr_data_control_manager_v1::ZwlrDataControlManagerV1: $crate::wayland::data_control::DataControlManagerGlobalData
+        ] => $crate::wayland::data_control::DataControlState);
+
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::reexports::wayland_protocols_wlr::data_control::v1::server::zwlr_data_control_manager_v1::ZwlrDataControlManagerV1: ()
+        ] => $crate::wayland::data_control::DataControlState);
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::reexports::wayland_protocols_wlr::data_control::v1::server::zwlr_data_control_device_v1::ZwlrDataControlDeviceV1: $crate::wayland::data_control::DataControlDeviceUserData
+        ] => $crate::wayland::data_control::DataControlState);
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::reexports::wayland_protocols_wlr::data_control::v1::server::zwlr_data_control_source_v1::ZwlrDataControlSourceV1: $crate::wayland::data_control::DataControlSourceUserData
+        ] => $crate::wayland::data_control::DataControlState);
+    };
+}
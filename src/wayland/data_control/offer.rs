@@ -0,0 +1,101 @@
+use std::{os::unix::io::OwnedFd, sync::Arc};
+
+use wayland_protocols_wlr::data_control::v1::server::{
+    zwlr_data_control_device_v1::ZwlrDataControlDeviceV1,
+    zwlr_data_control_offer_v1::{self, ZwlrDataControlOfferV1},
+};
+use wayland_server::{
+    backend::{protocol::Message, ClientId, Handle, ObjectData, ObjectId},
+    DisplayHandle, Resource,
+};
+
+use super::SelectionTarget;
+
+type SendFn<D> = Box<dyn Fn(&mut D, String, OwnedFd) + Send + Sync>;
+
+/// Announce a selection to a data control device
+///
+/// A new `zwlr_data_control_offer_v1` advertising the given mime types is created for the device.
+/// Whenever its client requests to receive one of them, `send` is called with the mime type and
+/// the fd to write into.
+pub(crate) fn offer_selection<D, F>(
+    dh: &DisplayHandle,
+    device: &ZwlrDataControlDeviceV1,
+    target: SelectionTarget,
+    mime_types: Vec<String>,
+    send: F,
+) where
+    D: 'static,
+    F: Fn(&mut D, String, OwnedFd) + Send + Sync + 'static,
+{
+    if target == SelectionTarget::Primary && device.version() < 2 {
+        return;
+    }
+    let client = match dh.get_client(device.id()) {
+        Ok(client) => client,
+        Err(_) => return,
+    };
+
+    let handle = dh.backend_handle();
+    // create a data offer
+    let offer = handle
+        .create_object::<D>(
+            client.id(),
+            ZwlrDataControlOfferV1::interface(),
+            device.version(),
+            Arc::new(DataControlOffer {
+                mime_types: mime_types.clone(),
+                send: Box::new(send) as SendFn<D>,
+            }),
+        )
+        .unwrap();
+    let offer = ZwlrDataControlOfferV1::from_id(dh, offer).unwrap();
+
+    // advertize the offer to the client
+    device.data_offer(&offer);
+    for mime_type in mime_types {
+        offer.offer(mime_type);
+    }
+    match target {
+        SelectionTarget::Clipboard => device.selection(Some(&offer)),
+        SelectionTarget::Primary => device.primary_selection(Some(&offer)),
+    }
+}
+
+/// Announce an empty selection to a data control device
+pub(crate) fn clear_selection(device: &ZwlrDataControlDeviceV1, target: SelectionTarget) {
+    match target {
+        SelectionTarget::Clipboard => device.selection(None),
+        SelectionTarget::Primary if device.version() >= 2 => device.primary_selection(None),
+        SelectionTarget::Primary => {}
+    }
+}
+
+struct DataControlOffer<D> {
+    mime_types: Vec<String>,
+    send: SendFn<D>,
+}
+
+impl<D: 'static> ObjectData<D> for DataControlOffer<D> {
+    fn request(
+        self: Arc<Self>,
+        dh: &Handle,
+        handler: &mut D,
+        _client_id: ClientId,
+        msg: Message<ObjectId, OwnedFd>,
+    ) -> Option<Arc<dyn ObjectData<D>>> {
+        let dh = DisplayHandle::from(dh.clone());
+        if let Ok((_resource, zwlr_data_control_offer_v1::Request::Receive { mime_type, fd })) =
+            ZwlrDataControlOfferV1::parse_request(&dh, msg)
+        {
+            // deny the receive of mime types that were never offered
+            if self.mime_types.contains(&mime_type) {
+                (self.send)(handler, mime_type, fd);
+            }
+        }
+
+        None
+    }
+
+    fn destroyed(&self, _data: &mut D, _client_id: ClientId, _object_id: ObjectId) {}
+}
@@ -174,7 +174,7 @@ where
                 if self.data_source.is_some() || self.origin.id().same_client_as(&surface.id()) {
                     for device in seat_data.known_devices() {
                         if device.id().same_client_as(&surface.id()) {
-                            device.motion(event.time, x, y);
+                            device.motion(event.time.as_millis_u32(), x, y);
                         }
                     }
                 }
@@ -242,7 +242,7 @@ where
                     }
                 }
             }
-            handle.unset_grab(data, event.serial, event.time);
+            handle.unset_grab(data, event.serial, event.time.as_millis_u32());
         }
     }
 
@@ -64,8 +64,17 @@
 //! // You're now ready to go!
 //! ```
 
-use std::{cell::RefCell, os::unix::io::OwnedFd};
+use std::{
+    cell::RefCell,
+    fmt,
+    os::unix::io::OwnedFd,
+    sync::{Arc, Mutex},
+};
 
+use wayland_protocols_wlr::data_control::v1::server::{
+    zwlr_data_control_device_v1::ZwlrDataControlDeviceV1,
+    zwlr_data_control_source_v1::ZwlrDataControlSourceV1,
+};
 use wayland_server::{
     backend::GlobalId,
     protocol::{
@@ -153,6 +162,10 @@ pub trait ServerDndGrabHandler {
 
     /// The Dnd was cancelled
     ///
+    /// This is also called if the drop was rejected, because the target client did not accept
+    /// any mime type or action. As the compositor itself is the source of a server-side
+    /// drag'n'drop, there is no `wl_data_source` to notify, this callback is the only notification.
+    ///
     /// The client can no longer interact
     fn cancelled(&mut self) {}
 
@@ -168,11 +181,27 @@ pub trait ServerDndGrabHandler {
     fn finished(&mut self) {}
 }
 
+/// Predicate deciding if a mime type may be offered to clients
+pub type OfferFilter = Box<dyn Fn(&str) -> bool + Send>;
+
 /// State of data device
-#[derive(Debug)]
 pub struct DataDeviceState {
     log: slog::Logger,
     manager_global: GlobalId,
+    offer_filter: Arc<Mutex<Option<OfferFilter>>>,
+}
+
+impl fmt::Debug for DataDeviceState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DataDeviceState")
+            .field("log", &self.log)
+            .field("manager_global", &self.manager_global)
+            .field(
+                "offer_filter",
+                &self.offer_filter.lock().unwrap().as_ref().map(|_| "..."),
+            )
+            .finish()
+    }
 }
 
 impl DataDeviceState {
@@ -187,13 +216,33 @@ impl DataDeviceState {
 
         let manager_global = display.create_global::<D, WlDataDeviceManager, _>(3, ());
 
-        Self { log, manager_global }
+        Self {
+            log,
+            manager_global,
+            offer_filter: Arc::new(Mutex::new(None)),
+        }
     }
 
     /// [WlDataDeviceManager] GlobalId getter
     pub fn global(&self) -> GlobalId {
         self.manager_global.clone()
     }
+
+    /// Set a filter for the mime types offered to clients through the selection
+    ///
+    /// The filter is checked whenever a selection `wl_data_offer` is created. Only
+    /// mime types accepted by the filter are advertised and if all mime types of a
+    /// selection are rejected, the offer is not sent to the client at all.
+    ///
+    /// This can be used to restrict what kind of content can be pasted between clients.
+    pub fn set_offer_filter(&mut self, filter: OfferFilter) {
+        *self.offer_filter.lock().unwrap() = Some(filter);
+    }
+
+    /// Remove a previously set offer filter
+    pub fn clear_offer_filter(&mut self) {
+        *self.offer_filter.lock().unwrap() = None;
+    }
 }
 
 /// A simple action chooser for DnD negociation
@@ -251,6 +300,45 @@ where
     );
 }
 
+/// Set the selection of this seat to a source of a data control client
+pub(crate) fn set_data_control_selection<D>(
+    dh: &DisplayHandle,
+    seat: &Seat<D>,
+    source: Option<ZwlrDataControlSourceV1>,
+) where
+    D: SeatHandler + DataDeviceHandler + 'static,
+{
+    seat.user_data()
+        .insert_if_missing(|| RefCell::new(SeatData::new()));
+    let seat_data = seat.user_data().get::<RefCell<SeatData>>().unwrap();
+    seat_data
+        .borrow_mut()
+        .set_selection::<D>(dh, source.map(Selection::DataControl).unwrap_or(Selection::Empty));
+}
+
+/// Mirror the selection of this seat to a data control device
+pub(crate) fn add_data_control_device<D>(dh: &DisplayHandle, seat: &Seat<D>, device: ZwlrDataControlDeviceV1)
+where
+    D: SeatHandler + DataDeviceHandler + 'static,
+{
+    seat.user_data()
+        .insert_if_missing(|| RefCell::new(SeatData::new()));
+    let seat_data = seat.user_data().get::<RefCell<SeatData>>().unwrap();
+    seat_data.borrow_mut().add_data_control_device::<D>(dh, device);
+}
+
+/// Stop mirroring the selection of this seat to a data control device
+pub(crate) fn remove_data_control_device<D>(seat: &Seat<D>, device: &ZwlrDataControlDeviceV1)
+where
+    D: SeatHandler + 'static,
+{
+    if let Some(seat_data) = seat.user_data().get::<RefCell<SeatData>>() {
+        seat_data
+            .borrow_mut()
+            .retain_data_control_devices(|known| known != device);
+    }
+}
+
 /// Start a drag'n'drop from a resource controlled by the compositor
 ///
 /// You'll receive events generated by the interaction of clients with your
@@ -350,7 +438,9 @@ mod handlers {
                             let data_device = data_init.init(id, DataDeviceUserData { wl_seat });
 
                             let seat_data = seat.user_data().get::<RefCell<SeatData>>().unwrap();
-                            seat_data.borrow_mut().add_device(data_device);
+                            let mut seat_data = seat_data.borrow_mut();
+                            seat_data.set_offer_filter(data_device_state.offer_filter.clone());
+                            seat_data.add_device(data_device);
                         }
                         None => {
                             error!(&data_device_state.log, "Unmanaged seat given to a data device.");
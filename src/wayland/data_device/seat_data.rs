@@ -1,9 +1,13 @@
 use std::{
     os::unix::io::{AsRawFd, OwnedFd},
-    sync::Arc,
+    sync::{Arc, Mutex},
 };
 
 use slog::debug;
+use wayland_protocols_wlr::data_control::v1::server::{
+    zwlr_data_control_device_v1::ZwlrDataControlDeviceV1,
+    zwlr_data_control_source_v1::ZwlrDataControlSourceV1,
+};
 use wayland_server::{
     backend::{protocol::Message, ClientId, Handle, ObjectData, ObjectId},
     protocol::{
@@ -14,32 +18,63 @@ use wayland_server::{
     Client, DisplayHandle, Resource,
 };
 
-use crate::utils::IsAlive;
+use crate::{
+    utils::IsAlive,
+    wayland::data_control::{self, SelectionTarget},
+};
 
-use super::{with_source_metadata, DataDeviceHandler, SourceMetadata};
+use super::{with_source_metadata, DataDeviceHandler, OfferFilter, SourceMetadata};
 
 pub enum Selection {
     Empty,
     Client(WlDataSource),
     Compositor(SourceMetadata),
+    DataControl(ZwlrDataControlSourceV1),
 }
 
 pub struct SeatData {
     known_devices: Vec<WlDataDevice>,
+    data_control_devices: Vec<ZwlrDataControlDeviceV1>,
     selection: Selection,
     current_focus: Option<Client>,
+    offer_filter: Arc<Mutex<Option<OfferFilter>>>,
 }
 
 impl Default for SeatData {
     fn default() -> Self {
         Self {
             known_devices: Vec::new(),
+            data_control_devices: Vec::new(),
             selection: Selection::Empty,
             current_focus: None,
+            offer_filter: Arc::new(Mutex::new(None)),
         }
     }
 }
 
+/// Apply the offer filter to a list of mime types
+///
+/// Returns `None` if the offer should be dropped, because the filter
+/// rejected all of its mime types.
+fn filter_mime_types(mime_types: &[String], filter: Option<&OfferFilter>) -> Option<Vec<String>> {
+    let filter = match filter {
+        Some(filter) => filter,
+        None => return Some(mime_types.to_vec()),
+    };
+
+    let mime_types = mime_types
+        .iter()
+        .filter(|mime_type| filter(mime_type))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    if mime_types.is_empty() {
+        None
+    } else {
+        Some(mime_types)
+    }
+}
+
 impl SeatData {
     pub fn new() -> Self {
         Self::default()
@@ -49,6 +84,10 @@ impl SeatData {
         &self.known_devices
     }
 
+    pub fn set_offer_filter(&mut self, filter: Arc<Mutex<Option<OfferFilter>>>) {
+        self.offer_filter = filter;
+    }
+
     pub fn add_device(&mut self, device: WlDataDevice) {
         self.known_devices.push(device);
     }
@@ -60,21 +99,41 @@ impl SeatData {
         self.known_devices.retain(f)
     }
 
+    pub fn add_data_control_device<D>(&mut self, dh: &DisplayHandle, device: ZwlrDataControlDeviceV1)
+    where
+        D: DataDeviceHandler,
+        D: 'static,
+    {
+        self.sanitize_selection();
+        self.send_data_control_selection::<D>(dh, &device);
+        self.data_control_devices.push(device);
+    }
+
+    pub fn retain_data_control_devices<F>(&mut self, f: F)
+    where
+        F: FnMut(&ZwlrDataControlDeviceV1) -> bool,
+    {
+        self.data_control_devices.retain(f)
+    }
+
     pub fn set_selection<D>(&mut self, dh: &DisplayHandle, new_selection: Selection)
     where
         D: DataDeviceHandler,
         D: 'static,
     {
-        if let Selection::Client(data_source) = &self.selection {
-            match &new_selection {
-                Selection::Client(new_data_source) if new_data_source == data_source => {}
-                _ => {
-                    data_source.cancelled();
-                }
-            }
+        match (&self.selection, &new_selection) {
+            (Selection::Client(data_source), Selection::Client(new_data_source))
+                if new_data_source == data_source => {}
+            (Selection::Client(data_source), _) => data_source.cancelled(),
+            (Selection::DataControl(source), Selection::DataControl(new_source)) if new_source == source => {}
+            (Selection::DataControl(source), _) => source.cancelled(),
+            _ => {}
         }
         self.selection = new_selection;
         self.send_selection::<D>(dh);
+        for device in &self.data_control_devices {
+            self.send_data_control_selection::<D>(dh, device);
+        }
     }
 
     pub fn set_focus<D>(&mut self, dh: &DisplayHandle, new_focus: Option<Client>)
@@ -91,40 +150,86 @@ impl SeatData {
         D: DataDeviceHandler,
         D: 'static,
     {
+        // first sanitize the selection, reseting it to null if the client holding
+        // it dropped it
+        self.sanitize_selection();
         let client = match self.current_focus.as_ref() {
             Some(c) => c,
             None => return,
         };
-        // first sanitize the selection, reseting it to null if the client holding
-        // it dropped it
-        let cleanup = if let Selection::Client(ref data_source) = self.selection {
-            !data_source.alive()
-        } else {
-            false
-        };
-        if cleanup {
-            self.selection = Selection::Empty;
-        }
 
         // then send it if appropriate
         match self.selection {
             Selection::Empty => {
                 // send an empty selection
+                self.send_empty_selection(dh, client);
+            }
+            Selection::Client(ref data_source) => {
+                let mime_types = with_source_metadata(data_source, |meta| {
+                    filter_mime_types(&meta.mime_types, self.offer_filter.lock().unwrap().as_ref())
+                })
+                .unwrap();
+                let mime_types = match mime_types {
+                    Some(mime_types) => mime_types,
+                    // everything was filtered out, the client must not keep a stale offer
+                    None => {
+                        self.send_empty_selection(dh, client);
+                        return;
+                    }
+                };
+
                 for dd in &self.known_devices {
                     // skip data devices not belonging to our client
                     if dh.get_client(dd.id()).map(|c| &c != client).unwrap_or(true) {
                         continue;
                     }
-                    dd.selection(None);
+                    let source = data_source.clone();
+                    let offered_mime_types = mime_types.clone();
+
+                    let handle = dh.backend_handle();
+                    // create a data offer
+                    let offer = handle
+                        .create_object::<D>(
+                            client.id(),
+                            WlDataOffer::interface(),
+                            dd.version(),
+                            Arc::new(ClientSelection {
+                                source,
+                                offered_mime_types,
+                            }),
+                        )
+                        .unwrap();
+                    let offer = WlDataOffer::from_id(dh, offer).unwrap();
+
+                    // advertize the offer to the client
+                    dd.data_offer(&offer);
+                    for mime_type in mime_types.iter().cloned() {
+                        offer.offer(mime_type);
+                    }
+                    dd.selection(Some(&offer));
                 }
             }
-            Selection::Client(ref data_source) => {
+            Selection::DataControl(ref source) => {
+                let mime_types = data_control::with_source_metadata(source, |meta| {
+                    filter_mime_types(&meta.mime_types, self.offer_filter.lock().unwrap().as_ref())
+                })
+                .unwrap();
+                let mime_types = match mime_types {
+                    Some(mime_types) => mime_types,
+                    // everything was filtered out, the client must not keep a stale offer
+                    None => {
+                        self.send_empty_selection(dh, client);
+                        return;
+                    }
+                };
+
                 for dd in &self.known_devices {
                     // skip data devices not belonging to our client
                     if dh.get_client(dd.id()).map(|c| &c != client).unwrap_or(true) {
                         continue;
                     }
-                    let source = data_source.clone();
+                    let source = source.clone();
+                    let offered_mime_types = mime_types.clone();
 
                     let handle = dh.backend_handle();
                     // create a data offer
@@ -133,30 +238,43 @@ impl SeatData {
                             client.id(),
                             WlDataOffer::interface(),
                             dd.version(),
-                            Arc::new(ClientSelection { source }),
+                            Arc::new(DataControlSelection {
+                                source,
+                                offered_mime_types,
+                            }),
                         )
                         .unwrap();
                     let offer = WlDataOffer::from_id(dh, offer).unwrap();
 
                     // advertize the offer to the client
                     dd.data_offer(&offer);
-                    with_source_metadata(data_source, |meta| {
-                        for mime_type in meta.mime_types.iter().cloned() {
-                            offer.offer(mime_type);
-                        }
-                    })
-                    .unwrap();
+                    for mime_type in mime_types.iter().cloned() {
+                        offer.offer(mime_type);
+                    }
                     dd.selection(Some(&offer));
                 }
             }
             Selection::Compositor(ref meta) => {
+                let mime_types =
+                    match filter_mime_types(&meta.mime_types, self.offer_filter.lock().unwrap().as_ref()) {
+                        Some(mime_types) => mime_types,
+                        // everything was filtered out, the client must not keep a stale offer
+                        None => {
+                            self.send_empty_selection(dh, client);
+                            return;
+                        }
+                    };
+
                 for dd in &self.known_devices {
                     // skip data devices not belonging to our client
                     if dh.get_client(dd.id()).map(|c| &c != client).unwrap_or(true) {
                         continue;
                     }
 
-                    let offer_meta = meta.clone();
+                    let offer_meta = SourceMetadata {
+                        mime_types: mime_types.clone(),
+                        dnd_action: meta.dnd_action,
+                    };
 
                     let handle = dh.backend_handle();
                     // create a data offer
@@ -172,7 +290,7 @@ impl SeatData {
 
                     // advertize the offer to the client
                     dd.data_offer(&offer);
-                    for mime_type in meta.mime_types.iter().cloned() {
+                    for mime_type in mime_types.iter().cloned() {
                         offer.offer(mime_type);
                     }
                     dd.selection(Some(&offer));
@@ -180,10 +298,121 @@ impl SeatData {
             }
         }
     }
+
+    fn send_empty_selection(&self, dh: &DisplayHandle, client: &Client) {
+        for dd in &self.known_devices {
+            // skip data devices not belonging to our client
+            if dh.get_client(dd.id()).map(|c| &c != client).unwrap_or(true) {
+                continue;
+            }
+            dd.selection(None);
+        }
+    }
+
+    /// Mirror the current selection to a data control device
+    ///
+    /// Data control clients are privileged, so they get every selection regardless
+    /// of the keyboard focus and the offer filter.
+    fn send_data_control_selection<D>(&self, dh: &DisplayHandle, device: &ZwlrDataControlDeviceV1)
+    where
+        D: DataDeviceHandler,
+        D: 'static,
+    {
+        let target = SelectionTarget::Clipboard;
+        match self.selection {
+            Selection::Empty => data_control::clear_selection(device, target),
+            Selection::Client(ref data_source) => {
+                let mime_types = with_source_metadata(data_source, |meta| meta.mime_types.clone()).unwrap();
+                let source = data_source.clone();
+                data_control::offer_selection::<D, _>(
+                    dh,
+                    device,
+                    target,
+                    mime_types,
+                    move |_, mime_type, fd| source.send(mime_type, fd.as_raw_fd()),
+                );
+            }
+            Selection::Compositor(ref meta) => {
+                data_control::offer_selection::<D, _>(
+                    dh,
+                    device,
+                    target,
+                    meta.mime_types.clone(),
+                    |handler, mime_type, fd| DataDeviceHandler::send_selection(handler, mime_type, fd),
+                );
+            }
+            Selection::DataControl(ref source) => {
+                let mime_types =
+                    data_control::with_source_metadata(source, |meta| meta.mime_types.clone()).unwrap();
+                let source = source.clone();
+                data_control::offer_selection::<D, _>(
+                    dh,
+                    device,
+                    target,
+                    mime_types,
+                    move |_, mime_type, fd| source.send(mime_type, fd.as_raw_fd()),
+                );
+            }
+        }
+    }
+
+    fn sanitize_selection(&mut self) {
+        let alive = match self.selection {
+            Selection::Client(ref data_source) => data_source.alive(),
+            Selection::DataControl(ref source) => source.alive(),
+            Selection::Empty | Selection::Compositor(_) => true,
+        };
+        if !alive {
+            self.selection = Selection::Empty;
+        }
+    }
+}
+
+struct DataControlSelection {
+    source: ZwlrDataControlSourceV1,
+    offered_mime_types: Vec<String>,
+}
+
+impl<D> ObjectData<D> for DataControlSelection
+where
+    D: DataDeviceHandler,
+{
+    fn request(
+        self: Arc<Self>,
+        dh: &Handle,
+        handler: &mut D,
+        _client_id: ClientId,
+        msg: Message<ObjectId, OwnedFd>,
+    ) -> Option<Arc<dyn ObjectData<D>>> {
+        let dh = DisplayHandle::from(dh.clone());
+        if let Ok((_resource, wl_data_offer::Request::Receive { fd, mime_type })) =
+            WlDataOffer::parse_request(&dh, msg)
+        {
+            // check if the source and associated mime type is still valid
+            let valid = self.offered_mime_types.contains(&mime_type)
+                && data_control::with_source_metadata(&self.source, |meta| {
+                    meta.mime_types.contains(&mime_type)
+                })
+                .unwrap_or(false);
+            if !valid {
+                debug!(
+                    handler.data_device_state().log,
+                    "Denying a wl_data_offer.receive with invalid source."
+                );
+            } else {
+                self.source.send(mime_type, fd.as_raw_fd());
+            }
+        }
+
+        None
+    }
+
+    fn destroyed(&self, _data: &mut D, _client_id: ClientId, _object_id: ObjectId) {}
 }
 
 struct ClientSelection {
     source: WlDataSource,
+    offered_mime_types: Vec<String>,
 }
 
 impl<D> ObjectData<D> for ClientSelection
@@ -199,7 +428,7 @@ where
     ) -> Option<Arc<dyn ObjectData<D>>> {
         let dh = DisplayHandle::from(dh.clone());
         if let Ok((_resource, request)) = WlDataOffer::parse_request(&dh, msg) {
-            handle_client_selection(handler, request, &self.source);
+            handle_client_selection(handler, request, &self.source, &self.offered_mime_types);
         }
 
         None
@@ -208,8 +437,12 @@ where
     fn destroyed(&self, _data: &mut D, _client_id: ClientId, _object_id: ObjectId) {}
 }
 
-fn handle_client_selection<D>(state: &mut D, request: wl_data_offer::Request, source: &WlDataSource)
-where
+fn handle_client_selection<D>(
+    state: &mut D,
+    request: wl_data_offer::Request,
+    source: &WlDataSource,
+    offered_mime_types: &[String],
+) where
     D: DataDeviceHandler,
 {
     let data_device_state = state.data_device_state();
@@ -217,8 +450,8 @@ where
     // selection data offers only care about the `receive` event
     if let wl_data_offer::Request::Receive { fd, mime_type } = request {
         // check if the source and associated mime type is still valid
-        let valid =
-            with_source_metadata(source, |meta| meta.mime_types.contains(&mime_type)).unwrap_or(false);
+        let valid = offered_mime_types.contains(&mime_type)
+            && with_source_metadata(source, |meta| meta.mime_types.contains(&mime_type)).unwrap_or(false);
         // TODO:?
         // && source.as_ref().is_alive();
         if !valid {
@@ -282,3 +515,48 @@ pub fn handle_server_selection<D>(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{filter_mime_types, OfferFilter};
+
+    fn mime_types(types: &[&str]) -> Vec<String> {
+        types.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn no_filter_offers_everything() {
+        let offered = mime_types(&["text/plain", "image/png"]);
+        assert_eq!(filter_mime_types(&offered, None), Some(offered.clone()));
+    }
+
+    #[test]
+    fn image_blocking_filter() {
+        let filter: OfferFilter = Box::new(|mime_type: &str| !mime_type.starts_with("image/"));
+
+        let mixed = mime_types(&["image/png", "text/plain;charset=utf-8", "image/jpeg"]);
+        assert_eq!(
+            filter_mime_types(&mixed, Some(&filter)),
+            Some(mime_types(&["text/plain;charset=utf-8"]))
+        );
+
+        let images = mime_types(&["image/png", "image/jpeg"]);
+        assert_eq!(filter_mime_types(&images, Some(&filter)), None);
+    }
+
+    #[test]
+    fn binary_blocking_filter() {
+        let filter: OfferFilter = Box::new(|mime_type: &str| {
+            mime_type.starts_with("text/") || mime_type == "UTF8_STRING" || mime_type == "STRING"
+        });
+
+        let mixed = mime_types(&["application/octet-stream", "UTF8_STRING", "text/html"]);
+        assert_eq!(
+            filter_mime_types(&mixed, Some(&filter)),
+            Some(mime_types(&["UTF8_STRING", "text/html"]))
+        );
+
+        let binary = mime_types(&["application/octet-stream", "application/x-executable"]);
+        assert_eq!(filter_mime_types(&binary, Some(&filter)), None);
+    }
+}
@@ -71,7 +71,7 @@ where
     ) {
         let location = event.location;
         let serial = event.serial;
-        let time = event.time;
+        let time = event.time.as_millis_u32();
 
         // While the grab is active, no client has pointer focus
         handle.motion(data, None, event);
@@ -169,7 +169,7 @@ where
 
     fn button(&mut self, data: &mut D, handle: &mut PointerInnerHandle<'_, D>, event: &ButtonEvent) {
         let serial = event.serial;
-        let time = event.time;
+        let time = event.time.as_millis_u32();
 
         if handle.current_pressed().is_empty() {
             // the user dropped, proceed to the drop
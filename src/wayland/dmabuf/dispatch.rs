@@ -1,8 +1,12 @@
 use std::sync::{atomic::AtomicBool, Mutex};
 
-use wayland_protocols::wp::linux_dmabuf::zv1::server::{zwp_linux_buffer_params_v1, zwp_linux_dmabuf_v1};
+use wayland_protocols::wp::linux_dmabuf::zv1::server::{
+    zwp_linux_buffer_params_v1, zwp_linux_dmabuf_feedback_v1, zwp_linux_dmabuf_v1,
+};
 use wayland_server::{
-    protocol::wl_buffer, Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+    backend::{ClientId, ObjectId},
+    protocol::wl_buffer,
+    Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
 };
 
 use crate::{
@@ -11,8 +15,9 @@ use crate::{
 };
 
 use super::{
-    DmabufData, DmabufGlobal, DmabufGlobalData, DmabufHandler, DmabufParamsData, DmabufState, ImportError,
-    Modifier,
+    feedback::{add_surface_feedback_instance, remove_surface_feedback_instance},
+    DmabufData, DmabufFeedbackData, DmabufGlobal, DmabufGlobalData, DmabufHandler, DmabufParamsData,
+    DmabufState, ImportError, Modifier,
 };
 
 impl<D> Dispatch<wl_buffer::WlBuffer, Dmabuf, D> for DmabufState
@@ -42,6 +47,7 @@ impl<D> Dispatch<zwp_linux_dmabuf_v1::ZwpLinuxDmabufV1, DmabufData, D> for Dmabu
 where
     D: Dispatch<zwp_linux_dmabuf_v1::ZwpLinuxDmabufV1, DmabufData>
         + Dispatch<zwp_linux_buffer_params_v1::ZwpLinuxBufferParamsV1, DmabufParamsData>
+        + Dispatch<zwp_linux_dmabuf_feedback_v1::ZwpLinuxDmabufFeedbackV1, DmabufFeedbackData>
         + 'static,
 {
     fn request(
@@ -69,13 +75,56 @@ where
                 );
             }
 
-            zwp_linux_dmabuf_v1::Request::GetDefaultFeedback { id: _ } => unimplemented!("v4"),
+            zwp_linux_dmabuf_v1::Request::GetDefaultFeedback { id } => {
+                let feedback = data_init.init(id, DmabufFeedbackData { surface: None });
+                // only globals with a default feedback are advertised with version 4
+                if let Some(default_feedback) = data.default_feedback.as_ref() {
+                    default_feedback.send(&feedback);
+                }
+            }
+
+            zwp_linux_dmabuf_v1::Request::GetSurfaceFeedback { id, surface } => {
+                let feedback = data_init.init(
+                    id,
+                    DmabufFeedbackData {
+                        surface: Some(surface.clone()),
+                    },
+                );
+                if let Some(default_feedback) = data.default_feedback.as_ref() {
+                    add_surface_feedback_instance(&surface, feedback, default_feedback);
+                }
+            }
 
-            zwp_linux_dmabuf_v1::Request::GetSurfaceFeedback { id: _, surface: _ } => unimplemented!("v4"),
+            _ => unreachable!(),
+        }
+    }
+}
 
+impl<D> Dispatch<zwp_linux_dmabuf_feedback_v1::ZwpLinuxDmabufFeedbackV1, DmabufFeedbackData, D>
+    for DmabufState
+where
+    D: Dispatch<zwp_linux_dmabuf_feedback_v1::ZwpLinuxDmabufFeedbackV1, DmabufFeedbackData>,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _resource: &zwp_linux_dmabuf_feedback_v1::ZwpLinuxDmabufFeedbackV1,
+        request: zwp_linux_dmabuf_feedback_v1::Request,
+        _data: &DmabufFeedbackData,
+        _dh: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            zwp_linux_dmabuf_feedback_v1::Request::Destroy => {}
             _ => unreachable!(),
         }
     }
+
+    fn destroyed(_state: &mut D, _client: ClientId, object_id: ObjectId, data: &DmabufFeedbackData) {
+        if let Some(surface) = data.surface.as_ref() {
+            remove_surface_feedback_instance(surface, &object_id);
+        }
+    }
 }
 
 impl<D> GlobalDispatch<zwp_linux_dmabuf_v1::ZwpLinuxDmabufV1, DmabufGlobalData, D> for DmabufState
@@ -83,6 +132,7 @@ where
     D: GlobalDispatch<zwp_linux_dmabuf_v1::ZwpLinuxDmabufV1, DmabufGlobalData>
         + Dispatch<zwp_linux_dmabuf_v1::ZwpLinuxDmabufV1, DmabufData>
         + Dispatch<zwp_linux_buffer_params_v1::ZwpLinuxBufferParamsV1, DmabufParamsData>
+        + Dispatch<zwp_linux_dmabuf_feedback_v1::ZwpLinuxDmabufFeedbackV1, DmabufFeedbackData>
         + 'static,
 {
     fn bind(
@@ -95,6 +145,7 @@ where
     ) {
         let data = DmabufData {
             formats: global_data.formats.clone(),
+            default_feedback: global_data.default_feedback.clone(),
             id: global_data.id,
             logger: global_data.logger.clone(),
         };
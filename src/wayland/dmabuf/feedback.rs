@@ -0,0 +1,351 @@
+use std::{
+    ffi::CString,
+    os::unix::io::AsRawFd,
+    sync::{Arc, Mutex},
+};
+
+use indexmap::IndexSet;
+use wayland_protocols::wp::linux_dmabuf::zv1::server::zwp_linux_dmabuf_feedback_v1::{
+    TrancheFlags, ZwpLinuxDmabufFeedbackV1,
+};
+use wayland_server::{backend::ObjectId, protocol::wl_surface::WlSurface, Resource};
+
+use crate::{
+    backend::allocator::Format,
+    output::{Output, WeakOutput},
+    utils::sealed_file::SealedFile,
+    wayland::compositor::{self, SurfaceData, TraversalAction},
+};
+
+/// Feedback about the preferred devices and formats for dmabuf allocation
+///
+/// The feedback consists of a main device, which is used for buffer import, and a list of
+/// tranches in descending order of preference. Each tranche advertises a target device, for
+/// example a scan-out or render device, together with the formats preferred for it.
+///
+/// Use a [`DmabufFeedbackBuilder`] to create a new feedback.
+/// A feedback is cheap to clone, all clones share the same format table.
+#[derive(Debug, Clone)]
+pub struct DmabufFeedback(Arc<DmabufFeedbackInner>);
+
+#[derive(Debug)]
+struct DmabufFeedbackInner {
+    format_table: SealedFile,
+    formats: Vec<Format>,
+    main_device: libc::dev_t,
+    tranches: Vec<DmabufFeedbackTranche>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct DmabufFeedbackTranche {
+    target_device: libc::dev_t,
+    flags: TrancheFlags,
+    indices: Vec<u16>,
+}
+
+impl PartialEq for DmabufFeedback {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+            || (self.0.main_device == other.0.main_device
+                && self.0.formats == other.0.formats
+                && self.0.tranches == other.0.tranches)
+    }
+}
+
+impl DmabufFeedback {
+    /// The main device of this feedback
+    pub fn main_device(&self) -> libc::dev_t {
+        self.0.main_device
+    }
+
+    /// All formats advertised by this feedback
+    pub fn formats(&self) -> &[Format] {
+        &self.0.formats
+    }
+
+    /// Send this feedback to a `zwp_linux_dmabuf_feedback_v1` object
+    pub(crate) fn send(&self, feedback: &ZwpLinuxDmabufFeedbackV1) {
+        feedback.format_table(self.0.format_table.as_raw_fd(), self.0.format_table.size() as u32);
+        feedback.main_device(self.0.main_device.to_ne_bytes().to_vec());
+
+        for tranche in &self.0.tranches {
+            feedback.tranche_target_device(tranche.target_device.to_ne_bytes().to_vec());
+            feedback.tranche_flags(tranche.flags);
+            feedback.tranche_formats(
+                tranche
+                    .indices
+                    .iter()
+                    .flat_map(|index| index.to_ne_bytes())
+                    .collect(),
+            );
+            feedback.tranche_done();
+        }
+
+        feedback.done();
+    }
+}
+
+/// Builder for [`DmabufFeedback`]
+#[derive(Debug)]
+pub struct DmabufFeedbackBuilder {
+    main_device: libc::dev_t,
+    main_formats: Vec<Format>,
+    preferred_tranches: Vec<(libc::dev_t, TrancheFlags, Vec<Format>)>,
+}
+
+impl DmabufFeedbackBuilder {
+    /// Create a new builder for the given main device and its supported formats
+    ///
+    /// The main device is the device buffers are imported with, usually the render node
+    /// of the gpu used for compositing. The formats of the main device form the tranche
+    /// with the lowest preference.
+    pub fn new(main_device: libc::dev_t, formats: impl IntoIterator<Item = Format>) -> Self {
+        DmabufFeedbackBuilder {
+            main_device,
+            main_formats: formats.into_iter().collect(),
+            preferred_tranches: Vec::new(),
+        }
+    }
+
+    /// Add a tranche preferred over the tranche of the main device
+    ///
+    /// Tranches are sent in the order they are added, the first one added has the highest preference.
+    /// Formats of a tranche should also be supported by the main device.
+    pub fn add_preference_tranche(
+        mut self,
+        target_device: libc::dev_t,
+        flags: Option<TrancheFlags>,
+        formats: impl IntoIterator<Item = Format>,
+    ) -> Self {
+        self.preferred_tranches.push((
+            target_device,
+            flags.unwrap_or_else(TrancheFlags::empty),
+            formats.into_iter().collect(),
+        ));
+        self
+    }
+
+    /// Build the [`DmabufFeedback`]
+    ///
+    /// Fails if the format table could not be created.
+    pub fn build(self) -> Result<DmabufFeedback, std::io::Error> {
+        let tranches = self
+            .preferred_tranches
+            .into_iter()
+            .chain(std::iter::once((
+                self.main_device,
+                TrancheFlags::empty(),
+                self.main_formats,
+            )))
+            .collect::<Vec<_>>();
+
+        let formats = tranches
+            .iter()
+            .flat_map(|(_, _, formats)| formats.iter().copied())
+            .collect::<IndexSet<Format>>();
+        // indices into the format table are 16 bit wide
+        if formats.len() > u16::MAX as usize + 1 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "too many formats for the dmabuf feedback format table",
+            ));
+        }
+
+        let format_table = formats
+            .iter()
+            .flat_map(|format| {
+                let mut entry = [0u8; 16];
+                entry[..4].copy_from_slice(&(format.code as u32).to_ne_bytes());
+                entry[8..].copy_from_slice(&u64::from(format.modifier).to_ne_bytes());
+                entry
+            })
+            .collect::<Vec<u8>>();
+        let format_table = SealedFile::with_data(
+            CString::new("smithay-dmabuf-feedback-format-table").unwrap(),
+            &format_table,
+        )?;
+
+        let tranches = tranches
+            .into_iter()
+            .map(|(target_device, flags, tranche_formats)| DmabufFeedbackTranche {
+                target_device,
+                flags,
+                indices: tranche_formats
+                    .iter()
+                    .map(|format| formats.get_index_of(format).unwrap() as u16)
+                    .collect(),
+            })
+            .collect();
+
+        Ok(DmabufFeedback(Arc::new(DmabufFeedbackInner {
+            format_table,
+            formats: formats.into_iter().collect(),
+            main_device: self.main_device,
+            tranches,
+        })))
+    }
+}
+
+/// Data associated with a dmabuf feedback protocol object.
+#[derive(Debug)]
+pub struct DmabufFeedbackData {
+    pub(super) surface: Option<WlSurface>,
+}
+
+#[derive(Debug, Default)]
+struct SurfaceDmabufFeedbackState {
+    feedback: Option<DmabufFeedback>,
+    instances: Vec<ZwpLinuxDmabufFeedbackV1>,
+}
+
+/// Set the dmabuf feedback of a surface
+///
+/// The feedback is sent to all `zwp_linux_dmabuf_feedback_v1` objects the client created for the
+/// surface, and to the ones created later on. Before a feedback is set for a surface, the default
+/// feedback of the dmabuf global is used.
+///
+/// The feedback is only sent if it differs from the feedback previously set for the surface.
+/// This allows to call this function whenever the primary output of a surface is evaluated
+/// with the feedback matching the drm device of that output, clients are only notified
+/// once the surface actually migrated to an output backed by a different device.
+pub fn set_surface_feedback(surface: &WlSurface, feedback: &DmabufFeedback) {
+    compositor::with_states(surface, |states| set_feedback(states, feedback))
+}
+
+fn set_feedback(states: &SurfaceData, feedback: &DmabufFeedback) {
+    states
+        .data_map
+        .insert_if_missing_threadsafe(|| Mutex::new(SurfaceDmabufFeedbackState::default()));
+    let mut state = states
+        .data_map
+        .get::<Mutex<SurfaceDmabufFeedbackState>>()
+        .unwrap()
+        .lock()
+        .unwrap();
+
+    if state.feedback.as_ref() == Some(feedback) {
+        return;
+    }
+
+    for instance in &state.instances {
+        feedback.send(instance);
+    }
+    state.feedback = Some(feedback.clone());
+}
+
+/// Selects the dmabuf feedback of surfaces based on the output they are primarily shown on
+///
+/// Every output can be assigned the feedback matching the drm device it is connected to,
+/// surfaces without a primary output or on an output without feedback use the default feedback.
+///
+/// Call [`update_surface_tree`](DmabufFeedbackManager::update_surface_tree) for the surfaces of
+/// an output after rendering it, e.g. next to sending the frame callbacks, with the same primary
+/// scan-out output function (like `desktop::utils::surface_primary_scanout_output`).
+/// The feedback is only re-sent once the primary output of a surface changed to an output with
+/// a different feedback, see [`set_surface_feedback`].
+#[derive(Debug, Clone)]
+pub struct DmabufFeedbackManager {
+    default_feedback: DmabufFeedback,
+    outputs: Vec<(WeakOutput, DmabufFeedback)>,
+}
+
+impl DmabufFeedbackManager {
+    /// Create a new manager, using the given feedback for surfaces not shown on any known output
+    ///
+    /// This should usually be the default feedback of the dmabuf global.
+    pub fn new(default_feedback: DmabufFeedback) -> Self {
+        DmabufFeedbackManager {
+            default_feedback,
+            outputs: Vec::new(),
+        }
+    }
+
+    /// Set the feedback for surfaces primarily shown on the given output
+    pub fn set_output_feedback(&mut self, output: &Output, feedback: DmabufFeedback) {
+        self.remove_output_feedback(output);
+        self.outputs.push((output.downgrade(), feedback));
+    }
+
+    /// Remove the feedback of the given output, e.g. after it was disconnected
+    ///
+    /// Surfaces primarily shown on the output fall back to the default feedback.
+    pub fn remove_output_feedback(&mut self, output: &Output) {
+        self.outputs
+            .retain(|(weak, _)| weak.upgrade().map(|o| &o != output).unwrap_or(false));
+    }
+
+    /// Returns the feedback for surfaces primarily shown on the given output
+    pub fn feedback_for_output(&self, output: Option<&Output>) -> &DmabufFeedback {
+        output
+            .and_then(|output| self.outputs.iter().find(|(weak, _)| weak == output))
+            .map(|(_, feedback)| feedback)
+            .unwrap_or(&self.default_feedback)
+    }
+
+    /// Update the feedback of a single surface from its primary output
+    pub fn update_surface(&self, surface: &WlSurface, primary_output: Option<&Output>) {
+        set_surface_feedback(surface, self.feedback_for_output(primary_output));
+    }
+
+    /// Update the feedback of a surface and its subsurfaces
+    ///
+    /// `primary_scan_out_output` is called for every surface of the tree to determine its primary output.
+    pub fn update_surface_tree<F>(&self, surface: &WlSurface, mut primary_scan_out_output: F)
+    where
+        F: FnMut(&WlSurface, &SurfaceData) -> Option<Output>,
+    {
+        compositor::with_surface_tree_downward(
+            surface,
+            (),
+            |_, _, &()| TraversalAction::DoChildren(()),
+            |surface, states, &()| {
+                let output = primary_scan_out_output(surface, states);
+                set_feedback(states, self.feedback_for_output(output.as_ref()));
+            },
+            |_, _, &()| true,
+        );
+    }
+}
+
+/// Register a new surface feedback object and send the current feedback of the surface to it
+pub(super) fn add_surface_feedback_instance(
+    surface: &WlSurface,
+    instance: ZwpLinuxDmabufFeedbackV1,
+    default_feedback: &DmabufFeedback,
+) {
+    compositor::with_states(surface, |states| {
+        states
+            .data_map
+            .insert_if_missing_threadsafe(|| Mutex::new(SurfaceDmabufFeedbackState::default()));
+        let mut state = states
+            .data_map
+            .get::<Mutex<SurfaceDmabufFeedbackState>>()
+            .unwrap()
+            .lock()
+            .unwrap();
+
+        state
+            .feedback
+            .as_ref()
+            .unwrap_or(default_feedback)
+            .send(&instance);
+        state.instances.push(instance);
+    })
+}
+
+/// Remove a destroyed surface feedback object
+pub(super) fn remove_surface_feedback_instance(surface: &WlSurface, instance: &ObjectId) {
+    if !surface.alive() {
+        return;
+    }
+
+    compositor::with_states(surface, |states| {
+        if let Some(state) = states.data_map.get::<Mutex<SurfaceDmabufFeedbackState>>() {
+            state
+                .lock()
+                .unwrap()
+                .instances
+                .retain(|feedback| &feedback.id() != instance);
+        }
+    })
+}
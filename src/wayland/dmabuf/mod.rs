@@ -18,6 +18,16 @@
 //! Accessing a [`Dmabuf`] associated with a [`WlBuffer`](wayland_server::protocol::wl_buffer::WlBuffer)
 //! may be achieved using [`get_dmabuf`].
 //!
+//! ## Dmabuf feedback
+//!
+//! Version 4 of the protocol allows the compositor to advertise the preferred devices and formats
+//! for buffer allocation as a [`DmabufFeedback`]. This is required for clients to pick the right gpu
+//! on multi-gpu systems. To enable it create the global with a default feedback using
+//! [`DmabufState::create_global_with_default_feedback`]. Surface specific feedback, e.g. when a surface
+//! is displayed on an output connected to a different device, can be provided with [`set_surface_feedback`].
+//! A [`DmabufFeedbackManager`] can take care of this, by selecting the feedback of a surface
+//! based on its primary output.
+//!
 //! ```no_run
 //! # extern crate wayland_server;
 //! use smithay::{
@@ -90,6 +100,11 @@
 //! ```
 
 mod dispatch;
+mod feedback;
+
+pub use feedback::{
+    set_surface_feedback, DmabufFeedback, DmabufFeedbackBuilder, DmabufFeedbackData, DmabufFeedbackManager,
+};
 
 use std::{
     collections::HashMap,
@@ -152,6 +167,59 @@ impl DmabufState {
         self.create_global_with_filter::<D, _, L>(display, formats, |_| true, logger)
     }
 
+    /// Creates a dmabuf global with the specified default feedback.
+    ///
+    /// The global is advertised with version 4 of the protocol, the supported formats are taken from
+    /// the feedback. Clients may request the default feedback or a surface specific feedback,
+    /// see [`set_surface_feedback`].
+    pub fn create_global_with_default_feedback<D, L>(
+        &mut self,
+        display: &DisplayHandle,
+        default_feedback: &DmabufFeedback,
+        logger: L,
+    ) -> DmabufGlobal
+    where
+        D: GlobalDispatch<zwp_linux_dmabuf_v1::ZwpLinuxDmabufV1, DmabufGlobalData>
+            + BufferHandler
+            + DmabufHandler
+            + 'static,
+        L: Into<Option<::slog::Logger>>,
+    {
+        self.create_global_with_filter_and_default_feedback::<D, _, L>(
+            display,
+            default_feedback,
+            |_| true,
+            logger,
+        )
+    }
+
+    /// Creates a dmabuf global with the specified default feedback and a filter.
+    ///
+    /// See [`DmabufState::create_global_with_default_feedback`] and [`DmabufState::create_global_with_filter`].
+    pub fn create_global_with_filter_and_default_feedback<D, F, L>(
+        &mut self,
+        display: &DisplayHandle,
+        default_feedback: &DmabufFeedback,
+        filter: F,
+        logger: L,
+    ) -> DmabufGlobal
+    where
+        D: GlobalDispatch<zwp_linux_dmabuf_v1::ZwpLinuxDmabufV1, DmabufGlobalData>
+            + BufferHandler
+            + DmabufHandler
+            + 'static,
+        F: for<'c> Fn(&'c Client) -> bool + Send + Sync + 'static,
+        L: Into<Option<::slog::Logger>>,
+    {
+        self.create_global_internal::<D, _, L>(
+            display,
+            default_feedback.formats().to_vec(),
+            Some(default_feedback.clone()),
+            filter,
+            logger,
+        )
+    }
+
     /// Creates a dmabuf global with the specified supported formats.
     ///
     /// This function unlike [`DmabufState::create_global`] also allows you to specify a filter function to
@@ -164,6 +232,25 @@ impl DmabufState {
         filter: F,
         logger: L,
     ) -> DmabufGlobal
+    where
+        D: GlobalDispatch<zwp_linux_dmabuf_v1::ZwpLinuxDmabufV1, DmabufGlobalData>
+            + BufferHandler
+            + DmabufHandler
+            + 'static,
+        F: for<'c> Fn(&'c Client) -> bool + Send + Sync + 'static,
+        L: Into<Option<::slog::Logger>>,
+    {
+        self.create_global_internal::<D, _, L>(display, formats, None, filter, logger)
+    }
+
+    fn create_global_internal<D, F, L>(
+        &mut self,
+        display: &DisplayHandle,
+        formats: Vec<Format>,
+        default_feedback: Option<DmabufFeedback>,
+        filter: F,
+        logger: L,
+    ) -> DmabufGlobal
     where
         D: GlobalDispatch<zwp_linux_dmabuf_v1::ZwpLinuxDmabufV1, DmabufGlobalData>
             + BufferHandler
@@ -176,15 +263,20 @@ impl DmabufState {
         let logger = crate::slog_or_fallback(logger)
             .new(slog::o!("smithay_module" => "wayland_dmabuf", "global" => id));
         let formats = Arc::new(formats);
+        let version = if default_feedback.is_some() {
+            FEEDBACK_GLOBAL_VERSION
+        } else {
+            GLOBAL_VERSION
+        };
         let data = DmabufGlobalData {
             filter: Box::new(filter),
             formats,
+            default_feedback,
             id,
             logger,
         };
 
-        let global =
-            display.create_global::<D, zwp_linux_dmabuf_v1::ZwpLinuxDmabufV1, _>(GLOBAL_VERSION, data);
+        let global = display.create_global::<D, zwp_linux_dmabuf_v1::ZwpLinuxDmabufV1, _>(version, data);
         self.globals.insert(id, global);
 
         DmabufGlobal { id }
@@ -213,6 +305,7 @@ impl DmabufState {
 pub struct DmabufGlobalData {
     filter: Box<dyn for<'c> Fn(&'c Client) -> bool + Send + Sync>,
     formats: Arc<Vec<Format>>,
+    default_feedback: Option<DmabufFeedback>,
     id: usize,
     logger: slog::Logger,
 }
@@ -221,6 +314,7 @@ pub struct DmabufGlobalData {
 #[derive(Debug)]
 pub struct DmabufData {
     formats: Arc<Vec<Format>>,
+    default_feedback: Option<DmabufFeedback>,
     id: usize,
     logger: slog::Logger,
 }
@@ -309,6 +403,8 @@ macro_rules! delegate_dmabuf {
             $crate::reexports::wayland_protocols::wp::linux_dmabuf::zv1::server::zwp_linux_dmabuf_v1::ZwpLinuxDmabufV1;
         type __ZwpLinuxBufferParamsV1 =
             $crate::reexports::wayland_protocols::wp::linux_dmabuf::zv1::server::zwp_linux_buffer_params_v1::ZwpLinuxBufferParamsV1;
+        type __ZwpLinuxDmabufFeedbackV1 =
+            $crate::reexports::wayland_protocols::wp::linux_dmabuf::zv1::server::zwp_linux_dmabuf_feedback_v1::ZwpLinuxDmabufFeedbackV1;
 
         $crate::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
             __ZwpLinuxDmabufV1: $crate::wayland::dmabuf::DmabufGlobalData
@@ -320,6 +416,9 @@ macro_rules! delegate_dmabuf {
         $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
             __ZwpLinuxBufferParamsV1: $crate::wayland::dmabuf::DmabufParamsData
         ] => $crate::wayland::dmabuf::DmabufState);
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            __ZwpLinuxDmabufFeedbackV1: $crate::wayland::dmabuf::DmabufFeedbackData
+        ] => $crate::wayland::dmabuf::DmabufState);
         $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
             $crate::reexports::wayland_server::protocol::wl_buffer::WlBuffer: $crate::backend::allocator::dmabuf::Dmabuf
         ] => $crate::wayland::dmabuf::DmabufState);
@@ -327,6 +426,7 @@ macro_rules! delegate_dmabuf {
 }
 
 const GLOBAL_VERSION: u32 = 3;
+const FEEDBACK_GLOBAL_VERSION: u32 = 4;
 
 impl DmabufParamsData {
     /// Emits a protocol error if the params have already been used to create a dmabuf.
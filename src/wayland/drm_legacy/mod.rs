@@ -0,0 +1,181 @@
+//! Minimal implementation of the legacy `wl_drm` protocol
+//!
+//! `wl_drm` is a mesa-specific protocol, that was used to share buffers between
+//! clients and the compositor before `zwp_linux_dmabuf_v1` existed. Some clients,
+//! most notably Xwayland, still try to bind it during their initialization and log
+//! confusing errors, if it is not present.
+//!
+//! This module only implements enough of the protocol to satisfy these clients:
+//! it advertises the path of the drm device, forwards authentication requests to
+//! the drm device and reports `prime` support. Creating buffers through `wl_drm`
+//! is not supported, use [`dmabuf`](crate::wayland::dmabuf) instead.
+//!
+//! This module is only available with the `wayland_drm_legacy` feature, which also pulls in
+//! `wayland-scanner` to generate the protocol bindings.
+//!
+//! ```no_run
+//! # extern crate wayland_server;
+//! use smithay::delegate_drm_legacy;
+//! use smithay::wayland::drm_legacy::DrmLegacyState;
+//! # use smithay::backend::drm::DrmDeviceFd;
+//! # use std::path::PathBuf;
+//!
+//! # struct State;
+//! # let mut display = wayland_server::Display::<State>::new().unwrap();
+//! # let device_fd: DrmDeviceFd = unimplemented!();
+//! let drm_legacy_state = DrmLegacyState::new::<State, _>(
+//!     &display.handle(),
+//!     PathBuf::from("/dev/dri/renderD128"),
+//!     device_fd,
+//!     None, // insert a logger here
+//! );
+//!
+//! delegate_drm_legacy!(State);
+//! ```
+
+use std::{os::unix::io::AsFd, path::PathBuf, sync::Arc};
+
+use wayland_server::{
+    backend::GlobalId, Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+};
+
+use crate::backend::drm::DrmDeviceFd;
+
+mod generated {
+    #![allow(dead_code, non_camel_case_types, unused_unsafe, unused_variables)]
+    #![allow(non_upper_case_globals, non_snake_case, unused_imports)]
+    #![allow(missing_docs, clippy::all)]
+
+    pub mod server {
+        use wayland_server;
+        use wayland_server::protocol::*;
+
+        pub mod __interfaces {
+            use wayland_server::backend as wayland_backend;
+            use wayland_server::protocol::__interfaces::*;
+            wayland_scanner::generate_interfaces!("resources/protocols/wayland-drm.xml");
+        }
+        use self::__interfaces::*;
+
+        wayland_scanner::generate_server_code!("resources/protocols/wayland-drm.xml");
+    }
+}
+
+pub use generated::server::wl_drm;
+
+/// Data associated with the `wl_drm` global
+#[derive(Debug, Clone)]
+pub struct DrmLegacyGlobalData {
+    device_path: Arc<PathBuf>,
+    device_fd: DrmDeviceFd,
+    log: slog::Logger,
+}
+
+/// State of the `wl_drm` global
+#[derive(Debug)]
+pub struct DrmLegacyState {
+    global: GlobalId,
+}
+
+impl DrmLegacyState {
+    /// Create a new `wl_drm` global
+    ///
+    /// `device_path` is advertised to clients, `device_fd` is used to authenticate clients
+    /// and therefor needs to be the drm master of the device.
+    pub fn new<D, L>(display: &DisplayHandle, device_path: PathBuf, device_fd: DrmDeviceFd, logger: L) -> Self
+    where
+        D: GlobalDispatch<wl_drm::WlDrm, DrmLegacyGlobalData>
+            + Dispatch<wl_drm::WlDrm, DrmLegacyGlobalData>
+            + 'static,
+        L: Into<Option<::slog::Logger>>,
+    {
+        let log = crate::slog_or_fallback(logger).new(slog::o!("smithay_module" => "wl_drm"));
+        let data = DrmLegacyGlobalData {
+            device_path: Arc::new(device_path),
+            device_fd,
+            log,
+        };
+        let global = display.create_global::<D, wl_drm::WlDrm, _>(2, data);
+
+        DrmLegacyState { global }
+    }
+
+    /// Returns the `wl_drm` global.
+    pub fn global(&self) -> GlobalId {
+        self.global.clone()
+    }
+}
+
+impl<D> GlobalDispatch<wl_drm::WlDrm, DrmLegacyGlobalData, D> for DrmLegacyState
+where
+    D: GlobalDispatch<wl_drm::WlDrm, DrmLegacyGlobalData>
+        + Dispatch<wl_drm::WlDrm, DrmLegacyGlobalData>
+        + 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<wl_drm::WlDrm>,
+        global_data: &DrmLegacyGlobalData,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        let drm = data_init.init(resource, global_data.clone());
+
+        drm.device(global_data.device_path.to_string_lossy().into_owned());
+        if drm.version() >= 2 {
+            drm.capabilities(wl_drm::Capability::Prime as u32);
+        }
+    }
+}
+
+impl<D> Dispatch<wl_drm::WlDrm, DrmLegacyGlobalData, D> for DrmLegacyState
+where
+    D: GlobalDispatch<wl_drm::WlDrm, DrmLegacyGlobalData>
+        + Dispatch<wl_drm::WlDrm, DrmLegacyGlobalData>
+        + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        drm: &wl_drm::WlDrm,
+        request: wl_drm::Request,
+        data: &DrmLegacyGlobalData,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            wl_drm::Request::Authenticate { id } => {
+                match drm_ffi::auth::auth_magic_token(data.device_fd.as_fd(), id) {
+                    Ok(_) => drm.authenticated(),
+                    Err(err) => {
+                        slog::warn!(data.log, "Failed to authenticate client: {}", err);
+                        drm.post_error(wl_drm::Error::AuthenticateFail, "authentication failed");
+                    }
+                }
+            }
+            wl_drm::Request::CreateBuffer { .. }
+            | wl_drm::Request::CreatePlanarBuffer { .. }
+            | wl_drm::Request::CreatePrimeBuffer { .. } => {
+                drm.post_error(
+                    wl_drm::Error::InvalidName,
+                    "buffer creation through wl_drm is not supported",
+                );
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Macro to delegate implementation of the `wl_drm` protocol
+#[macro_export]
+macro_rules! delegate_drm_legacy {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        $crate::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::wayland::drm_legacy::wl_drm::WlDrm: $crate::wayland::drm_legacy::DrmLegacyGlobalData
+        ] => $crate::wayland::drm_legacy::DrmLegacyState);
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::wayland::drm_legacy::wl_drm::WlDrm: $crate::wayland::drm_legacy::DrmLegacyGlobalData
+        ] => $crate::wayland::drm_legacy::DrmLegacyState);
+    };
+}
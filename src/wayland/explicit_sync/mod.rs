@@ -0,0 +1,446 @@
+//! Utilities for handling the `zwp_linux_explicit_synchronization_v1` protocol
+//!
+//! This protocol allows clients to attach an acquire fence to the buffer they commit,
+//! which the compositor has to wait for before accessing the buffer, and to request a
+//! release object, through which the compositor signals once it is done with the buffer.
+//! Fences are only supported for dmabuf based buffers.
+//!
+//! ## How to use it
+//!
+//! ### Initialization
+//!
+//! To initialize this implementation, create [`ExplicitSyncState`], store it in your `State` struct and
+//! implement the required traits, as shown in this example:
+//!
+//! ```
+//! use smithay::wayland::explicit_sync::ExplicitSyncState;
+//! use smithay::delegate_explicit_sync;
+//!
+//! # struct State;
+//! # let mut display = wayland_server::Display::<State>::new().unwrap();
+//!
+//! // Create the explicit synchronization state:
+//! let explicit_sync_state = ExplicitSyncState::new::<State, _>(
+//!     &display.handle(), // the display
+//!     None // provide a logger, if you want
+//! );
+//!
+//! // implement Dispatch for the explicit synchronization types
+//! delegate_explicit_sync!(State);
+//!
+//! // You're now ready to go!
+//! ```
+//!
+//! ### Use the synchronization state
+//!
+//! The [`synchronization state`](ExplicitSyncCachedState) is double-buffered and
+//! can be accessed by using the [`with_states`] function.
+//!
+//! The gpu has to wait for the acquire fence before sampling the buffer of a commit, and the release
+//! object has to be resolved once the buffer is no longer accessed. The renderer helpers take care of
+//! both: the gles renderer waits for the acquire fence when importing the buffer through
+//! `ImportDmaWl::import_dma_buffer`, and `on_commit_buffer_handler` resolves the release object once
+//! the buffer is replaced, using the fence last passed to `RendererSurfaceState::set_release_fence`.
+//! With the gles renderer this should be the completion fence of the last frame sampling the buffer
+//! (`Gles2Frame::completion_fence`), set once that frame has been committed to KMS.
+//!
+//! Compositors not using these helpers have to take both out of the current state themselves:
+//!
+//! ```no_compile
+//! let (acquire_fence, release) = with_states(surface, |states| {
+//!     let mut state = states.cached_state.current::<ExplicitSyncCachedState>();
+//!     (state.acquire_fence.take(), state.release.take())
+//! });
+//! ```
+
+use std::{
+    cell::RefCell,
+    os::unix::io::{AsRawFd, OwnedFd},
+};
+
+use wayland_protocols::wp::linux_explicit_synchronization::zv1::server::{
+    zwp_linux_buffer_release_v1, zwp_linux_explicit_synchronization_v1, zwp_linux_surface_synchronization_v1,
+};
+use wayland_server::{
+    backend::GlobalId, protocol::wl_surface, Dispatch, DisplayHandle, GlobalDispatch, Resource,
+};
+
+use crate::utils::IsAlive;
+
+use super::{
+    compositor::{self, with_states, BufferAssignment, Cacheable, SurfaceAttributes},
+    dmabuf::get_dmabuf,
+};
+
+/// State of the zwp_linux_explicit_synchronization_v1 Global
+#[derive(Debug)]
+pub struct ExplicitSyncState {
+    global: GlobalId,
+}
+
+impl ExplicitSyncState {
+    /// Create new [`zwp_linux_explicit_synchronization_v1`](zwp_linux_explicit_synchronization_v1) global.
+    ///
+    /// It returns the explicit synchronization state, which you can drop to remove these global from
+    /// the event loop in the future.
+    pub fn new<D, L>(display: &DisplayHandle, log: L) -> ExplicitSyncState
+    where
+        D: GlobalDispatch<
+                zwp_linux_explicit_synchronization_v1::ZwpLinuxExplicitSynchronizationV1,
+                slog::Logger,
+            > + Dispatch<zwp_linux_explicit_synchronization_v1::ZwpLinuxExplicitSynchronizationV1, slog::Logger>
+            + Dispatch<
+                zwp_linux_surface_synchronization_v1::ZwpLinuxSurfaceSynchronizationV1,
+                ExplicitSyncSurfaceData,
+            > + Dispatch<zwp_linux_buffer_release_v1::ZwpLinuxBufferReleaseV1, ()>
+            + 'static,
+        L: Into<Option<slog::Logger>>,
+    {
+        ExplicitSyncState {
+            global: display.create_global::<
+                D,
+                zwp_linux_explicit_synchronization_v1::ZwpLinuxExplicitSynchronizationV1,
+                slog::Logger,
+            >(
+                2,
+                crate::slog_or_fallback(log)
+                    .new(slog::o!("smithay_module" => "zwp_linux_explicit_synchronization")),
+            ),
+        }
+    }
+
+    /// Returns the explicit synchronization global.
+    pub fn global(&self) -> GlobalId {
+        self.global.clone()
+    }
+}
+
+impl<D>
+    GlobalDispatch<zwp_linux_explicit_synchronization_v1::ZwpLinuxExplicitSynchronizationV1, slog::Logger, D>
+    for ExplicitSyncState
+where
+    D: GlobalDispatch<zwp_linux_explicit_synchronization_v1::ZwpLinuxExplicitSynchronizationV1, slog::Logger>
+        + Dispatch<zwp_linux_explicit_synchronization_v1::ZwpLinuxExplicitSynchronizationV1, slog::Logger>
+        + Dispatch<
+            zwp_linux_surface_synchronization_v1::ZwpLinuxSurfaceSynchronizationV1,
+            ExplicitSyncSurfaceData,
+        > + Dispatch<zwp_linux_buffer_release_v1::ZwpLinuxBufferReleaseV1, ()>,
+{
+    fn bind(
+        _state: &mut D,
+        _handle: &DisplayHandle,
+        _client: &wayland_server::Client,
+        resource: wayland_server::New<
+            zwp_linux_explicit_synchronization_v1::ZwpLinuxExplicitSynchronizationV1,
+        >,
+        global_data: &slog::Logger,
+        data_init: &mut wayland_server::DataInit<'_, D>,
+    ) {
+        data_init.init(resource, global_data.clone());
+    }
+}
+
+impl<D> Dispatch<zwp_linux_explicit_synchronization_v1::ZwpLinuxExplicitSynchronizationV1, slog::Logger, D>
+    for ExplicitSyncState
+where
+    D: GlobalDispatch<zwp_linux_explicit_synchronization_v1::ZwpLinuxExplicitSynchronizationV1, slog::Logger>
+        + Dispatch<zwp_linux_explicit_synchronization_v1::ZwpLinuxExplicitSynchronizationV1, slog::Logger>
+        + Dispatch<
+            zwp_linux_surface_synchronization_v1::ZwpLinuxSurfaceSynchronizationV1,
+            ExplicitSyncSurfaceData,
+        > + Dispatch<zwp_linux_buffer_release_v1::ZwpLinuxBufferReleaseV1, ()>,
+{
+    fn request(
+        _state: &mut D,
+        _client: &wayland_server::Client,
+        resource: &zwp_linux_explicit_synchronization_v1::ZwpLinuxExplicitSynchronizationV1,
+        request: zwp_linux_explicit_synchronization_v1::Request,
+        data: &slog::Logger,
+        _dhandle: &DisplayHandle,
+        data_init: &mut wayland_server::DataInit<'_, D>,
+    ) {
+        match request {
+            zwp_linux_explicit_synchronization_v1::Request::GetSynchronization { id, surface } => {
+                let already_synchronized = with_states(&surface, |states| {
+                    states
+                        .data_map
+                        .get::<RefCell<Option<SynchronizationMarker>>>()
+                        .map(|v| v.borrow().is_some())
+                        .unwrap_or(false)
+                });
+
+                if already_synchronized {
+                    resource.post_error(
+                        zwp_linux_explicit_synchronization_v1::Error::SynchronizationExists as u32,
+                        "the surface already has a synchronization object associated".to_string(),
+                    );
+                    return;
+                }
+
+                compositor::add_pre_commit_hook(&surface, explicit_sync_commit_hook);
+
+                let synchronization = data_init.init(
+                    id,
+                    ExplicitSyncSurfaceData {
+                        surface: surface.clone(),
+                        log: data.new(slog::o!("surface" => format!("{:?}", surface))),
+                    },
+                );
+                with_states(&surface, |states| {
+                    states
+                        .data_map
+                        .insert_if_missing(|| RefCell::new(Option::<SynchronizationMarker>::None));
+                    *states
+                        .data_map
+                        .get::<RefCell<Option<SynchronizationMarker>>>()
+                        .unwrap()
+                        .borrow_mut() = Some(SynchronizationMarker(synchronization));
+                })
+            }
+            zwp_linux_explicit_synchronization_v1::Request::Destroy => {
+                // All is already handled by our destructor
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D>
+    Dispatch<
+        zwp_linux_surface_synchronization_v1::ZwpLinuxSurfaceSynchronizationV1,
+        ExplicitSyncSurfaceData,
+        D,
+    > for ExplicitSyncState
+where
+    D: GlobalDispatch<zwp_linux_explicit_synchronization_v1::ZwpLinuxExplicitSynchronizationV1, slog::Logger>
+        + Dispatch<zwp_linux_explicit_synchronization_v1::ZwpLinuxExplicitSynchronizationV1, slog::Logger>
+        + Dispatch<
+            zwp_linux_surface_synchronization_v1::ZwpLinuxSurfaceSynchronizationV1,
+            ExplicitSyncSurfaceData,
+        > + Dispatch<zwp_linux_buffer_release_v1::ZwpLinuxBufferReleaseV1, ()>,
+{
+    fn request(
+        _state: &mut D,
+        _client: &wayland_server::Client,
+        resource: &zwp_linux_surface_synchronization_v1::ZwpLinuxSurfaceSynchronizationV1,
+        request: zwp_linux_surface_synchronization_v1::Request,
+        data: &ExplicitSyncSurfaceData,
+        _dhandle: &DisplayHandle,
+        data_init: &mut wayland_server::DataInit<'_, D>,
+    ) {
+        match request {
+            zwp_linux_surface_synchronization_v1::Request::Destroy => {
+                if data.surface.alive() {
+                    with_states(&data.surface, |states| {
+                        states
+                            .data_map
+                            .get::<RefCell<Option<SynchronizationMarker>>>()
+                            .unwrap()
+                            .borrow_mut()
+                            .take();
+                    });
+                }
+            }
+            zwp_linux_surface_synchronization_v1::Request::SetAcquireFence { fd } => {
+                if !data.surface.alive() {
+                    resource.post_error(
+                        zwp_linux_surface_synchronization_v1::Error::NoSurface as u32,
+                        "the wl_surface was destroyed".to_string(),
+                    );
+                    return;
+                }
+
+                with_states(&data.surface, |states| {
+                    let mut state = states.cached_state.pending::<ExplicitSyncCachedState>();
+                    if state.acquire_fence.is_some() {
+                        resource.post_error(
+                            zwp_linux_surface_synchronization_v1::Error::DuplicateFence as u32,
+                            "an acquire fence was already set for this commit".to_string(),
+                        );
+                        return;
+                    }
+                    slog::trace!(data.log, "New acquire fence: {}", fd.as_raw_fd());
+                    state.acquire_fence = Some(fd);
+                });
+            }
+            zwp_linux_surface_synchronization_v1::Request::GetRelease { release } => {
+                if !data.surface.alive() {
+                    resource.post_error(
+                        zwp_linux_surface_synchronization_v1::Error::NoSurface as u32,
+                        "the wl_surface was destroyed".to_string(),
+                    );
+                    return;
+                }
+
+                let duplicate = with_states(&data.surface, |states| {
+                    states
+                        .cached_state
+                        .pending::<ExplicitSyncCachedState>()
+                        .release
+                        .is_some()
+                });
+                if duplicate {
+                    resource.post_error(
+                        zwp_linux_surface_synchronization_v1::Error::DuplicateRelease as u32,
+                        "a release object was already requested for this commit".to_string(),
+                    );
+                    return;
+                }
+
+                let release = data_init.init(release, ());
+                with_states(&data.surface, |states| {
+                    states.cached_state.pending::<ExplicitSyncCachedState>().release =
+                        Some(ExplicitBufferRelease { release });
+                });
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<zwp_linux_buffer_release_v1::ZwpLinuxBufferReleaseV1, (), D> for ExplicitSyncState
+where
+    D: GlobalDispatch<zwp_linux_explicit_synchronization_v1::ZwpLinuxExplicitSynchronizationV1, slog::Logger>
+        + Dispatch<zwp_linux_explicit_synchronization_v1::ZwpLinuxExplicitSynchronizationV1, slog::Logger>
+        + Dispatch<
+            zwp_linux_surface_synchronization_v1::ZwpLinuxSurfaceSynchronizationV1,
+            ExplicitSyncSurfaceData,
+        > + Dispatch<zwp_linux_buffer_release_v1::ZwpLinuxBufferReleaseV1, ()>,
+{
+    fn request(
+        _state: &mut D,
+        _client: &wayland_server::Client,
+        _resource: &zwp_linux_buffer_release_v1::ZwpLinuxBufferReleaseV1,
+        _request: zwp_linux_buffer_release_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        _data_init: &mut wayland_server::DataInit<'_, D>,
+    ) {
+        // zwp_linux_buffer_release_v1 has no requests
+    }
+}
+
+/// State of a single synchronization object attached to a surface
+#[derive(Debug)]
+pub struct ExplicitSyncSurfaceData {
+    surface: wl_surface::WlSurface,
+    log: slog::Logger,
+}
+
+struct SynchronizationMarker(zwp_linux_surface_synchronization_v1::ZwpLinuxSurfaceSynchronizationV1);
+
+fn explicit_sync_commit_hook(_dh: &DisplayHandle, surface: &wl_surface::WlSurface) {
+    with_states(surface, |states| {
+        let synchronization = states
+            .data_map
+            .get::<RefCell<Option<SynchronizationMarker>>>()
+            .unwrap()
+            .borrow();
+        let synchronization = match &*synchronization {
+            Some(synchronization) => synchronization,
+            None => return,
+        };
+
+        let mut sync_state = states.cached_state.pending::<ExplicitSyncCachedState>();
+        if sync_state.acquire_fence.is_none() && sync_state.release.is_none() {
+            return;
+        }
+
+        let buffer = match &states.cached_state.pending::<SurfaceAttributes>().buffer {
+            Some(BufferAssignment::NewBuffer(buffer)) => Some(buffer.clone()),
+            _ => None,
+        };
+        match buffer {
+            None => {
+                synchronization.0.post_error(
+                    zwp_linux_surface_synchronization_v1::Error::NoBuffer as u32,
+                    "no buffer was attached".to_string(),
+                );
+                *sync_state = ExplicitSyncCachedState::default();
+            }
+            Some(buffer) if sync_state.acquire_fence.is_some() && get_dmabuf(&buffer).is_err() => {
+                synchronization.0.post_error(
+                    zwp_linux_surface_synchronization_v1::Error::UnsupportedBuffer as u32,
+                    "acquire fences are only supported for dmabuf buffers".to_string(),
+                );
+                *sync_state = ExplicitSyncCachedState::default();
+            }
+            Some(_) => {}
+        }
+    });
+}
+
+/// Release object for a committed buffer
+///
+/// Exactly one of the release events has to be sent, once the compositor
+/// no longer accesses the buffer.
+#[derive(Debug)]
+pub struct ExplicitBufferRelease {
+    release: zwp_linux_buffer_release_v1::ZwpLinuxBufferReleaseV1,
+}
+
+impl ExplicitBufferRelease {
+    /// Release the buffer once the provided fence is signaled
+    pub fn fenced_release(self, fence: &impl AsRawFd) {
+        self.release.fenced_release(fence.as_raw_fd());
+    }
+
+    /// Release the buffer immediately
+    ///
+    /// Use this if the buffer is no longer accessed by the compositor,
+    /// for example because it was only read by the cpu.
+    pub fn immediate_release(self) {
+        self.release.immediate_release();
+    }
+}
+
+/// Represents the double-buffered explicit synchronization
+/// state of a [`WlSurface`](wl_surface::WlSurface)
+#[derive(Debug, Default)]
+pub struct ExplicitSyncCachedState {
+    /// The fence that has to be signaled before the buffer committed alongside it may be accessed
+    pub acquire_fence: Option<OwnedFd>,
+    /// The release object of the buffer committed alongside it
+    pub release: Option<ExplicitBufferRelease>,
+}
+
+impl Cacheable for ExplicitSyncCachedState {
+    fn commit(&mut self, _dh: &DisplayHandle) -> Self {
+        ExplicitSyncCachedState {
+            acquire_fence: self.acquire_fence.take(),
+            release: self.release.take(),
+        }
+    }
+
+    fn merge_into(self, into: &mut Self, _dh: &DisplayHandle) {
+        // Fences are tied to the buffer committed alongside them,
+        // the old buffer was replaced before it was ever used
+        if self.acquire_fence.is_some() || self.release.is_some() {
+            if let Some(release) = into.release.take() {
+                release.immediate_release();
+            }
+            into.acquire_fence = self.acquire_fence;
+            into.release = self.release;
+        }
+    }
+}
+
+#[allow(missing_docs)] // TODO
+#[macro_export]
+macro_rules! delegate_explicit_sync {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        $crate::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::reexports::wayland_protocols::wp::linux_explicit_synchronization::zv1::server::zwp_linux_explicit_synchronization_v1::ZwpLinuxExplicitSynchronizationV1: slog::Logger
+        ] => $crate::wayland::explicit_sync::ExplicitSyncState);
+
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::reexports::wayland_protocols::wp::linux_explicit_synchronization::zv1::server::zwp_linux_explicit_synchronization_v1::ZwpLinuxExplicitSynchronizationV1: slog::Logger
+        ] => $crate::wayland::explicit_sync::ExplicitSyncState);
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::reexports::wayland_protocols::wp::linux_explicit_synchronization::zv1::server::zwp_linux_surface_synchronization_v1::ZwpLinuxSurfaceSynchronizationV1: $crate::wayland::explicit_sync::ExplicitSyncSurfaceData
+        ] => $crate::wayland::explicit_sync::ExplicitSyncState);
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::reexports::wayland_protocols::wp::linux_explicit_synchronization::zv1::server::zwp_linux_buffer_release_v1::ZwpLinuxBufferReleaseV1: ()
+        ] => $crate::wayland::explicit_sync::ExplicitSyncState);
+    };
+}
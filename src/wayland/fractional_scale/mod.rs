@@ -46,6 +46,11 @@
 //!
 //! ### Use the fractional scale state
 //!
+//! Surfaces entered through [`Output::enter`](crate::output::Output::enter) automatically receive the
+//! fractional scale of that output as their preferred scale, which is updated on calls to
+//! [`Output::change_current_state`](crate::output::Output::change_current_state) until the surface
+//! leaves the output again.
+//!
 //! Whenever the fractional scale for a surface changes set the preferred
 //! fractional scale like shown in the example:
 //!
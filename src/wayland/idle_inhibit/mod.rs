@@ -0,0 +1,288 @@
+//! Utilities for handling the `zwp_idle_inhibit` protocol
+//!
+//! This protocol allows clients (e.g. video players or games) to prevent the system from
+//! going idle, while one of their surfaces is visible. Smithay keeps track of the inhibitors
+//! of every surface, it is up to the compositor to pause its idle handling (e.g. by not
+//! calling [`IdleNotifyState::poll`](super::idle_notify::IdleNotifyState::poll)) while a
+//! visible surface is inhibiting.
+//!
+//! ## How to use it
+//!
+//! ```
+//! # extern crate wayland_server;
+//! use smithay::delegate_idle_inhibit;
+//! use smithay::wayland::idle_inhibit::{IdleInhibitHandler, IdleInhibitState};
+//! # use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+//!
+//! # struct State { idle_inhibit_state: IdleInhibitState };
+//! # let mut display = wayland_server::Display::<State>::new().unwrap();
+//! // Create the new IdleInhibitState
+//! let idle_inhibit_state = IdleInhibitState::new::<State, _>(
+//!     &display.handle(),
+//!     None, // insert a logger here
+//! );
+//!
+//! // Insert IdleInhibitState into your compositor state.
+//! // …
+//!
+//! // implement the necessary traits
+//! impl IdleInhibitHandler for State {
+//!     fn idle_inhibit_state(&mut self) -> &mut IdleInhibitState {
+//!         &mut self.idle_inhibit_state
+//!     }
+//!
+//!     fn inhibitor_created(&mut self, surface: WlSurface) {
+//!         // re-check if any visible surface is inhibiting
+//!     }
+//!
+//!     fn inhibitor_destroyed(&mut self, surface: WlSurface) {
+//!         // re-check if any visible surface is still inhibiting
+//!     }
+//! }
+//! delegate_idle_inhibit!(State);
+//!
+//! // You're now ready to go!
+//! ```
+//!
+//! Whether any surface shown on an output is inhibiting can then be queried with
+//! [`IdleInhibitState::inhibited_by_any_visible_surface`]. Surfaces count as shown on an output
+//! once [`Output::enter`] was called for them.
+
+use std::sync::Mutex;
+
+use wayland_protocols::wp::idle_inhibit::zv1::server::{
+    zwp_idle_inhibit_manager_v1::{self, ZwpIdleInhibitManagerV1},
+    zwp_idle_inhibitor_v1::{self, ZwpIdleInhibitorV1},
+};
+use wayland_server::{
+    backend::{ClientId, GlobalId, ObjectId},
+    protocol::wl_surface::WlSurface,
+    Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+};
+
+use super::compositor::with_states;
+use crate::output::Output;
+
+/// State of the zwp_idle_inhibit_manager_v1 global
+#[derive(Debug)]
+pub struct IdleInhibitState {
+    global: GlobalId,
+}
+
+impl IdleInhibitState {
+    /// Create a new [`zwp_idle_inhibit_manager_v1`](ZwpIdleInhibitManagerV1) global
+    pub fn new<D, L>(display: &DisplayHandle, logger: L) -> Self
+    where
+        D: GlobalDispatch<ZwpIdleInhibitManagerV1, slog::Logger>
+            + Dispatch<ZwpIdleInhibitManagerV1, slog::Logger>
+            + Dispatch<ZwpIdleInhibitorV1, IdleInhibitorUserData>
+            + IdleInhibitHandler
+            + 'static,
+        L: Into<Option<slog::Logger>>,
+    {
+        let logger = crate::slog_or_fallback(logger).new(slog::o!("smithay_module" => "idle_inhibit"));
+        let global = display.create_global::<D, ZwpIdleInhibitManagerV1, _>(1, logger);
+
+        IdleInhibitState { global }
+    }
+
+    /// Returns the id of the zwp_idle_inhibit_manager_v1 global
+    pub fn global(&self) -> GlobalId {
+        self.global.clone()
+    }
+
+    /// Returns whether any surface shown on the given output holds an inhibitor
+    ///
+    /// Surfaces are considered shown on an output after [`Output::enter`] was called for them,
+    /// until [`Output::leave`] is called.
+    pub fn inhibited_by_any_visible_surface(&self, output: &Output) -> bool {
+        let surfaces = output
+            .inner
+            .0
+            .lock()
+            .unwrap()
+            .surfaces
+            .iter()
+            .filter_map(|surface| surface.upgrade().ok())
+            .collect::<Vec<_>>();
+        surfaces.iter().any(is_surface_inhibiting)
+    }
+}
+
+/// Returns whether the given surface currently holds any idle inhibitor
+pub fn is_surface_inhibiting(surface: &WlSurface) -> bool {
+    surface.is_alive()
+        && with_states(surface, |states| {
+            states
+                .data_map
+                .get::<IdleInhibitSurfaceData>()
+                .map(|data| !data.inhibitors.lock().unwrap().is_empty())
+                .unwrap_or(false)
+        })
+}
+
+/// Handler trait for zwp_idle_inhibit
+#[allow(unused_variables)]
+pub trait IdleInhibitHandler {
+    /// [`IdleInhibitState`] getter
+    fn idle_inhibit_state(&mut self) -> &mut IdleInhibitState;
+
+    /// A client created an inhibitor for the given surface
+    ///
+    /// The inhibitor only takes effect while the surface is visible.
+    fn inhibitor_created(&mut self, surface: WlSurface) {}
+
+    /// An inhibitor of the given surface was destroyed
+    ///
+    /// The surface might still hold other inhibitors, see [`is_surface_inhibiting`].
+    /// The surface may already be destroyed itself.
+    fn inhibitor_destroyed(&mut self, surface: WlSurface) {}
+}
+
+/// Inhibitors of a surface, stored in its data map
+#[derive(Debug, Default)]
+struct IdleInhibitSurfaceData {
+    inhibitors: Mutex<Vec<ObjectId>>,
+}
+
+/// User data of a zwp_idle_inhibitor_v1 object
+#[derive(Debug)]
+pub struct IdleInhibitorUserData {
+    surface: WlSurface,
+}
+
+impl IdleInhibitorUserData {
+    /// The surface this inhibitor was created for
+    pub fn surface(&self) -> &WlSurface {
+        &self.surface
+    }
+}
+
+impl<D> GlobalDispatch<ZwpIdleInhibitManagerV1, slog::Logger, D> for IdleInhibitState
+where
+    D: GlobalDispatch<ZwpIdleInhibitManagerV1, slog::Logger>
+        + Dispatch<ZwpIdleInhibitManagerV1, slog::Logger>
+        + Dispatch<ZwpIdleInhibitorV1, IdleInhibitorUserData>
+        + IdleInhibitHandler
+        + 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZwpIdleInhibitManagerV1>,
+        global_data: &slog::Logger,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(resource, global_data.clone());
+    }
+}
+
+impl<D> Dispatch<ZwpIdleInhibitManagerV1, slog::Logger, D> for IdleInhibitState
+where
+    D: GlobalDispatch<ZwpIdleInhibitManagerV1, slog::Logger>
+        + Dispatch<ZwpIdleInhibitManagerV1, slog::Logger>
+        + Dispatch<ZwpIdleInhibitorV1, IdleInhibitorUserData>
+        + IdleInhibitHandler
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        _resource: &ZwpIdleInhibitManagerV1,
+        request: zwp_idle_inhibit_manager_v1::Request,
+        logger: &slog::Logger,
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            zwp_idle_inhibit_manager_v1::Request::CreateInhibitor { id, surface } => {
+                let inhibitor = data_init.init(
+                    id,
+                    IdleInhibitorUserData {
+                        surface: surface.clone(),
+                    },
+                );
+                with_states(&surface, |states| {
+                    states
+                        .data_map
+                        .insert_if_missing_threadsafe(IdleInhibitSurfaceData::default);
+                    states
+                        .data_map
+                        .get::<IdleInhibitSurfaceData>()
+                        .unwrap()
+                        .inhibitors
+                        .lock()
+                        .unwrap()
+                        .push(inhibitor.id());
+                });
+                slog::trace!(logger, "Idle inhibitor created"; "surface" => ?surface.id());
+                state.inhibitor_created(surface);
+            }
+            zwp_idle_inhibit_manager_v1::Request::Destroy => {
+                // All is already handled by our destructor
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<ZwpIdleInhibitorV1, IdleInhibitorUserData, D> for IdleInhibitState
+where
+    D: GlobalDispatch<ZwpIdleInhibitManagerV1, slog::Logger>
+        + Dispatch<ZwpIdleInhibitManagerV1, slog::Logger>
+        + Dispatch<ZwpIdleInhibitorV1, IdleInhibitorUserData>
+        + IdleInhibitHandler
+        + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _resource: &ZwpIdleInhibitorV1,
+        request: zwp_idle_inhibitor_v1::Request,
+        _data: &IdleInhibitorUserData,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            zwp_idle_inhibitor_v1::Request::Destroy => {
+                // All is already handled by our destructor
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(state: &mut D, _client: ClientId, object_id: ObjectId, data: &IdleInhibitorUserData) {
+        if data.surface.is_alive() {
+            with_states(&data.surface, |states| {
+                if let Some(surface_data) = states.data_map.get::<IdleInhibitSurfaceData>() {
+                    surface_data
+                        .inhibitors
+                        .lock()
+                        .unwrap()
+                        .retain(|id| *id != object_id);
+                }
+            });
+        }
+        state.inhibitor_destroyed(data.surface.clone());
+    }
+}
+
+/// Macro to delegate implementation of the idle inhibit protocol
+///
+/// You must also implement [`IdleInhibitHandler`] to use this.
+#[macro_export]
+macro_rules! delegate_idle_inhibit {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        $crate::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::reexports::wayland_protocols::wp::idle_inhibit::zv1::server::zwp_idle_inhibit_manager_v1::ZwpIdleInhibitManagerV1: slog::Logger
+        ] => $crate::wayland::idle_inhibit::IdleInhibitState);
+
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::reexports::wayland_protocols::wp::idle_inhibit::zv1::server::zwp_idle_inhibit_manager_v1::ZwpIdleInhibitManagerV1: slog::Logger
+        ] => $crate::wayland::idle_inhibit::IdleInhibitState);
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::reexports::wayland_protocols::wp::idle_inhibit::zv1::server::zwp_idle_inhibitor_v1::ZwpIdleInhibitorV1: $crate::wayland::idle_inhibit::IdleInhibitorUserData
+        ] => $crate::wayland::idle_inhibit::IdleInhibitState);
+    };
+}
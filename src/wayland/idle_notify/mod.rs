@@ -0,0 +1,333 @@
+//! Utilities for handling the `ext-idle-notify` protocol
+//!
+//! This protocol allows clients to be notified when the user has been idle
+//! on a given seat for a certain amount of time, and when the user becomes
+//! active again.
+//!
+//! Idle timers are reset automatically whenever an event is sent through the
+//! [`PointerHandle`](crate::input::pointer::PointerHandle) or the
+//! [`KeyboardHandle`](crate::input::keyboard::KeyboardHandle) of a seat.
+//! For other kinds of input (touch, tablets, ...) you can call
+//! [`IdleNotifyState::notify_activity`] yourself.
+//!
+//! Smithay does not run the idle timers on its own, instead you have to call
+//! [`IdleNotifyState::poll`] regularly (for example from a calloop timer armed
+//! with the returned deadline) to deliver the `idled` events.
+//!
+//! ## How to use it
+//!
+//! ```
+//! # extern crate wayland_server;
+//! use smithay::delegate_idle_notify;
+//! use smithay::wayland::idle_notify::{IdleNotifyHandler, IdleNotifyState};
+//! # use smithay::input::{Seat, SeatHandler, SeatState, pointer::CursorImageStatus};
+//! # use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+//!
+//! # struct State { idle_notify_state: IdleNotifyState };
+//! # let mut display = wayland_server::Display::<State>::new().unwrap();
+//! // Create the new IdleNotifyState
+//! let idle_notify_state = IdleNotifyState::new::<State, _>(
+//!     &display.handle(),
+//!     None, // insert a logger here
+//! );
+//!
+//! // Insert IdleNotifyState into your compositor state.
+//! // …
+//!
+//! // implement the necessary traits
+//! # impl SeatHandler for State {
+//! #     type KeyboardFocus = WlSurface;
+//! #     type PointerFocus = WlSurface;
+//! #     fn seat_state(&mut self) -> &mut SeatState<Self> { unimplemented!() }
+//! #     fn focus_changed(&mut self, seat: &Seat<Self>, focused: Option<&WlSurface>) { unimplemented!() }
+//! #     fn cursor_image(&mut self, seat: &Seat<Self>, image: CursorImageStatus) { unimplemented!() }
+//! # }
+//! impl IdleNotifyHandler for State {
+//!     fn idle_notify_state(&mut self) -> &mut IdleNotifyState {
+//!         &mut self.idle_notify_state
+//!     }
+//! }
+//! delegate_idle_notify!(State);
+//!
+//! // You're now ready to go!
+//! ```
+
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex, Weak},
+    time::{Duration, Instant},
+};
+
+use wayland_protocols::ext::idle_notify::v1::server::{
+    ext_idle_notification_v1::{self, ExtIdleNotificationV1},
+    ext_idle_notifier_v1::{self, ExtIdleNotifierV1},
+};
+use wayland_server::{
+    backend::{ClientId, GlobalId, ObjectId},
+    Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+};
+
+use crate::input::{Seat, SeatHandler};
+
+/// State of a single idle timer
+#[derive(Debug, Clone, Copy)]
+struct IdleTimer {
+    timeout: Duration,
+    last_activity: Instant,
+    idle: bool,
+}
+
+impl IdleTimer {
+    fn deadline(&self) -> Instant {
+        self.last_activity + self.timeout
+    }
+}
+
+type IdleTimers = Mutex<HashMap<ExtIdleNotificationV1, IdleTimer>>;
+
+/// Idle timers registered on a seat, stored in the seat user data
+#[derive(Debug, Default)]
+struct SeatIdleTimers(Arc<IdleTimers>);
+
+fn seat_timers<D: SeatHandler + 'static>(seat: &Seat<D>) -> &Arc<IdleTimers> {
+    seat.user_data()
+        .insert_if_missing_threadsafe(SeatIdleTimers::default);
+    &seat.user_data().get::<SeatIdleTimers>().unwrap().0
+}
+
+/// Reset all idle timers of the given seat
+///
+/// Sends the `resumed` event to all notifications that were idle.
+pub(crate) fn notify_activity<D: SeatHandler + 'static>(seat: &Seat<D>) {
+    // avoid creating the user data for seats nobody is watching
+    let timers = match seat.user_data().get::<SeatIdleTimers>() {
+        Some(timers) => &timers.0,
+        None => return,
+    };
+
+    let now = Instant::now();
+    for (notification, timer) in timers.lock().unwrap().iter_mut() {
+        timer.last_activity = now;
+        if timer.idle {
+            timer.idle = false;
+            notification.resumed();
+        }
+    }
+}
+
+/// State of the ext_idle_notifier_v1 global
+#[derive(Debug)]
+pub struct IdleNotifyState {
+    global: GlobalId,
+    seats: Vec<Weak<IdleTimers>>,
+    log: ::slog::Logger,
+}
+
+impl IdleNotifyState {
+    /// Creates a new ext_idle_notifier_v1 global.
+    pub fn new<D, L>(display: &DisplayHandle, logger: L) -> Self
+    where
+        D: GlobalDispatch<ExtIdleNotifierV1, ()>
+            + Dispatch<ExtIdleNotifierV1, ()>
+            + Dispatch<ExtIdleNotificationV1, IdleNotificationUserData>
+            + IdleNotifyHandler
+            + 'static,
+        L: Into<Option<::slog::Logger>>,
+    {
+        let log = crate::slog_or_fallback(logger).new(slog::o!("smithay_module" => "ext_idle_notify"));
+        let global = display.create_global::<D, ExtIdleNotifierV1, _>(1, ());
+
+        IdleNotifyState {
+            global,
+            seats: Vec::new(),
+            log,
+        }
+    }
+
+    /// Returns the ext_idle_notifier_v1 global.
+    pub fn global(&self) -> GlobalId {
+        self.global.clone()
+    }
+
+    /// Reset the idle timers of the given seat
+    ///
+    /// Pointer and keyboard events already do this automatically, use this to report
+    /// any other kind of user activity.
+    pub fn notify_activity<D: SeatHandler + 'static>(&mut self, seat: &Seat<D>) {
+        notify_activity(seat);
+    }
+
+    /// Send the `idled` event to all notifications whose timeout has elapsed
+    ///
+    /// Returns the next instant at which a timeout will elapse, if any. Compositors
+    /// managing their own timer loop should call this method again at that point.
+    pub fn poll(&mut self) -> Option<Instant> {
+        let now = Instant::now();
+        let mut next_deadline: Option<Instant> = None;
+
+        self.seats.retain(|timers| timers.strong_count() > 0);
+        for timers in self.seats.iter().filter_map(Weak::upgrade) {
+            for (notification, timer) in timers.lock().unwrap().iter_mut() {
+                if timer.idle {
+                    continue;
+                }
+
+                let deadline = timer.deadline();
+                if deadline <= now {
+                    slog::trace!(self.log, "Notification idled";
+                        "notification" => format!("{:?}", notification.id())
+                    );
+                    timer.idle = true;
+                    notification.idled();
+                } else {
+                    next_deadline = Some(next_deadline.map_or(deadline, |next| next.min(deadline)));
+                }
+            }
+        }
+
+        next_deadline
+    }
+
+    fn track_seat(&mut self, timers: &Arc<IdleTimers>) {
+        if !self
+            .seats
+            .iter()
+            .any(|known| known.as_ptr() == Arc::as_ptr(timers))
+        {
+            self.seats.push(Arc::downgrade(timers));
+        }
+    }
+}
+
+/// Handler trait for ext_idle_notify
+pub trait IdleNotifyHandler {
+    /// [IdleNotifyState] getter
+    fn idle_notify_state(&mut self) -> &mut IdleNotifyState;
+}
+
+/// User data of [ExtIdleNotificationV1] object
+#[derive(Debug)]
+pub struct IdleNotificationUserData {
+    timers: Weak<IdleTimers>,
+}
+
+impl<D> GlobalDispatch<ExtIdleNotifierV1, (), D> for IdleNotifyState
+where
+    D: GlobalDispatch<ExtIdleNotifierV1, ()>
+        + Dispatch<ExtIdleNotifierV1, ()>
+        + Dispatch<ExtIdleNotificationV1, IdleNotificationUserData>
+        + IdleNotifyHandler
+        + 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<ExtIdleNotifierV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl<D> Dispatch<ExtIdleNotifierV1, (), D> for IdleNotifyState
+where
+    D: Dispatch<ExtIdleNotifierV1, ()>
+        + Dispatch<ExtIdleNotificationV1, IdleNotificationUserData>
+        + IdleNotifyHandler
+        + SeatHandler
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        _notifier: &ExtIdleNotifierV1,
+        request: ext_idle_notifier_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            ext_idle_notifier_v1::Request::GetIdleNotification { id, timeout, seat } => {
+                let seat = match Seat::<D>::from_resource(&seat) {
+                    Some(seat) => seat,
+                    None => {
+                        // The seat is gone, the notification will never fire
+                        data_init.init(id, IdleNotificationUserData { timers: Weak::new() });
+                        return;
+                    }
+                };
+
+                let timers = seat_timers(&seat).clone();
+                let notification = data_init.init(
+                    id,
+                    IdleNotificationUserData {
+                        timers: Arc::downgrade(&timers),
+                    },
+                );
+                timers.lock().unwrap().insert(
+                    notification,
+                    IdleTimer {
+                        timeout: Duration::from_millis(timeout as u64),
+                        last_activity: Instant::now(),
+                        idle: false,
+                    },
+                );
+                state.idle_notify_state().track_seat(&timers);
+            }
+            ext_idle_notifier_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<ExtIdleNotificationV1, IdleNotificationUserData, D> for IdleNotifyState
+where
+    D: Dispatch<ExtIdleNotificationV1, IdleNotificationUserData> + IdleNotifyHandler + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _notification: &ExtIdleNotificationV1,
+        request: ext_idle_notification_v1::Request,
+        _data: &IdleNotificationUserData,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            ext_idle_notification_v1::Request::Destroy => {
+                // Handled by the destructor
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(_state: &mut D, _client: ClientId, object_id: ObjectId, data: &IdleNotificationUserData) {
+        if let Some(timers) = data.timers.upgrade() {
+            timers
+                .lock()
+                .unwrap()
+                .retain(|notification, _| notification.id() != object_id);
+        }
+    }
+}
+
+/// Macro to delegate implementation of the ext idle notify protocol
+///
+/// You must also implement [`IdleNotifyHandler`] to use this.
+#[macro_export]
+macro_rules! delegate_idle_notify {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        $crate::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::reexports::wayland_protocols::ext::idle_notify::v1::server::ext_idle_notifier_v1::ExtIdleNotifierV1: ()
+        ] => $crate::wayland::idle_notify::IdleNotifyState);
+
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::reexports::wayland_protocols::ext::idle_notify::v1::server::ext_idle_notifier_v1::ExtIdleNotifierV1: ()
+        ] => $crate::wayland::idle_notify::IdleNotifyState);
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::reexports::wayland_protocols::ext::idle_notify::v1::server::ext_idle_notification_v1::ExtIdleNotificationV1: $crate::wayland::idle_notify::IdleNotificationUserData
+        ] => $crate::wayland::idle_notify::IdleNotifyState);
+    };
+}
@@ -47,16 +47,25 @@
 
 pub mod buffer;
 pub mod compositor;
+pub mod content_type;
+pub mod data_control;
 pub mod data_device;
 pub mod dmabuf;
+#[cfg(feature = "wayland_drm_legacy")]
+pub mod drm_legacy;
+pub mod explicit_sync;
 pub mod fractional_scale;
+pub mod idle_inhibit;
+pub mod idle_notify;
 pub mod input_method;
 pub mod keyboard_shortcuts_inhibit;
 pub mod output;
+pub mod pointer_gestures;
 pub mod presentation;
 pub mod primary_selection;
 pub mod relative_pointer;
 pub mod seat;
+pub mod session_lock;
 pub mod shell;
 pub mod shm;
 pub mod socket;
@@ -65,3 +74,4 @@ pub mod text_input;
 pub mod viewporter;
 pub mod virtual_keyboard;
 pub mod xdg_activation;
+pub mod xdg_foreign;
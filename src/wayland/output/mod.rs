@@ -62,6 +62,7 @@
 mod handlers;
 pub(crate) mod xdg;
 use crate::output::{Inner, Mode, Output, OutputData, Scale, Subpixel};
+use crate::utils::Transform;
 
 use wayland_protocols::xdg::xdg_output::zv1::server::zxdg_output_manager_v1::ZxdgOutputManagerV1;
 use wayland_server::{
@@ -73,7 +74,10 @@ use wayland_server::{
     Client, DisplayHandle, GlobalDispatch, Resource,
 };
 
-use crate::utils::{Logical, Point};
+use crate::{
+    utils::{Logical, Point},
+    wayland::{compositor::with_states, fractional_scale::with_fractional_scale},
+};
 
 pub use self::handlers::XdgOutputUserData;
 
@@ -188,7 +192,7 @@ impl Output {
         new_scale: Option<Scale>,
         new_location: Option<Point<i32, Logical>>,
     ) {
-        let inner = self.inner.0.lock().unwrap();
+        let mut inner = self.inner.0.lock().unwrap();
         // XdgOutput has to be updated before WlOutput
         // Because WlOutput::done() has to allways be called last
         if let Some(xdg_output) = inner.xdg_output.as_ref() {
@@ -216,6 +220,28 @@ impl Output {
                 output.done();
             }
         }
+
+        if new_scale.is_some() || new_transform.is_some() {
+            inner.surfaces.retain(|surface| surface.upgrade().is_ok());
+            for surface in inner.surfaces.iter().filter_map(|surface| surface.upgrade().ok()) {
+                if let Some(scale) = new_scale {
+                    send_preferred_scale(&surface, scale);
+                }
+                if let Some(transform) = new_transform {
+                    send_preferred_buffer_transform(&surface, transform);
+                }
+            }
+        }
+    }
+
+    pub(crate) fn wl_send_geometry(&self) {
+        let inner = self.inner.0.lock().unwrap();
+        for output in &inner.instances {
+            inner.send_geometry_to(output);
+            if output.version() >= 2 {
+                output.done();
+            }
+        }
     }
 
     /// Check is given [`wl_output`](WlOutput) instance is managed by this [`Output`].
@@ -252,21 +278,37 @@ impl Output {
 
     /// Sends `wl_surface.enter` for the provided surface
     /// with the matching client output
+    ///
+    /// If the surface has a fractional scale object attached, the fractional scale of
+    /// this output is sent as its preferred scale. It will be updated whenever the scale
+    /// of this output changes until [`Output::leave`] is called for the surface.
+    /// Likewise the inverse of the output transform is set as the preferred buffer transform
+    /// of the surface and updated whenever the transform of this output changes.
     pub fn enter(&self, surface: &wl_surface::WlSurface) {
-        let client = self
-            .inner
-            .0
-            .lock()
-            .unwrap()
-            .handle
-            .as_ref()
-            .and_then(|handle| handle.upgrade())
-            .and_then(|handle| handle.get_client(surface.id()).ok());
+        let (client, scale, transform) = {
+            let mut inner = self.inner.0.lock().unwrap();
+            inner.surfaces.retain(|s| s.upgrade().is_ok());
+            if !inner
+                .surfaces
+                .iter()
+                .any(|s| s.upgrade().map(|s| &s == surface).unwrap_or(false))
+            {
+                inner.surfaces.push(surface.downgrade());
+            }
+            let client = inner
+                .handle
+                .as_ref()
+                .and_then(|handle| handle.upgrade())
+                .and_then(|handle| handle.get_client(surface.id()).ok());
+            (client, inner.scale, inner.transform)
+        };
         if let Some(client) = client {
             for output in self.client_outputs_internal(client) {
                 surface.enter(&output);
             }
         }
+        send_preferred_scale(surface, scale);
+        send_preferred_buffer_transform(surface, transform);
     }
 
     /// Sends `wl_surface.leave` for the provided surface
@@ -286,9 +328,29 @@ impl Output {
                 surface.leave(&output);
             }
         }
+        self.inner
+            .0
+            .lock()
+            .unwrap()
+            .surfaces
+            .retain(|s| s.upgrade().map(|s| &s != surface).unwrap_or(false));
     }
 }
 
+fn send_preferred_scale(surface: &wl_surface::WlSurface, scale: Scale) {
+    with_states(surface, |states| {
+        with_fractional_scale(states, |fractional_scale| {
+            fractional_scale.set_preferred_scale(scale.fractional_scale());
+        });
+    });
+}
+
+fn send_preferred_buffer_transform(surface: &wl_surface::WlSurface, transform: Transform) {
+    with_states(surface, |states| {
+        states.set_preferred_buffer_transform(transform.invert());
+    });
+}
+
 #[allow(missing_docs)] // TODO
 #[macro_export]
 macro_rules! delegate_output {
@@ -65,6 +65,10 @@ use wayland_protocols::wp::primary_selection::zv1::server::{
     zwp_primary_selection_device_manager_v1::ZwpPrimarySelectionDeviceManagerV1 as PrimaryDeviceManager,
     zwp_primary_selection_source_v1::ZwpPrimarySelectionSourceV1 as PrimarySource,
 };
+use wayland_protocols_wlr::data_control::v1::server::{
+    zwlr_data_control_device_v1::ZwlrDataControlDeviceV1,
+    zwlr_data_control_source_v1::ZwlrDataControlSourceV1,
+};
 use wayland_server::{backend::GlobalId, Client, DisplayHandle, GlobalDispatch};
 
 use crate::input::{Seat, SeatHandler};
@@ -152,6 +156,45 @@ where
         .set_selection::<D>(dh, Selection::Compositor(SourceMetadata { mime_types }));
 }
 
+/// Set the primary selection of this seat to a source of a data control client
+pub(crate) fn set_data_control_primary_selection<D>(
+    dh: &DisplayHandle,
+    seat: &Seat<D>,
+    source: Option<ZwlrDataControlSourceV1>,
+) where
+    D: SeatHandler + PrimarySelectionHandler + 'static,
+{
+    seat.user_data()
+        .insert_if_missing(|| RefCell::new(SeatData::new()));
+    let seat_data = seat.user_data().get::<RefCell<SeatData>>().unwrap();
+    seat_data
+        .borrow_mut()
+        .set_selection::<D>(dh, source.map(Selection::DataControl).unwrap_or(Selection::Empty));
+}
+
+/// Mirror the primary selection of this seat to a data control device
+pub(crate) fn add_data_control_device<D>(dh: &DisplayHandle, seat: &Seat<D>, device: ZwlrDataControlDeviceV1)
+where
+    D: SeatHandler + PrimarySelectionHandler + 'static,
+{
+    seat.user_data()
+        .insert_if_missing(|| RefCell::new(SeatData::new()));
+    let seat_data = seat.user_data().get::<RefCell<SeatData>>().unwrap();
+    seat_data.borrow_mut().add_data_control_device::<D>(dh, device);
+}
+
+/// Stop mirroring the primary selection of this seat to a data control device
+pub(crate) fn remove_data_control_device<D>(seat: &Seat<D>, device: &ZwlrDataControlDeviceV1)
+where
+    D: SeatHandler + 'static,
+{
+    if let Some(seat_data) = seat.user_data().get::<RefCell<SeatData>>() {
+        seat_data
+            .borrow_mut()
+            .retain_data_control_devices(|known| known != device);
+    }
+}
+
 mod handlers {
     use std::cell::RefCell;
 
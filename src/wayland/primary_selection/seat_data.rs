@@ -9,12 +9,19 @@ use wayland_protocols::wp::primary_selection::zv1::server::{
     zwp_primary_selection_offer_v1::{self as primary_offer, ZwpPrimarySelectionOfferV1 as PrimaryOffer},
     zwp_primary_selection_source_v1::ZwpPrimarySelectionSourceV1 as PrimarySource,
 };
+use wayland_protocols_wlr::data_control::v1::server::{
+    zwlr_data_control_device_v1::ZwlrDataControlDeviceV1,
+    zwlr_data_control_source_v1::ZwlrDataControlSourceV1,
+};
 use wayland_server::{
     backend::{protocol::Message, ClientId, Handle, ObjectData, ObjectId},
     Client, DisplayHandle, Resource,
 };
 
-use crate::utils::IsAlive;
+use crate::{
+    utils::IsAlive,
+    wayland::data_control::{self, SelectionTarget},
+};
 
 use super::{with_source_metadata, PrimarySelectionHandler, SourceMetadata};
 
@@ -22,10 +29,12 @@ pub enum Selection {
     Empty,
     Client(PrimarySource),
     Compositor(SourceMetadata),
+    DataControl(ZwlrDataControlSourceV1),
 }
 
 pub struct SeatData {
     known_devices: Vec<PrimaryDevice>,
+    data_control_devices: Vec<ZwlrDataControlDeviceV1>,
     selection: Selection,
     current_focus: Option<Client>,
 }
@@ -34,6 +43,7 @@ impl Default for SeatData {
     fn default() -> Self {
         Self {
             known_devices: Vec::new(),
+            data_control_devices: Vec::new(),
             selection: Selection::Empty,
             current_focus: None,
         }
@@ -56,6 +66,23 @@ impl SeatData {
         self.known_devices.retain(f)
     }
 
+    pub fn add_data_control_device<D>(&mut self, dh: &DisplayHandle, device: ZwlrDataControlDeviceV1)
+    where
+        D: PrimarySelectionHandler,
+        D: 'static,
+    {
+        self.sanitize_selection();
+        self.send_data_control_selection::<D>(dh, &device);
+        self.data_control_devices.push(device);
+    }
+
+    pub fn retain_data_control_devices<F>(&mut self, f: F)
+    where
+        F: FnMut(&ZwlrDataControlDeviceV1) -> bool,
+    {
+        self.data_control_devices.retain(f)
+    }
+
     pub fn set_focus<D>(&mut self, dh: &DisplayHandle, new_focus: Option<Client>)
     where
         D: PrimarySelectionHandler,
@@ -70,16 +97,18 @@ impl SeatData {
         D: PrimarySelectionHandler,
         D: 'static,
     {
-        if let Selection::Client(source) = &self.selection {
-            match &new_selection {
-                Selection::Client(new_source) if new_source == source => {}
-                _ => {
-                    source.cancelled();
-                }
-            }
+        match (&self.selection, &new_selection) {
+            (Selection::Client(source), Selection::Client(new_source)) if new_source == source => {}
+            (Selection::Client(source), _) => source.cancelled(),
+            (Selection::DataControl(source), Selection::DataControl(new_source)) if new_source == source => {}
+            (Selection::DataControl(source), _) => source.cancelled(),
+            _ => {}
         }
         self.selection = new_selection;
         self.send_selection::<D>(dh);
+        for device in &self.data_control_devices {
+            self.send_data_control_selection::<D>(dh, device);
+        }
     }
 
     pub fn send_selection<D>(&mut self, dh: &DisplayHandle)
@@ -87,20 +116,13 @@ impl SeatData {
         D: PrimarySelectionHandler,
         D: 'static,
     {
+        // first sanitize the selection, reseting it to null if the client holding
+        // it dropped it
+        self.sanitize_selection();
         let client = match self.current_focus.as_ref() {
             Some(c) => c,
             None => return,
         };
-        // first sanitize the selection, reseting it to null if the client holding
-        // it dropped it
-        let cleanup = if let Selection::Client(ref source) = self.selection {
-            !source.alive()
-        } else {
-            false
-        };
-        if cleanup {
-            self.selection = Selection::Empty;
-        }
 
         // then send it if appropriate
         match self.selection {
@@ -145,6 +167,37 @@ impl SeatData {
                     pd.selection(Some(&offer));
                 }
             }
+            Selection::DataControl(ref source) => {
+                for pd in &self.known_devices {
+                    // skip data devices not belonging to our client
+                    if dh.get_client(pd.id()).map(|c| &c != client).unwrap_or(true) {
+                        continue;
+                    }
+                    let source_clone = source.clone();
+
+                    let handle = dh.backend_handle();
+                    // create a data offer
+                    let offer = handle
+                        .create_object::<D>(
+                            client.id(),
+                            PrimaryOffer::interface(),
+                            pd.version(),
+                            Arc::new(DataControlSelection { source: source_clone }),
+                        )
+                        .unwrap();
+                    let offer = PrimaryOffer::from_id(dh, offer).unwrap();
+
+                    // advertize the offer to the client
+                    pd.data_offer(&offer);
+                    data_control::with_source_metadata(source, |meta| {
+                        for mime_type in meta.mime_types.iter().cloned() {
+                            offer.offer(mime_type);
+                        }
+                    })
+                    .unwrap();
+                    pd.selection(Some(&offer));
+                }
+            }
             Selection::Compositor(ref meta) => {
                 for pd in &self.known_devices {
                     // skip data devices not belonging to our client
@@ -176,6 +229,103 @@ impl SeatData {
             }
         }
     }
+
+    /// Mirror the current selection to a data control device
+    ///
+    /// Data control clients are privileged, so they get every selection regardless
+    /// of the keyboard focus.
+    fn send_data_control_selection<D>(&self, dh: &DisplayHandle, device: &ZwlrDataControlDeviceV1)
+    where
+        D: PrimarySelectionHandler,
+        D: 'static,
+    {
+        let target = SelectionTarget::Primary;
+        match self.selection {
+            Selection::Empty => data_control::clear_selection(device, target),
+            Selection::Client(ref source) => {
+                let mime_types = with_source_metadata(source, |meta| meta.mime_types.clone()).unwrap();
+                let source = source.clone();
+                data_control::offer_selection::<D, _>(
+                    dh,
+                    device,
+                    target,
+                    mime_types,
+                    move |_, mime_type, fd| source.send(mime_type, fd.as_raw_fd()),
+                );
+            }
+            Selection::Compositor(ref meta) => {
+                data_control::offer_selection::<D, _>(
+                    dh,
+                    device,
+                    target,
+                    meta.mime_types.clone(),
+                    |handler, mime_type, fd| PrimarySelectionHandler::send_selection(handler, mime_type, fd),
+                );
+            }
+            Selection::DataControl(ref source) => {
+                let mime_types =
+                    data_control::with_source_metadata(source, |meta| meta.mime_types.clone()).unwrap();
+                let source = source.clone();
+                data_control::offer_selection::<D, _>(
+                    dh,
+                    device,
+                    target,
+                    mime_types,
+                    move |_, mime_type, fd| source.send(mime_type, fd.as_raw_fd()),
+                );
+            }
+        }
+    }
+
+    fn sanitize_selection(&mut self) {
+        let alive = match self.selection {
+            Selection::Client(ref source) => source.alive(),
+            Selection::DataControl(ref source) => source.alive(),
+            Selection::Empty | Selection::Compositor(_) => true,
+        };
+        if !alive {
+            self.selection = Selection::Empty;
+        }
+    }
+}
+
+struct DataControlSelection {
+    source: ZwlrDataControlSourceV1,
+}
+
+impl<D> ObjectData<D> for DataControlSelection
+where
+    D: PrimarySelectionHandler,
+{
+    fn request(
+        self: Arc<Self>,
+        dh: &Handle,
+        handler: &mut D,
+        _client_id: ClientId,
+        msg: Message<ObjectId, OwnedFd>,
+    ) -> Option<Arc<dyn ObjectData<D>>> {
+        let dh = DisplayHandle::from(dh.clone());
+        if let Ok((_resource, primary_offer::Request::Receive { fd, mime_type })) =
+            PrimaryOffer::parse_request(&dh, msg)
+        {
+            // check if the source and associated mime type is still valid
+            let valid =
+                data_control::with_source_metadata(&self.source, |meta| meta.mime_types.contains(&mime_type))
+                    .unwrap_or(false);
+            if !valid {
+                debug!(
+                    handler.primary_selection_state().log,
+                    "Denying a zwp_primary_selection_offer_v1.receive with invalid source."
+                );
+            } else {
+                self.source.send(mime_type, fd.as_raw_fd());
+            }
+        }
+
+        None
+    }
+
+    fn destroyed(&self, _data: &mut D, _client_id: ClientId, _object_id: ObjectId) {}
 }
 
 struct ClientSelection {
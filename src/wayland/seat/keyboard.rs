@@ -9,7 +9,7 @@ use wayland_server::{
     Dispatch, DisplayHandle, Resource,
 };
 
-use super::WaylandFocus;
+use super::{input_allowed, WaylandFocus};
 use crate::{
     backend::input::KeyState,
     input::{
@@ -46,7 +46,12 @@ where
 
         // prepare a tempfile with the keymap, to send it to the client
         let keymap_file = self.arc.keymap.lock().unwrap();
-        let ret = keymap_file.send(&kbd);
+        let client_keymaps = self.arc.client_keymaps.lock().unwrap();
+        let ret = kbd
+            .client()
+            .and_then(|client| client_keymaps.get(&client.id()))
+            .unwrap_or(&*keymap_file)
+            .send(&kbd);
 
         if let Err(e) = ret {
             warn!(self.arc.logger,
@@ -111,14 +116,20 @@ where
     ) {
     }
 
-    fn destroyed(_state: &mut D, _client_id: ClientId, object_id: ObjectId, data: &KeyboardUserData<D>) {
+    fn destroyed(_state: &mut D, client_id: ClientId, object_id: ObjectId, data: &KeyboardUserData<D>) {
         if let Some(ref handle) = data.handle {
-            handle
-                .arc
-                .known_kbds
-                .lock()
-                .unwrap()
-                .retain(|k| k.id() != object_id)
+            let has_kbds = {
+                let mut known_kbds = handle.arc.known_kbds.lock().unwrap();
+                known_kbds.retain(|k| k.id() != object_id);
+                known_kbds
+                    .iter()
+                    .any(|k| k.client().map(|c| c.id() == client_id).unwrap_or(false))
+            };
+
+            // drop the keymap of the client together with its last keyboard
+            if !has_kbds {
+                handle.arc.client_keymaps.lock().unwrap().remove(&client_id);
+            }
         }
     }
 }
@@ -144,7 +155,10 @@ fn serialize_pressed_keys(keys: Vec<u32>) -> Vec<u8> {
 }
 
 impl<D: SeatHandler + 'static> KeyboardTarget<D> for WlSurface {
-    fn enter(&self, seat: &Seat<D>, _data: &mut D, keys: Vec<KeysymHandle<'_>>, serial: Serial) {
+    fn enter(&self, seat: &Seat<D>, data: &mut D, keys: Vec<KeysymHandle<'_>>, serial: Serial) {
+        if !input_allowed(data, self) {
+            return;
+        }
         for_each_focused_kbds(seat, self, |kbd| {
             kbd.enter(
                 serial.into(),
@@ -161,18 +175,24 @@ impl<D: SeatHandler + 'static> KeyboardTarget<D> for WlSurface {
     fn key(
         &self,
         seat: &Seat<D>,
-        _data: &mut D,
+        data: &mut D,
         key: KeysymHandle<'_>,
         state: KeyState,
         serial: Serial,
         time: u32,
     ) {
+        if !input_allowed(data, self) {
+            return;
+        }
         for_each_focused_kbds(seat, self, |kbd| {
             kbd.key(serial.into(), time, key.raw_code() - 8, state.into())
         })
     }
 
-    fn modifiers(&self, seat: &Seat<D>, _data: &mut D, modifiers: ModifiersState, serial: Serial) {
+    fn modifiers(&self, seat: &Seat<D>, data: &mut D, modifiers: ModifiersState, serial: Serial) {
+        if !input_allowed(data, self) {
+            return;
+        }
         for_each_focused_kbds(seat, self, |kbd| {
             let modifiers = modifiers.serialized;
             kbd.modifiers(
@@ -108,9 +108,21 @@ impl WaylandFocus for wl_surface::WlSurface {
     }
 }
 
+/// Returns whether input may be delivered to the given surface
+///
+/// While the session is locked (see [`session_lock`](crate::wayland::session_lock)) only
+/// surfaces of the locking client receive input.
+pub(crate) fn input_allowed<D: SeatHandler>(data: &mut D, surface: &wl_surface::WlSurface) -> bool {
+    data.seat_state()
+        .session_lock
+        .as_ref()
+        .map(|lock| lock.allows(surface))
+        .unwrap_or(true)
+}
+
 impl<D: SeatHandler> Inner<D> {
     fn compute_caps(&self) -> wl_seat::Capability {
-        let mut caps = wl_seat::Capability::empty();
+        let mut caps = self.added_caps;
         if self.pointer.is_some() {
             caps |= wl_seat::Capability::Pointer;
         }
@@ -165,7 +177,7 @@ impl<D: SeatHandler + 'static> SeatState<D> {
     {
         let Seat { arc } = self.new_seat(name, logger);
 
-        let global_id = display.create_global::<D, _, _>(7, SeatGlobalData { arc: arc.clone() });
+        let global_id = display.create_global::<D, _, _>(8, SeatGlobalData { arc: arc.clone() });
         arc.inner.lock().unwrap().global = Some(global_id);
 
         Seat { arc }
@@ -258,6 +270,37 @@ impl<D: SeatHandler + 'static> Seat<D> {
             inner.send_all_caps();
         }
     }
+
+    /// Advertise a capability to the clients without adding the matching device
+    ///
+    /// This is useful if input devices are attached dynamically and the capability should be
+    /// advertised before the device handle is created, e.g. with [`Seat::add_pointer`].
+    /// Objects created by clients for a capability without a device will not receive any events.
+    ///
+    /// All `wl_seat` objects of this seat are immediately notified if the capabilities changed.
+    pub fn add_capability(&mut self, cap: wl_seat::Capability) {
+        let mut inner = self.arc.inner.lock().unwrap();
+        let old_caps = inner.compute_caps();
+        inner.added_caps |= cap;
+        if inner.compute_caps() != old_caps {
+            inner.send_all_caps();
+        }
+    }
+
+    /// Stop advertising a capability previously added with [`Seat::add_capability`]
+    ///
+    /// Capabilities backed by a device, like a pointer added with [`Seat::add_pointer`],
+    /// stay advertised until the device is removed.
+    ///
+    /// All `wl_seat` objects of this seat are immediately notified if the capabilities changed.
+    pub fn remove_capability(&mut self, cap: wl_seat::Capability) {
+        let mut inner = self.arc.inner.lock().unwrap();
+        let old_caps = inner.compute_caps();
+        inner.added_caps.remove(cap);
+        if inner.compute_caps() != old_caps {
+            inner.send_all_caps();
+        }
+    }
 }
 
 /// User data for seat
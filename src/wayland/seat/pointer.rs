@@ -1,6 +1,12 @@
 use std::{fmt, sync::Mutex};
 
-use wayland_protocols::wp::relative_pointer::zv1::server::zwp_relative_pointer_v1::ZwpRelativePointerV1;
+use wayland_protocols::wp::{
+    pointer_gestures::zv1::server::{
+        zwp_pointer_gesture_pinch_v1::ZwpPointerGesturePinchV1,
+        zwp_pointer_gesture_swipe_v1::ZwpPointerGestureSwipeV1,
+    },
+    relative_pointer::zv1::server::zwp_relative_pointer_v1::ZwpRelativePointerV1,
+};
 use wayland_server::{
     backend::{ClientId, ObjectId},
     protocol::{
@@ -17,8 +23,9 @@ use crate::{
     backend::input::{Axis, AxisSource, ButtonState},
     input::{
         pointer::{
-            AxisFrame, ButtonEvent, CursorImageAttributes, CursorImageStatus, MotionEvent, PointerHandle,
-            PointerInternal, PointerTarget, RelativeMotionEvent,
+            AxisFrame, ButtonEvent, CursorImageAttributes, CursorImageStatus, GesturePhase,
+            GesturePinchEvent, GestureSwipeEvent, MotionEvent, PointerHandle, PointerInternal, PointerTarget,
+            RelativeMotionEvent,
         },
         Seat,
     },
@@ -26,7 +33,7 @@ use crate::{
     wayland::compositor,
 };
 
-use super::{SeatHandler, SeatState, WaylandFocus};
+use super::{input_allowed, SeatHandler, SeatState, WaylandFocus};
 
 impl<D: SeatHandler> PointerHandle<D> {
     pub(crate) fn new_pointer(&self, pointer: WlPointer) {
@@ -38,6 +45,16 @@ impl<D: SeatHandler> PointerHandle<D> {
         let mut guard = self.known_relative_pointers.lock().unwrap();
         guard.push(pointer);
     }
+
+    pub(crate) fn new_swipe_gesture(&self, gesture: ZwpPointerGestureSwipeV1) {
+        let mut guard = self.known_swipe_gestures.lock().unwrap();
+        guard.push(gesture);
+    }
+
+    pub(crate) fn new_pinch_gesture(&self, gesture: ZwpPointerGesturePinchV1) {
+        let mut guard = self.known_pinch_gestures.lock().unwrap();
+        guard.push(gesture);
+    }
 }
 
 /// WlSurface role of a cursor image icon
@@ -73,12 +90,45 @@ fn for_each_focused_relative_pointers<D: SeatHandler + 'static>(
     }
 }
 
+fn for_each_focused_swipe_gestures<D: SeatHandler + 'static>(
+    seat: &Seat<D>,
+    surface: &WlSurface,
+    mut f: impl FnMut(ZwpPointerGestureSwipeV1),
+) {
+    if let Some(pointer) = seat.get_pointer() {
+        let inner = pointer.known_swipe_gestures.lock().unwrap();
+        for gesture in &*inner {
+            if gesture.id().same_client_as(&surface.id()) {
+                f(gesture.clone())
+            }
+        }
+    }
+}
+
+fn for_each_focused_pinch_gestures<D: SeatHandler + 'static>(
+    seat: &Seat<D>,
+    surface: &WlSurface,
+    mut f: impl FnMut(ZwpPointerGesturePinchV1),
+) {
+    if let Some(pointer) = seat.get_pointer() {
+        let inner = pointer.known_pinch_gestures.lock().unwrap();
+        for gesture in &*inner {
+            if gesture.id().same_client_as(&surface.id()) {
+                f(gesture.clone())
+            }
+        }
+    }
+}
+
 #[cfg(feature = "wayland_frontend")]
 impl<D> PointerTarget<D> for WlSurface
 where
     D: SeatHandler + 'static,
 {
-    fn enter(&self, seat: &Seat<D>, _data: &mut D, event: &MotionEvent) {
+    fn enter(&self, seat: &Seat<D>, data: &mut D, event: &MotionEvent) {
+        if !input_allowed(data, self) {
+            return;
+        }
         for_each_focused_pointers(seat, self, |ptr| {
             ptr.enter(event.serial.into(), self, event.location.x, event.location.y);
             if ptr.version() >= 5 {
@@ -94,15 +144,21 @@ where
             }
         })
     }
-    fn motion(&self, seat: &Seat<D>, _data: &mut D, event: &MotionEvent) {
+    fn motion(&self, seat: &Seat<D>, data: &mut D, event: &MotionEvent) {
+        if !input_allowed(data, self) {
+            return;
+        }
         for_each_focused_pointers(seat, self, |ptr| {
-            ptr.motion(event.time, event.location.x, event.location.y);
+            ptr.motion(event.time.as_millis_u32(), event.location.x, event.location.y);
             if ptr.version() >= 5 {
                 ptr.frame();
             }
         })
     }
-    fn relative_motion(&self, seat: &Seat<D>, _data: &mut D, event: &RelativeMotionEvent) {
+    fn relative_motion(&self, seat: &Seat<D>, data: &mut D, event: &RelativeMotionEvent) {
+        if !input_allowed(data, self) {
+            return;
+        }
         for_each_focused_relative_pointers(seat, self, |ptr| {
             let utime_hi = (event.utime >> 32) as u32;
             let utime_lo = (event.utime & 0xffffffff) as u32;
@@ -116,22 +172,41 @@ where
             );
         })
     }
-    fn button(&self, seat: &Seat<D>, _data: &mut D, event: &ButtonEvent) {
+    fn button(&self, seat: &Seat<D>, data: &mut D, event: &ButtonEvent) {
+        if !input_allowed(data, self) {
+            return;
+        }
         for_each_focused_pointers(seat, self, |ptr| {
-            ptr.button(event.serial.into(), event.time, event.button, event.state.into());
+            ptr.button(
+                event.serial.into(),
+                event.time.as_millis_u32(),
+                event.button,
+                event.state.into(),
+            );
             if ptr.version() >= 5 {
                 ptr.frame();
             }
         })
     }
-    fn axis(&self, seat: &Seat<D>, _data: &mut D, details: AxisFrame) {
+    fn axis(&self, seat: &Seat<D>, data: &mut D, details: AxisFrame) {
+        if !input_allowed(data, self) {
+            return;
+        }
         for_each_focused_pointers(seat, self, |ptr| {
             // axis
             if details.axis.0 != 0.0 {
-                ptr.axis(details.time, WlAxis::HorizontalScroll, details.axis.0);
+                ptr.axis(
+                    details.time.as_millis_u32(),
+                    WlAxis::HorizontalScroll,
+                    details.axis.0,
+                );
             }
             if details.axis.1 != 0.0 {
-                ptr.axis(details.time, WlAxis::VerticalScroll, details.axis.1);
+                ptr.axis(
+                    details.time.as_millis_u32(),
+                    WlAxis::VerticalScroll,
+                    details.axis.1,
+                );
             }
             if ptr.version() >= 5 {
                 // axis source
@@ -149,8 +224,21 @@ where
                     .into();
                     ptr.axis_source(source);
                 }
-                // axis discrete
-                if let Some((x, y)) = details.discrete {
+                if ptr.version() >= 8 {
+                    // axis value120, replacing axis discrete since version 8
+                    let high_res = details
+                        .high_res
+                        .or_else(|| details.discrete.map(|(x, y)| (x * 120, y * 120)));
+                    if let Some((x, y)) = high_res {
+                        if x != 0 {
+                            ptr.axis_value120(WlAxis::HorizontalScroll, x);
+                        }
+                        if y != 0 {
+                            ptr.axis_value120(WlAxis::VerticalScroll, y);
+                        }
+                    }
+                } else if let Some((x, y)) = details.discrete {
+                    // axis discrete
                     if x != 0 {
                         ptr.axis_discrete(WlAxis::HorizontalScroll, x);
                     }
@@ -160,16 +248,38 @@ where
                 }
                 // stop
                 if details.stop.0 {
-                    ptr.axis_stop(details.time, WlAxis::HorizontalScroll);
+                    ptr.axis_stop(details.time.as_millis_u32(), WlAxis::HorizontalScroll);
                 }
                 if details.stop.1 {
-                    ptr.axis_stop(details.time, WlAxis::VerticalScroll);
+                    ptr.axis_stop(details.time.as_millis_u32(), WlAxis::VerticalScroll);
                 }
                 // frame
                 ptr.frame();
             }
         })
     }
+    fn gesture_swipe(&self, seat: &Seat<D>, data: &mut D, event: &GestureSwipeEvent) {
+        if !input_allowed(data, self) {
+            return;
+        }
+        for_each_focused_swipe_gestures(seat, self, |gesture| match event.phase {
+            GesturePhase::Begin => gesture.begin(event.serial.into(), event.time, self, event.fingers),
+            GesturePhase::Update => gesture.update(event.time, event.dx, event.dy),
+            GesturePhase::End => gesture.end(event.serial.into(), event.time, event.cancelled as i32),
+        })
+    }
+    fn gesture_pinch(&self, seat: &Seat<D>, data: &mut D, event: &GesturePinchEvent) {
+        if !input_allowed(data, self) {
+            return;
+        }
+        for_each_focused_pinch_gestures(seat, self, |gesture| match event.phase {
+            GesturePhase::Begin => gesture.begin(event.serial.into(), event.time, self, event.fingers),
+            GesturePhase::Update => {
+                gesture.update(event.time, event.dx, event.dy, event.scale, event.rotation)
+            }
+            GesturePhase::End => gesture.end(event.serial.into(), event.time, event.cancelled as i32),
+        })
+    }
 }
 
 /// User data for pointer
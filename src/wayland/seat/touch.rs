@@ -1,5 +1,5 @@
 use std::{
-    collections::HashMap,
+    collections::{HashMap, VecDeque},
     sync::{Arc, Mutex},
 };
 
@@ -10,7 +10,7 @@ use wayland_server::{
 };
 
 use super::{SeatHandler, SeatState};
-use crate::backend::input::TouchSlot;
+use crate::backend::input::{TouchSlot, TouchSlotMapper};
 use crate::utils::Serial;
 use crate::utils::{Logical, Point};
 use crate::wayland::seat::wl_surface::WlSurface;
@@ -41,6 +41,10 @@ impl TouchHandle {
     }
 
     /// Notify clients about new touch points.
+    ///
+    /// `time` should be the timestamp of the input event in milliseconds, as returned by
+    /// [`Event::time_msec`](crate::backend::input::Event::time_msec), so clients
+    /// measuring the touch latency get the time of the hardware event.
     pub fn down(
         &mut self,
         serial: Serial,
@@ -83,6 +87,66 @@ impl TouchHandle {
     pub fn cancel(&self) {
         self.inner.lock().unwrap().cancel();
     }
+
+    /// Estimate the velocity of a touch point, e.g. to start kinetic scrolling once it is lifted
+    ///
+    /// The velocity is estimated from the last motion events of the slot since it went down,
+    /// weighting recent motion more heavily. It is returned as `(vx, vy)` in logical pixels per second.
+    ///
+    /// Returns `None` if less than two events were recorded for the slot.
+    pub fn estimate_velocity(&self, slot: TouchSlot) -> Option<(f64, f64)> {
+        self.inner
+            .lock()
+            .unwrap()
+            .velocity
+            .get(&slot)
+            .and_then(VelocityTracker::velocity)
+    }
+}
+
+/// Number of events used for velocity estimation
+const VELOCITY_HISTORY: usize = 8;
+/// Weight of the most recent sample when smoothing the velocity
+const VELOCITY_SMOOTHING: f64 = 0.5;
+
+/// Recent locations of a touch point
+#[derive(Default, Debug)]
+struct VelocityTracker {
+    events: VecDeque<(Point<f64, Logical>, u32)>,
+}
+
+impl VelocityTracker {
+    fn push(&mut self, location: Point<f64, Logical>, time: u32) {
+        if self.events.len() == VELOCITY_HISTORY {
+            self.events.pop_front();
+        }
+        self.events.push_back((location, time));
+    }
+
+    fn velocity(&self) -> Option<(f64, f64)> {
+        if self.events.len() < 2 {
+            return None;
+        }
+
+        let mut velocity: Option<(f64, f64)> = None;
+        for ((from, from_time), (to, to_time)) in self.events.iter().zip(self.events.iter().skip(1)) {
+            let elapsed = to_time.wrapping_sub(*from_time);
+            if elapsed == 0 {
+                continue;
+            }
+            // pixels per millisecond
+            let sample = ((to.x - from.x) / elapsed as f64, (to.y - from.y) / elapsed as f64);
+            velocity = Some(match velocity {
+                Some((vx, vy)) => (
+                    VELOCITY_SMOOTHING * sample.0 + (1.0 - VELOCITY_SMOOTHING) * vx,
+                    VELOCITY_SMOOTHING * sample.1 + (1.0 - VELOCITY_SMOOTHING) * vy,
+                ),
+                None => sample,
+            });
+        }
+
+        velocity.map(|(vx, vy)| (vx * 1000.0, vy * 1000.0))
+    }
 }
 
 /// Touch-slot focused Wayland client state.
@@ -96,6 +160,8 @@ struct TouchFocus {
 struct TouchInternal {
     known_handles: Vec<WlTouch>,
     focus: HashMap<TouchSlot, TouchFocus>,
+    slots: TouchSlotMapper,
+    velocity: HashMap<TouchSlot, VelocityTracker>,
 }
 
 impl TouchInternal {
@@ -121,13 +187,20 @@ impl TouchInternal {
         }
 
         let (x, y) = (location - focus.surface_offset).into();
-        self.with_focused_handles(slot, |handle| {
-            handle.down(serial.into(), time, surface, slot.into(), x, y)
-        });
+        let mut tracker = VelocityTracker::default();
+        tracker.push(location, time);
+        self.velocity.insert(slot, tracker);
+
+        let id = self.slots.down(slot);
+        self.with_focused_handles(slot, |handle| handle.down(serial.into(), time, surface, id, x, y));
     }
 
     fn up(&mut self, serial: Serial, time: u32, slot: TouchSlot) {
-        self.with_focused_handles(slot, |handle| handle.up(serial.into(), time, slot.into()));
+        let id = match self.slots.up(slot) {
+            Some(id) => id,
+            None => return,
+        };
+        self.with_focused_handles(slot, |handle| handle.up(serial.into(), time, id));
 
         // Clear this slot's associated WlTouch handles.
         if let Some(focus) = self.focus.get_mut(&slot) {
@@ -135,28 +208,47 @@ impl TouchInternal {
         }
     }
 
-    fn motion(&self, time: u32, slot: TouchSlot, location: Point<f64, Logical>) {
+    fn motion(&mut self, time: u32, slot: TouchSlot, location: Point<f64, Logical>) {
+        if let Some(tracker) = self.velocity.get_mut(&slot) {
+            tracker.push(location, time);
+        }
+
         let focus = match self.focus.get(&slot) {
             Some(slot) => slot,
             None => return,
         };
 
+        let id = match self.slots.id(slot) {
+            Some(id) => id,
+            None => return,
+        };
+
         let (x, y) = (location - focus.surface_offset).into();
-        self.with_focused_handles(slot, |handle| handle.motion(time, slot.into(), x, y));
+        self.with_focused_handles(slot, |handle| handle.motion(time, id, x, y));
     }
 
     fn shape(&self, slot: TouchSlot, major: f64, minor: f64) {
+        let id = match self.slots.id(slot) {
+            Some(id) => id,
+            None => return,
+        };
+
         self.with_focused_handles(slot, |handle| {
             if handle.version() >= 6 {
-                handle.shape(slot.into(), major, minor);
+                handle.shape(id, major, minor);
             }
         });
     }
 
     fn orientation(&self, slot: TouchSlot, orientation: f64) {
+        let id = match self.slots.id(slot) {
+            Some(id) => id,
+            None => return,
+        };
+
         self.with_focused_handles(slot, |handle| {
             if handle.version() >= 6 {
-                handle.orientation(slot.into(), orientation);
+                handle.orientation(id, orientation);
             }
         });
     }
@@ -167,6 +259,8 @@ impl TouchInternal {
         }
 
         self.focus.clear();
+        self.slots.clear();
+        self.velocity.clear();
     }
 
     #[inline]
@@ -217,3 +311,27 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::VelocityTracker;
+
+    #[test]
+    fn velocity_estimation() {
+        let mut tracker = VelocityTracker::default();
+        tracker.push((0.0, 0.0).into(), 100);
+        assert_eq!(tracker.velocity(), None);
+
+        // constant motion of 1 pixel per millisecond to the right
+        for i in 1..=10 {
+            tracker.push((i as f64 * 10.0, 0.0).into(), 100 + i * 10);
+        }
+        assert_eq!(tracker.events.len(), 8);
+        assert_eq!(tracker.velocity(), Some((1000.0, 0.0)));
+
+        // slowing down is weighted more heavily
+        tracker.push((110.0, 0.0).into(), 220);
+        let (vx, _) = tracker.velocity().unwrap();
+        assert!(vx < 1000.0 && vx > 500.0);
+    }
+}
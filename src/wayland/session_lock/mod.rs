@@ -0,0 +1,613 @@
+//! Utilities for handling the `ext-session-lock` protocol
+//!
+//! This protocol allows a privileged client, usually a lock screen, to lock the session.
+//! While the session is locked the compositor must not display any content of other clients
+//! and must not send them any input. Instead the locking client creates a lock surface
+//! for every output, which the compositor displays instead.
+//!
+//! Whenever a client requests a lock, [`SessionLockHandler::lock_requested`] is called
+//! with a [`SessionLocker`]. Once the compositor stopped displaying any other content
+//! (e.g. after blanking all outputs) it confirms the lock by calling [`SessionLocker::lock`].
+//! Dropping the [`SessionLocker`] without confirming the lock denies it.
+//!
+//! While [`SessionLockState::is_locked`] returns `true`, the seats drop all keyboard and pointer
+//! input to surfaces of other clients than the locking one. Compositors are still expected to
+//! move the focus to the lock surfaces and to not map any surfaces of other clients,
+//! [`SessionLockState::is_surface_allowed`] tells which surfaces may receive input.
+//!
+//! ## How to use it
+//!
+//! ```
+//! # extern crate wayland_server;
+//! use smithay::delegate_session_lock;
+//! use smithay::wayland::session_lock::{
+//!     LockSurface, SessionLockHandler, SessionLockState, SessionLocker,
+//! };
+//! use smithay::reexports::wayland_server::protocol::wl_output::WlOutput;
+//! # use smithay::input::{SeatHandler, SeatState};
+//! # use smithay::reexports::wayland_server::protocol::wl_surface::WlSurface;
+//!
+//! # struct State { session_lock_state: SessionLockState, seat_state: SeatState<State> };
+//! # impl SeatHandler for State {
+//! #     type KeyboardFocus = WlSurface;
+//! #     type PointerFocus = WlSurface;
+//! #     fn seat_state(&mut self) -> &mut SeatState<Self> { &mut self.seat_state }
+//! # }
+//! # let mut display = wayland_server::Display::<State>::new().unwrap();
+//! // Create the new SessionLockState
+//! let session_lock_state = SessionLockState::new::<State, _>(
+//!     &display.handle(),
+//!     None, // insert a logger here
+//! );
+//!
+//! // Insert SessionLockState into your compositor state.
+//! // …
+//!
+//! // implement the necessary traits
+//! impl SessionLockHandler for State {
+//!     fn lock_state(&mut self) -> &mut SessionLockState {
+//!         &mut self.session_lock_state
+//!     }
+//!
+//!     fn lock_requested(&mut self, locker: SessionLocker) {
+//!         // hide all other content, then confirm the lock
+//!         locker.lock();
+//!     }
+//!
+//!     fn unlock_requested(&mut self) {
+//!         // show the regular content again
+//!     }
+//!
+//!     fn new_surface(&mut self, surface: LockSurface, output: WlOutput) {
+//!         // configure the surface with the size of the output
+//!         surface.send_configure((1920, 1080).into());
+//!     }
+//! }
+//! delegate_session_lock!(State);
+//!
+//! // You're now ready to go!
+//! ```
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, Mutex,
+};
+
+use wayland_protocols::ext::session_lock::v1::server::{
+    ext_session_lock_manager_v1::{self, ExtSessionLockManagerV1},
+    ext_session_lock_surface_v1::ExtSessionLockSurfaceV1,
+    ext_session_lock_v1::{self, ExtSessionLockV1},
+};
+use wayland_server::{
+    backend::{ClientId, GlobalId},
+    protocol::{wl_output::WlOutput, wl_surface::WlSurface},
+    Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+};
+
+use super::compositor::{self, SurfaceAttributes};
+use crate::{input::SeatHandler, utils::IsAlive};
+
+mod surface;
+
+pub use surface::{
+    LockSurface, LockSurfaceAttributes, LockSurfaceData, LockSurfaceUserData, LOCK_SURFACE_ROLE,
+};
+
+/// State of the ext_session_lock_manager_v1 global
+#[derive(Debug)]
+pub struct SessionLockState {
+    global: GlobalId,
+    lock: Option<ExtSessionLockV1>,
+    filter: Arc<SessionLockFilter>,
+    log: ::slog::Logger,
+}
+
+/// Lock state shared with the [`SeatState`](crate::input::SeatState)
+///
+/// The `WlSurface` input targets consult it to drop input to other clients while the session is locked.
+#[derive(Debug, Default)]
+pub(crate) struct SessionLockFilter {
+    locked: Arc<AtomicBool>,
+    client: Mutex<Option<ClientId>>,
+}
+
+impl SessionLockFilter {
+    /// Returns whether input may be delivered to the given surface
+    pub(crate) fn allows(&self, surface: &WlSurface) -> bool {
+        !self.locked.load(Ordering::SeqCst)
+            || surface.client().map(|client| client.id()) == *self.client.lock().unwrap()
+    }
+
+    fn set_client(&self, lock: Option<&ExtSessionLockV1>) {
+        *self.client.lock().unwrap() = lock.and_then(|lock| lock.client()).map(|client| client.id());
+    }
+}
+
+impl SessionLockState {
+    /// Creates a new ext_session_lock_manager_v1 global.
+    pub fn new<D, L>(display: &DisplayHandle, logger: L) -> Self
+    where
+        D: GlobalDispatch<ExtSessionLockManagerV1, ()>
+            + Dispatch<ExtSessionLockManagerV1, ()>
+            + Dispatch<ExtSessionLockV1, SessionLockUserData>
+            + Dispatch<ExtSessionLockSurfaceV1, LockSurfaceUserData>
+            + SessionLockHandler
+            + SeatHandler
+            + 'static,
+        L: Into<Option<::slog::Logger>>,
+    {
+        let log = crate::slog_or_fallback(logger).new(slog::o!("smithay_module" => "ext_session_lock"));
+        let global = display.create_global::<D, ExtSessionLockManagerV1, _>(1, ());
+
+        SessionLockState {
+            global,
+            lock: None,
+            filter: Arc::new(SessionLockFilter::default()),
+            log,
+        }
+    }
+
+    /// Returns the ext_session_lock_manager_v1 global.
+    pub fn global(&self) -> GlobalId {
+        self.global.clone()
+    }
+
+    /// Returns whether the session is currently locked
+    ///
+    /// If the locking client dies without unlocking the session, the session stays locked
+    /// until another client locks and unlocks it again.
+    pub fn is_locked(&self) -> bool {
+        self.filter.locked.load(Ordering::SeqCst)
+    }
+
+    /// Returns the client currently holding the lock
+    pub fn lock_client(&self) -> Option<ClientId> {
+        self.lock
+            .as_ref()
+            .filter(|lock| lock.alive())
+            .and_then(|lock| lock.client())
+            .map(|client| client.id())
+    }
+
+    /// Returns whether input may be delivered to the given surface
+    ///
+    /// While the session is locked this only returns `true` for surfaces of the locking client.
+    ///
+    /// The seats already drop input to surfaces, for which this returns `false`.
+    pub fn is_surface_allowed(&self, surface: &WlSurface) -> bool {
+        self.filter.allows(surface)
+    }
+
+    fn has_active_lock(&self) -> bool {
+        self.lock.as_ref().map(|lock| lock.alive()).unwrap_or(false)
+    }
+}
+
+/// Handler trait for ext_session_lock
+pub trait SessionLockHandler {
+    /// [SessionLockState] getter
+    fn lock_state(&mut self) -> &mut SessionLockState;
+
+    /// A client requested to lock the session
+    ///
+    /// Call [`SessionLocker::lock`] once no other content is displayed anymore.
+    /// Dropping the locker without confirming denies the lock.
+    fn lock_requested(&mut self, locker: SessionLocker);
+
+    /// The locking client unlocked the session
+    fn unlock_requested(&mut self);
+
+    /// The locking client created a new lock surface for an output
+    ///
+    /// The surface has to be configured with [`LockSurface::send_configure`] before
+    /// the client may attach a buffer to it.
+    fn new_surface(&mut self, surface: LockSurface, output: WlOutput);
+}
+
+/// Confirmation of a requested session lock
+#[derive(Debug)]
+pub struct SessionLocker {
+    lock: ExtSessionLockV1,
+    locked: Arc<AtomicBool>,
+    confirmed: bool,
+}
+
+impl SessionLocker {
+    /// Returns the client requesting the lock
+    pub fn client(&self) -> Option<Client> {
+        self.lock.client()
+    }
+
+    /// Confirm the lock, notifying the client that the session is locked
+    pub fn lock(mut self) {
+        if !self.lock.alive() {
+            return;
+        }
+
+        self.confirmed = true;
+        self.locked.store(true, Ordering::SeqCst);
+        self.lock.locked();
+    }
+}
+
+impl Drop for SessionLocker {
+    fn drop(&mut self) {
+        if !self.confirmed && self.lock.alive() {
+            self.lock.finished();
+        }
+    }
+}
+
+/// User data of [ExtSessionLockV1] object
+#[derive(Debug)]
+pub struct SessionLockUserData {
+    locked: Arc<AtomicBool>,
+    outputs: Mutex<Vec<WlOutput>>,
+}
+
+impl<D> GlobalDispatch<ExtSessionLockManagerV1, (), D> for SessionLockState
+where
+    D: GlobalDispatch<ExtSessionLockManagerV1, ()>
+        + Dispatch<ExtSessionLockManagerV1, ()>
+        + Dispatch<ExtSessionLockV1, SessionLockUserData>
+        + Dispatch<ExtSessionLockSurfaceV1, LockSurfaceUserData>
+        + SessionLockHandler
+        + 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _handle: &DisplayHandle,
+        _client: &Client,
+        resource: New<ExtSessionLockManagerV1>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl<D> Dispatch<ExtSessionLockManagerV1, (), D> for SessionLockState
+where
+    D: Dispatch<ExtSessionLockManagerV1, ()>
+        + Dispatch<ExtSessionLockV1, SessionLockUserData>
+        + Dispatch<ExtSessionLockSurfaceV1, LockSurfaceUserData>
+        + SessionLockHandler
+        + SeatHandler
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        _manager: &ExtSessionLockManagerV1,
+        request: ext_session_lock_manager_v1::Request,
+        _data: &(),
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            ext_session_lock_manager_v1::Request::Lock { id } => {
+                // let the seats drop input to other clients once locked
+                let filter = state.lock_state().filter.clone();
+                state.seat_state().session_lock = Some(filter);
+
+                let lock_state = state.lock_state();
+                let locked = lock_state.filter.locked.clone();
+                let lock = data_init.init(
+                    id,
+                    SessionLockUserData {
+                        locked: locked.clone(),
+                        outputs: Mutex::new(Vec::new()),
+                    },
+                );
+
+                // Only a single client may hold the lock at a time
+                if lock_state.has_active_lock() {
+                    slog::debug!(lock_state.log, "Denying lock, the session is already locked");
+                    lock.finished();
+                    return;
+                }
+                lock_state.lock = Some(lock.clone());
+                lock_state.filter.set_client(Some(&lock));
+
+                // A new client may take over the lock of a dead locking client
+                if locked.load(Ordering::SeqCst) {
+                    lock.locked();
+                    return;
+                }
+
+                state.lock_requested(SessionLocker {
+                    lock,
+                    locked,
+                    confirmed: false,
+                });
+            }
+            ext_session_lock_manager_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<ExtSessionLockV1, SessionLockUserData, D> for SessionLockState
+where
+    D: Dispatch<ExtSessionLockV1, SessionLockUserData>
+        + Dispatch<ExtSessionLockSurfaceV1, LockSurfaceUserData>
+        + SessionLockHandler
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        lock: &ExtSessionLockV1,
+        request: ext_session_lock_v1::Request,
+        data: &SessionLockUserData,
+        _dhandle: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        let is_active_lock = state.lock_state().lock.as_ref() == Some(lock);
+        match request {
+            ext_session_lock_v1::Request::GetLockSurface { id, surface, output } => {
+                {
+                    let mut outputs = data.outputs.lock().unwrap();
+                    if outputs.contains(&output) {
+                        lock.post_error(
+                            ext_session_lock_v1::Error::DuplicateOutput,
+                            "a lock surface was already created for this output",
+                        );
+                        return;
+                    }
+                    outputs.push(output.clone());
+                }
+
+                let has_buffer = compositor::with_states(&surface, |states| {
+                    states
+                        .cached_state
+                        .current::<SurfaceAttributes>()
+                        .buffer
+                        .is_some()
+                });
+                if has_buffer {
+                    lock.post_error(
+                        ext_session_lock_v1::Error::AlreadyConstructed,
+                        "the surface already has a buffer attached",
+                    );
+                    return;
+                }
+
+                if compositor::give_role(&surface, LOCK_SURFACE_ROLE).is_err() {
+                    lock.post_error(ext_session_lock_v1::Error::Role, "Surface already has a role.");
+                    return;
+                }
+
+                let lock_surface = surface::init_lock_surface(data_init, id, surface.clone());
+
+                // Surfaces of a denied or replaced lock are never shown
+                if is_active_lock {
+                    state.new_surface(lock_surface, output);
+                }
+            }
+            ext_session_lock_v1::Request::UnlockAndDestroy => {
+                if !is_active_lock || !data.locked.load(Ordering::SeqCst) {
+                    lock.post_error(
+                        ext_session_lock_v1::Error::InvalidUnlock,
+                        "the session was not locked by this object",
+                    );
+                    return;
+                }
+
+                let lock_state = state.lock_state();
+                lock_state.lock = None;
+                lock_state.filter.set_client(None);
+                lock_state.filter.locked.store(false, Ordering::SeqCst);
+                state.unlock_requested();
+            }
+            ext_session_lock_v1::Request::Destroy => {
+                if is_active_lock && data.locked.load(Ordering::SeqCst) {
+                    lock.post_error(
+                        ext_session_lock_v1::Error::InvalidDestroy,
+                        "the session is locked, use unlock_and_destroy",
+                    );
+                    return;
+                }
+
+                if is_active_lock {
+                    let lock_state = state.lock_state();
+                    lock_state.lock = None;
+                    lock_state.filter.set_client(None);
+                }
+            }
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Macro to delegate implementation of the ext session lock protocol
+///
+/// You must also implement [`SessionLockHandler`] to use this.
+#[macro_export]
+macro_rules! delegate_session_lock {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        $crate::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::reexports::wayland_protocols::ext::session_lock::v1::server::ext_session_lock_manager_v1::ExtSessionLockManagerV1: ()
+        ] => $crate::wayland::session_lock::SessionLockState);
+
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::reexports::wayland_protocols::ext::session_lock::v1::server::ext_session_lock_manager_v1::ExtSessionLockManagerV1: ()
+        ] => $crate::wayland::session_lock::SessionLockState);
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::reexports::wayland_protocols::ext::session_lock::v1::server::ext_session_lock_v1::ExtSessionLockV1: $crate::wayland::session_lock::SessionLockUserData
+        ] => $crate::wayland::session_lock::SessionLockState);
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            $crate::reexports::wayland_protocols::ext::session_lock::v1::server::ext_session_lock_surface_v1::ExtSessionLockSurfaceV1: $crate::wayland::session_lock::LockSurfaceUserData
+        ] => $crate::wayland::session_lock::SessionLockState);
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{ErrorKind, Read},
+        os::unix::net::UnixStream,
+        sync::{atomic::Ordering, Arc},
+    };
+
+    use wayland_server::{
+        backend::{ClientData, ClientId, DisconnectReason},
+        protocol::{
+            wl_keyboard::WlKeyboard,
+            wl_pointer::WlPointer,
+            wl_surface::{self, WlSurface},
+        },
+        Client, DataInit, Dispatch, Display, DisplayHandle,
+    };
+
+    use super::SessionLockFilter;
+    use crate::{
+        backend::input::KeyState,
+        input::{
+            keyboard::{FilterResult, XkbConfig},
+            pointer::MotionEvent,
+            Seat, SeatHandler, SeatState,
+        },
+        utils::{Time, SERIAL_COUNTER},
+        wayland::seat::{KeyboardUserData, PointerUserData},
+    };
+
+    struct State {
+        seat_state: SeatState<State>,
+    }
+
+    impl SeatHandler for State {
+        type KeyboardFocus = WlSurface;
+        type PointerFocus = WlSurface;
+
+        fn seat_state(&mut self) -> &mut SeatState<Self> {
+            &mut self.seat_state
+        }
+    }
+    crate::delegate_seat!(State);
+
+    impl Dispatch<WlSurface, ()> for State {
+        fn request(
+            _state: &mut Self,
+            _client: &Client,
+            _resource: &WlSurface,
+            _request: wl_surface::Request,
+            _data: &(),
+            _dhandle: &DisplayHandle,
+            _data_init: &mut DataInit<'_, Self>,
+        ) {
+        }
+    }
+
+    struct ClientState;
+    impl ClientData for ClientState {
+        fn initialized(&self, _client_id: ClientId) {}
+        fn disconnected(&self, _client_id: ClientId, _reason: DisconnectReason) {}
+    }
+
+    // a client with a surface, a pointer and a keyboard, the objects are created server-side
+    fn new_client(dh: &mut DisplayHandle, seat: &Seat<State>) -> (Client, WlSurface, UnixStream) {
+        let (server_stream, client_stream) = UnixStream::pair().unwrap();
+        client_stream.set_nonblocking(true).unwrap();
+        let client = dh.insert_client(server_stream, Arc::new(ClientState)).unwrap();
+        let surface = client.create_resource::<WlSurface, _, State>(dh, 5, ()).unwrap();
+
+        let pointer = seat.get_pointer().unwrap();
+        let wl_pointer = client
+            .create_resource::<WlPointer, _, State>(
+                dh,
+                8,
+                PointerUserData {
+                    handle: Some(pointer.clone()),
+                },
+            )
+            .unwrap();
+        pointer.new_pointer(wl_pointer);
+
+        let keyboard = seat.get_keyboard().unwrap();
+        let wl_keyboard = client
+            .create_resource::<WlKeyboard, _, State>(
+                dh,
+                8,
+                KeyboardUserData {
+                    handle: Some(keyboard.clone()),
+                },
+            )
+            .unwrap();
+        keyboard.new_kbd(wl_keyboard);
+
+        (client, surface, client_stream)
+    }
+
+    // number of bytes received by the client since the last call
+    fn received(display: &mut Display<State>, stream: &mut UnixStream) -> usize {
+        display.flush_clients().unwrap();
+        let mut buf = [0u8; 4096];
+        let mut total = 0;
+        loop {
+            match stream.read(&mut buf) {
+                Ok(0) => return total,
+                Ok(n) => total += n,
+                Err(err) if err.kind() == ErrorKind::WouldBlock => return total,
+                Err(err) => panic!("{}", err),
+            }
+        }
+    }
+
+    fn send_input(state: &mut State, seat: &Seat<State>, surface: &WlSurface) {
+        let keyboard = seat.get_keyboard().unwrap();
+        keyboard.set_focus(state, Some(surface.clone()), SERIAL_COUNTER.next_serial());
+        keyboard.input::<(), _>(
+            state,
+            38,
+            KeyState::Pressed,
+            SERIAL_COUNTER.next_serial(),
+            0,
+            |_, _, _| FilterResult::Forward,
+        );
+        seat.get_pointer().unwrap().motion(
+            state,
+            Some((surface.clone(), (0, 0).into())),
+            &MotionEvent {
+                location: (10.0, 10.0).into(),
+                serial: SERIAL_COUNTER.next_serial(),
+                time: Time::from_millis(0),
+            },
+        );
+    }
+
+    #[test]
+    fn locked_session_drops_input_to_other_clients() {
+        let mut display = Display::<State>::new().unwrap();
+        let mut dh = display.handle();
+        let mut state = State {
+            seat_state: SeatState::new(),
+        };
+        let mut seat = state.seat_state.new_wl_seat(&dh, "seat-0", None);
+        seat.add_pointer();
+        seat.add_keyboard(XkbConfig::default(), 200, 25).unwrap();
+
+        let (lock_client, lock_surface, mut lock_stream) = new_client(&mut dh, &seat);
+        let (_other_client, other_surface, mut other_stream) = new_client(&mut dh, &seat);
+        // drop the keymaps
+        received(&mut display, &mut lock_stream);
+        received(&mut display, &mut other_stream);
+
+        let filter = Arc::new(SessionLockFilter::default());
+        filter.locked.store(true, Ordering::SeqCst);
+        *filter.client.lock().unwrap() = Some(lock_client.id());
+        state.seat_state.session_lock = Some(filter.clone());
+
+        send_input(&mut state, &seat, &other_surface);
+        assert_eq!(received(&mut display, &mut other_stream), 0);
+
+        send_input(&mut state, &seat, &lock_surface);
+        assert!(received(&mut display, &mut lock_stream) > 0);
+        // leaving the focus is still delivered
+        received(&mut display, &mut other_stream);
+
+        filter.locked.store(false, Ordering::SeqCst);
+        send_input(&mut state, &seat, &other_surface);
+        assert!(received(&mut display, &mut other_stream) > 0);
+    }
+}
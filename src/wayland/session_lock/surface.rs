@@ -0,0 +1,196 @@
+use std::sync::Mutex;
+
+use wayland_protocols::ext::session_lock::v1::server::ext_session_lock_surface_v1::{
+    self, ExtSessionLockSurfaceV1,
+};
+use wayland_server::{protocol::wl_surface::WlSurface, Client, DataInit, Dispatch, DisplayHandle, New};
+
+use crate::{
+    utils::{IsAlive, Logical, Serial, Size, SERIAL_COUNTER},
+    wayland::compositor::{self, BufferAssignment, SurfaceAttributes},
+};
+
+use super::{SessionLockHandler, SessionLockState};
+
+/// The role of a session lock surface.
+pub const LOCK_SURFACE_ROLE: &str = "ext_session_lock_surface_v1";
+
+/// Attributes of a session lock surface
+#[derive(Debug)]
+pub struct LockSurfaceAttributes {
+    surface: ExtSessionLockSurfaceV1,
+    /// Sizes sent to the client, which it did not acknowledge yet
+    pending_configures: Vec<(Serial, Size<u32, Logical>)>,
+    /// The last size acknowledged by the client
+    pub current_size: Option<Size<u32, Logical>>,
+}
+
+/// Data associated with session lock surfaces
+///
+/// ```no_run
+/// use smithay::wayland::compositor;
+/// use smithay::wayland::session_lock::LockSurfaceData;
+///
+/// # let wl_surface = todo!();
+/// compositor::with_states(&wl_surface, |states| {
+///     states.data_map.get::<LockSurfaceData>();
+/// });
+/// ```
+pub type LockSurfaceData = Mutex<LockSurfaceAttributes>;
+
+/// User data of [ExtSessionLockSurfaceV1] object
+#[derive(Debug)]
+pub struct LockSurfaceUserData {
+    surface: WlSurface,
+}
+
+/// A handle to a session lock surface
+#[derive(Debug, Clone, PartialEq)]
+pub struct LockSurface {
+    surface: WlSurface,
+    shell_surface: ExtSessionLockSurfaceV1,
+}
+
+impl IsAlive for LockSurface {
+    fn alive(&self) -> bool {
+        self.surface.alive()
+    }
+}
+
+impl LockSurface {
+    /// Access the underlying `wl_surface` of this lock surface
+    pub fn wl_surface(&self) -> &WlSurface {
+        &self.surface
+    }
+
+    /// Send a configure with the given size to the client
+    ///
+    /// The size should match the size of the output the surface was created for.
+    /// The client has to attach a buffer of exactly this size.
+    pub fn send_configure(&self, size: Size<u32, Logical>) -> Serial {
+        let serial = SERIAL_COUNTER.next_serial();
+        compositor::with_states(&self.surface, |states| {
+            states
+                .data_map
+                .get::<LockSurfaceData>()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .pending_configures
+                .push((serial, size));
+        });
+        self.shell_surface.configure(serial.into(), size.w, size.h);
+        serial
+    }
+
+    /// The last size acknowledged by the client
+    pub fn current_size(&self) -> Option<Size<u32, Logical>> {
+        compositor::with_states(&self.surface, |states| {
+            states
+                .data_map
+                .get::<LockSurfaceData>()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .current_size
+        })
+    }
+}
+
+pub(super) fn init_lock_surface<D>(
+    data_init: &mut DataInit<'_, D>,
+    id: New<ExtSessionLockSurfaceV1>,
+    surface: WlSurface,
+) -> LockSurface
+where
+    D: Dispatch<ExtSessionLockSurfaceV1, LockSurfaceUserData> + 'static,
+{
+    let shell_surface = data_init.init(
+        id,
+        LockSurfaceUserData {
+            surface: surface.clone(),
+        },
+    );
+
+    compositor::with_states(&surface, |states| {
+        states.data_map.insert_if_missing_threadsafe(|| {
+            Mutex::new(LockSurfaceAttributes {
+                surface: shell_surface.clone(),
+                pending_configures: Vec::new(),
+                current_size: None,
+            })
+        });
+    });
+
+    compositor::add_pre_commit_hook(&surface, |_dh, surface| {
+        compositor::with_states(surface, |states| {
+            let attributes = states.data_map.get::<LockSurfaceData>().unwrap().lock().unwrap();
+            if !attributes.surface.alive() {
+                return;
+            }
+
+            if attributes.current_size.is_none() {
+                attributes.surface.post_error(
+                    ext_session_lock_surface_v1::Error::CommitBeforeFirstAck,
+                    "surface committed before the first configure was acknowledged",
+                );
+                return;
+            }
+
+            if let Some(BufferAssignment::Removed) = states.cached_state.pending::<SurfaceAttributes>().buffer
+            {
+                attributes.surface.post_error(
+                    ext_session_lock_surface_v1::Error::NullBuffer,
+                    "a null buffer was attached to a lock surface",
+                );
+            }
+        });
+    });
+
+    LockSurface {
+        surface,
+        shell_surface,
+    }
+}
+
+impl<D> Dispatch<ExtSessionLockSurfaceV1, LockSurfaceUserData, D> for SessionLockState
+where
+    D: Dispatch<ExtSessionLockSurfaceV1, LockSurfaceUserData> + SessionLockHandler + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        lock_surface: &ExtSessionLockSurfaceV1,
+        request: ext_session_lock_surface_v1::Request,
+        data: &LockSurfaceUserData,
+        _dhandle: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            ext_session_lock_surface_v1::Request::AckConfigure { serial } => {
+                let serial = Serial::from(serial);
+                let found = compositor::with_states(&data.surface, |states| {
+                    let mut attributes = states.data_map.get::<LockSurfaceData>().unwrap().lock().unwrap();
+                    let index = attributes
+                        .pending_configures
+                        .iter()
+                        .position(|(configure_serial, _)| *configure_serial == serial)?;
+                    let (_, size) = attributes.pending_configures[index];
+                    // older configures are implicitly acknowledged
+                    attributes.pending_configures.drain(..=index);
+                    attributes.current_size = Some(size);
+                    Some(())
+                });
+
+                if found.is_none() {
+                    lock_surface.post_error(
+                        ext_session_lock_surface_v1::Error::InvalidSerial,
+                        format!("wrong configure serial: {}", <u32>::from(serial)),
+                    );
+                }
+            }
+            ext_session_lock_surface_v1::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
@@ -111,16 +111,15 @@ where
                     },
                 );
 
-                state
-                    .xdg_shell_state()
-                    .inner
-                    .lock()
-                    .unwrap()
-                    .known_toplevels
-                    .push(make_toplevel_handle(&toplevel));
-
                 let handle = make_toplevel_handle(&toplevel);
 
+                let wm_capabilities = {
+                    let mut inner = state.xdg_shell_state().inner.lock().unwrap();
+                    inner.known_toplevels.push(handle.clone());
+                    inner.wm_capabilities.clone()
+                };
+                handle.send_wm_capabilities(&wm_capabilities);
+
                 XdgShellHandler::new_toplevel(state, handle);
             }
             xdg_surface::Request::GetPopup {
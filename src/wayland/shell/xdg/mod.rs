@@ -118,6 +118,9 @@ pub mod decoration;
 pub(super) mod handlers;
 pub use handlers::{XdgPositionerUserData, XdgShellSurfaceUserData, XdgSurfaceUserData, XdgWmBaseUserData};
 
+mod rules;
+pub use rules::{AppIdMatcher, WindowPolicy, WindowRuleSet};
+
 /// The role of an XDG toplevel surface.
 pub const XDG_TOPLEVEL_ROLE: &str = "xdg_toplevel";
 
@@ -619,6 +622,136 @@ impl PositionerState {
 
         geometry
     }
+
+    /// Get the geometry for a popup as defined by this positioner, constrained to a target area.
+    ///
+    /// `target` is the area the popup should be kept in (e.g. the output the parent is shown on),
+    /// relative to the parent surface `window_geometry` like the returned geometry.
+    ///
+    /// If the geometry returned by [`get_geometry`](PositionerState::get_geometry) is not fully
+    /// inside of `target`, the `constraint_adjustment` is applied for each axis in the order
+    /// mandated by the `xdg_shell` protocol: flipping, then sliding and finally resizing.
+    /// Adjustments not set by the client are skipped, so the result may still not fit.
+    pub fn get_unconstrained_geometry(&self, target: Rectangle<i32, Logical>) -> Rectangle<i32, Logical> {
+        let mut geometry = self.get_geometry();
+        let adjustment = self.constraint_adjustment;
+
+        // flipping only applies if the flipped geometry is not constrained
+        if !fits_x(geometry, target) && adjustment.contains(xdg_positioner::ConstraintAdjustment::FlipX) {
+            let mut flipped = *self;
+            flipped.anchor_edges = flip_anchor_x(self.anchor_edges);
+            flipped.gravity = flip_gravity_x(self.gravity);
+            let flipped_geometry = flipped.get_geometry();
+            if fits_x(flipped_geometry, target) {
+                geometry.loc.x = flipped_geometry.loc.x;
+            }
+        }
+        if !fits_y(geometry, target) && adjustment.contains(xdg_positioner::ConstraintAdjustment::FlipY) {
+            let mut flipped = *self;
+            flipped.anchor_edges = flip_anchor_y(self.anchor_edges);
+            flipped.gravity = flip_gravity_y(self.gravity);
+            let flipped_geometry = flipped.get_geometry();
+            if fits_y(flipped_geometry, target) {
+                geometry.loc.y = flipped_geometry.loc.y;
+            }
+        }
+
+        // sliding prefers the left and top edges, if the popup is bigger than the target
+        if !fits_x(geometry, target) && adjustment.contains(xdg_positioner::ConstraintAdjustment::SlideX) {
+            let overflow_right = geometry.loc.x + geometry.size.w - (target.loc.x + target.size.w);
+            if overflow_right > 0 {
+                geometry.loc.x -= overflow_right;
+            }
+            geometry.loc.x = geometry.loc.x.max(target.loc.x);
+        }
+        if !fits_y(geometry, target) && adjustment.contains(xdg_positioner::ConstraintAdjustment::SlideY) {
+            let overflow_bottom = geometry.loc.y + geometry.size.h - (target.loc.y + target.size.h);
+            if overflow_bottom > 0 {
+                geometry.loc.y -= overflow_bottom;
+            }
+            geometry.loc.y = geometry.loc.y.max(target.loc.y);
+        }
+
+        // resizing is only done if some part of the popup stays visible
+        if !fits_x(geometry, target) && adjustment.contains(xdg_positioner::ConstraintAdjustment::ResizeX) {
+            let left = geometry.loc.x.max(target.loc.x);
+            let right = (geometry.loc.x + geometry.size.w).min(target.loc.x + target.size.w);
+            if right > left {
+                geometry.loc.x = left;
+                geometry.size.w = right - left;
+            }
+        }
+        if !fits_y(geometry, target) && adjustment.contains(xdg_positioner::ConstraintAdjustment::ResizeY) {
+            let top = geometry.loc.y.max(target.loc.y);
+            let bottom = (geometry.loc.y + geometry.size.h).min(target.loc.y + target.size.h);
+            if bottom > top {
+                geometry.loc.y = top;
+                geometry.size.h = bottom - top;
+            }
+        }
+
+        geometry
+    }
+}
+
+fn fits_x(geometry: Rectangle<i32, Logical>, target: Rectangle<i32, Logical>) -> bool {
+    geometry.loc.x >= target.loc.x && geometry.loc.x + geometry.size.w <= target.loc.x + target.size.w
+}
+
+fn fits_y(geometry: Rectangle<i32, Logical>, target: Rectangle<i32, Logical>) -> bool {
+    geometry.loc.y >= target.loc.y && geometry.loc.y + geometry.size.h <= target.loc.y + target.size.h
+}
+
+fn flip_anchor_x(anchor: xdg_positioner::Anchor) -> xdg_positioner::Anchor {
+    use xdg_positioner::Anchor;
+    match anchor {
+        Anchor::Left => Anchor::Right,
+        Anchor::Right => Anchor::Left,
+        Anchor::TopLeft => Anchor::TopRight,
+        Anchor::TopRight => Anchor::TopLeft,
+        Anchor::BottomLeft => Anchor::BottomRight,
+        Anchor::BottomRight => Anchor::BottomLeft,
+        anchor => anchor,
+    }
+}
+
+fn flip_anchor_y(anchor: xdg_positioner::Anchor) -> xdg_positioner::Anchor {
+    use xdg_positioner::Anchor;
+    match anchor {
+        Anchor::Top => Anchor::Bottom,
+        Anchor::Bottom => Anchor::Top,
+        Anchor::TopLeft => Anchor::BottomLeft,
+        Anchor::BottomLeft => Anchor::TopLeft,
+        Anchor::TopRight => Anchor::BottomRight,
+        Anchor::BottomRight => Anchor::TopRight,
+        anchor => anchor,
+    }
+}
+
+fn flip_gravity_x(gravity: xdg_positioner::Gravity) -> xdg_positioner::Gravity {
+    use xdg_positioner::Gravity;
+    match gravity {
+        Gravity::Left => Gravity::Right,
+        Gravity::Right => Gravity::Left,
+        Gravity::TopLeft => Gravity::TopRight,
+        Gravity::TopRight => Gravity::TopLeft,
+        Gravity::BottomLeft => Gravity::BottomRight,
+        Gravity::BottomRight => Gravity::BottomLeft,
+        gravity => gravity,
+    }
+}
+
+fn flip_gravity_y(gravity: xdg_positioner::Gravity) -> xdg_positioner::Gravity {
+    use xdg_positioner::Gravity;
+    match gravity {
+        Gravity::Top => Gravity::Bottom,
+        Gravity::Bottom => Gravity::Top,
+        Gravity::TopLeft => Gravity::BottomLeft,
+        Gravity::BottomLeft => Gravity::TopLeft,
+        Gravity::TopRight => Gravity::BottomRight,
+        Gravity::BottomRight => Gravity::TopRight,
+        gravity => gravity,
+    }
 }
 
 /// State of a regular toplevel surface
@@ -648,6 +781,74 @@ impl Clone for ToplevelState {
     }
 }
 
+/// Snapshot of a toplevel surface, suitable for restoring it in a later session
+///
+/// Created by [`ToplevelSurface::capture_restore_state`]. With the `serde` feature enabled
+/// this type can be serialized and deserialized, so a compositor can persist the state of
+/// its windows and re-apply it once the same application maps a toplevel again, for example
+/// by matching the `app_id` and `title`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct XdgToplevelRestoreState {
+    /// The window geometry of the toplevel
+    ///
+    /// The location is relative to the surface, compositors are expected to
+    /// replace it with the position the window had in their space.
+    #[cfg_attr(feature = "serde", serde(with = "restore_geometry"))]
+    pub geometry: Rectangle<i32, Logical>,
+    /// Whether the toplevel was maximized
+    pub maximized: bool,
+    /// Whether the toplevel was minimized
+    ///
+    /// `xdg_toplevel` has no minimized state, so this is always `false` when captured
+    /// and left to the compositor to fill in.
+    pub minimized: bool,
+    /// Whether the toplevel was fullscreen
+    pub fullscreen: bool,
+    /// The app id of the toplevel
+    pub app_id: Option<String>,
+    /// The title of the toplevel
+    pub title: Option<String>,
+}
+
+#[cfg(feature = "serde")]
+mod restore_geometry {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::utils::{Logical, Rectangle};
+
+    #[derive(Serialize, Deserialize)]
+    struct Geometry {
+        x: i32,
+        y: i32,
+        width: i32,
+        height: i32,
+    }
+
+    pub fn serialize<S: Serializer>(
+        geometry: &Rectangle<i32, Logical>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        Geometry {
+            x: geometry.loc.x,
+            y: geometry.loc.y,
+            width: geometry.size.w,
+            height: geometry.size.h,
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Rectangle<i32, Logical>, D::Error> {
+        let geometry = Geometry::deserialize(deserializer)?;
+        Ok(Rectangle::from_loc_and_size(
+            (geometry.x, geometry.y),
+            (geometry.width, geometry.height),
+        ))
+    }
+}
+
 /// Container holding the states for a `XdgToplevel`
 ///
 /// This container will prevent the `XdgToplevel` from
@@ -837,6 +1038,10 @@ pub trait XdgShellHandler {
     fn unfullscreen_request(&mut self, surface: ToplevelSurface) {}
 
     /// A toplevel surface requested to be minimized
+    ///
+    /// There is no minimized state the client would be notified about, it is up to the compositor
+    /// to unmap the surface, e.g. by removing it from the space it is displayed in, and to restore it
+    /// later on. The request may also be ignored, if minimizing is not supported.
     fn minimize_request(&mut self, surface: ToplevelSurface) {}
 
     /// The client requests the window menu to be displayed on this surface at this location
@@ -882,6 +1087,7 @@ pub trait XdgShellHandler {
 pub(crate) struct InnerState {
     known_toplevels: Vec<ToplevelSurface>,
     known_popups: Vec<PopupSurface>,
+    wm_capabilities: Vec<xdg_toplevel::WmCapabilities>,
 }
 
 /// Shell global state
@@ -903,18 +1109,37 @@ impl XdgShellState {
         D: GlobalDispatch<XdgWmBase, ()> + 'static,
     {
         let log = crate::slog_or_fallback(logger);
-        let global = display.create_global::<D, XdgWmBase, _>(3, ());
+        let global = display.create_global::<D, XdgWmBase, _>(5, ());
 
         XdgShellState {
             inner: Arc::new(Mutex::new(InnerState {
                 known_toplevels: Vec::new(),
                 known_popups: Vec::new(),
+                wm_capabilities: vec![
+                    xdg_toplevel::WmCapabilities::WindowMenu,
+                    xdg_toplevel::WmCapabilities::Maximize,
+                    xdg_toplevel::WmCapabilities::Fullscreen,
+                    xdg_toplevel::WmCapabilities::Minimize,
+                ],
             })),
             global,
             _log: log.new(slog::o!("smithay_module" => "xdg_shell_handler")),
         }
     }
 
+    /// Declare the window management capabilities supported by the compositor
+    ///
+    /// The capabilities are advertised to every new toplevel before its initial configure,
+    /// by default all capabilities are advertised.
+    /// Clients binding `xdg_wm_base` with a version lower than 5 do not receive them
+    /// and assume all capabilities to be supported.
+    ///
+    /// See [`ToplevelSurface::send_wm_capabilities`] to change the capabilities of an existing toplevel.
+    pub fn with_wm_capabilities(self, caps: &[xdg_toplevel::WmCapabilities]) -> Self {
+        self.inner.lock().unwrap().wm_capabilities = caps.to_vec();
+        self
+    }
+
     /// Access all the shell surfaces known by this handler
     pub fn toplevel_surfaces<T, F: FnMut(&[ToplevelSurface]) -> T>(&self, mut cb: F) -> T {
         cb(&self.inner.lock().unwrap().known_toplevels)
@@ -1092,6 +1317,38 @@ impl ToplevelSurface {
         attributes.server_pending.take()
     }
 
+    /// Send the bounds the toplevel surface should stay within
+    ///
+    /// The bounds are a recommendation for the maximum size of the window, e.g. the
+    /// usable area of the output the window will be mapped on, and take effect with the
+    /// next [`send_configure`](#method.send_configure). A size of zero means the bounds are unknown.
+    ///
+    /// Clients binding `xdg_wm_base` with a version lower than 4 do not support this
+    /// event, for them this is a no-op.
+    pub fn send_configure_bounds(&self, bounds: Size<i32, Logical>) {
+        if self.shell_surface.version() >= xdg_toplevel::EVT_CONFIGURE_BOUNDS_SINCE {
+            self.shell_surface.configure_bounds(bounds.w, bounds.h);
+        }
+    }
+
+    /// Send the window management capabilities supported for this toplevel surface
+    ///
+    /// The capabilities declared with [`XdgShellState::with_wm_capabilities`] are sent automatically
+    /// when the toplevel is created. If they change afterwards, this has to be followed by a
+    /// [`send_configure`](#method.send_configure) for the client to apply them.
+    ///
+    /// Clients binding `xdg_wm_base` with a version lower than 5 do not support this
+    /// event, for them this is a no-op.
+    pub fn send_wm_capabilities(&self, caps: &[xdg_toplevel::WmCapabilities]) {
+        if self.shell_surface.version() >= xdg_toplevel::EVT_WM_CAPABILITIES_SINCE {
+            let caps = caps
+                .iter()
+                .flat_map(|cap| (*cap as u32).to_ne_bytes())
+                .collect::<Vec<u8>>();
+            self.shell_surface.wm_capabilities(caps);
+        }
+    }
+
     /// Send a configure event to this toplevel surface to suggest it a new configuration
     ///
     /// The serial of this configure will be tracked waiting for the client to ACK it.
@@ -1251,6 +1508,40 @@ impl ToplevelSurface {
         })
     }
 
+    /// Capture the current state of this toplevel for session restore
+    ///
+    /// The geometry is the window geometry set by the client, falling back to the
+    /// current configured size if the client did not set any.
+    pub fn capture_restore_state(&self) -> XdgToplevelRestoreState {
+        compositor::with_states(&self.wl_surface, |states| {
+            let attributes = states
+                .data_map
+                .get::<XdgToplevelSurfaceData>()
+                .unwrap()
+                .lock()
+                .unwrap();
+            let geometry = states
+                .cached_state
+                .current::<SurfaceCachedState>()
+                .geometry
+                .unwrap_or_else(|| {
+                    Rectangle::from_loc_and_size((0, 0), attributes.current.size.unwrap_or_default())
+                });
+
+            XdgToplevelRestoreState {
+                geometry,
+                maximized: attributes.current.states.contains(xdg_toplevel::State::Maximized),
+                minimized: false,
+                fullscreen: attributes
+                    .current
+                    .states
+                    .contains(xdg_toplevel::State::Fullscreen),
+                app_id: attributes.app_id.clone(),
+                title: attributes.title.clone(),
+            }
+        })
+    }
+
     /// Returns the parent of this toplevel surface.
     pub fn parent(&self) -> Option<wl_surface::WlSurface> {
         handlers::get_parent(&self.shell_surface)
@@ -1268,8 +1559,7 @@ impl ToplevelSurface {
             }
         }
 
-        // Unset the parent
-        handlers::set_parent(&self.shell_surface, None);
+        handlers::set_parent(&self.shell_surface, parent.cloned());
 
         true
     }
@@ -1434,6 +1724,19 @@ impl PopupSurface {
         self.send_configure_internal(Some(token))
     }
 
+    /// Reposition this popup in response to a [`reposition_request`](XdgShellHandler::reposition_request)
+    ///
+    /// The geometry is recomputed from the new positioner and constrained to `target`, see
+    /// [`PositionerState::get_unconstrained_geometry`]. Afterwards the `repositioned`, `xdg_popup.configure`
+    /// and `xdg_surface.configure` events are sent to the client.
+    pub fn reposition(&self, positioner: PositionerState, target: Rectangle<i32, Logical>, token: u32) {
+        self.with_pending_state(|state| {
+            state.geometry = positioner.get_unconstrained_geometry(target);
+            state.positioner = positioner;
+        });
+        self.send_repositioned(token);
+    }
+
     /// Handles the role specific commit logic
     ///
     /// This should be called when the underlying WlSurface
@@ -1607,3 +1910,57 @@ macro_rules! delegate_xdg_shell {
         ] => $crate::wayland::shell::xdg::XdgShellState);
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use super::PositionerState;
+    use crate::utils::{Logical, Rectangle};
+    use wayland_protocols::xdg::shell::server::xdg_positioner::{Anchor, ConstraintAdjustment, Gravity};
+
+    fn rect(x: i32, y: i32, w: i32, h: i32) -> Rectangle<i32, Logical> {
+        Rectangle::from_loc_and_size((x, y), (w, h))
+    }
+
+    fn positioner(constraint_adjustment: ConstraintAdjustment) -> PositionerState {
+        PositionerState {
+            rect_size: (100, 50).into(),
+            anchor_rect: rect(150, 0, 20, 20),
+            anchor_edges: Anchor::BottomRight,
+            gravity: Gravity::BottomRight,
+            constraint_adjustment,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn unconstrained_geometry() {
+        let target = rect(0, 0, 200, 200);
+        assert_eq!(
+            positioner(ConstraintAdjustment::empty()).get_geometry(),
+            rect(170, 20, 100, 50)
+        );
+        assert_eq!(
+            positioner(ConstraintAdjustment::empty()).get_unconstrained_geometry(target),
+            rect(170, 20, 100, 50)
+        );
+        // flipped to the left of the anchor rect
+        assert_eq!(
+            positioner(ConstraintAdjustment::FlipX).get_unconstrained_geometry(target),
+            rect(50, 20, 100, 50)
+        );
+        assert_eq!(
+            positioner(ConstraintAdjustment::SlideX).get_unconstrained_geometry(target),
+            rect(100, 20, 100, 50)
+        );
+        assert_eq!(
+            positioner(ConstraintAdjustment::ResizeX).get_unconstrained_geometry(target),
+            rect(170, 20, 30, 50)
+        );
+        // flipping would still not fit into a narrow target, so the popup is slid instead
+        assert_eq!(
+            positioner(ConstraintAdjustment::FlipX | ConstraintAdjustment::SlideX)
+                .get_unconstrained_geometry(rect(100, 0, 120, 200)),
+            rect(120, 20, 100, 50)
+        );
+    }
+}
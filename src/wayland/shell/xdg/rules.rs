@@ -0,0 +1,199 @@
+//! Declarative placement policies for toplevel surfaces
+//!
+//! A [`WindowRuleSet`] maps the `app_id` of a toplevel surface to a [`WindowPolicy`],
+//! describing the defaults a compositor should use when the toplevel is first mapped.
+//!
+//! Clients usually set their `app_id` after creating the toplevel, so the rules can not be applied
+//! in [`XdgShellHandler::new_toplevel`](super::XdgShellHandler::new_toplevel). Apply them on the
+//! initial commit of the toplevel instead, right before sending the initial configure.
+//!
+//! ```no_run
+//! use smithay::wayland::{
+//!     compositor,
+//!     shell::xdg::{AppIdMatcher, WindowPolicy, WindowRuleSet, XdgToplevelSurfaceData},
+//! };
+//! # use smithay::wayland::shell::xdg::ToplevelSurface;
+//!
+//! let mut rules = WindowRuleSet::new();
+//! rules.add_rule(
+//!     AppIdMatcher::Exact("org.gnome.Calculator".into()),
+//!     WindowPolicy {
+//!         size: Some((400, 600).into()),
+//!         ..Default::default()
+//!     },
+//! );
+//! rules.add_rule(
+//!     AppIdMatcher::Prefix("org.mozilla.".into()),
+//!     WindowPolicy {
+//!         output: Some("DP-1".into()),
+//!         maximized: true,
+//!         ..Default::default()
+//!     },
+//! );
+//!
+//! # let toplevel: ToplevelSurface = todo!();
+//! // in CompositorHandler::commit
+//! let initial_configure_sent = compositor::with_states(toplevel.wl_surface(), |states| {
+//!     states
+//!         .data_map
+//!         .get::<XdgToplevelSurfaceData>()
+//!         .unwrap()
+//!         .lock()
+//!         .unwrap()
+//!         .initial_configure_sent
+//! });
+//! if !initial_configure_sent {
+//!     if let Some(policy) = rules.apply(&toplevel) {
+//!         // place the window on `policy.output`
+//!     }
+//!     toplevel.send_configure();
+//! }
+//! ```
+
+use wayland_protocols::xdg::{
+    decoration::zv1::server::zxdg_toplevel_decoration_v1::Mode as DecorationMode, shell::server::xdg_toplevel,
+};
+
+use crate::{
+    utils::{Logical, Size},
+    wayland::compositor,
+};
+
+use super::{ToplevelSurface, XdgToplevelSurfaceData};
+
+/// Matches the `app_id` of a toplevel surface
+#[derive(Debug, Clone)]
+pub enum AppIdMatcher {
+    /// The `app_id` has to be equal to the given string
+    Exact(String),
+    /// The `app_id` has to start with the given string
+    Prefix(String),
+    /// The `app_id` has to match the given regular expression
+    #[cfg(feature = "regex")]
+    Regex(regex::Regex),
+}
+
+impl AppIdMatcher {
+    /// Returns whether the given `app_id` is matched
+    pub fn matches(&self, app_id: &str) -> bool {
+        match self {
+            AppIdMatcher::Exact(exact) => app_id == exact,
+            AppIdMatcher::Prefix(prefix) => app_id.starts_with(prefix.as_str()),
+            #[cfg(feature = "regex")]
+            AppIdMatcher::Regex(regex) => regex.is_match(app_id),
+        }
+    }
+}
+
+/// Defaults to use for a newly mapped toplevel surface
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct WindowPolicy {
+    /// The initial size of the toplevel
+    pub size: Option<Size<i32, Logical>>,
+    /// Name of the output the toplevel should be placed on
+    pub output: Option<String>,
+    /// Whether the toplevel should start maximized
+    pub maximized: bool,
+    /// Whether the toplevel should start fullscreen
+    pub fullscreen: bool,
+    /// The decoration mode the toplevel should use
+    pub decoration_mode: Option<DecorationMode>,
+}
+
+/// An ordered set of rules mapping `app_id`s to [`WindowPolicy`]s
+#[derive(Debug, Default, Clone)]
+pub struct WindowRuleSet {
+    rules: Vec<(AppIdMatcher, WindowPolicy)>,
+}
+
+impl WindowRuleSet {
+    /// Create an empty rule set
+    pub fn new() -> Self {
+        WindowRuleSet::default()
+    }
+
+    /// Add a rule to this set
+    ///
+    /// Rules are evaluated in the order they were added, the first matching rule wins.
+    pub fn add_rule(&mut self, matcher: AppIdMatcher, policy: WindowPolicy) {
+        self.rules.push((matcher, policy));
+    }
+
+    /// Returns the policy of the first rule matching the given `app_id`
+    pub fn policy_for(&self, app_id: &str) -> Option<&WindowPolicy> {
+        self.rules
+            .iter()
+            .find(|(matcher, _)| matcher.matches(app_id))
+            .map(|(_, policy)| policy)
+    }
+
+    /// Apply the first rule matching the `app_id` of the given toplevel
+    ///
+    /// The size, maximized and fullscreen states and decoration mode of the policy are set
+    /// in the pending state of the toplevel, the matching policy is returned so the compositor
+    /// can take care of the output placement. It is up to the caller to send a configure afterwards.
+    ///
+    /// This should be called on the initial commit of the toplevel, before the initial configure
+    /// is sent. Earlier, e.g. in [`XdgShellHandler::new_toplevel`](super::XdgShellHandler::new_toplevel),
+    /// the client most likely did not set its `app_id` yet.
+    ///
+    /// Returns `None` if the toplevel has no `app_id` or no rule matched.
+    pub fn apply(&self, toplevel: &ToplevelSurface) -> Option<WindowPolicy> {
+        let app_id = compositor::with_states(toplevel.wl_surface(), |states| {
+            states
+                .data_map
+                .get::<XdgToplevelSurfaceData>()
+                .unwrap()
+                .lock()
+                .unwrap()
+                .app_id
+                .clone()
+        })?;
+        let policy = self.policy_for(&app_id)?.clone();
+
+        toplevel.with_pending_state(|state| {
+            if policy.size.is_some() {
+                state.size = policy.size;
+            }
+            if policy.maximized {
+                state.states.set(xdg_toplevel::State::Maximized);
+            }
+            if policy.fullscreen {
+                state.states.set(xdg_toplevel::State::Fullscreen);
+            }
+            if policy.decoration_mode.is_some() {
+                state.decoration_mode = policy.decoration_mode;
+            }
+        });
+
+        Some(policy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AppIdMatcher, WindowPolicy, WindowRuleSet};
+
+    #[test]
+    fn first_matching_rule_wins() {
+        let mut rules = WindowRuleSet::new();
+        rules.add_rule(
+            AppIdMatcher::Exact("org.example.App".into()),
+            WindowPolicy {
+                maximized: true,
+                ..Default::default()
+            },
+        );
+        rules.add_rule(
+            AppIdMatcher::Prefix("org.example.".into()),
+            WindowPolicy {
+                fullscreen: true,
+                ..Default::default()
+            },
+        );
+
+        assert!(rules.policy_for("org.example.App").unwrap().maximized);
+        assert!(rules.policy_for("org.example.Other").unwrap().fullscreen);
+        assert!(rules.policy_for("org.example").is_none());
+    }
+}
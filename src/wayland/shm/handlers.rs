@@ -34,8 +34,8 @@ where
         let shm = data_init.init(resource, ());
 
         // send the formats
-        for &f in &state.shm_state().formats[..] {
-            shm.format(f);
+        for format in state.shm_state().supported_formats() {
+            shm.format(format);
         }
     }
 }
@@ -163,6 +163,20 @@ impl ShmState {
     pub fn global(&self) -> GlobalId {
         self.shm.clone()
     }
+
+    /// Advertize an additional format, e.g. one supported by the renderer
+    ///
+    /// The format is only advertized to clients binding the global afterwards.
+    pub fn add_format(&mut self, format: wl_shm::Format) {
+        if !self.formats.contains(&format) {
+            self.formats.push(format);
+        }
+    }
+
+    /// Returns the formats advertized by the global
+    pub fn supported_formats(&self) -> impl Iterator<Item = wl_shm::Format> + '_ {
+        self.formats.iter().copied()
+    }
 }
 
 /// Shm global handler
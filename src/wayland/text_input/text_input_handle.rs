@@ -93,6 +93,27 @@ impl TextInputHandle {
         let inner = self.inner.lock().unwrap();
         inner.with_focused_text_input(f);
     }
+
+    /// Send a pre-edit string to the client of the focused text input surface
+    ///
+    /// This can be used to display an in-progress composition, e.g. of a dead-key sequence.
+    /// The cursor positions are byte offsets into `text`, if both are `-1` the cursor is hidden.
+    pub fn set_preedit(&self, text: &str, cursor_begin: i32, cursor_end: i32) {
+        self.with_focused_text_input(|ti, _surface, serial| {
+            ti.preedit_string(Some(text.to_string()), cursor_begin, cursor_end);
+            ti.done(*serial);
+        });
+    }
+
+    /// Clear the pre-edit string of the focused text input surface
+    ///
+    /// Resets the display of an in-progress composition set with [`TextInputHandle::set_preedit`].
+    pub fn preedit_cleared(&self) {
+        self.with_focused_text_input(|ti, _surface, serial| {
+            ti.preedit_string(None, 0, 0);
+            ti.done(*serial);
+        });
+    }
 }
 
 /// User data of ZwpTextInputV3 object
@@ -0,0 +1,443 @@
+//! Utilities for handling the `zxdg_foreign_v2` protocol
+//!
+//! This protocol allows a client to export a handle to one of its `xdg_toplevel` surfaces,
+//! which another client can then import in order to set up a parent-child relationship
+//! between its own toplevel and the foreign one. This is used for example by sandboxed
+//! applications to parent a dialog of a portal process to their own window.
+//!
+//! Exported handles are random strings generated by the compositor. They are invalidated
+//! once the exporting client destroys its `zxdg_exported_v2` object (or disconnects), at which
+//! point every importer of the handle is notified through `zxdg_imported_v2::destroyed`.
+//!
+//! Parenting requests are applied directly to the [`ToplevelSurface`](crate::wayland::shell::xdg::ToplevelSurface)
+//! of the importing client, hence your state also needs to implement
+//! [`XdgShellHandler`](crate::wayland::shell::xdg::XdgShellHandler).
+//!
+//! ### Example
+//!
+//! ```no_run
+//! # extern crate wayland_server;
+//! use smithay::delegate_xdg_foreign;
+//! use smithay::wayland::xdg_foreign::{XdgForeignHandler, XdgForeignState};
+//! # use smithay::wayland::shell::xdg::{XdgShellHandler, XdgShellState, ToplevelSurface, PopupSurface, PositionerState};
+//! # use smithay::utils::Serial;
+//! # use smithay::reexports::wayland_server::protocol::wl_seat;
+//!
+//! # struct State { xdg_shell_state: XdgShellState, xdg_foreign_state: XdgForeignState }
+//! # let mut display = wayland_server::Display::<State>::new().unwrap();
+//! // Create the xdg foreign globals
+//! let xdg_foreign_state = XdgForeignState::new::<State, _>(&display.handle(), None);
+//!
+//! impl XdgForeignHandler for State {
+//!     fn xdg_foreign_state(&mut self) -> &mut XdgForeignState {
+//!         &mut self.xdg_foreign_state
+//!     }
+//! }
+//! # impl XdgShellHandler for State {
+//! #     fn xdg_shell_state(&mut self) -> &mut XdgShellState { unimplemented!() }
+//! #     fn new_toplevel(&mut self, surface: ToplevelSurface) { unimplemented!() }
+//! #     fn new_popup(&mut self, surface: PopupSurface, positioner: PositionerState) { unimplemented!() }
+//! #     fn grab(&mut self, surface: PopupSurface, seat: wl_seat::WlSeat, serial: Serial) { unimplemented!() }
+//! # }
+//!
+//! // Delegate xdg foreign handling for State to XdgForeignState.
+//! delegate_xdg_foreign!(State);
+//! ```
+
+use std::{collections::HashMap, sync::Mutex};
+
+use rand::distributions::{Alphanumeric, DistString};
+use wayland_protocols::xdg::foreign::zv2::server::{
+    zxdg_exported_v2::{self, ZxdgExportedV2},
+    zxdg_exporter_v2::{self, ZxdgExporterV2},
+    zxdg_imported_v2::{self, ZxdgImportedV2},
+    zxdg_importer_v2::{self, ZxdgImporterV2},
+};
+use wayland_server::{
+    backend::{ClientId, GlobalId, ObjectId},
+    protocol::wl_surface::WlSurface,
+    Client, DataInit, Dispatch, DisplayHandle, GlobalDispatch, New, Resource,
+};
+
+use crate::wayland::{
+    compositor,
+    shell::xdg::{ToplevelSurface, XdgShellHandler, XDG_TOPLEVEL_ROLE},
+};
+
+/// State of the xdg foreign globals
+#[derive(Debug)]
+pub struct XdgForeignState {
+    exporter: GlobalId,
+    importer: GlobalId,
+    exports: HashMap<String, WlSurface>,
+    imports: Vec<ZxdgImportedV2>,
+    log: ::slog::Logger,
+}
+
+impl XdgForeignState {
+    /// Creates the `zxdg_exporter_v2` and `zxdg_importer_v2` globals.
+    ///
+    /// In order to use this abstraction, your `D` type needs to implement [`XdgForeignHandler`].
+    pub fn new<D, L>(display: &DisplayHandle, logger: L) -> XdgForeignState
+    where
+        D: GlobalDispatch<ZxdgExporterV2, ()>
+            + GlobalDispatch<ZxdgImporterV2, ()>
+            + Dispatch<ZxdgExporterV2, ()>
+            + Dispatch<ZxdgImporterV2, ()>
+            + Dispatch<ZxdgExportedV2, XdgExportedUserData>
+            + Dispatch<ZxdgImportedV2, XdgImportedUserData>
+            + XdgForeignHandler
+            + XdgShellHandler
+            + 'static,
+        L: Into<Option<::slog::Logger>>,
+    {
+        let log = crate::slog_or_fallback(logger).new(slog::o!("smithay_module" => "xdg_foreign"));
+        let exporter = display.create_global::<D, ZxdgExporterV2, _>(1, ());
+        let importer = display.create_global::<D, ZxdgImporterV2, _>(1, ());
+
+        XdgForeignState {
+            exporter,
+            importer,
+            exports: HashMap::new(),
+            imports: Vec::new(),
+            log,
+        }
+    }
+
+    /// Returns the `zxdg_exporter_v2` global.
+    pub fn exporter_global(&self) -> GlobalId {
+        self.exporter.clone()
+    }
+
+    /// Returns the `zxdg_importer_v2` global.
+    pub fn importer_global(&self) -> GlobalId {
+        self.importer.clone()
+    }
+
+    /// Returns the surface exported under the given handle, if any.
+    pub fn exported_surface(&self, handle: &str) -> Option<&WlSurface> {
+        self.exports.get(handle)
+    }
+
+    fn export(&mut self, surface: WlSurface) -> String {
+        let handle = loop {
+            let handle = Alphanumeric.sample_string(&mut rand::thread_rng(), 32);
+            if !self.exports.contains_key(&handle) {
+                break handle;
+            }
+        };
+        self.exports.insert(handle.clone(), surface);
+        handle
+    }
+
+    fn unexport(&mut self, handle: &str) -> Vec<(ZxdgImportedV2, Vec<WlSurface>)> {
+        if self.exports.remove(handle).is_none() {
+            return Vec::new();
+        }
+
+        let mut invalidated = Vec::new();
+        self.imports.retain(|imported| {
+            let data = imported.data::<XdgImportedUserData>().unwrap();
+            if data.handle != handle {
+                return true;
+            }
+            let children = std::mem::take(&mut *data.children.lock().unwrap());
+            invalidated.push((imported.clone(), children));
+            false
+        });
+        invalidated
+    }
+}
+
+/// Handler trait for the xdg foreign protocol.
+pub trait XdgForeignHandler {
+    /// [`XdgForeignState`] getter
+    fn xdg_foreign_state(&mut self) -> &mut XdgForeignState;
+}
+
+/// User data of `zxdg_exported_v2` objects
+#[derive(Debug)]
+pub struct XdgExportedUserData {
+    handle: String,
+}
+
+impl XdgExportedUserData {
+    /// The handle this surface was exported under
+    pub fn handle(&self) -> &str {
+        &self.handle
+    }
+}
+
+/// User data of `zxdg_imported_v2` objects
+#[derive(Debug)]
+pub struct XdgImportedUserData {
+    handle: String,
+    children: Mutex<Vec<WlSurface>>,
+}
+
+impl XdgImportedUserData {
+    /// The handle this surface was imported from
+    pub fn handle(&self) -> &str {
+        &self.handle
+    }
+}
+
+fn find_toplevel<D: XdgShellHandler>(state: &mut D, surface: &WlSurface) -> Option<ToplevelSurface> {
+    state
+        .xdg_shell_state()
+        .toplevel_surfaces(|toplevels| toplevels.iter().find(|t| t.wl_surface() == surface).cloned())
+}
+
+/// Remove the parent of every still alive child, that is still parented to `parent`
+fn unset_parents<D: XdgShellHandler>(state: &mut D, parent: Option<&WlSurface>, children: Vec<WlSurface>) {
+    for child in children.into_iter().filter(|child| child.alive()) {
+        if let Some(toplevel) = find_toplevel(state, &child) {
+            if parent.is_none() || toplevel.parent().as_ref() == parent {
+                toplevel.set_parent(None);
+            }
+        }
+    }
+}
+
+impl<D> GlobalDispatch<ZxdgExporterV2, (), D> for XdgForeignState
+where
+    D: GlobalDispatch<ZxdgExporterV2, ()> + Dispatch<ZxdgExporterV2, ()> + 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _dh: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZxdgExporterV2>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl<D> GlobalDispatch<ZxdgImporterV2, (), D> for XdgForeignState
+where
+    D: GlobalDispatch<ZxdgImporterV2, ()> + Dispatch<ZxdgImporterV2, ()> + 'static,
+{
+    fn bind(
+        _state: &mut D,
+        _dh: &DisplayHandle,
+        _client: &Client,
+        resource: New<ZxdgImporterV2>,
+        _global_data: &(),
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        data_init.init(resource, ());
+    }
+}
+
+impl<D> Dispatch<ZxdgExporterV2, (), D> for XdgForeignState
+where
+    D: Dispatch<ZxdgExporterV2, ()>
+        + Dispatch<ZxdgExportedV2, XdgExportedUserData>
+        + XdgForeignHandler
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        exporter: &ZxdgExporterV2,
+        request: zxdg_exporter_v2::Request,
+        _data: &(),
+        _dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            zxdg_exporter_v2::Request::ExportToplevel { id, surface } => {
+                if compositor::get_role(&surface) != Some(XDG_TOPLEVEL_ROLE) {
+                    exporter.post_error(
+                        zxdg_exporter_v2::Error::InvalidSurface,
+                        "surface must have the xdg_toplevel role",
+                    );
+                    return;
+                }
+
+                let foreign_state = state.xdg_foreign_state();
+                let handle = foreign_state.export(surface);
+                slog::trace!(foreign_state.log, "Exported toplevel"; "handle" => &handle);
+
+                let exported = data_init.init(
+                    id,
+                    XdgExportedUserData {
+                        handle: handle.clone(),
+                    },
+                );
+                exported.handle(handle);
+            }
+            zxdg_exporter_v2::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<ZxdgExportedV2, XdgExportedUserData, D> for XdgForeignState
+where
+    D: Dispatch<ZxdgExportedV2, XdgExportedUserData> + XdgForeignHandler + XdgShellHandler + 'static,
+{
+    fn request(
+        _state: &mut D,
+        _client: &Client,
+        _exported: &ZxdgExportedV2,
+        request: zxdg_exported_v2::Request,
+        _data: &XdgExportedUserData,
+        _dh: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            // Handled by the destructor
+            zxdg_exported_v2::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(state: &mut D, _client: ClientId, _resource: ObjectId, data: &XdgExportedUserData) {
+        let foreign_state = state.xdg_foreign_state();
+        let parent = foreign_state.exports.get(&data.handle).cloned();
+        let invalidated = foreign_state.unexport(&data.handle);
+        slog::trace!(foreign_state.log, "Unexported toplevel"; "handle" => &data.handle);
+
+        for (imported, children) in invalidated {
+            imported.destroyed();
+            unset_parents(state, parent.as_ref(), children);
+        }
+    }
+}
+
+impl<D> Dispatch<ZxdgImporterV2, (), D> for XdgForeignState
+where
+    D: Dispatch<ZxdgImporterV2, ()>
+        + Dispatch<ZxdgImportedV2, XdgImportedUserData>
+        + XdgForeignHandler
+        + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        _importer: &ZxdgImporterV2,
+        request: zxdg_importer_v2::Request,
+        _data: &(),
+        _dh: &DisplayHandle,
+        data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            zxdg_importer_v2::Request::ImportToplevel { id, handle } => {
+                let imported = data_init.init(
+                    id,
+                    XdgImportedUserData {
+                        handle: handle.clone(),
+                        children: Mutex::new(Vec::new()),
+                    },
+                );
+
+                let foreign_state = state.xdg_foreign_state();
+                match foreign_state.exports.get(&handle) {
+                    Some(surface) if surface.alive() => foreign_state.imports.push(imported),
+                    // The handle is unknown or refers to a dead surface, the import is invalid
+                    _ => imported.destroyed(),
+                }
+            }
+            zxdg_importer_v2::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+}
+
+impl<D> Dispatch<ZxdgImportedV2, XdgImportedUserData, D> for XdgForeignState
+where
+    D: Dispatch<ZxdgImportedV2, XdgImportedUserData> + XdgForeignHandler + XdgShellHandler + 'static,
+{
+    fn request(
+        state: &mut D,
+        _client: &Client,
+        imported: &ZxdgImportedV2,
+        request: zxdg_imported_v2::Request,
+        data: &XdgImportedUserData,
+        _dh: &DisplayHandle,
+        _data_init: &mut DataInit<'_, D>,
+    ) {
+        match request {
+            zxdg_imported_v2::Request::SetParentOf { surface } => {
+                let toplevel = match find_toplevel(state, &surface) {
+                    Some(toplevel) => toplevel,
+                    None => {
+                        imported.post_error(
+                            zxdg_imported_v2::Error::InvalidSurface,
+                            "surface must have the xdg_toplevel role",
+                        );
+                        return;
+                    }
+                };
+
+                let foreign_state = state.xdg_foreign_state();
+                if !foreign_state.imports.contains(imported) {
+                    // the exported surface is already gone
+                    return;
+                }
+                if let Some(parent) = foreign_state.exports.get(&data.handle) {
+                    if toplevel.set_parent(Some(parent)) {
+                        let mut children = data.children.lock().unwrap();
+                        if !children.contains(&surface) {
+                            children.push(surface);
+                        }
+                    }
+                }
+            }
+            // Handled by the destructor
+            zxdg_imported_v2::Request::Destroy => {}
+            _ => unreachable!(),
+        }
+    }
+
+    fn destroyed(state: &mut D, _client: ClientId, resource: ObjectId, data: &XdgImportedUserData) {
+        let foreign_state = state.xdg_foreign_state();
+        foreign_state.imports.retain(|imported| imported.id() != resource);
+        let parent = foreign_state.exports.get(&data.handle).cloned();
+
+        let children = std::mem::take(&mut *data.children.lock().unwrap());
+        if let Some(parent) = parent {
+            unset_parents(state, Some(&parent), children);
+        }
+    }
+}
+
+/// Macro to delegate implementation of the xdg foreign protocol to [`XdgForeignState`].
+///
+/// You must also implement [`XdgForeignHandler`] and [`XdgShellHandler`] to use this.
+#[macro_export]
+macro_rules! delegate_xdg_foreign {
+    ($(@<$( $lt:tt $( : $clt:tt $(+ $dlt:tt )* )? ),+>)? $ty: ty) => {
+        type __ZxdgExporterV2 =
+            $crate::reexports::wayland_protocols::xdg::foreign::zv2::server::zxdg_exporter_v2::ZxdgExporterV2;
+        type __ZxdgImporterV2 =
+            $crate::reexports::wayland_protocols::xdg::foreign::zv2::server::zxdg_importer_v2::ZxdgImporterV2;
+        type __ZxdgExportedV2 =
+            $crate::reexports::wayland_protocols::xdg::foreign::zv2::server::zxdg_exported_v2::ZxdgExportedV2;
+        type __ZxdgImportedV2 =
+            $crate::reexports::wayland_protocols::xdg::foreign::zv2::server::zxdg_imported_v2::ZxdgImportedV2;
+
+        $crate::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            __ZxdgExporterV2: ()
+        ] => $crate::wayland::xdg_foreign::XdgForeignState);
+        $crate::reexports::wayland_server::delegate_global_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            __ZxdgImporterV2: ()
+        ] => $crate::wayland::xdg_foreign::XdgForeignState);
+
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            __ZxdgExporterV2: ()
+        ] => $crate::wayland::xdg_foreign::XdgForeignState);
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            __ZxdgImporterV2: ()
+        ] => $crate::wayland::xdg_foreign::XdgForeignState);
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            __ZxdgExportedV2: $crate::wayland::xdg_foreign::XdgExportedUserData
+        ] => $crate::wayland::xdg_foreign::XdgForeignState);
+        $crate::reexports::wayland_server::delegate_dispatch!($(@< $( $lt $( : $clt $(+ $dlt )* )? ),+ >)? $ty: [
+            __ZxdgImportedV2: $crate::wayland::xdg_foreign::XdgImportedUserData
+        ] => $crate::wayland::xdg_foreign::XdgForeignState);
+    };
+}
@@ -2,7 +2,10 @@ use crate::{
     backend::{input::KeyState, renderer::element::Id},
     input::{
         keyboard::{KeyboardTarget, KeysymHandle, ModifiersState},
-        pointer::{AxisFrame, ButtonEvent, MotionEvent, PointerTarget, RelativeMotionEvent},
+        pointer::{
+            AxisFrame, ButtonEvent, GesturePinchEvent, GestureSwipeEvent, MotionEvent, PointerTarget,
+            RelativeMotionEvent,
+        },
         Seat, SeatHandler,
     },
     utils::{user_data::UserDataMap, IsAlive, Logical, Rectangle, Serial, Size},
@@ -950,6 +953,18 @@ impl<D: SeatHandler + 'static> PointerTarget<D> for X11Surface {
         }
     }
 
+    fn gesture_swipe(&self, seat: &Seat<D>, data: &mut D, event: &GestureSwipeEvent) {
+        if let Some(surface) = self.state.lock().unwrap().wl_surface.as_ref() {
+            PointerTarget::gesture_swipe(surface, seat, data, event);
+        }
+    }
+
+    fn gesture_pinch(&self, seat: &Seat<D>, data: &mut D, event: &GesturePinchEvent) {
+        if let Some(surface) = self.state.lock().unwrap().wl_surface.as_ref() {
+            PointerTarget::gesture_pinch(surface, seat, data, event);
+        }
+    }
+
     fn leave(&self, seat: &Seat<D>, data: &mut D, serial: Serial, time: u32) {
         if let Some(surface) = self.state.lock().unwrap().wl_surface.as_ref() {
             PointerTarget::leave(surface, seat, data, serial, time);
@@ -4,7 +4,7 @@ use smithay::{
     backend::{
         allocator::dmabuf::Dmabuf,
         renderer::{
-            Frame, ImportDma, ImportDmaWl, ImportEgl, ImportMem, ImportMemWl, Renderer, Texture,
+            BlendMode, Frame, ImportDma, ImportDmaWl, ImportEgl, ImportMem, ImportMemWl, Renderer, Texture,
             TextureFilter,
         },
         SwapBuffersError,
@@ -164,6 +164,7 @@ impl Frame for DummyFrame {
         _damage: &[Rectangle<i32, Physical>],
         _src_transform: Transform,
         _alpha: f32,
+        _blend_mode: BlendMode,
     ) -> Result<(), Self::Error> {
         Ok(())
     }